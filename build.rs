@@ -0,0 +1,37 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::process::Command;
+
+/*
+ * Embed the git commit the program is built from. Building outside of a
+ * git repository, e.g. from a source archive, is still possible.
+ */
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+
+    let output = Command::new("git").args(["rev-parse", "HEAD"]).output();
+
+    if let Ok(output) = output {
+        let commit = String::from_utf8_lossy(&output.stdout);
+
+        if output.status.success() && !commit.trim().is_empty() {
+            println!("cargo:rustc-env=DEP2J_GIT_COMMIT={}", commit.trim());
+        }
+    }
+}
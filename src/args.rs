@@ -15,39 +15,688 @@
  * along with this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
+use std::iter::Peekable;
+use std::mem;
 use std::process::exit;
 
+use crate::dependency::{MergePolicy, MergeStrategy};
+use crate::package::Backend;
+use crate::separators::Separators;
+use crate::unicode::Form;
+use crate::utf8::Utf8Policy;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Query {
+    Affected(String),
+    Orphans,
+    Path(String, String),
+    Prerequisites,
+    Roots,
+    Targets,
+}
+
+impl Query {
+    /*
+     * Return whether the query prints a plain list of paths.
+     */
+    pub fn is_list(&self) -> bool {
+        matches!(
+            self,
+            Query::Affected(_)
+                | Query::Prerequisites
+                | Query::Roots
+                | Query::Targets
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stats {
+    Components,
+    CriticalPath,
+    Hotspots,
+    Metrics,
+    Summary,
+}
+
+const STATS: &[(&str, Stats)] = &[
+    ("components", Stats::Components),
+    ("critical-path", Stats::CriticalPath),
+    ("hotspots", Stats::Hotspots),
+    ("metrics", Stats::Metrics),
+    ("summary", Stats::Summary),
+];
+
+impl Stats {
+    fn parse(name: &str) -> Option<Self> {
+        STATS
+            .iter()
+            .find(|(x, _)| *x == name)
+            .map(|(_, stats)| *stats)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Report {
+    Junit,
+    Sarif,
+}
+
+/*
+ * Parse a report given as "<kind>=<file>".
+ */
+fn parse_report(value: &str) -> Option<(Report, String)> {
+    let (kind, path) = value.split_once('=')?;
+
+    let report = match kind {
+        "junit" => Report::Junit,
+        "sarif" => Report::Sarif,
+        _ => return None,
+    };
+
+    (!path.is_empty()).then(|| (report, String::from(path)))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Convert,
+    Graph,
+    Query(Query),
+    Stats(Stats),
+    Diff(String, String),
+    Why(String, String),
+    Check,
+    Lint,
+    Watch,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Format {
+    Json,
+    Csv,
+    Cytoscape,
+    D3,
+    Dot,
+    Html,
+    Ninja,
+    PlantUml,
+    Template,
+    TreeJson,
+    Batches,
+    Manifest,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputFormat {
+    Make,
+    Json,
+    Tlog,
+    Iar,
+    Ti,
+    Arm,
+    Swift,
+    P1689,
+    Fls,
+    Makedepend,
+}
+
+const INPUT_FORMATS: &[(&str, InputFormat)] = &[
+    ("make", InputFormat::Make),
+    ("json", InputFormat::Json),
+    ("tlog", InputFormat::Tlog),
+    ("iar", InputFormat::Iar),
+    ("keil", InputFormat::Iar),
+    ("ti", InputFormat::Ti),
+    ("arm", InputFormat::Arm),
+    ("swift", InputFormat::Swift),
+    ("p1689", InputFormat::P1689),
+    ("fls", InputFormat::Fls),
+    ("makedepend", InputFormat::Makedepend),
+];
+
+impl InputFormat {
+    fn parse(name: &str) -> Option<Self> {
+        INPUT_FORMATS
+            .iter()
+            .find(|(x, _)| *x == name)
+            .map(|(_, format)| *format)
+    }
+
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        INPUT_FORMATS.iter().map(|(name, _)| *name)
+    }
+}
+
+const FORMATS: &[(&str, Format)] = &[
+    ("json", Format::Json),
+    ("csv", Format::Csv),
+    ("cytoscape", Format::Cytoscape),
+    ("d3", Format::D3),
+    ("dot", Format::Dot),
+    ("html", Format::Html),
+    ("ninja", Format::Ninja),
+    ("plantuml", Format::PlantUml),
+    ("template", Format::Template),
+    ("tree-json", Format::TreeJson),
+    ("batches", Format::Batches),
+    ("manifest", Format::Manifest),
+];
+
+impl Format {
+    fn parse(name: &str) -> Option<Self> {
+        FORMATS
+            .iter()
+            .find(|(x, _)| *x == name)
+            .map(|(_, format)| *format)
+    }
+
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        FORMATS.iter().map(|(name, _)| *name)
+    }
+
+    /*
+     * Infer the format of an output file from its extension. The JSON
+     * based formats share the same extension and are not inferred.
+     */
+    pub fn from_extension(path: &str) -> Option<Self> {
+        let path = path.strip_suffix(".zst").unwrap_or(path);
+        let (_, ext) = path.rsplit_once('.')?;
+
+        match ext {
+            "csv" => Some(Format::Csv),
+            "dot" | "gv" => Some(Format::Dot),
+            "htm" | "html" => Some(Format::Html),
+            "plantuml" | "puml" => Some(Format::PlantUml),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct Args {
+    pub command: Command,
     pub input: Vec<String>,
-    pub output: String,
+    pub input_dirs: Vec<(usize, String)>,
+    pub outputs: Vec<String>,
+    pub format: Format,
+    pub input_format: InputFormat,
+    pub merge: MergeStrategy,
+    pub merge_policy: MergePolicy,
+    pub utf8: Utf8Policy,
+    pub cache_dir: String,
+    pub compile_db: String,
+    pub hash: String,
+    pub compress: String,
+    pub json_diagnostics: bool,
+    pub daemon: String,
+    pub roots: Vec<String>,
+    pub roots_file: String,
+    pub system_dirs: Vec<String>,
+    pub kind_rules: Vec<String>,
+    pub only_ext: Vec<String>,
+    pub drop_ext: Vec<String>,
+    pub source_rules: Vec<String>,
+    pub defines: Vec<String>,
+    pub rewrites: Vec<String>,
+    pub prefix_map_file: String,
+    pub normalize_paths: bool,
+    pub bazel: bool,
+    pub bazel_execroot: String,
+    pub output_separators: Separators,
+    pub unicode_normalize: Option<Form>,
+    pub as_uri: bool,
+    pub log_file: String,
+    pub pipe: String,
+    pub template: String,
+    pub query: String,
+    pub policy: String,
+    pub reports: Vec<(Report, String)>,
+    pub top: usize,
+    pub interval: u64,
+    pub max_prereqs: usize,
+    pub max_edges: usize,
+    pub max_memory: u64,
+    pub chunk_size: u64,
+    pub all: bool,
+    pub classify: bool,
+    pub drop_project: bool,
+    pub drop_system: bool,
+    pub drop_frameworks: bool,
+    pub kind: bool,
+    pub tag_generated: bool,
+    pub print0: bool,
+    pub name_template: String,
+    pub stream: bool,
+    pub group_by_input: bool,
+    pub coalesce_identical: bool,
+    pub dedupe_by_inode: bool,
+    pub server: bool,
+    pub sort: bool,
+    pub strict: bool,
+    pub table: bool,
+    pub transitive: bool,
+    pub with_metadata: bool,
+    pub with_packages: bool,
+    pub package_manager: Option<Backend>,
+    pub with_fingerprint: bool,
+    pub print_fingerprint: bool,
+    pub with_locations: bool,
+    pub infer_source: bool,
+    pub expand_env: bool,
+    pub report_conflicts: bool,
+    pub keep_going: bool,
+    pub expand_tilde: bool,
+    pub strip_source: bool,
+    pub force: bool,
+    pub fail_on_budget: bool,
+    pub sandbox_roots: Vec<String>,
+    pub sandbox_warn: bool,
+    pub timings: bool,
+    pub stats: bool,
+    pub events: bool,
+    pub quiet: bool,
     pub help: bool,
     pub version: bool,
+    pub version_json: bool,
 }
 
 impl Args {
     pub fn new() -> Self {
         Self {
+            command: Command::Convert,
             input: Vec::new(),
-            output: String::new(),
+            input_dirs: Vec::new(),
+            outputs: Vec::new(),
+            format: Format::Json,
+            input_format: InputFormat::Make,
+            merge: MergeStrategy::Hash,
+            merge_policy: MergePolicy::Union,
+            utf8: Utf8Policy::Warn,
+            cache_dir: String::new(),
+            compile_db: String::new(),
+            hash: String::new(),
+            compress: String::new(),
+            json_diagnostics: false,
+            daemon: String::new(),
+            roots: Vec::new(),
+            roots_file: String::new(),
+            system_dirs: Vec::new(),
+            kind_rules: Vec::new(),
+            only_ext: Vec::new(),
+            drop_ext: Vec::new(),
+            source_rules: Vec::new(),
+            defines: Vec::new(),
+            rewrites: Vec::new(),
+            prefix_map_file: String::new(),
+            normalize_paths: false,
+            bazel: false,
+            bazel_execroot: String::new(),
+            output_separators: Separators::Keep,
+            unicode_normalize: None,
+            as_uri: false,
+            log_file: String::new(),
+            pipe: String::new(),
+            template: String::new(),
+            query: String::new(),
+            policy: String::new(),
+            reports: Vec::new(),
+            top: usize::MAX,
+            interval: 500,
+            max_prereqs: usize::MAX,
+            max_edges: usize::MAX,
+            max_memory: u64::MAX,
+            chunk_size: 0,
+            all: false,
+            classify: false,
+            drop_project: false,
+            drop_system: false,
+            drop_frameworks: false,
+            kind: false,
+            tag_generated: false,
+            print0: false,
+            name_template: String::new(),
+            stream: false,
+            group_by_input: false,
+            coalesce_identical: false,
+            dedupe_by_inode: false,
+            server: false,
+            sort: false,
+            strict: false,
+            table: false,
+            transitive: false,
+            with_metadata: false,
+            with_packages: false,
+            package_manager: None,
+            with_fingerprint: false,
+            print_fingerprint: false,
+            with_locations: false,
+            infer_source: false,
+            expand_env: false,
+            report_conflicts: false,
+            keep_going: false,
+            expand_tilde: false,
+            strip_source: false,
+            force: false,
+            fail_on_budget: false,
+            sandbox_roots: Vec::new(),
+            sandbox_warn: false,
+            timings: false,
+            stats: false,
+            events: false,
+            quiet: false,
             help: false,
             version: false,
+            version_json: false,
         }
     }
+
+    /*
+     * Return the first option which needs all rules at once and therefore
+     * cannot be used with "--stream" and "--group-by-input".
+     */
+    pub fn unstreamable(&self) -> Option<&'static str> {
+        let options = [
+            (self.command != Command::Convert, "a command"),
+            (self.format != Format::Json, "--format"),
+            (self.outputs.len() > 1, "several outputs"),
+            (self.merge_policy != MergePolicy::Union, "--merge-policy"),
+            (!self.cache_dir.is_empty(), "--cache-dir"),
+            (!self.compile_db.is_empty(), "--compile-db"),
+            (!self.hash.is_empty(), "--hash"),
+            (!self.roots.is_empty(), "--root"),
+            (!self.roots_file.is_empty(), "--roots-file"),
+            (!self.system_dirs.is_empty(), "--system-dir"),
+            (!self.sandbox_roots.is_empty(), "--sandbox-root"),
+            (self.timings, "--timings"),
+            (self.stats, "--stats"),
+            (!self.kind_rules.is_empty(), "--kind-rule"),
+            (!self.only_ext.is_empty(), "--only-ext"),
+            (!self.drop_ext.is_empty(), "--drop-ext"),
+            (!self.source_rules.is_empty(), "--source-rule"),
+            (!self.defines.is_empty(), "--define"),
+            (!self.rewrites.is_empty(), "--rewrite"),
+            (!self.prefix_map_file.is_empty(), "--prefix-map-file"),
+            (!self.input_dirs.is_empty(), "\"--input\" with a directory"),
+            (self.normalize_paths, "--normalize-paths"),
+            (self.bazel, "--bazel"),
+            (
+                self.output_separators != Separators::Keep,
+                "--output-separators",
+            ),
+            (self.as_uri, "--as-uri"),
+            (self.unicode_normalize.is_some(), "--unicode-normalize"),
+            (!self.query.is_empty(), "--query"),
+            (self.max_prereqs != usize::MAX, "--max-prereqs-per-target"),
+            (self.max_edges != usize::MAX, "--max-total-edges"),
+            (self.max_memory != u64::MAX, "--max-memory"),
+            (self.chunk_size != 0, "--chunk-size"),
+            (self.classify, "--classify"),
+            (self.drop_project, "--drop-project"),
+            (self.drop_system, "--drop-system"),
+            (self.drop_frameworks, "--drop-frameworks"),
+            (self.kind, "--kind"),
+            (self.tag_generated, "--tag-generated"),
+            (!self.name_template.is_empty(), "--name-template"),
+            (self.sort, "--sort"),
+            (self.coalesce_identical, "--coalesce-identical"),
+            (self.dedupe_by_inode, "--dedupe-by-inode"),
+            (self.with_metadata, "--with-metadata"),
+            (self.with_packages, "--with-packages"),
+            (self.with_fingerprint, "--with-fingerprint"),
+            (self.print_fingerprint, "--print-fingerprint"),
+            (self.with_locations, "--with-locations"),
+            (self.infer_source, "--infer-source"),
+            (self.strip_source, "--strip-source"),
+            (self.expand_env, "--expand-env"),
+            (self.expand_tilde, "--expand-tilde"),
+            (self.report_conflicts, "--report-conflicts"),
+            (self.events, "--events"),
+        ];
+
+        options.iter().find(|(x, _)| *x).map(|(_, name)| *name)
+    }
+
+    /*
+     * Return the error for the first option which cannot be used with the
+     * other options or which requires another option.
+     */
+    pub fn conflict(&self) -> Option<String> {
+        let why = matches!(self.command, Command::Why(..));
+        let locations = if why { "why" } else { "--with-locations" };
+        let list = matches!(&self.command, Command::Query(x) if x.is_list());
+        let mode = if self.stream {
+            "--stream"
+        } else {
+            "--group-by-input"
+        };
+
+        let unstreamable = (self.stream || self.group_by_input)
+            .then(|| self.unstreamable())
+            .flatten()
+            .map(|x| match x.starts_with('-') {
+                true => format!("\"{x}\""),
+                false => String::from(x),
+            });
+
+        let conflicts = [
+            (
+                !self.compress.is_empty() && !self.pipe.is_empty(),
+                String::from("\"--compress\" cannot be used with \"--pipe\""),
+            ),
+            (
+                self.print0 && !list,
+                String::from(
+                    "\"--print0\" requires a query which prints a list of \
+                     paths",
+                ),
+            ),
+            (
+                self.sandbox_warn && self.sandbox_roots.is_empty(),
+                String::from("\"--sandbox-warn\" requires \"--sandbox-root\""),
+            ),
+            (
+                self.as_uri && !self.sandbox_roots.is_empty(),
+                String::from(
+                    "\"--sandbox-root\" cannot be used with \"--as-uri\"",
+                ),
+            ),
+            (
+                self.as_uri && self.dedupe_by_inode,
+                String::from(
+                    "\"--dedupe-by-inode\" cannot be used with \"--as-uri\"",
+                ),
+            ),
+            (
+                self.as_uri && self.output_separators == Separators::Windows,
+                String::from(
+                    "\"--as-uri\" cannot be used with \"--output-separators \
+                     windows\"",
+                ),
+            ),
+            (
+                !self.name_template.is_empty()
+                    && (self.command != Command::Convert
+                        || self.format != Format::Json
+                        || !self.outputs.is_empty()
+                        || !self.pipe.is_empty()),
+                String::from(
+                    "\"--name-template\" cannot be used with \"-o\", \
+                     \"--pipe\", \"--format\", or a command",
+                ),
+            ),
+            /* The attributes of a single target cannot be written for a group */
+            (
+                self.coalesce_identical
+                    && (self.command != Command::Convert
+                        || !self.name_template.is_empty()
+                        || !self.compile_db.is_empty()
+                        || self.with_locations
+                        || self.infer_source
+                        || self.strip_source),
+                String::from(
+                    "\"--coalesce-identical\" cannot be used with a command, \
+                     \"--name-template\", \"--compile-db\", \
+                     \"--with-locations\", \"--infer-source\", or \
+                     \"--strip-source\"",
+                ),
+            ),
+            (
+                self.command == Command::Watch
+                    && self.events
+                    && (!self.outputs.is_empty()
+                        || !self.pipe.is_empty()
+                        || !self.compress.is_empty()
+                        || self.format != Format::Json
+                        || self.kind
+                        || self.tag_generated),
+                String::from("\"--events\" only writes plain JSON to stdout"),
+            ),
+            (
+                unstreamable.is_some(),
+                format!(
+                    "\"{mode}\" cannot be used with {}",
+                    unstreamable.unwrap_or_default()
+                ),
+            ),
+            (
+                (self.with_locations || why)
+                    && self.input_format != InputFormat::Make,
+                format!("\"{locations}\" requires Makefile input"),
+            ),
+            (
+                (self.with_locations || why) && !self.input_dirs.is_empty(),
+                format!(
+                    "\"{locations}\" cannot be used with \"--input\" with a \
+                     directory"
+                ),
+            ),
+        ];
+
+        conflicts.into_iter().find(|(x, _)| *x).map(|(_, msg)| msg)
+    }
+}
+
+/*
+ * Parse a number of bytes with an optional binary suffix, e.g. "512M".
+ */
+fn parse_size(value: &str) -> Option<u64> {
+    let (num, shift) = match value.as_bytes().last()? {
+        b'K' | b'k' => (&value[..value.len() - 1], 10),
+        b'M' | b'm' => (&value[..value.len() - 1], 20),
+        b'G' | b'g' => (&value[..value.len() - 1], 30),
+        _ => (value, 0),
+    };
+
+    num.parse::<u64>().ok()?.checked_mul(1 << shift)
+}
+
+/*
+ * Take the arguments of the command "name" from "argv" or exit with an
+ * error if there are not enough of them.
+ */
+fn take_args<I: Iterator<Item = String>, const N: usize>(
+    name: &str,
+    argv: &mut I,
+) -> [String; N] {
+    [(); N].map(|_| {
+        argv.next().unwrap_or_else(|| {
+            error!("invalid-argument", "missing arguments for \"{name}\"");
+            exit(1);
+        })
+    })
+}
+
+/*
+ * Parse the command "arg" and its arguments. The commands "path" and
+ * "hotspots" are kept as aliases of "query path" and "stats hotspots".
+ */
+fn parse_command<I: Iterator<Item = String>>(
+    arg: &str,
+    argv: &mut Peekable<I>,
+) -> Option<Command> {
+    let command = match arg {
+        "convert" => Command::Convert,
+        "graph" => Command::Graph,
+        "check" => Command::Check,
+        "lint" => Command::Lint,
+        "watch" => Command::Watch,
+        "diff" => {
+            let [old, new] = take_args(arg, argv);
+            Command::Diff(old, new)
+        }
+        "path" => {
+            let [from, to] = take_args(arg, argv);
+            Command::Query(Query::Path(from, to))
+        }
+        "why" => {
+            let [target, prereq] = take_args(arg, argv);
+            Command::Why(target, prereq)
+        }
+        "hotspots" => Command::Stats(Stats::Hotspots),
+        "query" => {
+            let [name] = take_args(arg, argv);
+
+            let query = match name.as_str() {
+                "affected" => {
+                    let [rev] = take_args(&name, argv);
+                    Query::Affected(rev)
+                }
+                "orphans" => Query::Orphans,
+                "prerequisites" => Query::Prerequisites,
+                "roots" => Query::Roots,
+                "targets" => Query::Targets,
+                "path" => {
+                    let [from, to] = take_args(&name, argv);
+                    Query::Path(from, to)
+                }
+                _ => {
+                    error!("invalid-argument", "invalid query \"{name}\"");
+                    exit(1);
+                }
+            };
+
+            Command::Query(query)
+        }
+        "stats" => {
+            /* Without a statistic, e.g. "stats --top 20", print a summary */
+            let stats = argv.peek().and_then(|x| Stats::parse(x));
+
+            if stats.is_some() {
+                argv.next();
+            }
+
+            Command::Stats(stats.unwrap_or(Stats::Summary))
+        }
+        _ => return None,
+    };
+
+    Some(command)
 }
 
 #[must_use]
-pub fn parse<I: Iterator<Item = String> + ExactSizeIterator>(
-    mut argv: I,
-) -> Args {
+pub fn parse<I: Iterator<Item = String> + ExactSizeIterator>(argv: I) -> Args {
+    let mut argv = argv.peekable();
     let mut result = Args::new();
     let mut dash_dash = false;
 
     /* Skip the name of the program */
     argv.next();
 
+    /*
+     * A command has to be the first argument. Input files sharing the name
+     * of a command can still be specified after "--" or as "./<name>".
+     */
+    let mut first = true;
+
     while let Some(arg) = argv.next() {
-        if !arg.starts_with('-') || dash_dash {
+        if mem::take(&mut first) {
+            if let Some(command) = parse_command(&arg, &mut argv) {
+                result.command = command;
+                continue;
+            }
+        }
+
+        if !arg.starts_with('-') || arg == "-" || dash_dash {
             if result.input.capacity() == 0 {
                 result.input.reserve(argv.len());
             }
@@ -55,22 +704,370 @@ pub fn parse<I: Iterator<Item = String> + ExactSizeIterator>(
             result.input.push(arg);
         } else if arg == "--" {
             dash_dash = true;
+        } else if arg == "--all" {
+            result.all = true;
+        } else if arg == "--classify" {
+            result.classify = true;
+        } else if arg == "--components" {
+            result.command = Command::Stats(Stats::Components);
+        } else if arg == "--critical-path" {
+            result.command = Command::Stats(Stats::CriticalPath);
+        } else if arg == "--drop-project" {
+            result.drop_project = true;
+        } else if arg == "--drop-system" || arg == "--no-system" {
+            result.drop_system = true;
+        } else if arg == "--drop-frameworks" {
+            result.drop_frameworks = true;
+        } else if arg == "--lossy-utf8" {
+            result.utf8 = Utf8Policy::Lossy;
+        } else if arg == "--require-utf8" {
+            result.utf8 = Utf8Policy::Require;
+        } else if arg == "--stream" {
+            result.stream = true;
+        } else if arg == "--group-by-input" {
+            result.group_by_input = true;
+        } else if arg == "--print0" {
+            result.print0 = true;
+        } else if arg == "--tag-generated" {
+            result.tag_generated = true;
+        } else if arg == "--kind" {
+            result.kind = true;
+        } else if arg == "--metrics" {
+            result.command = Command::Stats(Stats::Metrics);
+        } else if arg == "--orphans" {
+            result.command = Command::Query(Query::Orphans);
+        } else if arg == "--server" {
+            result.server = true;
+        } else if arg == "--sort" {
+            result.sort = true;
+        } else if arg == "--coalesce-identical" {
+            result.coalesce_identical = true;
+        } else if arg == "--dedupe-by-inode" {
+            result.dedupe_by_inode = true;
+        } else if arg == "--events" {
+            result.events = true;
+        } else if arg == "--fail-on-budget" {
+            result.fail_on_budget = true;
+        } else if arg == "--sandbox-warn" {
+            result.sandbox_warn = true;
+        } else if arg == "--timings" {
+            result.timings = true;
+        } else if arg == "--stats" {
+            result.stats = true;
+        } else if arg == "--strict" {
+            result.strict = true;
+        } else if arg == "--table" {
+            result.table = true;
+        } else if arg == "--transitive" {
+            result.transitive = true;
+        } else if arg == "--with-locations" {
+            result.with_locations = true;
+        } else if arg == "--report-conflicts" {
+            result.report_conflicts = true;
+        } else if arg == "--keep-going" {
+            result.keep_going = true;
+        } else if arg == "--expand-env" {
+            result.expand_env = true;
+        } else if arg == "--as-uri" {
+            result.as_uri = true;
+        } else if arg == "--normalize-paths" {
+            result.normalize_paths = true;
+        } else if arg == "--bazel" {
+            result.bazel = true;
+        } else if arg == "--expand-tilde" {
+            result.expand_tilde = true;
+        } else if arg == "--infer-source" {
+            result.infer_source = true;
+        } else if arg == "--strip-source" {
+            result.strip_source = true;
+        } else if arg == "--with-metadata" {
+            result.with_metadata = true;
+        } else if arg == "--with-packages" {
+            result.with_packages = true;
+        } else if arg == "--with-fingerprint" {
+            result.with_fingerprint = true;
+        } else if arg == "--print-fingerprint" {
+            result.print_fingerprint = true;
+        } else if arg == "--force" {
+            result.force = true;
+        } else if arg == "--quiet" || arg == "-q" {
+            result.quiet = true;
         } else if arg == "--help" || arg == "-h" {
             result.help = true;
         } else if arg == "--version" {
             result.version = true;
+        } else if arg == "--version-json" {
+            result.version = true;
+            result.version_json = true;
         } else {
             let value = argv.next();
 
             if value.is_none() {
-                eprintln!("error: missing argument for \"{arg}\"");
+                error!("invalid-argument", "missing argument for \"{arg}\"");
                 exit(1);
             }
 
             if arg == "-o" {
-                result.output = value.unwrap();
+                result.outputs.push(value.unwrap());
+            } else if arg == "--input" {
+                let value = value.unwrap();
+
+                /* The relative paths of the input are below "dir" */
+                match value.rsplit_once(":dir=") {
+                    Some((path, dir)) if !path.is_empty() => {
+                        let index = result.input.len();
+
+                        result.input_dirs.push((index, String::from(dir)));
+                        result.input.push(String::from(path));
+                    }
+                    _ => result.input.push(value),
+                }
+            } else if arg == "--format" {
+                let value = value.unwrap();
+
+                result.format = Format::parse(&value).unwrap_or_else(|| {
+                    error!("invalid-argument", "unknown format \"{value}\"");
+                    exit(1);
+                });
+            } else if arg == "--input-format" {
+                let value = value.unwrap();
+
+                result.input_format = InputFormat::parse(&value)
+                    .unwrap_or_else(|| {
+                        error!(
+                            "invalid-argument",
+                            "unknown input format \"{value}\""
+                        );
+                        exit(1);
+                    });
+            } else if arg == "--merge" {
+                let value = value.unwrap();
+
+                result.merge = match value.as_str() {
+                    "hash" => MergeStrategy::Hash,
+                    "sort" => MergeStrategy::Sort,
+                    _ => {
+                        error!(
+                            "invalid-argument",
+                            "unknown merge strategy \"{value}\""
+                        );
+                        exit(1);
+                    }
+                };
+            } else if arg == "--merge-policy" {
+                let value = value.unwrap();
+
+                result.merge_policy = match value.as_str() {
+                    "union" => MergePolicy::Union,
+                    "first" => MergePolicy::First,
+                    "last" => MergePolicy::Last,
+                    "error" => MergePolicy::Error,
+                    _ => {
+                        error!(
+                            "invalid-argument",
+                            "unknown merge policy \"{value}\""
+                        );
+                        exit(1);
+                    }
+                };
+            } else if arg == "--output-separators" {
+                let value = value.unwrap();
+
+                result.output_separators = match value.as_str() {
+                    "posix" => Separators::Posix,
+                    "windows" => Separators::Windows,
+                    _ => {
+                        error!(
+                            "invalid-argument",
+                            "unknown separators \"{value}\""
+                        );
+                        exit(1);
+                    }
+                };
+            } else if arg == "--unicode-normalize" {
+                let value = value.unwrap();
+
+                result.unicode_normalize = match value.as_str() {
+                    "nfc" => Some(Form::Nfc),
+                    "nfd" => Some(Form::Nfd),
+                    _ => {
+                        error!(
+                            "invalid-argument",
+                            "unknown normalization form \"{value}\""
+                        );
+                        exit(1);
+                    }
+                };
+            } else if arg == "--cache-dir" {
+                result.cache_dir = value.unwrap();
+            } else if arg == "--compile-db" {
+                result.compile_db = value.unwrap();
+            } else if arg == "--daemon" {
+                result.daemon = value.unwrap();
+            } else if arg == "--diagnostics" {
+                let value = value.unwrap();
+
+                result.json_diagnostics = match value.as_str() {
+                    "text" => false,
+                    "json" => true,
+                    _ => {
+                        error!(
+                            "invalid-argument",
+                            "unknown diagnostics format \"{value}\""
+                        );
+                        exit(1);
+                    }
+                };
+            } else if arg == "--compress" {
+                let value = value.unwrap();
+
+                if value != "zstd" {
+                    error!(
+                        "invalid-argument",
+                        "unknown compression \"{value}\""
+                    );
+                    exit(1);
+                }
+
+                result.compress = value;
+            } else if arg == "--hash" {
+                let value = value.unwrap();
+
+                if value != "sha256" {
+                    error!(
+                        "invalid-argument",
+                        "unknown hash function \"{value}\""
+                    );
+                    exit(1);
+                }
+
+                result.hash = value;
+            } else if arg == "--list" {
+                let value = value.unwrap();
+
+                let query = match value.as_str() {
+                    "prerequisites" => Query::Prerequisites,
+                    "roots" => Query::Roots,
+                    "targets" => Query::Targets,
+                    _ => {
+                        error!("invalid-argument", "invalid list \"{value}\"");
+                        exit(1);
+                    }
+                };
+
+                result.command = Command::Query(query);
+            } else if arg == "--changed-since" {
+                let rev = value.unwrap();
+                result.command = Command::Query(Query::Affected(rev));
+            } else if arg == "--top" {
+                result.top = value.unwrap().parse().unwrap_or_else(|_| {
+                    error!("invalid-argument", "invalid number for \"{arg}\"");
+                    exit(1);
+                });
+            } else if arg == "--interval" {
+                result.interval = value.unwrap().parse().unwrap_or_else(|_| {
+                    error!("invalid-argument", "invalid number for \"{arg}\"");
+                    exit(1);
+                });
+            } else if arg == "--max-prereqs-per-target" {
+                result.max_prereqs =
+                    value.unwrap().parse().unwrap_or_else(|_| {
+                        error!(
+                            "invalid-argument",
+                            "invalid number for \"{arg}\""
+                        );
+                        exit(1);
+                    });
+            } else if arg == "--max-total-edges" {
+                result.max_edges =
+                    value.unwrap().parse().unwrap_or_else(|_| {
+                        error!(
+                            "invalid-argument",
+                            "invalid number for \"{arg}\""
+                        );
+                        exit(1);
+                    });
+            } else if arg == "--max-memory" {
+                result.max_memory =
+                    parse_size(&value.unwrap()).unwrap_or_else(|| {
+                        error!(
+                            "invalid-argument",
+                            "invalid size for \"{arg}\""
+                        );
+                        exit(1);
+                    });
+            } else if arg == "--chunk-size" {
+                result.chunk_size = parse_size(&value.unwrap())
+                    .filter(|&x| x != 0)
+                    .unwrap_or_else(|| {
+                        error!(
+                            "invalid-argument",
+                            "invalid size for \"{arg}\""
+                        );
+                        exit(1);
+                    });
+            } else if arg == "--root" {
+                result.roots.push(value.unwrap());
+            } else if arg == "--roots-file" {
+                result.roots_file = value.unwrap();
+            } else if arg == "--define" {
+                result.defines.push(value.unwrap());
+            } else if arg == "--name-template" {
+                result.name_template = value.unwrap();
+            } else if arg == "--prefix-map-file" {
+                result.prefix_map_file = value.unwrap();
+            } else if arg == "--package-manager" {
+                let value = value.unwrap();
+
+                result.with_packages = true;
+                result.package_manager =
+                    Some(Backend::parse(&value).unwrap_or_else(|| {
+                        error!(
+                            "invalid-argument",
+                            "unknown package manager \"{value}\""
+                        );
+                        exit(1);
+                    }));
+            } else if arg == "--bazel-execroot" {
+                result.bazel = true;
+                result.bazel_execroot = value.unwrap();
+            } else if arg == "--log-file" {
+                result.log_file = value.unwrap();
+            } else if arg == "--pipe" {
+                result.pipe = value.unwrap();
+            } else if arg == "--report" {
+                let report =
+                    parse_report(&value.unwrap()).unwrap_or_else(|| {
+                        error!(
+                            "invalid-argument",
+                            "invalid report for \"{arg}\""
+                        );
+                        exit(1);
+                    });
+
+                result.reports.push(report);
+            } else if arg == "--policy" {
+                result.policy = value.unwrap();
+            } else if arg == "--query" {
+                result.query = value.unwrap();
+            } else if arg == "--template" {
+                result.template = value.unwrap();
+            } else if arg == "--rewrite" {
+                result.rewrites.push(value.unwrap());
+            } else if arg == "--kind-rule" {
+                result.kind_rules.push(value.unwrap());
+            } else if arg == "--only-ext" {
+                result.only_ext.push(value.unwrap());
+            } else if arg == "--drop-ext" {
+                result.drop_ext.push(value.unwrap());
+            } else if arg == "--source-rule" {
+                result.source_rules.push(value.unwrap());
+            } else if arg == "--system-dir" {
+                result.system_dirs.push(value.unwrap());
+            } else if arg == "--sandbox-root" {
+                result.sandbox_roots.push(value.unwrap());
             } else {
-                eprintln!("error: unknown argument \"{arg}\"");
+                error!("invalid-argument", "unknown argument \"{arg}\"");
                 exit(1);
             }
         }
@@ -128,7 +1125,7 @@ mod tests {
 
         assert_eq!(false, args.help);
         assert_eq!(false, args.version);
-        assert_eq!("output.json", args.output);
+        assert_eq!(Vec::from(["output.json"]), args.outputs);
         assert_eq!(1, args.input.len());
         assert_eq!("input.d", args.input[0]);
     }
@@ -171,9 +1168,1595 @@ mod tests {
         let args = do_parse(vec);
 
         assert_eq!(true, args.version);
-        assert_eq!("output.json", args.output);
+        assert_eq!(Vec::from(["output.json"]), args.outputs);
         assert_eq!(2, args.input.len());
         assert_eq!("-h", args.input[0]);
         assert_eq!("-input.d", args.input[1]);
     }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the '--components'
+     * argument.
+     */
+    #[test]
+    fn parse_006() {
+        let vec = Vec::from(["dep2j", "--components", "input.d"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(Command::Stats(Stats::Components), args.command);
+        assert_eq!(1, args.input.len());
+        assert_eq!("input.d", args.input[0]);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "path" command.
+     */
+    #[test]
+    fn parse_007() {
+        let vec = Vec::from(["dep2j", "path", "a.o", "b.h", "--all", "in.d"]);
+
+        let args = do_parse(vec);
+
+        let path = Query::Path("a.o".into(), "b.h".into());
+
+        assert_eq!(Command::Query(path), args.command);
+        assert!(args.all);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function only interprets the first argument as
+     * a command.
+     */
+    #[test]
+    fn parse_008() {
+        let vec = Vec::from(["dep2j", "in.d", "path"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(Command::Convert, args.command);
+        assert_eq!(Vec::from(["in.d", "path"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles multiple '--root'
+     * arguments.
+     */
+    #[test]
+    fn parse_009() {
+        let vec =
+            Vec::from(["dep2j", "--orphans", "--root", "a", "--root", "b"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(Command::Query(Query::Orphans), args.command);
+        assert_eq!(Vec::from(["a", "b"]), args.roots);
+        assert!(args.input.is_empty());
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "hotspots" command
+     * with a limit.
+     */
+    #[test]
+    fn parse_010() {
+        let vec = Vec::from(["dep2j", "hotspots", "--top", "20", "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(Command::Stats(Stats::Hotspots), args.command);
+        assert_eq!(20, args.top);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the '--format' argument.
+     */
+    #[test]
+    fn parse_011() {
+        let vec = Vec::from(["dep2j", "--format", "cytoscape", "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(Format::Cytoscape, args.format);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the '--server' argument.
+     */
+    #[test]
+    fn parse_012() {
+        let vec = Vec::from(["dep2j", "--server"]);
+
+        let args = do_parse(vec);
+
+        assert!(args.server);
+        assert!(args.input.is_empty());
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the '--daemon' argument.
+     */
+    #[test]
+    fn parse_013() {
+        let vec = Vec::from(["dep2j", "--daemon", "/tmp/dep2j.sock"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!("/tmp/dep2j.sock", args.daemon);
+        assert!(args.input.is_empty());
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the '--cache-dir' argument.
+     */
+    #[test]
+    fn parse_014() {
+        let vec = Vec::from(["dep2j", "--cache-dir", ".cache", "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(".cache", args.cache_dir);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the '--with-metadata'
+     * argument.
+     */
+    #[test]
+    fn parse_015() {
+        let vec = Vec::from(["dep2j", "--with-metadata", "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert!(args.with_metadata);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the '--hash' argument.
+     */
+    #[test]
+    fn parse_016() {
+        let vec = Vec::from(["dep2j", "--hash", "sha256", "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!("sha256", args.hash);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the arguments to classify
+     * the prerequisites.
+     */
+    #[test]
+    fn parse_017() {
+        let vec = Vec::from([
+            "dep2j",
+            "--classify",
+            "--system-dir",
+            "/opt/sdk",
+            "--drop-system",
+            "in.d",
+        ]);
+
+        let args = do_parse(vec);
+
+        assert!(args.classify);
+        assert!(args.drop_system);
+        assert!(!args.drop_project);
+        assert_eq!(Vec::from(["/opt/sdk"]), args.system_dirs);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the arguments to tag the
+     * kind of the prerequisites.
+     */
+    #[test]
+    fn parse_018() {
+        let vec = Vec::from([
+            "dep2j",
+            "--kind",
+            "--kind-rule",
+            "generated=out/",
+            "in.d",
+        ]);
+
+        let args = do_parse(vec);
+
+        assert!(args.kind);
+        assert_eq!(Vec::from(["generated=out/"]), args.kind_rules);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the '--version-json'
+     * argument.
+     */
+    #[test]
+    fn parse_019() {
+        let vec = Vec::from(["dep2j", "--version-json"]);
+
+        let args = do_parse(vec);
+
+        assert!(args.version);
+        assert!(args.version_json);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the '--input-format'
+     * argument.
+     */
+    #[test]
+    fn parse_020() {
+        let vec = Vec::from(["dep2j", "--input-format", "json", "in.json"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(InputFormat::Json, args.input_format);
+        assert_eq!(Vec::from(["in.json"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "lint" command.
+     */
+    #[test]
+    fn parse_021() {
+        let vec = Vec::from(["dep2j", "lint", "a.d", "b.d"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(Command::Lint, args.command);
+        assert_eq!(Vec::from(["a.d", "b.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the '--sort' argument.
+     */
+    #[test]
+    fn parse_022() {
+        let vec = Vec::from(["dep2j", "--sort", "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert!(args.sort);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the '--merge' argument.
+     */
+    #[test]
+    fn parse_023() {
+        let vec = Vec::from(["dep2j", "--merge", "sort", "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(MergeStrategy::Sort, args.merge);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the memory limit is parsed with and without a suffix.
+     */
+    #[test]
+    fn parse_024() {
+        let vec = Vec::from(["dep2j", "--max-memory", "512M", "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(512 << 20, args.max_memory);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+
+        assert_eq!(Some(4096), parse_size("4096"));
+        assert_eq!(Some(2 << 30), parse_size("2G"));
+        assert_eq!(None, parse_size("M"));
+        assert_eq!(None, parse_size("-1K"));
+        assert_eq!(None, parse_size("99999999999999999G"));
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the '--chunk-size'
+     * argument.
+     */
+    #[test]
+    fn parse_025() {
+        let vec = Vec::from(["dep2j", "--chunk-size", "64K", "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(64 << 10, args.chunk_size);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the UTF-8 policy
+     * arguments and that the last one takes precedence.
+     */
+    #[test]
+    fn parse_026() {
+        let vec = Vec::from(["dep2j", "in.d"]);
+        assert_eq!(Utf8Policy::Warn, do_parse(vec).utf8);
+
+        let vec = Vec::from(["dep2j", "--require-utf8", "in.d"]);
+        assert_eq!(Utf8Policy::Require, do_parse(vec).utf8);
+
+        let vec = Vec::from(["dep2j", "--require-utf8", "--lossy-utf8"]);
+        assert_eq!(Utf8Policy::Lossy, do_parse(vec).utf8);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the '--strict' argument.
+     */
+    #[test]
+    fn parse_027() {
+        let vec = Vec::from(["dep2j", "--strict", "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert!(args.strict);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles multiple '--define'
+     * arguments.
+     */
+    #[test]
+    fn parse_028() {
+        let vec = Vec::from([
+            "dep2j",
+            "--define",
+            "OBJDIR=build",
+            "--define",
+            "ARCH=x86",
+            "in.d",
+        ]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(Vec::from(["OBJDIR=build", "ARCH=x86"]), args.defines);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "tlog" input format.
+     */
+    #[test]
+    fn parse_029() {
+        let vec =
+            Vec::from(["dep2j", "--input-format", "tlog", "CL.read.1.tlog"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(InputFormat::Tlog, args.input_format);
+        assert_eq!(Vec::from(["CL.read.1.tlog"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that "keil" is an alias of the "iar" input format.
+     */
+    #[test]
+    fn parse_030() {
+        for name in ["iar", "keil"] {
+            let vec = Vec::from(["dep2j", "--input-format", name, "main.d"]);
+
+            assert_eq!(InputFormat::Iar, do_parse(vec).input_format);
+        }
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles multiple "--rewrite"
+     * arguments.
+     */
+    #[test]
+    fn parse_031() {
+        let vec = Vec::from([
+            "dep2j",
+            "--rewrite",
+            "s|^/build/||",
+            "--rewrite",
+            "s/a/b/g",
+            "in.d",
+        ]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(Vec::from(["s|^/build/||", "s/a/b/g"]), args.rewrites);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--prefix-map-file"
+     * argument.
+     */
+    #[test]
+    fn parse_032() {
+        let vec = Vec::from(["dep2j", "--prefix-map-file", "map.txt", "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!("map.txt", args.prefix_map_file);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that "--no-system" is an alias of "--drop-system".
+     */
+    #[test]
+    fn parse_033() {
+        let args = do_parse(Vec::from(["dep2j", "--no-system", "in.d"]));
+
+        assert!(args.drop_system);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--only-ext" and
+     * "--drop-ext" arguments.
+     */
+    #[test]
+    fn parse_034() {
+        let vec = Vec::from([
+            "dep2j",
+            "--only-ext",
+            "h,hpp",
+            "--only-ext",
+            "inc",
+            "--drop-ext",
+            "in",
+            "in.d",
+        ]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(Vec::from(["h,hpp", "inc"]), args.only_ext);
+        assert_eq!(Vec::from(["in"]), args.drop_ext);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "query" and "stats"
+     * commands.
+     */
+    #[test]
+    fn parse_035() {
+        let tests = [
+            (
+                Vec::from(["dep2j", "query", "path", "a.o", "b.h", "in.d"]),
+                Command::Query(Query::Path("a.o".into(), "b.h".into())),
+            ),
+            (
+                Vec::from(["dep2j", "query", "affected", "HEAD~1", "in.d"]),
+                Command::Query(Query::Affected("HEAD~1".into())),
+            ),
+            (
+                Vec::from(["dep2j", "query", "orphans", "in.d"]),
+                Command::Query(Query::Orphans),
+            ),
+            (
+                Vec::from(["dep2j", "stats", "critical-path", "in.d"]),
+                Command::Stats(Stats::CriticalPath),
+            ),
+            (
+                Vec::from(["dep2j", "--changed-since", "HEAD", "in.d"]),
+                Command::Query(Query::Affected("HEAD".into())),
+            ),
+            (
+                Vec::from(["dep2j", "--metrics", "in.d"]),
+                Command::Stats(Stats::Metrics),
+            ),
+        ];
+
+        for (vec, expected) in tests {
+            let args = do_parse(vec);
+
+            assert_eq!(expected, args.command);
+            assert_eq!(Vec::from(["in.d"]), args.input);
+        }
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "convert", "graph",
+     * "diff", and "watch" commands.
+     */
+    #[test]
+    fn parse_036() {
+        let args = do_parse(Vec::from(["dep2j", "convert", "in.d"]));
+        assert_eq!(Command::Convert, args.command);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+
+        let args = do_parse(Vec::from(["dep2j", "graph", "--format", "d3"]));
+        assert_eq!(Command::Graph, args.command);
+        assert_eq!(Format::D3, args.format);
+
+        let args = do_parse(Vec::from(["dep2j", "diff", "a.json", "b.json"]));
+        assert_eq!(
+            Command::Diff("a.json".into(), "b.json".into()),
+            args.command
+        );
+        assert!(args.input.is_empty());
+
+        let vec = Vec::from(["dep2j", "watch", "--interval", "100", "in.d"]);
+        let args = do_parse(vec);
+        assert_eq!(Command::Watch, args.command);
+        assert_eq!(100, args.interval);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function interprets "-" as an input file.
+     */
+    #[test]
+    fn parse_037() {
+        let vec = Vec::from(["dep2j", "a.d", "-", "--sort", "b.d"]);
+
+        let args = do_parse(vec);
+
+        assert!(args.sort);
+        assert_eq!(Vec::from(["a.d", "-", "b.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--force" argument.
+     */
+    #[test]
+    fn parse_038() {
+        let vec = Vec::from(["dep2j", "-o", "out.json", "--force", "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert!(args.force);
+        assert_eq!(Vec::from(["out.json"]), args.outputs);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--quiet" argument.
+     */
+    #[test]
+    fn parse_039() {
+        for arg in ["--quiet", "-q"] {
+            let args = do_parse(Vec::from(["dep2j", arg, "in.d"]));
+
+            assert!(args.quiet);
+            assert_eq!(Vec::from(["in.d"]), args.input);
+        }
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--log-file"
+     * argument.
+     */
+    #[test]
+    fn parse_040() {
+        let vec = Vec::from(["dep2j", "-q", "--log-file", "ci.log", "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert!(args.quiet);
+        assert_eq!("ci.log", args.log_file);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function collects multiple output files.
+     */
+    #[test]
+    fn parse_041() {
+        let vec = Vec::from([
+            "dep2j",
+            "-o",
+            "deps.json",
+            "-o",
+            "deps.dot",
+            "-o",
+            "deps.csv",
+            "in.d",
+        ]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(
+            Vec::from(["deps.json", "deps.dot", "deps.csv"]),
+            args.outputs
+        );
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * Format::from_extension()
+     *
+     * Verify that only the formats with a distinct extension are inferred.
+     */
+    #[test]
+    fn from_extension_001() {
+        let tests = [
+            ("deps.csv", Some(Format::Csv)),
+            ("out/deps.gv", Some(Format::Dot)),
+            ("deps.dot", Some(Format::Dot)),
+            ("index.html", Some(Format::Html)),
+            ("deps.puml", Some(Format::PlantUml)),
+            ("deps.csv.zst", Some(Format::Csv)),
+            ("deps.json", None),
+            ("deps", None),
+        ];
+
+        for (path, expected) in tests {
+            assert_eq!(expected, Format::from_extension(path), "{path}");
+        }
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--pipe" argument.
+     */
+    #[test]
+    fn parse_042() {
+        let vec = Vec::from(["dep2j", "--pipe", "zstd -q -o deps.zst", "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!("zstd -q -o deps.zst", args.pipe);
+        assert!(args.outputs.is_empty());
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--template"
+     * argument.
+     */
+    #[test]
+    fn parse_043() {
+        let vec = Vec::from([
+            "dep2j",
+            "--format",
+            "template",
+            "--template",
+            "{{target}} <- {{prereq}}\\n",
+            "in.d",
+        ]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(Format::Template, args.format);
+        assert_eq!("{{target}} <- {{prereq}}\\n", args.template);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--query" argument.
+     */
+    #[test]
+    fn parse_044() {
+        let query = r#"prereq ~ "\.h$" && count > 10"#;
+        let vec = Vec::from(["dep2j", "--query", query, "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(query, args.query);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--report" argument.
+     */
+    #[test]
+    fn parse_045() {
+        let vec =
+            Vec::from(["dep2j", "lint", "--report", "junit=out.xml", "a.d"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(Command::Lint, args.command);
+        assert_eq!(
+            Vec::from([(Report::Junit, "out.xml".into())]),
+            args.reports
+        );
+        assert_eq!(Vec::from(["a.d"]), args.input);
+
+        assert_eq!(None, parse_report("junit="));
+        assert_eq!(None, parse_report("xml=out.xml"));
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "check" command.
+     */
+    #[test]
+    fn parse_046() {
+        let vec =
+            Vec::from(["dep2j", "check", "--policy", "deps.policy", "a.d"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(Command::Check, args.command);
+        assert_eq!("deps.policy", args.policy);
+        assert_eq!(Vec::from(["a.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the dependency budget
+     * arguments.
+     */
+    #[test]
+    fn parse_047() {
+        let vec = Vec::from(["dep2j", "in.d"]);
+        let args = do_parse(vec);
+
+        assert_eq!(usize::MAX, args.max_prereqs);
+        assert_eq!(usize::MAX, args.max_edges);
+        assert!(!args.fail_on_budget);
+
+        let vec = Vec::from([
+            "dep2j",
+            "--max-prereqs-per-target",
+            "50",
+            "--max-total-edges",
+            "1000",
+            "--fail-on-budget",
+            "in.d",
+        ]);
+        let args = do_parse(vec);
+
+        assert_eq!(50, args.max_prereqs);
+        assert_eq!(1000, args.max_edges);
+        assert!(args.fail_on_budget);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--events" argument.
+     */
+    #[test]
+    fn parse_048() {
+        let vec = Vec::from(["dep2j", "watch", "--events", "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(Command::Watch, args.command);
+        assert!(args.events);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--with-locations"
+     * argument.
+     */
+    #[test]
+    fn parse_049() {
+        let vec = Vec::from(["dep2j", "--with-locations", "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert!(args.with_locations);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--infer-source" and
+     * "--strip-source" arguments.
+     */
+    #[test]
+    fn parse_050() {
+        let vec = Vec::from(["dep2j", "--infer-source", "in.d"]);
+        let args = do_parse(vec);
+
+        assert!(args.infer_source);
+        assert!(!args.strip_source);
+
+        let vec = Vec::from(["dep2j", "--strip-source", "in.d"]);
+        let args = do_parse(vec);
+
+        assert!(!args.infer_source);
+        assert!(args.strip_source);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles multiple "--source-rule"
+     * arguments.
+     */
+    #[test]
+    fn parse_051() {
+        let vec = Vec::from([
+            "dep2j",
+            "--source-rule",
+            "build/%.o=src/%.c",
+            "--source-rule",
+            "build/%.o=src/%.cpp",
+            "in.d",
+        ]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(
+            Vec::from(["build/%.o=src/%.c", "build/%.o=src/%.cpp"]),
+            args.source_rules
+        );
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--expand-env" and
+     * "--expand-tilde" arguments.
+     */
+    #[test]
+    fn parse_052() {
+        let vec =
+            Vec::from(["dep2j", "--expand-env", "--expand-tilde", "in.d"]);
+
+        let args = do_parse(vec);
+
+        assert!(args.expand_env);
+        assert!(args.expand_tilde);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--compress" argument.
+     */
+    #[test]
+    fn parse_053() {
+        let vec = Vec::from(["dep2j", "--compress", "zstd", "-o", "a.zst"]);
+
+        let args = do_parse(vec);
+
+        assert_eq!("zstd", args.compress);
+        assert_eq!(Vec::from(["a.zst"]), args.outputs);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--report-conflicts"
+     * argument.
+     */
+    #[test]
+    fn parse_054() {
+        let vec = Vec::from(["dep2j", "--report-conflicts", "a.d", "b.d"]);
+
+        let args = do_parse(vec);
+
+        assert!(args.report_conflicts);
+        assert_eq!(Vec::from(["a.d", "b.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "stats" command
+     * without a statistic.
+     */
+    #[test]
+    fn parse_055() {
+        let vec = Vec::from(["dep2j", "stats", "--top", "20", "in.d"]);
+        let args = do_parse(vec);
+
+        assert_eq!(Command::Stats(Stats::Summary), args.command);
+        assert_eq!(20, args.top);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+
+        let vec = Vec::from(["dep2j", "stats", "summary", "in.d"]);
+        let args = do_parse(vec);
+
+        assert_eq!(Command::Stats(Stats::Summary), args.command);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+
+        let vec = Vec::from(["dep2j", "stats", "in.d"]);
+        let args = do_parse(vec);
+
+        assert_eq!(Command::Stats(Stats::Summary), args.command);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--diagnostics"
+     * argument.
+     */
+    #[test]
+    fn parse_056() {
+        let vec = Vec::from(["dep2j", "in.d"]);
+        assert!(!do_parse(vec).json_diagnostics);
+
+        let vec = Vec::from(["dep2j", "--diagnostics", "json", "in.d"]);
+        assert!(do_parse(vec).json_diagnostics);
+
+        let vec = Vec::from(["dep2j", "--diagnostics", "text", "in.d"]);
+        assert!(!do_parse(vec).json_diagnostics);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--keep-going"
+     * argument.
+     */
+    #[test]
+    fn parse_057() {
+        let vec = Vec::from(["dep2j", "a.d", "b.d"]);
+        assert!(!do_parse(vec).keep_going);
+
+        let vec = Vec::from(["dep2j", "--keep-going", "a.d", "b.d"]);
+        let args = do_parse(vec);
+
+        assert!(args.keep_going);
+        assert_eq!(Vec::from(["a.d", "b.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles a SARIF report.
+     */
+    #[test]
+    fn parse_058() {
+        let vec = Vec::from([
+            "dep2j",
+            "check",
+            "--report",
+            "sarif=out.sarif",
+            "--report",
+            "junit=out.xml",
+            "a.d",
+        ]);
+
+        let args = do_parse(vec);
+
+        assert_eq!(
+            Vec::from([
+                (Report::Sarif, "out.sarif".into()),
+                (Report::Junit, "out.xml".into()),
+            ]),
+            args.reports
+        );
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--with-fingerprint"
+     * and "--print-fingerprint" arguments.
+     */
+    #[test]
+    fn parse_059() {
+        let vec = Vec::from(["dep2j", "in.d"]);
+        let args = do_parse(vec);
+
+        assert!(!args.with_fingerprint);
+        assert!(!args.print_fingerprint);
+
+        let vec = Vec::from(["dep2j", "--with-fingerprint", "in.d"]);
+        assert!(do_parse(vec).with_fingerprint);
+
+        let vec = Vec::from(["dep2j", "--print-fingerprint", "in.d"]);
+        assert!(do_parse(vec).print_fingerprint);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--merge-policy"
+     * argument.
+     */
+    #[test]
+    fn parse_060() {
+        let vec = Vec::from(["dep2j", "in.d"]);
+        assert_eq!(MergePolicy::Union, do_parse(vec).merge_policy);
+
+        let tests = [
+            ("union", MergePolicy::Union),
+            ("first", MergePolicy::First),
+            ("last", MergePolicy::Last),
+            ("error", MergePolicy::Error),
+        ];
+
+        for (name, policy) in tests {
+            let vec = Vec::from(["dep2j", "--merge-policy", name, "in.d"]);
+            assert_eq!(policy, do_parse(vec).merge_policy);
+        }
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--tag-generated"
+     * argument.
+     */
+    #[test]
+    fn parse_061() {
+        let vec = Vec::from(["dep2j", "in.d"]);
+        assert!(!do_parse(vec).tag_generated);
+
+        let vec = Vec::from(["dep2j", "--tag-generated", "in.d"]);
+        assert!(do_parse(vec).tag_generated);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that "-" is an output of "-o" and not stdin as an input.
+     */
+    #[test]
+    fn parse_062() {
+        let vec = Vec::from(["dep2j", "-o", "-", "-o", "a.dot", "in.d"]);
+        let args = do_parse(vec);
+
+        assert_eq!(Vec::from(["-", "a.dot"]), args.outputs);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the list queries and the
+     * "--print0" argument.
+     */
+    #[test]
+    fn parse_063() {
+        let vec = Vec::from(["dep2j", "query", "targets", "--print0", "in.d"]);
+        let args = do_parse(vec);
+
+        assert_eq!(Command::Query(Query::Targets), args.command);
+        assert!(args.print0);
+
+        let vec = Vec::from(["dep2j", "query", "prerequisites", "in.d"]);
+        let args = do_parse(vec);
+
+        assert_eq!(Command::Query(Query::Prerequisites), args.command);
+        assert!(!args.print0);
+
+        assert!(Query::Affected("HEAD".into()).is_list());
+        assert!(!Query::Orphans.is_list());
+        assert!(!Query::Path("a".into(), "b".into()).is_list());
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--bazel" and
+     * "--bazel-execroot" arguments.
+     */
+    #[test]
+    fn parse_064() {
+        let vec = Vec::from(["dep2j", "--bazel", "in.d"]);
+        let args = do_parse(vec);
+
+        assert!(args.bazel);
+        assert!(args.bazel_execroot.is_empty());
+
+        let vec = Vec::from(["dep2j", "--bazel-execroot", "/b/x", "in.d"]);
+        let args = do_parse(vec);
+
+        assert!(args.bazel);
+        assert_eq!("/b/x", args.bazel_execroot);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--normalize-paths"
+     * argument.
+     */
+    #[test]
+    fn parse_065() {
+        let vec = Vec::from(["dep2j", "--normalize-paths", "in.d"]);
+        let args = do_parse(vec);
+
+        assert!(args.normalize_paths);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--name-template"
+     * argument.
+     */
+    #[test]
+    fn parse_066() {
+        let template = "{dir}/{stem}.deps.json";
+        let vec = Vec::from(["dep2j", "--name-template", template, "in.d"]);
+        let args = do_parse(vec);
+
+        assert_eq!(template, args.name_template);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "why" command.
+     */
+    #[test]
+    fn parse_067() {
+        let vec = Vec::from(["dep2j", "why", "a.o", "a.h", "in.d"]);
+        let args = do_parse(vec);
+
+        let expected = Command::Why(String::from("a.o"), String::from("a.h"));
+
+        assert_eq!(expected, args.command);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "roots" query and its
+     * alias "--list roots".
+     */
+    #[test]
+    fn parse_068() {
+        let tests = [
+            (Vec::from(["dep2j", "query", "roots", "in.d"]), Query::Roots),
+            (
+                Vec::from(["dep2j", "--list", "roots", "in.d"]),
+                Query::Roots,
+            ),
+            (Vec::from(["dep2j", "--list", "targets"]), Query::Targets),
+        ];
+
+        for (vec, expected) in tests {
+            let args = do_parse(vec);
+
+            assert_eq!(Command::Query(expected), args.command);
+        }
+
+        assert!(Query::Roots.is_list());
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--stream" argument
+     * and that options which need all rules are reported.
+     */
+    #[test]
+    fn parse_069() {
+        let vec = Vec::from(["dep2j", "--stream", "-o", "out.json", "in.d"]);
+        let args = do_parse(vec);
+
+        assert!(args.stream);
+        assert_eq!(None, args.unstreamable());
+
+        let tests = [
+            (Vec::from(["dep2j", "--stream", "--sort"]), "--sort"),
+            (
+                Vec::from(["dep2j", "--stream", "-o", "a", "-o", "b"]),
+                "several outputs",
+            ),
+            (Vec::from(["dep2j", "graph", "--stream"]), "a command"),
+        ];
+
+        for (vec, expected) in tests {
+            assert_eq!(Some(expected), do_parse(vec).unstreamable());
+        }
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--output-separators"
+     * argument.
+     */
+    #[test]
+    fn parse_070() {
+        let args = do_parse(Vec::from(["dep2j", "in.d"]));
+
+        assert_eq!(Separators::Keep, args.output_separators);
+
+        let vec =
+            Vec::from(["dep2j", "--output-separators", "windows", "in.d"]);
+        let args = do_parse(vec);
+
+        assert_eq!(Separators::Windows, args.output_separators);
+
+        let vec = Vec::from(["dep2j", "--output-separators", "posix", "in.d"]);
+        let args = do_parse(vec);
+
+        assert_eq!(Separators::Posix, args.output_separators);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--as-uri" argument.
+     */
+    #[test]
+    fn parse_071() {
+        let args = do_parse(Vec::from(["dep2j", "in.d"]));
+
+        assert!(!args.as_uri);
+
+        let args = do_parse(Vec::from(["dep2j", "--as-uri", "in.d"]));
+
+        assert!(args.as_uri);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--group-by-input"
+     * argument.
+     */
+    #[test]
+    fn parse_072() {
+        let args = do_parse(Vec::from(["dep2j", "a.d", "b.d"]));
+
+        assert!(!args.group_by_input);
+
+        let vec = Vec::from(["dep2j", "--group-by-input", "a.d", "b.d"]);
+        let args = do_parse(vec);
+
+        assert!(args.group_by_input);
+        assert!(!args.stream);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--coalesce-identical"
+     * argument.
+     */
+    #[test]
+    fn parse_073() {
+        let args = do_parse(Vec::from(["dep2j", "in.d"]));
+
+        assert!(!args.coalesce_identical);
+
+        let args =
+            do_parse(Vec::from(["dep2j", "--coalesce-identical", "in.d"]));
+
+        assert!(args.coalesce_identical);
+        assert_eq!(Some("--coalesce-identical"), args.unstreamable());
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--with-packages" and
+     * "--package-manager" arguments.
+     */
+    #[test]
+    fn parse_074() {
+        let args = do_parse(Vec::from(["dep2j", "--with-packages", "in.d"]));
+
+        assert!(args.with_packages);
+        assert_eq!(None, args.package_manager);
+
+        let vec = Vec::from(["dep2j", "--package-manager", "rpm", "in.d"]);
+        let args = do_parse(vec);
+
+        assert!(args.with_packages);
+        assert_eq!(Some(Backend::Rpm), args.package_manager);
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--input" argument
+     * with and without a base directory.
+     */
+    #[test]
+    fn parse_075() {
+        let vec = Vec::from([
+            "dep2j",
+            "--input",
+            "build/a.d:dir=/src/a",
+            "b.d",
+            "--input",
+            "c:d.d",
+            "--input",
+            "build/a.d:dir=/src/b",
+        ]);
+        let args = do_parse(vec);
+
+        assert_eq!(
+            Vec::from(["build/a.d", "b.d", "c:d.d", "build/a.d"]),
+            args.input
+        );
+        assert_eq!(
+            Vec::from([
+                (0, String::from("/src/a")),
+                (3, String::from("/src/b"))
+            ]),
+            args.input_dirs
+        );
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--dedupe-by-inode"
+     * argument.
+     */
+    #[test]
+    fn parse_076() {
+        let args = do_parse(Vec::from(["dep2j", "in.d"]));
+
+        assert!(!args.dedupe_by_inode);
+
+        let args = do_parse(Vec::from(["dep2j", "--dedupe-by-inode", "in.d"]));
+
+        assert!(args.dedupe_by_inode);
+        assert_eq!(Some("--dedupe-by-inode"), args.unstreamable());
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--sandbox-root" and
+     * "--sandbox-warn" arguments.
+     */
+    #[test]
+    fn parse_077() {
+        let args = do_parse(Vec::from(["dep2j", "in.d"]));
+
+        assert!(args.sandbox_roots.is_empty());
+        assert!(!args.sandbox_warn);
+
+        let vec = Vec::from([
+            "dep2j",
+            "--sandbox-root",
+            "src",
+            "--sandbox-root",
+            "/build",
+            "--sandbox-warn",
+            "in.d",
+        ]);
+        let args = do_parse(vec);
+
+        assert_eq!(Vec::from(["src", "/build"]), args.sandbox_roots);
+        assert!(args.sandbox_warn);
+        assert_eq!(Some("--sandbox-root"), args.unstreamable());
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--timings" argument.
+     */
+    #[test]
+    fn parse_078() {
+        let args = do_parse(Vec::from(["dep2j", "in.d"]));
+
+        assert!(!args.timings);
+
+        let args = do_parse(Vec::from(["dep2j", "--timings", "in.d"]));
+
+        assert!(args.timings);
+        assert_eq!(Some("--timings"), args.unstreamable());
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--stats" argument.
+     */
+    #[test]
+    fn parse_079() {
+        let args = do_parse(Vec::from(["dep2j", "in.d"]));
+
+        assert!(!args.stats);
+
+        let args = do_parse(Vec::from(["dep2j", "--stats", "in.d"]));
+
+        assert!(args.stats);
+        assert_eq!(Some("--stats"), args.unstreamable());
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--unicode-normalize"
+     * argument.
+     */
+    #[test]
+    fn parse_080() {
+        let args = do_parse(Vec::from(["dep2j", "in.d"]));
+
+        assert_eq!(None, args.unicode_normalize);
+
+        let vec = Vec::from(["dep2j", "--unicode-normalize", "nfc", "in.d"]);
+        let args = do_parse(vec);
+
+        assert_eq!(Some(Form::Nfc), args.unicode_normalize);
+
+        let vec = Vec::from(["dep2j", "--unicode-normalize", "nfd", "in.d"]);
+        let args = do_parse(vec);
+
+        assert_eq!(Some(Form::Nfd), args.unicode_normalize);
+        assert_eq!(Some("--unicode-normalize"), args.unstreamable());
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--drop-frameworks"
+     * argument.
+     */
+    #[test]
+    fn parse_081() {
+        let args = do_parse(Vec::from(["dep2j", "in.d"]));
+
+        assert!(!args.drop_frameworks);
+
+        let args = do_parse(Vec::from(["dep2j", "--drop-frameworks", "in.d"]));
+
+        assert!(args.drop_frameworks);
+        assert!(!args.drop_system);
+        assert_eq!(Some("--drop-frameworks"), args.unstreamable());
+    }
+
+    /**
+     * parse()
+     *
+     * Verify that the function correctly handles the "--table" argument.
+     */
+    #[test]
+    fn parse_082() {
+        let args = do_parse(Vec::from(["dep2j", "stats", "hotspots", "in.d"]));
+
+        assert!(!args.table);
+
+        let vec = Vec::from(["dep2j", "stats", "hotspots", "--table", "in.d"]);
+        let args = do_parse(vec);
+
+        assert!(args.table);
+        assert_eq!(Command::Stats(Stats::Hotspots), args.command);
+        assert_eq!(Vec::from(["in.d"]), args.input);
+    }
+
+    /**
+     * Args::conflict()
+     *
+     * Verify that the function accepts options which can be used together.
+     */
+    #[test]
+    fn conflict_001() {
+        let args = do_parse(Vec::from(["dep2j", "in.d"]));
+
+        assert_eq!(None, args.conflict());
+
+        let args = do_parse(Vec::from(["dep2j", "--with-locations", "in.d"]));
+
+        assert_eq!(None, args.conflict());
+
+        let args =
+            do_parse(Vec::from(["dep2j", "--as-uri", "--force", "in.d"]));
+
+        assert_eq!(None, args.conflict());
+    }
+
+    /**
+     * Args::conflict()
+     *
+     * Verify that the function reports options which cannot be used together.
+     */
+    #[test]
+    fn conflict_002() {
+        let vec = Vec::from(["dep2j", "--as-uri", "--dedupe-by-inode", "in.d"]);
+        let args = do_parse(vec);
+
+        assert_eq!(
+            Some("\"--dedupe-by-inode\" cannot be used with \"--as-uri\""),
+            args.conflict().as_deref()
+        );
+
+        let args = do_parse(Vec::from(["dep2j", "--sandbox-warn", "in.d"]));
+
+        assert_eq!(
+            Some("\"--sandbox-warn\" requires \"--sandbox-root\""),
+            args.conflict().as_deref()
+        );
+
+        let vec = Vec::from(["dep2j", "--stream", "--with-locations", "in.d"]);
+        let args = do_parse(vec);
+
+        assert_eq!(
+            Some("\"--stream\" cannot be used with \"--with-locations\""),
+            args.conflict().as_deref()
+        );
+
+        let vec = Vec::from(["dep2j", "why", "a.o", "a.h", "--input-format"]);
+        let args = do_parse([vec, Vec::from(["json", "in.json"])].concat());
+
+        assert_eq!(
+            Some("\"why\" requires Makefile input"),
+            args.conflict().as_deref()
+        );
+    }
 }
@@ -0,0 +1,146 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::borrow::Cow;
+
+const EXECROOT: &str = "/execroot/";
+const BAZEL_OUT: &str = "bazel-out/";
+const EXTERNAL: &str = "external/";
+
+/*
+ * Remap the paths of dependency files written inside a Bazel execroot or
+ * sandbox to paths relative to the workspace. They are resolved through
+ * the convenience symlinks Bazel creates in the workspace:
+ *
+ *     <execroot>/<path>             -> <path>
+ *     bazel-out/<config>/bin/<path> -> bazel-bin/<path>
+ *     external/<repo>/<path>        -> bazel-<workspace>/external/<repo>/<path>
+ *
+ * If an absolute path is not below the configured execroot, everything up
+ * to and including its "execroot/<name>/" components is removed, which
+ * covers the default execroot and the sandboxes. The execroot only has to
+ * be configured if it does not follow this layout, e.g. on a remote
+ * execution worker.
+ */
+pub struct BazelMap {
+    execroot: String,
+    workspace: String,
+}
+
+impl BazelMap {
+    /*
+     * "workspace" is the name of the workspace directory, which is part of
+     * the "bazel-<workspace>" symlink to the execroot.
+     */
+    pub fn new(execroot: &str, workspace: &str) -> Self {
+        Self {
+            execroot: execroot.trim_end_matches('/').to_string(),
+            workspace: workspace.to_string(),
+        }
+    }
+
+    pub fn apply<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        let rel = self.strip_execroot(path);
+
+        if let Some(rest) = rel.strip_prefix(BAZEL_OUT) {
+            let generated = rest
+                .split_once('/')
+                .and_then(|(_, rest)| rest.split_once('/'))
+                .filter(|(dir, _)| *dir == "bin" || *dir == "genfiles");
+
+            if let Some((_, rest)) = generated {
+                return Cow::Owned(format!("bazel-bin/{rest}"));
+            }
+        }
+
+        if rel.starts_with(EXTERNAL) {
+            return Cow::Owned(format!("bazel-{}/{rel}", self.workspace));
+        }
+
+        Cow::Borrowed(rel)
+    }
+
+    fn strip_execroot<'a>(&self, path: &'a str) -> &'a str {
+        let rest = path
+            .strip_prefix(self.execroot.as_str())
+            .and_then(|rest| rest.strip_prefix('/'));
+
+        if let Some(rest) = rest.filter(|_| !self.execroot.is_empty()) {
+            return rest;
+        }
+
+        if !path.starts_with('/') {
+            return path;
+        }
+
+        path.find(EXECROOT)
+            .map(|i| &path[i + EXECROOT.len()..])
+            .and_then(|rest| Some(rest.split_once('/')?.1))
+            .unwrap_or(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * BazelMap::apply()
+     *
+     * Verify that the function remaps the paths of the sandboxes, the
+     * generated files, and the external repositories.
+     */
+    #[test]
+    fn apply_001() {
+        let map = BazelMap::new("", "app");
+
+        let tests = [
+            ("/b/sandbox/7/execroot/_main/src/a.c", "src/a.c"),
+            ("/b/execroot/_main/bazel-out/k8/bin/a.h", "bazel-bin/a.h"),
+            ("bazel-out/k8/genfiles/b.h", "bazel-bin/b.h"),
+            ("bazel-out/k8/include/c.h", "bazel-out/k8/include/c.h"),
+            ("external/zlib/zlib.h", "bazel-app/external/zlib/zlib.h"),
+            ("src/external/d.h", "src/external/d.h"),
+            ("/usr/include/stdio.h", "/usr/include/stdio.h"),
+        ];
+
+        for (path, expected) in tests {
+            assert_eq!(expected, map.apply(path), "{path}");
+        }
+    }
+
+    /**
+     * BazelMap::apply()
+     *
+     * Verify that the function strips the configured execroot and still
+     * handles the sandboxes.
+     */
+    #[test]
+    fn apply_002() {
+        let map = BazelMap::new("/worker/build/5e1f/", "app");
+
+        let tests = [
+            ("/worker/build/5e1f/src/a.c", "src/a.c"),
+            ("/worker/build/5e1fa/a.c", "/worker/build/5e1fa/a.c"),
+            ("/b/sandbox/1/execroot/app/a.c", "a.c"),
+        ];
+
+        for (path, expected) in tests {
+            assert_eq!(expected, map.apply(path), "{path}");
+        }
+    }
+}
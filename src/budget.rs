@@ -0,0 +1,115 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dependency::Dependency;
+
+/*
+ * Limits for the number of prerequisites of each target and for the total
+ * number of edges, i.e. pairs of target and prerequisite. A limit of
+ * "usize::MAX" is never exceeded.
+ */
+pub struct Budget {
+    pub max_prereqs: usize,
+    pub max_edges: usize,
+}
+
+impl Budget {
+    /*
+     * Describe each exceeded limit. The targets with too many
+     * prerequisites are listed first, the largest ones at the beginning.
+     */
+    pub fn check(&self, deps: &[Dependency]) -> Vec<String> {
+        let mut offenders: Vec<_> = deps
+            .iter()
+            .map(|x| (x.target, x.prerequisites.len()))
+            .filter(|&(_, n)| n > self.max_prereqs)
+            .collect();
+
+        offenders.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+        let max = self.max_prereqs;
+        let mut result: Vec<_> = offenders
+            .into_iter()
+            .map(|(target, n)| {
+                format!(
+                    "\"{target}\" has {n} prerequisites, the limit is {max}"
+                )
+            })
+            .collect();
+
+        let edges: usize = deps.iter().map(|x| x.prerequisites.len()).sum();
+
+        if edges > self.max_edges {
+            let max = self.max_edges;
+
+            result.push(format!(
+                "the dependencies have {edges} edges, the limit is {max}"
+            ));
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * check()
+     *
+     * Verify that the targets exceeding the limit are reported with the
+     * largest first, followed by the total number of edges.
+     */
+    #[test]
+    fn check_001() {
+        let deps = Vec::from([
+            Dependency {
+                target: "a.o",
+                prerequisites: Vec::from(["a.c", "a.h", "b.h"]),
+            },
+            Dependency {
+                target: "b.o",
+                prerequisites: Vec::from(["b.c", "b.h"]),
+            },
+            Dependency {
+                target: "c.o",
+                prerequisites: Vec::from(["c.c", "a.h", "b.h", "c.h"]),
+            },
+        ]);
+
+        let budget = Budget {
+            max_prereqs: 2,
+            max_edges: 8,
+        };
+
+        let expected = Vec::from([
+            "\"c.o\" has 4 prerequisites, the limit is 2",
+            "\"a.o\" has 3 prerequisites, the limit is 2",
+            "the dependencies have 9 edges, the limit is 8",
+        ]);
+
+        assert_eq!(expected, budget.check(&deps));
+
+        let budget = Budget {
+            max_prereqs: 4,
+            max_edges: usize::MAX,
+        };
+
+        assert!(budget.check(&deps).is_empty());
+    }
+}
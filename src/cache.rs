@@ -0,0 +1,314 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process;
+
+use crate::dependency::{DependencyBuf, DependencyParser};
+use crate::hash;
+
+/*
+ * The magic number is changed whenever the layout of an entry changes,
+ * so entries of older versions are simply ignored.
+ */
+const MAGIC: &[u8] = b"dep2j-cache-1\n";
+
+/*
+ * Store the parsed dependencies of a file in a directory. An entry is
+ * identified by the SHA-256 digest of the file content and the options of
+ * the parser, so unchanged files do not have to be parsed again,
+ * regardless of their path.
+ */
+pub struct Cache {
+    dir: PathBuf,
+    strict: bool,
+    keep_going: bool,
+}
+
+impl Cache {
+    pub fn new(dir: &str) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+
+        Ok(Self {
+            dir: PathBuf::from(dir),
+            strict: false,
+            keep_going: false,
+        })
+    }
+
+    /*
+     * Parse the files like "DependencyParser::set_strict()".
+     */
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /*
+     * Parse the files like "DependencyParser::set_keep_going()".
+     */
+    pub fn set_keep_going(&mut self, keep_going: bool) {
+        self.keep_going = keep_going;
+    }
+
+    fn entry(&self, data: &[u8]) -> PathBuf {
+        let mut name = hash::to_hex(&hash::sha256(data));
+
+        if self.strict {
+            name.push_str("-strict");
+        }
+
+        if self.keep_going {
+            name.push_str("-keep-going");
+        }
+
+        self.dir.join(name)
+    }
+
+    /*
+     * Return the dependencies of "data" from the cache or parse them and
+     * add them to the cache. Failing to update the cache is not fatal.
+     * Input with errors is never added to the cache.
+     */
+    pub fn parse(&self, data: Vec<u8>) -> Result<Vec<DependencyBuf>, String> {
+        let path = self.entry(&data);
+
+        if let Some(deps) = fs::read(&path).ok().and_then(|x| decode(&x)) {
+            return Ok(deps);
+        }
+
+        let mut parser = DependencyParser::new();
+        parser.set_strict(self.strict);
+        parser.set_keep_going(self.keep_going);
+
+        parser.feed(data)?;
+
+        let deps: Vec<_> = parser.finish().iter().map(|x| x.to_buf()).collect();
+
+        /*
+         * Write the entry to a temporary file first. Renaming it is atomic,
+         * so concurrent runs never read a partially written entry.
+         */
+        let mut tmp = path.clone();
+        tmp.set_extension(format!("tmp{}", process::id()));
+
+        let result = fs::write(&tmp, encode(&deps))
+            .and_then(|_| fs::rename(&tmp, &path));
+
+        if let Err(err) = result {
+            let path = path.display();
+
+            warning!(
+                "write-failed",
+                "failed to write cache entry \"{path}\": {err}"
+            );
+            let _ = fs::remove_file(&tmp);
+        }
+
+        Ok(deps)
+    }
+}
+
+fn encode(deps: &[DependencyBuf]) -> Vec<u8> {
+    fn write_str(buf: &mut Vec<u8>, data: &str) {
+        buf.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        buf.extend_from_slice(data.as_bytes());
+    }
+
+    let mut buf = Vec::from(MAGIC);
+    buf.extend_from_slice(&(deps.len() as u64).to_le_bytes());
+
+    for dep in deps {
+        write_str(&mut buf, &dep.target);
+
+        let len = dep.prerequisites.len() as u64;
+        buf.extend_from_slice(&len.to_le_bytes());
+
+        for prereq in &dep.prerequisites {
+            write_str(&mut buf, prereq);
+        }
+    }
+
+    buf
+}
+
+fn decode(data: &[u8]) -> Option<Vec<DependencyBuf>> {
+    struct Reader<'a> {
+        data: &'a [u8],
+    }
+
+    impl Reader<'_> {
+        fn read_u64(&mut self) -> Option<usize> {
+            let (bytes, rest) = self.data.split_first_chunk::<8>()?;
+            self.data = rest;
+
+            usize::try_from(u64::from_le_bytes(*bytes)).ok()
+        }
+
+        fn read_str(&mut self) -> Option<String> {
+            let len = self.read_u64()?;
+
+            if len > self.data.len() {
+                return None;
+            }
+
+            let (bytes, rest) = self.data.split_at(len);
+            self.data = rest;
+
+            String::from_utf8(Vec::from(bytes)).ok()
+        }
+    }
+
+    let mut reader = Reader {
+        data: data.strip_prefix(MAGIC)?,
+    };
+
+    let len = reader.read_u64()?;
+    let mut deps = Vec::with_capacity(len.min(reader.data.len()));
+
+    for _ in 0..len {
+        let target = reader.read_str()?;
+        let len = reader.read_u64()?;
+        let mut prerequisites = Vec::with_capacity(len.min(reader.data.len()));
+
+        for _ in 0..len {
+            prerequisites.push(reader.read_str()?);
+        }
+
+        deps.push(DependencyBuf {
+            target,
+            prerequisites,
+        });
+    }
+
+    reader.data.is_empty().then_some(deps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn make_deps() -> Vec<DependencyBuf> {
+        Vec::from([
+            DependencyBuf {
+                target: String::from("a.o"),
+                prerequisites: Vec::from([
+                    String::from("a.c"),
+                    String::from("a.h"),
+                ]),
+            },
+            DependencyBuf {
+                target: String::from("b.o"),
+                prerequisites: Vec::new(),
+            },
+        ])
+    }
+
+    /**
+     * decode()
+     *
+     * Verify that decoding an encoded entry returns the original
+     * dependencies.
+     */
+    #[test]
+    fn decode_001() {
+        let deps = make_deps();
+
+        assert_eq!(Some(deps.clone()), decode(&encode(&deps)));
+    }
+
+    /**
+     * decode()
+     *
+     * Verify that truncated or otherwise invalid entries are rejected.
+     */
+    #[test]
+    fn decode_002() {
+        let data = encode(&make_deps());
+
+        for len in 0..data.len() {
+            assert_eq!(None, decode(&data[..len]));
+        }
+
+        let mut data = data;
+        data.push(0);
+
+        assert_eq!(None, decode(&data));
+        assert_eq!(None, decode(b"dep2j-cache-0\n"));
+    }
+
+    /**
+     * Cache::parse()
+     *
+     * Verify that parsed dependencies are stored in the cache and read
+     * from it for identical content.
+     */
+    #[test]
+    fn parse_001() {
+        let mut dir = env::temp_dir();
+        dir.push(format!("dep2j-cache-{}", process::id()));
+
+        let cache = Cache::new(dir.to_str().unwrap()).unwrap();
+        let data = b"a.o: a.c a.h\nb.o:\n";
+
+        let parse = |cache: &Cache| cache.parse(Vec::from(data.as_slice()));
+
+        assert_eq!(Ok(make_deps()), parse(&cache));
+
+        let path = dir.join(hash::to_hex(&hash::sha256(data)));
+        assert_eq!(Some(make_deps()), decode(&fs::read(&path).unwrap()));
+
+        /* Modify the entry to verify that it is actually used */
+        fs::write(&path, encode(&make_deps()[1..])).unwrap();
+        assert_eq!(Ok(Vec::from(&make_deps()[1..])), parse(&cache));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /**
+     * Cache::parse()
+     *
+     * Verify that the options of the parser are part of the entry, so a
+     * recipe line skipped before is rejected in strict mode.
+     */
+    #[test]
+    fn parse_002() {
+        let mut dir = env::temp_dir();
+        dir.push(format!("dep2j-cache-strict-{}", process::id()));
+
+        let mut cache = Cache::new(dir.to_str().unwrap()).unwrap();
+        let data = b"a.o: a.c\n\tcc -c a.c\n";
+        let parse = |cache: &Cache| cache.parse(Vec::from(data.as_slice()));
+
+        assert_eq!(1, parse(&cache).unwrap().len());
+
+        cache.set_strict(true);
+        cache.set_keep_going(true);
+
+        assert!(parse(&cache).is_err());
+        assert!(!dir
+            .join(format!(
+                "{}-strict-keep-going",
+                hash::to_hex(&hash::sha256(data))
+            ))
+            .exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,118 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::dependency::DependencyParser;
+use crate::separators;
+
+/*
+ * Parse the Makefile dependency file "data", e.g. written by "cc -MD", and
+ * return each of its prerequisites once in the order of appearance. The
+ * escaped spaces, tabs, '#', and "$$" of make are unescaped, so the
+ * results are the actual file names.
+ */
+pub fn prerequisites(data: Vec<u8>) -> Result<Vec<String>, String> {
+    let mut parser = DependencyParser::new();
+    let mut seen = HashSet::new();
+
+    parser.set_keep_going(true);
+
+    let deps = parser.feed(data)?;
+    let result = deps
+        .iter()
+        .flat_map(|dep| &dep.prerequisites)
+        .filter(|x| seen.insert(**x))
+        .map(|x| separators::unescape(x).into_owned())
+        .collect();
+
+    Ok(result)
+}
+
+/*
+ * Print a "cargo:rerun-if-changed" line for each prerequisite of the
+ * dependency file "path" in a build script. The paths are relative to the
+ * directory the compiler ran in. The dependency file itself is not printed, since it is usually
+ * rewritten by each build.
+ */
+pub fn rerun_if_changed<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    let data = fs::read(path)?;
+
+    let prereqs = prerequisites(data).map_err(|err| {
+        let msg = format!("failed to parse \"{}\": {err}", path.display());
+
+        io::Error::new(io::ErrorKind::InvalidData, msg)
+    })?;
+
+    let mut out = io::stdout().lock();
+
+    for prereq in prereqs {
+        writeln!(out, "cargo:rerun-if-changed={prereq}")?;
+    }
+
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * prerequisites()
+     *
+     * Verify that the function returns each prerequisite once, including
+     * those of continued lines.
+     */
+    #[test]
+    fn prerequisites_001() {
+        let data = b"a.o: a.c \\\n  a.h b.h\nb.o: b.c a.h\n";
+        let result = prerequisites(data.to_vec()).unwrap();
+
+        assert_eq!(["a.c", "a.h", "b.h", "b.c"], result.as_slice());
+    }
+
+    /**
+     * prerequisites()
+     *
+     * Verify that the function returns an error for invalid input instead
+     * of exiting.
+     */
+    #[test]
+    fn prerequisites_002() {
+        let data = b"a.o a.c\n";
+
+        assert!(prerequisites(data.to_vec()).is_err());
+    }
+
+    /**
+     * prerequisites()
+     *
+     * Verify that the function returns the actual file names of
+     * prerequisites with escaped spaces, '#', and "$$".
+     */
+    #[test]
+    fn prerequisites_003() {
+        let data = b"a.o: my\\ file.c \\#a.h cost$$.h\n";
+        let result = prerequisites(data.to_vec()).unwrap();
+
+        assert_eq!(["my file.c", "#a.h", "cost$.h"], result.as_slice());
+    }
+}
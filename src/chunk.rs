@@ -0,0 +1,285 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::hash_map::{Entry, HashMap};
+use std::collections::HashSet;
+use std::hash::BuildHasherDefault;
+use std::io::{self, Read};
+use std::mem;
+use std::rc::Rc;
+
+use crate::dependency::{Dependency, DependencyParser, MergeStrategy};
+use crate::hash::PathHasher;
+
+struct Rule {
+    target: u32,
+    prerequisites: Vec<u32>,
+    /* The number of prerequisites of the first chunk with this target */
+    first: usize,
+}
+
+/*
+ * The merged dependencies of all parsed chunks. Each distinct path is
+ * stored only once, so the memory usage depends on the size of the graph
+ * and not on the size of the input.
+ */
+struct Store {
+    ids: HashMap<Rc<str>, u32, BuildHasherDefault<PathHasher>>,
+    paths: Vec<Rc<str>>,
+    targets: HashMap<u32, usize>,
+    rules: Vec<Rule>,
+}
+
+impl Store {
+    fn intern(&mut self, path: &str) -> u32 {
+        if let Some(&id) = self.ids.get(path) {
+            return id;
+        }
+
+        let id = u32::try_from(self.paths.len()).expect("too many paths");
+        let path: Rc<str> = Rc::from(path);
+
+        self.ids.insert(Rc::clone(&path), id);
+        self.paths.push(path);
+
+        id
+    }
+
+    fn add(&mut self, dep: &Dependency) {
+        let target = self.intern(dep.target);
+        let prerequisites: Vec<u32> =
+            dep.prerequisites.iter().map(|x| self.intern(x)).collect();
+
+        match self.targets.entry(target) {
+            Entry::Occupied(entry) => {
+                let rule = &mut self.rules[*entry.get()];
+                rule.prerequisites.extend(prerequisites);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(self.rules.len());
+
+                self.rules.push(Rule {
+                    target,
+                    first: prerequisites.len(),
+                    prerequisites,
+                });
+            }
+        }
+    }
+
+    /*
+     * Remove the prerequisites which are duplicated by the chunks merged
+     * into a rule. The result is identical to merging all dependencies at
+     * once with the same strategy.
+     */
+    fn dedup(&mut self, strategy: MergeStrategy) {
+        let mut set = HashSet::new();
+
+        for rule in &mut self.rules {
+            match strategy {
+                MergeStrategy::Hash => {
+                    if rule.first == rule.prerequisites.len() {
+                        continue;
+                    }
+
+                    let tail = rule.prerequisites.split_off(rule.first);
+
+                    set.clear();
+                    set.extend(rule.prerequisites.iter().copied());
+
+                    let tail = tail.into_iter().filter(|x| set.insert(*x));
+                    rule.prerequisites.extend(tail);
+                }
+                MergeStrategy::Sort => {
+                    let paths = &self.paths;
+
+                    rule.prerequisites.sort_unstable_by(|a, b| {
+                        paths[*a as usize].cmp(&paths[*b as usize])
+                    });
+                    rule.prerequisites.dedup();
+                }
+            }
+
+            rule.first = rule.prerequisites.len();
+        }
+    }
+}
+
+/*
+ * Parse dependency files which do not fit into memory. The input is read
+ * in chunks which end on a rule boundary, i.e. a newline which is not
+ * escaped. Each chunk is parsed on its own and merged into the
+ * dependencies of the previous chunks. The inputs passed to "read()" are
+ * treated as if they were concatenated.
+ */
+pub struct ChunkParser {
+    parser: DependencyParser<'static>,
+    strategy: MergeStrategy,
+    chunk_size: u64,
+    carry: Vec<u8>,
+    store: Store,
+}
+
+impl ChunkParser {
+    pub fn new(chunk_size: u64, strategy: MergeStrategy) -> Self {
+        let mut parser = DependencyParser::new();
+        parser.set_merge_strategy(strategy);
+        parser.set_reject_directives(true);
+
+        Self {
+            parser,
+            strategy,
+            chunk_size: chunk_size.max(1),
+            carry: Vec::new(),
+            store: Store {
+                ids: HashMap::default(),
+                paths: Vec::new(),
+                targets: HashMap::new(),
+                rules: Vec::new(),
+            },
+        }
+    }
+
+    pub fn set_strict(&mut self, strict: bool) {
+        self.parser.set_strict(strict);
+    }
+
+    pub fn read<R: Read>(&mut self, mut reader: R) -> io::Result<()> {
+        loop {
+            let mut data = mem::take(&mut self.carry);
+            let mut chunk = reader.by_ref().take(self.chunk_size);
+
+            if chunk.read_to_end(&mut data)? == 0 {
+                self.carry = data;
+                return Ok(());
+            }
+
+            /* A rule exceeding the chunk size is read in several steps */
+            match boundary(&data) {
+                Some(pos) => {
+                    self.carry = data.split_off(pos);
+                    self.parse(data);
+                }
+                None => self.carry = data,
+            }
+        }
+    }
+
+    /*
+     * Parse the remaining input and return the merged dependencies.
+     */
+    pub fn finish(&mut self) -> Vec<Dependency<'_>> {
+        let data = mem::take(&mut self.carry);
+        self.parse(data);
+
+        self.store.dedup(self.strategy);
+
+        let paths = &self.store.paths;
+
+        self.store
+            .rules
+            .iter()
+            .map(|rule| Dependency {
+                target: &paths[rule.target as usize],
+                prerequisites: rule
+                    .prerequisites
+                    .iter()
+                    .map(|x| &*paths[*x as usize])
+                    .collect(),
+            })
+            .collect()
+    }
+
+    fn parse(&mut self, data: Vec<u8>) {
+        if data.is_empty() {
+            return;
+        }
+
+        for dep in self.parser.parse(data) {
+            self.store.add(dep);
+        }
+    }
+}
+
+/*
+ * Return the position after the last newline which terminates a rule.
+ */
+fn boundary(data: &[u8]) -> Option<usize> {
+    (0..data.len())
+        .rev()
+        .find(|&i| data[i] == b'\n' && (i == 0 || data[i - 1] != b'\\'))
+        .map(|i| i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn do_parse(data: &str, chunk_size: u64, strategy: MergeStrategy) {
+        let mut parser = DependencyParser::new();
+        parser.set_merge_strategy(strategy);
+
+        let expected = parser.parse(Vec::from(data.as_bytes()));
+
+        let mut chunked = ChunkParser::new(chunk_size, strategy);
+        let (first, second) = data.split_at(data.len() / 2);
+
+        chunked.read(first.as_bytes()).unwrap();
+        chunked.read(second.as_bytes()).unwrap();
+
+        assert_eq!(*expected, chunked.finish());
+    }
+
+    /**
+     * boundary()
+     *
+     * Verify that escaped newlines do not terminate a rule.
+     */
+    #[test]
+    fn boundary_001() {
+        assert_eq!(None, boundary(b""));
+        assert_eq!(None, boundary(b"a.o: a.c \\\n"));
+        assert_eq!(Some(1), boundary(b"\na.o: a.c \\\n"));
+        assert_eq!(Some(9), boundary(b"a.o: a.c\nb.o"));
+    }
+
+    /**
+     * ChunkParser::finish()
+     *
+     * Verify that parsing the input in chunks of any size yields the same
+     * dependencies as parsing all of it at once.
+     */
+    #[test]
+    fn finish_001() {
+        let data = "\
+a.o: a.c a.h a.h \\
+  b.h
+# comment
+b.o: b.c b.h
+a.o: c.h a.h b.h \\
+  d.h
+c.o: c.c
+b.o: a.h b.c
+";
+
+        for strategy in [MergeStrategy::Hash, MergeStrategy::Sort] {
+            for chunk_size in 1..data.len() + 1 {
+                do_parse(data, chunk_size as u64, strategy);
+            }
+        }
+    }
+}
@@ -0,0 +1,394 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+use std::env;
+use std::process::Command;
+
+use crate::dependency::Dependency;
+use crate::json::{Attributes, Value};
+
+/*
+ * Directories which contain the headers of the C library, the compiler
+ * resource directories of GCC and Clang, and the SDKs of Xcode.
+ */
+const SYSTEM_DIRS: &[&str] = &[
+    "/usr/include",
+    "/usr/local/include",
+    "/usr/lib/gcc",
+    "/usr/lib64/gcc",
+    "/usr/lib/clang",
+    "/usr/lib64/clang",
+    "/usr/lib/llvm",
+    "/Library/Developer",
+    "/Applications/Xcode.app",
+    "/System/Library/Frameworks",
+    "/Library/Frameworks",
+];
+
+/*
+ * The class of a file. The headers of the frameworks in the system
+ * locations, e.g. of the macOS SDK, are distinct from other system files.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Class {
+    Project,
+    System,
+    Framework,
+}
+
+impl Class {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Class::Project => "project",
+            Class::System => "system",
+            Class::Framework => "framework",
+        }
+    }
+}
+
+/*
+ * The SDKs are directories like "MacOSX14.0.sdk", which may be installed
+ * anywhere, e.g. for several versions of Xcode.
+ */
+fn is_sdk(path: &str) -> bool {
+    path.starts_with('/')
+        && path
+            .split('/')
+            .any(|x| x.len() > ".sdk".len() && x.ends_with(".sdk"))
+}
+
+/*
+ * Check for a header or a module map of a framework bundle, e.g.
+ * "Foo.framework/Headers/foo.h" or "Foo.framework/Versions/A/Headers/foo.h".
+ */
+fn is_framework_header(path: &str) -> bool {
+    let parts: Vec<&str> = path.split('/').collect();
+
+    parts.iter().enumerate().any(|(i, part)| {
+        if part.len() <= ".framework".len() || !part.ends_with(".framework") {
+            return false;
+        }
+
+        let mut rest = &parts[i + 1..];
+
+        if rest.len() > 2 && rest[0] == "Versions" {
+            rest = &rest[2..];
+        }
+
+        rest.len() > 1
+            && matches!(rest[0], "Headers" | "PrivateHeaders" | "Modules")
+    })
+}
+
+/*
+ * Return the installation directory and the first library directory from
+ * the output of "cc -print-search-dirs". The former is the resource
+ * directory of GCC, the latter the one of Clang.
+ */
+fn parse_search_dirs(output: &str) -> Vec<String> {
+    let mut dirs = Vec::new();
+
+    for line in output.lines() {
+        let dir = match line.split_once(": ") {
+            Some(("install", dir)) => dir,
+            Some(("libraries", dirs)) => {
+                let dirs = dirs.strip_prefix('=').unwrap_or(dirs);
+                dirs.split(':').next().unwrap_or_default()
+            }
+            _ => continue,
+        };
+
+        let dir = dir.trim().trim_end_matches('/');
+
+        if dir.starts_with('/') && !dirs.iter().any(|x| x == dir) {
+            dirs.push(String::from(dir));
+        }
+    }
+
+    dirs
+}
+
+/*
+ * Ask the compiler given by "CC", or "cc" by default, for the directories
+ * of its own headers. Nothing is returned if there is no such compiler.
+ */
+pub fn compiler_dirs() -> Vec<String> {
+    let cc = env::var("CC").unwrap_or_default();
+    let mut args = cc.split_whitespace();
+    let program = args.next().unwrap_or("cc");
+
+    let output = Command::new(program)
+        .args(args)
+        .arg("-print-search-dirs")
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => {
+            parse_search_dirs(&String::from_utf8_lossy(&output.stdout))
+        }
+        _ => Vec::new(),
+    }
+}
+
+/*
+ * Distinguish system files from project files by their location. Relative
+ * paths always belong to the project. The files of the SDKs are system
+ * files wherever the SDKs are installed.
+ */
+pub struct Classifier {
+    dirs: Vec<String>,
+}
+
+impl Classifier {
+    pub fn new(dirs: &[String]) -> Self {
+        let mut result = Self {
+            dirs: SYSTEM_DIRS.iter().map(|x| String::from(*x)).collect(),
+        };
+
+        for dir in dirs {
+            result.dirs.push(String::from(dir.trim_end_matches('/')));
+        }
+
+        result
+    }
+
+    pub fn is_system(&self, path: &str) -> bool {
+        let in_dir = |dir: &String| match path.strip_prefix(dir.as_str()) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        };
+
+        self.dirs.iter().any(in_dir) || is_sdk(path)
+    }
+
+    /*
+     * The headers of the frameworks built by the project are project
+     * files like any other.
+     */
+    pub fn class(&self, path: &str) -> Class {
+        if !self.is_system(path) {
+            Class::Project
+        } else if is_framework_header(path) {
+            Class::Framework
+        } else {
+            Class::System
+        }
+    }
+
+    pub fn attributes(
+        &self,
+        deps: &[Dependency],
+    ) -> HashMap<String, Attributes> {
+        let mut result = HashMap::new();
+
+        for &prereq in deps.iter().flat_map(|dep| &dep.prerequisites) {
+            if !result.contains_key(prereq) {
+                let class = self.class(prereq).as_str();
+
+                let attrs = Vec::from([("class", Value::String(class.into()))]);
+                result.insert(String::from(prereq), attrs);
+            }
+        }
+
+        result
+    }
+
+    /*
+     * Remove the prerequisites of the given classes.
+     */
+    pub fn filter<'a>(
+        &self,
+        deps: &[Dependency<'a>],
+        classes: &[Class],
+    ) -> Vec<Dependency<'a>> {
+        deps.iter()
+            .map(|dep| Dependency {
+                target: dep.target,
+                prerequisites: dep
+                    .prerequisites
+                    .iter()
+                    .filter(|x| !classes.contains(&self.class(x)))
+                    .copied()
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * Classifier::is_system()
+     *
+     * Verify that only files within the system directories are
+     * classified as system files.
+     */
+    #[test]
+    fn is_system_001() {
+        let classifier = Classifier::new(&[String::from("/opt/sdk/")]);
+
+        assert!(classifier.is_system("/usr/include/stdio.h"));
+        assert!(classifier.is_system("/opt/sdk/include/foo.h"));
+        assert!(!classifier.is_system("/usr/include2/stdio.h"));
+        assert!(!classifier.is_system("usr/include/stdio.h"));
+        assert!(!classifier.is_system("/home/user/main.c"));
+    }
+
+    /**
+     * parse_search_dirs()
+     *
+     * Verify that the resource directories of GCC and Clang are found.
+     */
+    #[test]
+    fn parse_search_dirs_001() {
+        let gcc = "\
+install: /opt/gcc/lib/gcc/arm-none-eabi/12/
+programs: =/opt/gcc/libexec/gcc/arm-none-eabi/12/:/opt/gcc/bin/
+libraries: =/opt/gcc/lib/gcc/arm-none-eabi/12/:/opt/gcc/arm-none-eabi/lib/
+";
+        let clang = "\
+programs: =/usr/bin
+libraries: =/usr/lib/llvm-14/lib/clang/14.0.6:/usr/lib/x86_64-linux-gnu
+";
+
+        assert_eq!(
+            Vec::from(["/opt/gcc/lib/gcc/arm-none-eabi/12"]),
+            parse_search_dirs(gcc)
+        );
+        assert_eq!(
+            Vec::from(["/usr/lib/llvm-14/lib/clang/14.0.6"]),
+            parse_search_dirs(clang)
+        );
+        assert!(parse_search_dirs("cc: error: unknown option\n").is_empty());
+    }
+
+    /**
+     * Classifier::filter()
+     *
+     * Verify that the function removes the prerequisites of the given
+     * class only.
+     */
+    #[test]
+    fn filter_001() {
+        let classifier = Classifier::new(&[]);
+
+        let vec = Vec::from([Dependency {
+            target: "main.o",
+            prerequisites: Vec::from(["main.c", "/usr/include/stdio.h"]),
+        }]);
+
+        let system = classifier.filter(&vec, &[Class::System]);
+        let project = classifier.filter(&vec, &[Class::Project]);
+
+        assert_eq!(Vec::from(["main.c"]), system[0].prerequisites);
+        assert_eq!(
+            Vec::from(["/usr/include/stdio.h"]),
+            project[0].prerequisites
+        );
+    }
+
+    /**
+     * Classifier::attributes()
+     *
+     * Verify that each prerequisite is tagged with its class.
+     */
+    #[test]
+    fn attributes_001() {
+        let classifier = Classifier::new(&[]);
+
+        let vec = Vec::from([Dependency {
+            target: "main.o",
+            prerequisites: Vec::from(["main.c", "/usr/include/stdio.h"]),
+        }]);
+
+        let map = classifier.attributes(&vec);
+
+        assert_eq!(
+            Vec::from([("class", Value::String("project".into()))]),
+            map["main.c"]
+        );
+        assert_eq!(
+            Vec::from([("class", Value::String("system".into()))]),
+            map["/usr/include/stdio.h"]
+        );
+    }
+
+    /**
+     * Classifier::class()
+     *
+     * Verify that the headers of the frameworks in the system locations
+     * and the SDKs are distinguished from other system files, while the
+     * frameworks of the project are project files.
+     */
+    #[test]
+    fn class_001() {
+        let classifier = Classifier::new(&[]);
+        let sdk = "/opt/Xcode-15.app/Contents/Developer/Platforms/\
+                   MacOSX.platform/Developer/SDKs/MacOSX14.0.sdk";
+
+        let data = [
+            ("/usr/include/stdio.h", Class::System),
+            (
+                "/System/Library/Frameworks/Foo.framework/Headers/foo.h",
+                Class::Framework,
+            ),
+            (
+                "/Library/Frameworks/Foo.framework/Versions/A/Headers/foo.h",
+                Class::Framework,
+            ),
+            (
+                &format!(
+                    "{sdk}/System/Library/Frameworks/Bar.framework/\
+                          Frameworks/Baz.framework/PrivateHeaders/baz.h"
+                ),
+                Class::Framework,
+            ),
+            (&format!("{sdk}/usr/include/stdlib.h"), Class::System),
+            (
+                "/System/Library/Frameworks/Foo.framework/Resources/a.txt",
+                Class::System,
+            ),
+            ("build/Foo.framework/Headers/foo.h", Class::Project),
+            ("/home/user/Foo.framework/Headers/foo.h", Class::Project),
+            ("/home/user/.sdk/foo.h", Class::Project),
+            ("main.c", Class::Project),
+        ];
+
+        for (path, class) in data {
+            assert_eq!(class, classifier.class(path), "{path}");
+        }
+
+        let vec = Vec::from([Dependency {
+            target: "main.o",
+            prerequisites: Vec::from([
+                "main.c",
+                "/usr/include/stdio.h",
+                "/System/Library/Frameworks/Foo.framework/Headers/foo.h",
+            ]),
+        }]);
+
+        let result = classifier.filter(&vec, &[Class::Framework]);
+
+        assert_eq!(
+            Vec::from(["main.c", "/usr/include/stdio.h"]),
+            result[0].prerequisites
+        );
+    }
+}
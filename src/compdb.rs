@@ -0,0 +1,236 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::dependency::Dependency;
+use crate::json::{Attributes, JsonParser, Value};
+
+struct Entry {
+    directory: String,
+    command: String,
+    output: Option<String>,
+}
+
+/*
+ * A compilation database as specified by
+ * https://clang.llvm.org/docs/JSONCompilationDatabase.html
+ *
+ * Each entry can be looked up by its output and by its source file. Both
+ * are registered as written in the database and resolved against the
+ * directory of the entry, since dependency files contain the paths as
+ * passed to the compiler.
+ */
+pub struct CompileDb {
+    entries: Vec<Entry>,
+    outputs: HashMap<String, usize>,
+    files: HashMap<String, usize>,
+}
+
+impl CompileDb {
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let value = JsonParser::new(data).parse()?;
+
+        let list = value
+            .as_array()
+            .ok_or_else(|| String::from("expected an array of entries"))?;
+
+        let mut db = Self {
+            entries: Vec::with_capacity(list.len()),
+            outputs: HashMap::with_capacity(2 * list.len()),
+            files: HashMap::with_capacity(2 * list.len()),
+        };
+
+        for (i, item) in list.iter().enumerate() {
+            let get = |key| item.get(key).and_then(Value::as_str);
+            let missing = |key| format!("entry {i}: missing \"{key}\"");
+
+            let directory =
+                get("directory").ok_or_else(|| missing("directory"))?;
+            let file = get("file").ok_or_else(|| missing("file"))?;
+
+            let command = match (get("command"), item.get("arguments")) {
+                (Some(command), _) => String::from(command),
+                (None, Some(Value::Array(args))) => join_arguments(args),
+                _ => return Err(missing("command")),
+            };
+
+            let output = get("output").map(String::from);
+
+            for path in resolve(directory, file) {
+                db.files.entry(path).or_insert(i);
+            }
+
+            if let Some(output) = &output {
+                for path in resolve(directory, output) {
+                    db.outputs.entry(path).or_insert(i);
+                }
+            }
+
+            db.entries.push(Entry {
+                directory: String::from(directory),
+                command,
+                output,
+            });
+        }
+
+        Ok(db)
+    }
+
+    fn find(&self, dep: &Dependency) -> Option<&Entry> {
+        let index = self.outputs.get(dep.target).or_else(|| {
+            dep.prerequisites.first().and_then(|x| self.files.get(*x))
+        })?;
+
+        Some(&self.entries[*index])
+    }
+
+    /*
+     * Match each target against the outputs of the entries or, if that
+     * fails, the first prerequisite against their source files.
+     */
+    pub fn attributes(&self, deps: &[Dependency]) -> Vec<Attributes> {
+        deps.iter()
+            .map(|dep| {
+                let entry = match self.find(dep) {
+                    Some(entry) => entry,
+                    None => return Attributes::new(),
+                };
+
+                let mut attrs = Vec::from([
+                    ("command", Value::String(entry.command.clone())),
+                    ("directory", Value::String(entry.directory.clone())),
+                ]);
+
+                if let Some(output) = &entry.output {
+                    attrs.push(("output", Value::String(output.clone())));
+                }
+
+                attrs
+            })
+            .collect()
+    }
+}
+
+fn resolve(directory: &str, path: &str) -> Vec<String> {
+    let mut result = Vec::from([String::from(path)]);
+
+    if Path::new(path).is_relative() {
+        let joined = Path::new(directory).join(path);
+        result.push(joined.to_string_lossy().into_owned());
+    }
+
+    result
+}
+
+/*
+ * Turn the "arguments" of an entry into a command line. Arguments which
+ * would be split by a shell are quoted.
+ */
+fn join_arguments(args: &[Value]) -> String {
+    let args = args.iter().filter_map(Value::as_str).map(|arg| {
+        let safe =
+            |c: char| c.is_ascii_alphanumeric() || "-_./=+:,@%".contains(c);
+
+        if !arg.is_empty() && arg.chars().all(safe) {
+            String::from(arg)
+        } else {
+            format!("'{}'", arg.replace('\'', "'\\''"))
+        }
+    });
+
+    args.collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * CompileDb::attributes()
+     *
+     * Verify that targets are matched against the outputs of the entries
+     * and first prerequisites against their source files.
+     */
+    #[test]
+    fn attributes_001() {
+        let data = br#"[
+            {
+                "directory": "/build",
+                "file": "/src/a.c",
+                "command": "cc -c /src/a.c -o a.o",
+                "output": "a.o"
+            },
+            {
+                "directory": "/build",
+                "file": "b.c",
+                "arguments": ["cc", "-DX=\"1 2\"", "-c", "b.c"]
+            }
+        ]"#;
+
+        let db = CompileDb::parse(data).unwrap();
+
+        let deps = Vec::from([
+            Dependency {
+                target: "/build/a.o",
+                prerequisites: Vec::from(["/src/a.c"]),
+            },
+            Dependency {
+                target: "b.o",
+                prerequisites: Vec::from(["b.c"]),
+            },
+            Dependency {
+                target: "c.o",
+                prerequisites: Vec::from(["c.c"]),
+            },
+        ]);
+
+        let attrs = db.attributes(&deps);
+
+        assert_eq!(3, attrs.len());
+        assert_eq!(
+            Vec::from([
+                ("command", Value::String("cc -c /src/a.c -o a.o".into())),
+                ("directory", Value::String("/build".into())),
+                ("output", Value::String("a.o".into())),
+            ]),
+            attrs[0]
+        );
+        assert_eq!(
+            Vec::from([
+                ("command", Value::String("cc '-DX=\"1 2\"' -c b.c".into())),
+                ("directory", Value::String("/build".into())),
+            ]),
+            attrs[1]
+        );
+        assert!(attrs[2].is_empty());
+    }
+
+    /**
+     * CompileDb::parse()
+     *
+     * Verify that the function rejects entries without a command.
+     */
+    #[test]
+    fn parse_001() {
+        let data = br#"[{"directory": "/", "file": "a.c"}]"#;
+
+        assert!(CompileDb::parse(data).is_err());
+        assert!(CompileDb::parse(b"{}").is_err());
+    }
+}
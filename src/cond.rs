@@ -0,0 +1,365 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::str;
+
+use crate::vars::Variables;
+
+const KEYWORDS: &[&[u8]] =
+    &[b"ifeq", b"ifneq", b"ifdef", b"ifndef", b"else", b"endif"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Branch {
+    Taken,
+    Skipped,
+    Unknown,
+}
+
+/*
+ * The state of a conditional. If a condition cannot be decided, e.g.
+ * because it depends on a variable which is not defined on the command
+ * line, the lines of all of its branches are kept.
+ */
+struct Frame {
+    branch: Branch,
+    taken: bool,
+    unknown: bool,
+}
+
+impl Frame {
+    fn new(branch: Branch) -> Self {
+        Self {
+            branch,
+            taken: branch == Branch::Taken,
+            unknown: branch == Branch::Unknown,
+        }
+    }
+
+    fn next(&mut self, branch: Branch) {
+        self.branch = match (self.taken, self.unknown, branch) {
+            (true, _, _) | (_, _, Branch::Skipped) => Branch::Skipped,
+            (false, true, _) => Branch::Unknown,
+            (false, false, _) => branch,
+        };
+
+        self.taken |= self.branch == Branch::Taken;
+        self.unknown |= self.branch == Branch::Unknown;
+    }
+}
+
+fn split_keyword(line: &[u8]) -> Option<(&'static [u8], &[u8])> {
+    let start = line.iter().position(|&x| x != b' ')?;
+    let line = &line[start..];
+
+    KEYWORDS.iter().find_map(|keyword| {
+        let rest = line.strip_prefix(*keyword)?;
+
+        match rest.first() {
+            None | Some(b' ' | b'\t' | b'\r' | b'\n') => Some((*keyword, rest)),
+            _ => None,
+        }
+    })
+}
+
+/*
+ * Return whether "line" starts with a conditional directive. It is
+ * sufficient to pass the first few bytes of a line.
+ */
+pub fn is_directive(line: &[u8]) -> bool {
+    split_keyword(line).is_some()
+}
+
+/*
+ * Return the keyword of the conditional directive "line" starts with.
+ */
+pub fn keyword(line: &[u8]) -> Option<&'static [u8]> {
+    split_keyword(line).map(|(keyword, _)| keyword)
+}
+
+/*
+ * Return whether "data" might contain a conditional directive. Any
+ * keyword is looked for, so that unbalanced conditionals are reported.
+ */
+pub fn has_directives(data: &[u8]) -> bool {
+    data.windows(4).any(|x| {
+        matches!(x, b"ifeq" | b"ifne" | b"ifde" | b"ifnd" | b"else" | b"endi")
+    })
+}
+
+/*
+ * Split the arguments of "ifeq" and "ifneq", which are given either as
+ * "(a,b)" or as two quoted strings.
+ */
+fn split_args(args: &str) -> Option<(&str, &str)> {
+    if let Some(args) = args.strip_prefix('(') {
+        let args = args.strip_suffix(')')?;
+        let mut depth = 0;
+
+        for (i, c) in args.char_indices() {
+            match c {
+                '(' | '{' => depth += 1,
+                ')' | '}' => depth -= 1,
+                ',' if depth == 0 => {
+                    return Some((args[..i].trim(), args[i + 1..].trim()));
+                }
+                _ => {}
+            }
+        }
+
+        return None;
+    }
+
+    let quoted = |x: &str| -> Option<(usize, usize)> {
+        let quote = x.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let end = x[1..].find(quote)? + 1;
+
+        Some((1, end))
+    };
+
+    let (begin, end) = quoted(args)?;
+    let first = &args[begin..end];
+
+    let rest = args[end + 1..].trim_start();
+    let (begin, end) = quoted(rest)?;
+
+    rest[end + 1..]
+        .trim()
+        .is_empty()
+        .then_some((first, &rest[begin..end]))
+}
+
+fn evaluate(
+    keyword: &[u8],
+    args: &str,
+    vars: Option<&Variables>,
+) -> Result<Branch, String> {
+    let decide = |value: bool| match value {
+        true => Branch::Taken,
+        false => Branch::Skipped,
+    };
+
+    match keyword {
+        b"ifdef" | b"ifndef" => {
+            let value = vars.and_then(|x| x.get(args));
+
+            Ok(match value {
+                Some(value) => {
+                    decide(value.is_empty() == (keyword == b"ifndef"))
+                }
+                None => Branch::Unknown,
+            })
+        }
+        _ => {
+            let (lhs, rhs) = split_args(args)
+                .ok_or_else(|| format!("invalid arguments \"{args}\""))?;
+
+            let expand = |x: &str| match vars {
+                Some(vars) => vars.expand(x).into_owned(),
+                None => String::from(x),
+            };
+
+            let (lhs, rhs) = (expand(lhs), expand(rhs));
+
+            if lhs.contains("$(") || rhs.contains("$(") {
+                return Ok(Branch::Unknown);
+            }
+
+            if lhs.contains("${") || rhs.contains("${") {
+                return Ok(Branch::Unknown);
+            }
+
+            Ok(decide((lhs == rhs) == (keyword == b"ifeq")))
+        }
+    }
+}
+
+/*
+ * Remove the conditional directives of Makefile-style input and the lines
+ * of the branches which are not taken. A condition is decided with the
+ * variables defined on the command line. Removed lines are replaced with
+ * empty lines to keep the line numbers.
+ */
+pub fn preprocess(
+    data: &[u8],
+    vars: Option<&Variables>,
+) -> Result<Vec<u8>, String> {
+    let mut buf = Vec::with_capacity(data.len());
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut continued = false;
+
+    for (i, line) in data.split_inclusive(|&x| x == b'\n').enumerate() {
+        let directive = match continued {
+            true => None,
+            false => split_keyword(line),
+        };
+
+        continued = line.ends_with(b"\\\n") || line.ends_with(b"\\\r\n");
+
+        let (keyword, rest) = match directive {
+            Some(val) => val,
+            None => {
+                match stack.iter().all(|x| x.branch != Branch::Skipped) {
+                    true => buf.extend_from_slice(line),
+                    false if line.ends_with(b"\n") => buf.push(b'\n'),
+                    false => {}
+                }
+
+                continue;
+            }
+        };
+
+        let error = |msg: &str| format!("line {}: {msg}", i + 1);
+        let rest = str::from_utf8(rest)
+            .map_err(|_| error("invalid UTF-8"))?
+            .trim();
+
+        match keyword {
+            b"else" => {
+                let frame = stack
+                    .last_mut()
+                    .ok_or_else(|| error("\"else\" without conditional"))?;
+
+                let branch = match split_keyword(rest.as_bytes()) {
+                    None if rest.is_empty() => Branch::Taken,
+                    Some((keyword, args)) if keyword.starts_with(b"if") => {
+                        let args = str::from_utf8(args).unwrap().trim();
+                        evaluate(keyword, args, vars).map_err(|x| error(&x))?
+                    }
+                    _ => return Err(error("invalid \"else\"")),
+                };
+
+                frame.next(branch);
+            }
+            b"endif" => {
+                stack
+                    .pop()
+                    .ok_or_else(|| error("\"endif\" without conditional"))?;
+            }
+            _ => {
+                let branch =
+                    evaluate(keyword, rest, vars).map_err(|x| error(&x))?;
+
+                stack.push(Frame::new(branch));
+            }
+        }
+
+        if line.ends_with(b"\n") {
+            buf.push(b'\n');
+        }
+    }
+
+    match stack.is_empty() {
+        true => Ok(buf),
+        false => Err(String::from("missing \"endif\"")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn do_preprocess(data: &str, defines: &[&str]) -> Result<String, String> {
+        let defines: Vec<String> =
+            defines.iter().map(|x| String::from(*x)).collect();
+        let vars = Variables::new(&defines).unwrap();
+
+        preprocess(data.as_bytes(), Some(&vars))
+            .map(|x| String::from_utf8(x).unwrap())
+    }
+
+    /**
+     * split_args()
+     *
+     * Verify that both forms of arguments of "ifeq" are supported.
+     */
+    #[test]
+    fn split_args_001() {
+        assert_eq!(Some(("a", "b")), split_args("(a, b)"));
+        assert_eq!(Some(("$(X,y)", "")), split_args("($(X,y),)"));
+        assert_eq!(Some(("a b", "c")), split_args("\"a b\" 'c'"));
+        assert_eq!(None, split_args("(a b)"));
+        assert_eq!(None, split_args("\"a\" \"b\" c"));
+    }
+
+    /**
+     * preprocess()
+     *
+     * Verify that decidable conditions are honored and that the branches
+     * of undecidable conditions are kept.
+     */
+    #[test]
+    fn preprocess_001() {
+        let data = "\
+ifeq ($(ARCH),x86)
+a.o: x86.h
+else ifdef DEBUG
+a.o: debug.h
+else
+a.o: arm.h
+endif
+ifdef UNKNOWN
+b.o: b.h
+  ifneq \"$(ARCH)\" \"x86\"
+b.o: c.h
+  endif
+endif
+";
+
+        let expected = "\na.o: x86.h\n\n\n\n\n\n\nb.o: b.h\n\n\n\n\n";
+        assert_eq!(
+            Ok(String::from(expected)),
+            do_preprocess(data, &["ARCH=x86"])
+        );
+
+        let expected = "\n\n\na.o: debug.h\n\n\n\n\nb.o: b.h\n\nb.o: c.h\n\n\n";
+        assert_eq!(
+            Ok(String::from(expected)),
+            do_preprocess(data, &["ARCH=arm", "DEBUG=1"])
+        );
+
+        let expected = "\na.o: x86.h\n\na.o: debug.h\n\na.o: arm.h\n\n\nb.o: b.h\n\nb.o: c.h\n\n\n";
+        assert_eq!(Ok(String::from(expected)), do_preprocess(data, &[]));
+    }
+
+    /**
+     * preprocess()
+     *
+     * Verify that unbalanced conditionals are rejected.
+     */
+    #[test]
+    fn preprocess_002() {
+        assert!(do_preprocess("ifdef A\n", &[]).is_err());
+        assert!(do_preprocess("else\n", &[]).is_err());
+        assert!(do_preprocess("endif\n", &[]).is_err());
+        assert!(do_preprocess("ifeq a\nendif\n", &[]).is_err());
+        assert!(do_preprocess("else: a\nendif.o: b\n", &[]).is_ok());
+    }
+
+    /**
+     * has_directives()
+     *
+     * Verify that a conditional is found by any of its keywords.
+     */
+    #[test]
+    fn has_directives_001() {
+        assert!(has_directives(b"ifdef A\na.o: a.h\n"));
+        assert!(has_directives(b"a.o: a.h\nifndef A\n"));
+        assert!(has_directives(b"else\n"));
+        assert!(has_directives(b"endif\n"));
+        assert!(!has_directives(b"a.o: a.c a.h\n"));
+    }
+}
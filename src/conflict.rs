@@ -0,0 +1,172 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::dependency::Dependency;
+
+/*
+ * A target whose rules in different inputs have different prerequisites.
+ * The difference contains the prerequisites which are missing in at least
+ * one of the inputs.
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct Conflict {
+    pub target: String,
+    pub inputs: Vec<String>,
+    pub difference: Vec<String>,
+}
+
+/*
+ * Collect the rules of each input to find the targets which are defined
+ * differently by several inputs, e.g. due to a stale dependency file of an
+ * aborted build. Rules of the same input are merged like usual.
+ */
+#[derive(Default)]
+pub struct ConflictFinder {
+    inputs: Vec<String>,
+    targets: Vec<String>,
+    rules: HashMap<String, Vec<(usize, BTreeSet<String>)>>,
+}
+
+impl ConflictFinder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, input: &str, deps: &[Dependency]) {
+        let index = self.inputs.len();
+        self.inputs.push(String::from(input));
+
+        for dep in deps {
+            let rules = match self.rules.get_mut(dep.target) {
+                Some(val) => val,
+                None => {
+                    self.targets.push(String::from(dep.target));
+                    self.rules.entry(dep.target.into()).or_default()
+                }
+            };
+
+            if rules.last().is_none_or(|(i, _)| *i != index) {
+                rules.push((index, BTreeSet::new()));
+            }
+
+            let (_, set) = rules.last_mut().unwrap();
+            set.extend(dep.prerequisites.iter().map(|x| String::from(*x)));
+        }
+    }
+
+    /*
+     * Return the conflicts in the order in which the targets were found.
+     */
+    pub fn finish(self) -> Vec<Conflict> {
+        let mut result = Vec::new();
+
+        for target in self.targets {
+            let rules = &self.rules[&target];
+            let first = &rules[0].1;
+
+            if rules.iter().all(|(_, set)| set == first) {
+                continue;
+            }
+
+            let union: BTreeSet<_> =
+                rules.iter().flat_map(|(_, set)| set).collect();
+
+            let difference = union
+                .into_iter()
+                .filter(|x| rules.iter().any(|(_, set)| !set.contains(*x)))
+                .cloned()
+                .collect();
+
+            let inputs =
+                rules.iter().map(|(i, _)| self.inputs[*i].clone()).collect();
+
+            result.push(Conflict {
+                target,
+                inputs,
+                difference,
+            });
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * ConflictFinder::finish()
+     *
+     * Verify that only targets with different prerequisites in several
+     * inputs are reported and that the rules of one input are merged.
+     */
+    #[test]
+    fn finish_001() {
+        let mut finder = ConflictFinder::new();
+
+        finder.add(
+            "x.d",
+            &[
+                Dependency {
+                    target: "a.o",
+                    prerequisites: Vec::from(["a.c", "a.h"]),
+                },
+                Dependency {
+                    target: "b.o",
+                    prerequisites: Vec::from(["b.c"]),
+                },
+                Dependency {
+                    target: "b.o",
+                    prerequisites: Vec::from(["b.h"]),
+                },
+            ],
+        );
+
+        finder.add(
+            "y.d",
+            &[
+                Dependency {
+                    target: "b.o",
+                    prerequisites: Vec::from(["b.h", "b.c"]),
+                },
+                Dependency {
+                    target: "a.o",
+                    prerequisites: Vec::from(["a.c", "old.h"]),
+                },
+            ],
+        );
+
+        finder.add(
+            "z.d",
+            &[Dependency {
+                target: "c.o",
+                prerequisites: Vec::from(["c.c"]),
+            }],
+        );
+
+        let expected = Vec::from([Conflict {
+            target: String::from("a.o"),
+            inputs: Vec::from([String::from("x.d"), String::from("y.d")]),
+            difference: Vec::from([String::from("a.h"), String::from("old.h")]),
+        }]);
+
+        assert_eq!(expected, finder.finish());
+    }
+}
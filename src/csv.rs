@@ -0,0 +1,112 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dependency::Dependency;
+
+/*
+ * Generate comma-separated values as described in RFC 4180 with one row
+ * for each pair of target and prerequisite. Targets without prerequisites
+ * get a row with an empty prerequisite.
+ */
+pub struct CsvSerializer {
+    buf: Vec<u8>,
+}
+
+impl CsvSerializer {
+    pub fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    pub fn get_csv(&self) -> &[u8] {
+        self.buf.as_slice()
+    }
+
+    pub fn write_vec(&mut self, vec: &[Dependency]) {
+        self.buf.reserve(256 * vec.len());
+        self.buf.extend_from_slice(b"target,prerequisite\r\n");
+
+        for dep in vec {
+            if dep.prerequisites.is_empty() {
+                self.write_row(dep.target, "");
+            }
+
+            for prereq in &dep.prerequisites {
+                self.write_row(dep.target, prereq);
+            }
+        }
+    }
+
+    fn write_row(&mut self, target: &str, prereq: &str) {
+        self.write_field(target);
+        self.buf.push(b',');
+        self.write_field(prereq);
+        self.buf.extend_from_slice(b"\r\n");
+    }
+
+    /*
+     * Fields containing a delimiter, a quote, or a line break are quoted
+     * and their quotes are doubled.
+     */
+    fn write_field(&mut self, data: &str) {
+        if !data.contains([',', '"', '\r', '\n']) {
+            self.buf.extend_from_slice(data.as_bytes());
+            return;
+        }
+
+        self.buf.push(b'"');
+        self.buf
+            .extend_from_slice(data.replace('"', "\"\"").as_bytes());
+        self.buf.push(b'"');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * CsvSerializer::write_vec()
+     *
+     * Verify that each prerequisite is written as a row, that fields are
+     * quoted if necessary, and that a target without prerequisites is
+     * written with an empty prerequisite.
+     */
+    #[test]
+    fn write_vec_001() {
+        let vec = Vec::from([
+            Dependency {
+                target: "a.o",
+                prerequisites: Vec::from(["a.c", "x,\"y\".h"]),
+            },
+            Dependency {
+                target: "b.o",
+                prerequisites: Vec::new(),
+            },
+        ]);
+
+        let mut serializer = CsvSerializer::new();
+        serializer.write_vec(&vec);
+
+        assert_eq!(
+            "target,prerequisite\r\n\
+            a.o,a.c\r\n\
+            a.o,\"x,\"\"y\"\".h\"\r\n\
+            b.o,\r\n",
+            String::from_utf8_lossy(serializer.get_csv())
+        );
+    }
+}
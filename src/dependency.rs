@@ -18,12 +18,13 @@
 use std::collections::{hash_map::Entry, HashMap, HashSet};
 use std::hash::BuildHasherDefault;
 use std::process::exit;
-use std::{cmp, mem, ptr, str};
+use std::{cmp, error, fmt, mem, ptr, str};
 
 use crate::hash::PathHasher;
+use crate::intern::Interner;
 
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Dependency<'a> {
     pub target: &'a str,
     pub prerequisites: Vec<&'a str>,
@@ -31,16 +32,176 @@ pub struct Dependency<'a> {
 
 impl<'a> Dependency<'a> {
     pub fn new(name: &'a str) -> Self {
+        /*
+         * Most rules only have a handful of prerequisites. Preallocating a
+         * large capacity wastes memory for every target, so just rely on
+         * the growth policy of the vector.
+         */
         Self {
             target: name,
-            prerequisites: Vec::with_capacity(32),
+            prerequisites: Vec::new(),
+        }
+    }
+
+    pub fn to_buf(&self) -> DependencyBuf {
+        DependencyBuf {
+            target: String::from(self.target),
+            prerequisites: self
+                .prerequisites
+                .iter()
+                .map(|x| String::from(*x))
+                .collect(),
+        }
+    }
+}
+
+/*
+ * An owned version of a dependency which does not borrow from the
+ * parsed data, e.g. to keep it in a cache.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DependencyBuf {
+    pub target: String,
+    pub prerequisites: Vec<String>,
+}
+
+impl DependencyBuf {
+    pub fn as_dependency(&self) -> Dependency<'_> {
+        Dependency {
+            target: &self.target,
+            prerequisites: self
+                .prerequisites
+                .iter()
+                .map(|x| x.as_str())
+                .collect(),
+        }
+    }
+}
+
+/*
+ * Apply "f" to the targets and prerequisites. Afterwards, different
+ * targets may be identical and have to be merged again.
+ */
+pub fn map_paths<F>(deps: &[Dependency], f: F) -> Vec<DependencyBuf>
+where
+    F: Fn(&str) -> String,
+{
+    deps.iter()
+        .map(|dep| DependencyBuf {
+            target: f(dep.target),
+            prerequisites: dep.prerequisites.iter().map(|x| f(x)).collect(),
+        })
+        .collect()
+}
+
+/*
+ * The strategy to remove duplicated prerequisites when merging the rules
+ * of a target. "Hash" preserves the order of the prerequisites, "Sort"
+ * sorts them and is faster for targets with huge lists of prerequisites.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeStrategy {
+    Hash,
+    Sort,
+}
+
+impl MergeStrategy {
+    pub fn merge(self, deps: Vec<Dependency>) -> Vec<Dependency> {
+        match self {
+            MergeStrategy::Hash => merge(deps),
+            MergeStrategy::Sort => merge_sorted(deps),
         }
     }
 }
 
+/*
+ * What to do with a target which has several rules. "Union" combines the
+ * prerequisites of all rules, "First" and "Last" keep only the first or
+ * the last rule, and "Error" rejects rules of the same target with
+ * different prerequisites.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergePolicy {
+    Union,
+    First,
+    Last,
+    Error,
+}
+
+impl MergePolicy {
+    /*
+     * Remove the rules which are not kept by the policy. The remaining
+     * rules still have to be merged with "MergeStrategy", which removes
+     * duplicated prerequisites. A target keeps the position of its first
+     * rule.
+     */
+    pub fn apply<'a>(
+        self,
+        deps: Vec<Dependency<'a>>,
+    ) -> Result<Vec<Dependency<'a>>, String> {
+        if self == MergePolicy::Union {
+            return Ok(deps);
+        }
+
+        let hasher = BuildHasherDefault::<PathHasher>::default();
+        let mut targets: HashMap<&str, usize, _> =
+            HashMap::with_capacity_and_hasher(deps.len(), hasher);
+        let mut result: Vec<Dependency> = Vec::with_capacity(deps.len());
+
+        for dep in deps {
+            let index = match targets.entry(dep.target) {
+                Entry::Occupied(entry) => *entry.get(),
+                Entry::Vacant(entry) => {
+                    entry.insert(result.len());
+                    result.push(dep);
+                    continue;
+                }
+            };
+
+            let prev = &mut result[index];
+
+            match self {
+                MergePolicy::Last => *prev = dep,
+                MergePolicy::Error
+                    if prev.prerequisites != dep.prerequisites =>
+                {
+                    let target = dep.target;
+
+                    return Err(format!(
+                        "target \"{target}\" has rules with different \
+                         prerequisites"
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/*
+ * Chunks are used with "--chunk-size" and for input exceeding
+ * "--max-memory", so the error names both.
+ */
+const IN_CHUNKS: &str = "cannot be parsed in chunks, see \"--chunk-size\" \
+                         and \"--max-memory\"";
+
+/*
+ * The parser keeps the buffers passed to it, so the parsed dependencies
+ * can borrow their paths from them until the next "reset()".
+ */
 pub struct DependencyParser<'a> {
-    data: Vec<u8>,
+    data: Vec<Vec<u8>>,
     deps: Vec<Dependency<'a>>,
+    strategy: MergeStrategy,
+    policy: MergePolicy,
+    strict: bool,
+    keep_going: bool,
+    reject_directives: bool,
+    collect_warnings: bool,
+    warnings: Vec<String>,
+    error: Option<String>,
 }
 
 impl<'a> DependencyParser<'a> {
@@ -48,12 +209,125 @@ impl<'a> DependencyParser<'a> {
         Self {
             data: Vec::new(),
             deps: Vec::new(),
+            strategy: MergeStrategy::Hash,
+            policy: MergePolicy::Union,
+            strict: false,
+            keep_going: false,
+            reject_directives: false,
+            collect_warnings: false,
+            warnings: Vec::new(),
+            error: None,
         }
     }
 
+    pub fn set_merge_strategy(&mut self, strategy: MergeStrategy) {
+        self.strategy = strategy;
+    }
+
+    pub fn set_merge_policy(&mut self, policy: MergePolicy) {
+        self.policy = policy;
+    }
+
+    /*
+     * In strict mode, the input has to be a plain dependency file. Recipe
+     * lines of a Makefile are rejected instead of being skipped.
+     */
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /*
+     * By default, invalid input is fatal. If "keep_going" is set, parsing
+     * stops at the first error instead, which is returned by "feed()".
+     */
+    pub fn set_keep_going(&mut self, keep_going: bool) {
+        self.keep_going = keep_going;
+    }
+
+    /*
+     * Include and conditional directives are resolved before the input is
+     * passed to the parser. If the input is parsed in chunks, they cannot
+     * be resolved, so they are rejected instead of being skipped.
+     */
+    pub fn set_reject_directives(&mut self, reject: bool) {
+        self.reject_directives = reject;
+    }
+
+    /*
+     * Keep the warnings about the input instead of printing them, so the
+     * caller can retrieve them with "take_warnings()".
+     */
+    pub fn set_collect_warnings(&mut self, collect: bool) {
+        self.collect_warnings = collect;
+    }
+
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        mem::take(&mut self.warnings)
+    }
+
     #[must_use]
     pub fn parse(&mut self, data: Vec<u8>) -> &Vec<Dependency> {
-        self.data = data;
+        self.reset();
+        self.parse_buffer(data);
+        self.merge_deps();
+
+        &self.deps
+    }
+
+    /*
+     * Discard the buffers and the dependencies parsed so far, but keep the
+     * allocated memory for the next input.
+     */
+    pub fn reset(&mut self) {
+        self.data.clear();
+        self.deps.clear();
+        self.error = None;
+    }
+
+    /*
+     * Parse "data" and add its rules to those of the buffers fed since the
+     * last "reset()", which are merged by "finish()". The unmerged rules of
+     * "data" are returned, so the caller can attribute them to its input.
+     * If the parser keeps going, the rules of a buffer with an error are
+     * discarded.
+     */
+    pub fn feed(&mut self, data: Vec<u8>) -> Result<&[Dependency<'_>], String> {
+        let first = self.deps.len();
+
+        self.parse_buffer(data);
+
+        if let Some(err) = &self.error {
+            self.deps.truncate(first);
+            return Err(err.clone());
+        }
+
+        Ok(&self.deps[first..])
+    }
+
+    /*
+     * Merge the rules of all buffers fed so far. More buffers may be fed
+     * afterwards.
+     */
+    #[must_use]
+    pub fn finish(&mut self) -> &Vec<Dependency<'_>> {
+        self.merge_deps();
+
+        &self.deps
+    }
+
+    fn parse_buffer(&mut self, data: Vec<u8>) {
+        /*
+         * The paths are split at ASCII characters only, so they are valid
+         * UTF-8 if the input is. Invalid sequences are replaced to uphold
+         * this for arbitrary input.
+         */
+        let data = match String::from_utf8(data) {
+            Ok(val) => val.into_bytes(),
+            Err(err) => {
+                let data = err.into_bytes();
+                String::from_utf8_lossy(&data).into_owned().into_bytes()
+            }
+        };
 
         if self.deps.capacity() == 0 {
             /*
@@ -61,21 +335,30 @@ impl<'a> DependencyParser<'a> {
              * input. We just assume that a dependency occurs an specific
              * amount of characters to reflect this and get a rough estimate.
              */
-            let estimate = cmp::max(1 + self.data.len() / 256, 16);
+            let estimate = cmp::max(1 + data.len() / 256, 16);
             self.deps.reserve(estimate);
         }
 
-        self.deps.clear();
+        /*
+         * Moving the buffer into "data" does not move its content, which
+         * the dependencies point to.
+         */
+        self.data.push(data);
+        self.error = None;
         self.parse_rules();
-        self.merge_deps();
+    }
 
-        &self.deps
+    /*
+     * Return the buffer which is currently being parsed.
+     */
+    fn buffer(&self) -> &[u8] {
+        self.data.last().map_or(&[], Vec::as_slice)
     }
 
     fn parse_rules(&mut self) {
         unsafe {
-            let mut ptr = self.data.as_ptr();
-            let end = ptr.add(self.data.len());
+            let mut ptr = self.buffer().as_ptr();
+            let end = ptr.add(self.buffer().len());
 
             while ptr < end {
                 match *ptr {
@@ -96,46 +379,14 @@ impl<'a> DependencyParser<'a> {
     }
 
     fn merge_deps(&mut self) {
-        type DependencyMap<'a> = HashMap<&'a str, usize>;
-        type StrHashSet<'a> = HashSet<&'a str, BuildHasherDefault<PathHasher>>;
-        type PrerequisiteMap<'a> = HashMap<&'a str, StrHashSet<'a>>;
-
-        let len = self.deps.len();
-        let deps = mem::replace(&mut self.deps, Vec::with_capacity(len));
-        let mut deps_map: DependencyMap = HashMap::with_capacity(len);
-        let mut prereq_map: PrerequisiteMap = HashMap::with_capacity(len);
-
-        for dep in deps {
-            match deps_map.entry(dep.target) {
-                Entry::Occupied(entry) => {
-                    let merged_dep = &mut self.deps[*entry.get()];
-                    let set = prereq_map.get_mut(merged_dep.target).unwrap();
-
-                    set.reserve(dep.prerequisites.len());
-
-                    for &prereq in &dep.prerequisites {
-                        if set.insert(prereq) {
-                            merged_dep.prerequisites.push(prereq);
-                        }
-                    }
-                }
-                Entry::Vacant(entry) => {
-                    let hasher = BuildHasherDefault::<PathHasher>::default();
-                    let mut set = HashSet::with_hasher(hasher);
+        let deps = mem::take(&mut self.deps);
 
-                    let capacity = 2 * dep.prerequisites.len();
-                    set.reserve(capacity);
+        let deps = self.policy.apply(deps).unwrap_or_else(|err| {
+            error!("invalid-input", "{err}");
+            exit(1);
+        });
 
-                    for &prereq in &dep.prerequisites {
-                        set.insert(prereq);
-                    }
-
-                    entry.insert(self.deps.len());
-                    prereq_map.insert(dep.target, set);
-                    self.deps.push(dep);
-                }
-            };
-        }
+        self.deps = self.strategy.merge(deps);
     }
 
     unsafe fn parse_rule(
@@ -147,12 +398,22 @@ impl<'a> DependencyParser<'a> {
 
         while ptr < end {
             match *ptr {
-                b' ' | b'\n' => {
+                b'\t' if self.is_line_start(ptr) => {
+                    ptr = self.skip_recipe(ptr, end);
+                }
+                b' ' | b'\t' | b'\n' => {
                     ptr = ptr.add(1);
                 }
                 b'#' => {
                     ptr = util::skip_comment(ptr, end);
                 }
+                b'i' | b'-' | b's' if util::is_include(ptr, end) => {
+                    ptr = self.skip_include(ptr, end);
+                }
+                b'i' | b'e' if util::is_conditional(ptr, end) => {
+                    /* Conditionals are evaluated before, see "cond" */
+                    ptr = self.skip_conditional(ptr, end);
+                }
                 _ => {
                     ptr = self.parse_targets(ptr, end);
                 }
@@ -162,6 +423,96 @@ impl<'a> DependencyParser<'a> {
         ptr
     }
 
+    unsafe fn is_line_start(&self, ptr: *const u8) -> bool {
+        ptr == self.buffer().as_ptr() || *ptr.sub(1) == b'\n'
+    }
+
+    /*
+     * A line starting with a tab is a recipe line of a Makefile, which
+     * does not contain any dependencies.
+     */
+    unsafe fn skip_recipe(
+        &mut self,
+        begin: *const u8,
+        end: *const u8,
+    ) -> *const u8 {
+        let ptr = util::skip_continued_line(begin, end);
+
+        if self.strict {
+            let line = util::make_str(begin.add(1), cmp::min(ptr, end));
+            let line = line.trim_end();
+
+            return self
+                .fail(format!("unexpected recipe line \"{line}\""), end);
+        }
+
+        ptr
+    }
+
+    /*
+     * Report invalid input and return "end" to stop parsing if the parser
+     * keeps going.
+     */
+    fn fail(&mut self, msg: String, end: *const u8) -> *const u8 {
+        if !self.keep_going {
+            error!("invalid-input", "{msg}");
+            exit(1);
+        }
+
+        self.error = Some(msg);
+        end
+    }
+
+    fn warn(&mut self, msg: String) {
+        match self.collect_warnings {
+            true => self.warnings.push(msg),
+            false => warning!("invalid-input", "{msg}"),
+        }
+    }
+
+    /*
+     * Include directives are resolved before the input is passed to the
+     * parser, see "Includer". Any remaining directive cannot be resolved.
+     */
+    unsafe fn skip_include(
+        &mut self,
+        begin: *const u8,
+        end: *const u8,
+    ) -> *const u8 {
+        let ptr = util::skip_continued_line(begin, end);
+        let line = util::make_str(begin, cmp::min(ptr, end)).trim_end();
+
+        if self.reject_directives {
+            return self.fail(
+                format!("include directive \"{line}\" {IN_CHUNKS}"),
+                end,
+            );
+        }
+
+        self.warn(format!("ignoring include directive \"{line}\""));
+
+        ptr
+    }
+
+    unsafe fn skip_conditional(
+        &mut self,
+        begin: *const u8,
+        end: *const u8,
+    ) -> *const u8 {
+        let ptr = util::skip_continued_line(begin, end);
+
+        if self.reject_directives {
+            let line = util::make_str(begin, cmp::min(ptr, end)).trim_end();
+
+            return self.fail(
+                format!("conditional directive \"{line}\" {IN_CHUNKS}"),
+                end,
+            );
+        }
+
+        ptr
+    }
+
     unsafe fn parse_targets(
         &mut self,
         begin: *const u8,
@@ -203,15 +554,15 @@ impl<'a> DependencyParser<'a> {
                     }
                 }
                 b'#' => {
-                    eprintln!("error: invalid comment in target definition");
-                    exit(1)
+                    let msg = "invalid comment in target definition";
+                    return self.fail(msg.into(), end);
                 }
                 b'\n' => {
                     let prev = ptr.sub(1);
 
                     if ptr != str_begin && *prev != b':' {
-                        eprintln!("error: invalid dependency file syntax");
-                        exit(1);
+                        let msg = "invalid dependency file syntax";
+                        return self.fail(msg.into(), end);
                     }
 
                     self.emit_target(str_begin, prev);
@@ -247,6 +598,14 @@ impl<'a> DependencyParser<'a> {
 
         while ptr < end && !done {
             match *ptr {
+                /* An escaped space or '#' starts a prerequisite */
+                b'\\'
+                    if ptr.add(1) < end
+                        && matches!(*ptr.add(1), b' ' | b'\t' | b'#') =>
+                {
+                    (ptr, done) = self.parse_prerequisite(start, ptr, end);
+                    continue;
+                }
                 b' ' | b'\t' | b'\\' => {}
                 b'\n' => {
                     if ptr != begin && *ptr.sub(1) != b'\\' {
@@ -254,7 +613,8 @@ impl<'a> DependencyParser<'a> {
                     }
                 }
                 b'#' => {
-                    ptr = util::skip_comment(ptr, begin);
+                    /* The newline still terminates the rule */
+                    ptr = util::find_newline(ptr, end);
                     continue;
                 }
                 _ => {
@@ -289,15 +649,17 @@ impl<'a> DependencyParser<'a> {
                 b'#' => {
                     if ptr != begin && *ptr.sub(1) != b'\\' {
                         self.emit_prerequisite(start, begin, ptr);
-                        ptr = util::skip_comment(ptr, end);
+                        ptr = util::find_newline(ptr, end);
 
                         return (ptr, false);
                     }
                 }
                 b' ' | b'\t' => {
-                    self.emit_prerequisite(start, begin, ptr);
+                    if *ptr.sub(1) != b'\\' {
+                        self.emit_prerequisite(start, begin, ptr);
 
-                    return (ptr.add(1), false);
+                        return (ptr.add(1), false);
+                    }
                 }
                 _ => {}
             }
@@ -324,9 +686,157 @@ impl<'a> DependencyParser<'a> {
     }
 }
 
+/*
+ * Invalid input found by "parse_checked()".
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl error::Error for ParseError {}
+
+/*
+ * The rules of valid input parsed by "parse_checked()" and the warnings
+ * about constructs which were skipped, e.g. include directives.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Parsed {
+    pub deps: Vec<DependencyBuf>,
+    pub warnings: Vec<String>,
+}
+
+/*
+ * Parse untrusted input, e.g. a dependency file of an external
+ * contributor. Unlike "DependencyParser::parse()", invalid input never
+ * terminates the process, but is returned as an error, and warnings are
+ * returned instead of being printed. Arbitrary bytes are accepted, invalid
+ * UTF-8 sequences are replaced.
+ */
+pub fn parse_checked(data: &[u8]) -> Result<Parsed, ParseError> {
+    let mut parser = DependencyParser::new();
+
+    parser.set_keep_going(true);
+    parser.set_collect_warnings(true);
+    parser
+        .feed(Vec::from(data))
+        .map_err(|message| ParseError { message })?;
+
+    let deps = parser.finish().iter().map(Dependency::to_buf).collect();
+    let warnings = parser.take_warnings();
+
+    Ok(Parsed { deps, warnings })
+}
+
+/*
+ * Merge dependencies with identical targets into one dependency. The order
+ * of the targets and their prerequisites is preserved and duplicated
+ * prerequisites are removed.
+ */
+pub fn merge(deps: Vec<Dependency>) -> Vec<Dependency> {
+    type IdSet = HashSet<u32, BuildHasherDefault<PathHasher>>;
+
+    let len = deps.len();
+    let mut result: Vec<Dependency> = Vec::with_capacity(len);
+    let mut sets: Vec<IdSet> = Vec::with_capacity(len);
+    let mut targets: HashMap<u32, usize> = HashMap::with_capacity(len);
+    let mut interner = Interner::with_capacity(2 * len);
+
+    for dep in deps {
+        match targets.entry(interner.intern(dep.target)) {
+            Entry::Occupied(entry) => {
+                let index = *entry.get();
+                let set = &mut sets[index];
+
+                set.reserve(dep.prerequisites.len());
+
+                for &prereq in &dep.prerequisites {
+                    if set.insert(interner.intern(prereq)) {
+                        result[index].prerequisites.push(prereq);
+                    }
+                }
+            }
+            Entry::Vacant(entry) => {
+                let hasher = BuildHasherDefault::<PathHasher>::default();
+                let mut set = HashSet::with_hasher(hasher);
+
+                let capacity = 2 * dep.prerequisites.len();
+                set.reserve(capacity);
+
+                for &prereq in &dep.prerequisites {
+                    set.insert(interner.intern(prereq));
+                }
+
+                entry.insert(result.len());
+                sets.push(set);
+                result.push(dep);
+            }
+        };
+    }
+
+    result
+}
+
+/*
+ * Merge dependencies with identical targets into one dependency like
+ * "merge()", but sort the prerequisites and remove adjacent duplicates
+ * instead of tracking them in a set per target.
+ */
+pub fn merge_sorted(deps: Vec<Dependency>) -> Vec<Dependency> {
+    let len = deps.len();
+    let mut result: Vec<Dependency> = Vec::with_capacity(len);
+    let mut targets: HashMap<&str, usize, BuildHasherDefault<PathHasher>> =
+        HashMap::with_capacity_and_hasher(len, Default::default());
+
+    for dep in deps {
+        match targets.entry(dep.target) {
+            Entry::Occupied(entry) => {
+                let merged_dep = &mut result[*entry.get()];
+
+                merged_dep
+                    .prerequisites
+                    .extend_from_slice(&dep.prerequisites);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(result.len());
+                result.push(dep);
+            }
+        }
+    }
+
+    for dep in &mut result {
+        dep.prerequisites.sort_unstable();
+        dep.prerequisites.dedup();
+    }
+
+    result
+}
+
+/*
+ * Sort the dependencies by their targets and the prerequisites of each
+ * dependency. Paths are compared bytewise, so the order does not depend on
+ * the locale.
+ */
+pub fn sort(deps: &mut [Dependency]) {
+    for dep in deps.iter_mut() {
+        dep.prerequisites.sort_unstable();
+    }
+
+    deps.sort_by(|a, b| a.target.cmp(b.target));
+}
+
 mod util {
     use std::slice;
-    use std::str;
+    use std::{cmp, str};
+
+    use crate::cond;
+    use crate::include;
 
     pub fn make_str<'a>(begin: *const u8, end: *const u8) -> &'a str {
         unsafe {
@@ -337,7 +847,11 @@ mod util {
         }
     }
 
-    pub fn skip_line(begin: *const u8, end: *const u8) -> *const u8 {
+    /*
+     * Return the newline terminating the line of "begin" or "end" if there
+     * is none.
+     */
+    pub fn find_newline(begin: *const u8, end: *const u8) -> *const u8 {
         unsafe {
             let mut ptr = begin;
 
@@ -345,13 +859,65 @@ mod util {
                 ptr = ptr.add(1);
             }
 
-            ptr.add(1)
+            ptr
+        }
+    }
+
+    /*
+     * Skip a line including its newline. The result never exceeds "end",
+     * even if the last line lacks a newline.
+     */
+    pub fn skip_line(begin: *const u8, end: *const u8) -> *const u8 {
+        let ptr = find_newline(begin, end);
+
+        if ptr < end {
+            unsafe { ptr.add(1) }
+        } else {
+            ptr
         }
     }
 
     pub fn skip_comment(begin: *const u8, end: *const u8) -> *const u8 {
         skip_line(begin, end)
     }
+
+    pub fn is_include(begin: *const u8, end: *const u8) -> bool {
+        let size = cmp::min(end as usize - begin as usize, 16);
+        let prefix = unsafe { slice::from_raw_parts(begin, size) };
+
+        include::is_directive(prefix)
+    }
+
+    pub fn is_conditional(begin: *const u8, end: *const u8) -> bool {
+        let size = cmp::min(end as usize - begin as usize, 8);
+        let prefix = unsafe { slice::from_raw_parts(begin, size) };
+
+        cond::is_directive(prefix)
+    }
+
+    /*
+     * Skip a line including the lines which continue it with a trailing
+     * backslash.
+     */
+    pub fn skip_continued_line(begin: *const u8, end: *const u8) -> *const u8 {
+        unsafe {
+            let mut ptr = skip_line(begin, end);
+
+            /*
+             * The backslash precedes the newline in front of "ptr", which
+             * must not be read before "begin" for a line of a single
+             * newline.
+             */
+            while ptr < end
+                && ptr as usize - begin as usize >= 2
+                && *ptr.sub(2) == b'\\'
+            {
+                ptr = skip_line(ptr, end);
+            }
+
+            ptr
+        }
+    }
 }
 
 #[cfg(test)]
@@ -945,7 +1511,7 @@ mod tests {
         let _ = parser.parse(Vec::from(""));
 
         assert_eq!(0, parser.deps.len());
-        assert_eq!(0, parser.data.len());
+        assert_eq!(0, parser.buffer().len());
     }
 
     /**
@@ -1128,4 +1694,422 @@ mod tests {
         assert_eq!("b", parser.deps[0].prerequisites[0]);
         assert_eq!("c", parser.deps[0].prerequisites[1]);
     }
+
+    /**
+     * sort()
+     *
+     * Verify that the function sorts the targets and prerequisites
+     * bytewise.
+     */
+    #[test]
+    fn sort_001() {
+        let mut vec = Vec::from([
+            Dependency {
+                target: "b",
+                prerequisites: Vec::from(["z", "Z", "a"]),
+            },
+            Dependency {
+                target: "B",
+                prerequisites: Vec::new(),
+            },
+            Dependency {
+                target: "a",
+                prerequisites: Vec::from(["b"]),
+            },
+        ]);
+
+        sort(&mut vec);
+
+        assert_eq!("B", vec[0].target);
+        assert_eq!("a", vec[1].target);
+        assert_eq!("b", vec[2].target);
+        assert_eq!(Vec::from(["Z", "a", "z"]), vec[2].prerequisites);
+    }
+
+    /**
+     * DependencyParser::parse()
+     *
+     * Verify that parsing identical input always produces identical output.
+     */
+    #[test]
+    fn parse_determinism_001() {
+        let data = b"c.o: c.c x.h\n a.o: a.c z.h y.h x.h\nc.o: w.h x.h\n";
+
+        let mut parser = DependencyParser::new();
+        let expected = parser.parse(Vec::from(data.as_slice())).clone();
+
+        for _ in 0..16 {
+            let mut parser = DependencyParser::new();
+
+            assert_eq!(&expected, parser.parse(Vec::from(data.as_slice())));
+        }
+    }
+
+    /**
+     * DependencyParser::merge_deps()
+     *
+     * Verify that the sort strategy merges identical targets and returns
+     * their prerequisites sorted and without duplicates.
+     */
+    #[test]
+    fn merge_deps_005() {
+        let mut parser = DependencyParser::new();
+        parser.set_merge_strategy(MergeStrategy::Sort);
+
+        let data = b"a: d b d\nc: x\na: c b\n";
+        let deps = parser.parse(Vec::from(data.as_slice()));
+
+        assert_eq!(2, deps.len());
+        assert_eq!("a", deps[0].target);
+        assert_eq!(Vec::from(["b", "c", "d"]), deps[0].prerequisites);
+        assert_eq!("c", deps[1].target);
+        assert_eq!(Vec::from(["x"]), deps[1].prerequisites);
+    }
+
+    /**
+     * DependencyParser::parse()
+     *
+     * Verify that invalid UTF-8 sequences are replaced in the parsed
+     * paths.
+     */
+    #[test]
+    fn parse_005() {
+        let mut parser = DependencyParser::new();
+
+        let deps = parser.parse(Vec::from(b"a\xff.o: b.c \xc3\n".as_slice()));
+
+        assert_eq!(1, deps.len());
+        assert_eq!("a\u{fffd}.o", deps[0].target);
+        assert_eq!(Vec::from(["b.c", "\u{fffd}"]), deps[0].prerequisites);
+    }
+
+    /**
+     * DependencyParser::parse()
+     *
+     * Verify that recipe lines of a Makefile, including their continuation
+     * lines, are skipped.
+     */
+    #[test]
+    fn parse_006() {
+        let data = "\
+all: main
+main: main.o \\
+\tutil.o
+\tcc -o main \\
+\t  main.o util.o
+\t@echo done
+main.o: main.c
+\tcc -c main.c";
+
+        let mut parser = DependencyParser::new();
+        let deps = parser.parse(Vec::from(data));
+
+        assert_eq!(3, deps.len());
+        assert_eq!(Vec::from(["main"]), deps[0].prerequisites);
+        assert_eq!(Vec::from(["main.o", "util.o"]), deps[1].prerequisites);
+        assert_eq!("main.o", deps[2].target);
+        assert_eq!(Vec::from(["main.c"]), deps[2].prerequisites);
+    }
+
+    /**
+     * DependencyParser::parse()
+     *
+     * Verify that include directives are skipped and that targets
+     * starting like a directive are still parsed.
+     */
+    #[test]
+    fn parse_007() {
+        let data =
+            "include a.d\nincludes.o: b.c\n-include c.d \\\n d.d\nsinclude";
+
+        let mut parser = DependencyParser::new();
+        let deps = parser.parse(Vec::from(data));
+
+        assert_eq!(1, deps.len());
+        assert_eq!("includes.o", deps[0].target);
+        assert_eq!(Vec::from(["b.c"]), deps[0].prerequisites);
+    }
+
+    /**
+     * DependencyParser::parse()
+     *
+     * Verify that conditional directives are skipped and that targets
+     * with similar names are not.
+     */
+    #[test]
+    fn parse_008() {
+        let data = "ifeq ($(A),x)\na.o: a.c\nelse ifdef B\nelse.o: b.c\n\
+                    else\n  endif\n";
+
+        let mut parser = DependencyParser::new();
+        let deps = parser.parse(Vec::from(data));
+
+        assert_eq!(2, deps.len());
+        assert_eq!("a.o", deps[0].target);
+        assert_eq!("else.o", deps[1].target);
+        assert_eq!(Vec::from(["b.c"]), deps[1].prerequisites);
+    }
+
+    /**
+     * DependencyParser::parse()
+     *
+     * Verify that a comment after the prerequisites does not continue the
+     * rule on the next line and that a tab in front of a target is not
+     * taken for an empty target.
+     */
+    #[test]
+    fn parse_009() {
+        let data = "a: b #c d\ne: f# g\nh: i\n \tj: k\n";
+
+        let mut parser = DependencyParser::new();
+        let deps = parser.parse(Vec::from(data));
+
+        assert_eq!(4, deps.len());
+        assert_eq!("a", deps[0].target);
+        assert_eq!(Vec::from(["b"]), deps[0].prerequisites);
+        assert_eq!("e", deps[1].target);
+        assert_eq!(Vec::from(["f"]), deps[1].prerequisites);
+        assert_eq!("h", deps[2].target);
+        assert_eq!("j", deps[3].target);
+    }
+
+    /**
+     * DependencyParser::parse()
+     *
+     * Verify that escaped spaces are part of a prerequisite like of a
+     * target.
+     */
+    #[test]
+    fn parse_010() {
+        let data = "a\\ b.o: a\\ b.c c\\\td.h \\\n e.h\n";
+
+        let mut parser = DependencyParser::new();
+        let deps = parser.parse(Vec::from(data));
+
+        assert_eq!(1, deps.len());
+        assert_eq!("a\\ b.o", deps[0].target);
+        assert_eq!(
+            Vec::from(["a\\ b.c", "c\\\td.h", "e.h"]),
+            deps[0].prerequisites
+        );
+    }
+
+    /**
+     * DependencyParser::feed()
+     *
+     * Verify that the rules of several buffers are merged into one result
+     * and that the rules of each buffer are returned.
+     */
+    #[test]
+    fn feed_001() {
+        let mut parser = DependencyParser::new();
+
+        parser.feed(Vec::from("a.o: a.c a.h\n")).unwrap();
+
+        let rules = parser.feed(Vec::from("b.o: b.c\na.o: b.h\n"));
+
+        assert_eq!(2, rules.unwrap().len());
+
+        let deps = parser.finish().clone();
+
+        assert_eq!(2, deps.len());
+        assert_eq!("a.o", deps[0].target);
+        assert_eq!(Vec::from(["a.c", "a.h", "b.h"]), deps[0].prerequisites);
+        assert_eq!("b.o", deps[1].target);
+    }
+
+    /**
+     * DependencyParser::feed()
+     *
+     * Verify that an error is returned and the rules of the buffer are
+     * discarded if the parser keeps going.
+     */
+    #[test]
+    fn feed_002() {
+        let mut parser = DependencyParser::new();
+        parser.set_keep_going(true);
+
+        parser.feed(Vec::from("a.o: a.c\n")).unwrap();
+
+        let result = parser.feed(Vec::from("b.o: b.c\nc.o # x\n"));
+
+        assert!(result.is_err());
+
+        let deps = parser.finish().clone();
+
+        assert_eq!(1, deps.len());
+        assert_eq!("a.o", deps[0].target);
+    }
+
+    /**
+     * DependencyParser::feed()
+     *
+     * Verify that include and conditional directives are rejected if they
+     * cannot be resolved.
+     */
+    #[test]
+    fn feed_003() {
+        let mut parser = DependencyParser::new();
+        parser.set_keep_going(true);
+        parser.set_reject_directives(true);
+
+        parser.feed(Vec::from("a.o: a.c\n")).unwrap();
+
+        for data in ["include x.d\n", "ifeq ($(A),1)\nb.o: b.c\nendif\n"] {
+            let err = parser.feed(Vec::from(data)).unwrap_err();
+
+            assert!(err.contains("cannot be parsed in chunks"), "{err}");
+        }
+    }
+
+    /**
+     * DependencyParser::reset()
+     *
+     * Verify that the parser does not keep the rules of the buffers fed
+     * before a reset.
+     */
+    #[test]
+    fn reset_001() {
+        let mut parser = DependencyParser::new();
+
+        parser.feed(Vec::from("a.o: a.c\n")).unwrap();
+        parser.reset();
+        parser.feed(Vec::from("b.o: b.c\n")).unwrap();
+
+        let deps = parser.finish();
+
+        assert_eq!(1, deps.len());
+        assert_eq!("b.o", deps[0].target);
+    }
+
+    /**
+     * MergePolicy::apply()
+     *
+     * Verify that only the rules kept by each policy remain and that a
+     * target keeps the position of its first rule.
+     */
+    #[test]
+    fn apply_001() {
+        let dep = |target, prereqs: &[&'static str]| Dependency {
+            target,
+            prerequisites: Vec::from(prereqs),
+        };
+
+        let deps = Vec::from([
+            dep("a.o", &["a.c"]),
+            dep("b.o", &["b.c"]),
+            dep("a.o", &["a.h"]),
+        ]);
+
+        let union = MergePolicy::Union.apply(deps.clone()).unwrap();
+        assert_eq!(deps, union);
+
+        let first = MergePolicy::First.apply(deps.clone()).unwrap();
+        assert_eq!(
+            Vec::from([dep("a.o", &["a.c"]), dep("b.o", &["b.c"])]),
+            first
+        );
+
+        let last = MergePolicy::Last.apply(deps.clone()).unwrap();
+        assert_eq!(
+            Vec::from([dep("a.o", &["a.h"]), dep("b.o", &["b.c"])]),
+            last
+        );
+
+        let err = MergePolicy::Error.apply(deps).unwrap_err();
+        assert_eq!(
+            "target \"a.o\" has rules with different prerequisites",
+            err
+        );
+
+        let deps = Vec::from([dep("a.h", &[]), dep("a.h", &[])]);
+        let result = MergePolicy::Error.apply(deps).unwrap();
+
+        assert_eq!(Vec::from([dep("a.h", &[])]), result);
+    }
+
+    /**
+     * parse_checked()
+     *
+     * Verify that the function handles degenerate input.
+     */
+    #[test]
+    fn parse_checked_001() {
+        for data in ["", "\n", "\\", "\\\n", "\n\\\n", "#", "\t", "\n\t\\\n"] {
+            assert_eq!(Ok(Parsed::default()), parse_checked(data.as_bytes()));
+        }
+
+        let deps = parse_checked(b"a:\n").unwrap().deps;
+
+        assert_eq!(1, deps.len());
+        assert_eq!("a", deps[0].target);
+
+        let deps = parse_checked(b"a: \xff\n").unwrap().deps;
+
+        assert_eq!(Vec::from(["\u{fffd}"]), deps[0].prerequisites);
+    }
+
+    /**
+     * parse_checked()
+     *
+     * Verify that the function returns an error for invalid input instead
+     * of terminating the process.
+     */
+    #[test]
+    fn parse_checked_002() {
+        let err = parse_checked(b"a.o: a.c\nb c\n").unwrap_err();
+
+        assert_eq!("invalid dependency file syntax", err.message);
+
+        let err = parse_checked(b"a # b: c\n").unwrap_err();
+
+        assert_eq!("invalid comment in target definition", err.to_string());
+    }
+
+    /**
+     * parse_checked()
+     *
+     * Verify that the function does not panic on pseudo-random input made
+     * of the characters the parser handles specially.
+     */
+    #[test]
+    fn parse_checked_003() {
+        let alphabet = b" \t\n\\:#ia-e\xff";
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+
+        for _ in 0..4096 {
+            let mut data = Vec::new();
+
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+
+            for i in 0..(state % 48) {
+                let index = (state >> (i % 56)) as usize + i as usize;
+                data.push(alphabet[index % alphabet.len()]);
+            }
+
+            if let Ok(parsed) = parse_checked(&data) {
+                let deps = parsed.deps;
+                assert!(deps.iter().all(|dep| !dep.target.contains('\n')));
+            }
+        }
+    }
+
+    /**
+     * parse_checked()
+     *
+     * Verify that the function returns the warnings about the input
+     * instead of printing them.
+     */
+    #[test]
+    fn parse_checked_004() {
+        let parsed = parse_checked(b"include a.mk\na.o: a.c\n").unwrap();
+
+        assert_eq!(1, parsed.deps.len());
+        assert_eq!(
+            Vec::from(["ignoring include directive \"include a.mk\""]),
+            parsed.warnings
+        );
+    }
 }
@@ -0,0 +1,130 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fmt;
+use std::sync::Mutex;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/*
+ * Format a diagnostic as a single line. The "file" and the "line" are None
+ * if the diagnostic does not refer to a location.
+ */
+pub type Format =
+    fn(Severity, &str, Option<&str>, Option<usize>, &str) -> String;
+
+static FORMAT: Mutex<Option<Format>> = Mutex::new(None);
+
+/*
+ * Print the diagnostics formatted by "format", one per line, e.g. as JSON
+ * objects, instead of text.
+ */
+pub fn set_format(format: Option<Format>) {
+    *FORMAT.lock().unwrap() = format;
+}
+
+/*
+ * Print a diagnostic to stderr. The "code" identifies the kind of the
+ * diagnostic for tools and is only part of the JSON output.
+ */
+pub fn emit(
+    severity: Severity,
+    code: &str,
+    file: Option<&str>,
+    line: Option<usize>,
+    message: &str,
+) {
+    let format = *FORMAT.lock().unwrap();
+
+    match format {
+        Some(format) => {
+            eprintln!("{}", format(severity, code, file, line, message))
+        }
+        None => eprintln!("{severity}: {message}"),
+    }
+}
+
+/*
+ * Print an error with the code "$code" and a message formatted like with
+ * "format!()". A file and a line can be given before the message.
+ */
+macro_rules! error {
+    ($code:literal, file = $file:expr, line = $line:expr, $($arg:tt)+) => {
+        $crate::diag::emit(
+            $crate::diag::Severity::Error,
+            $code,
+            Some($file),
+            Some($line),
+            &format!($($arg)+),
+        )
+    };
+    ($code:literal, file = $file:expr, $($arg:tt)+) => {
+        $crate::diag::emit(
+            $crate::diag::Severity::Error,
+            $code,
+            Some($file),
+            None,
+            &format!($($arg)+),
+        )
+    };
+    ($code:literal, $($arg:tt)+) => {
+        $crate::diag::emit(
+            $crate::diag::Severity::Error,
+            $code,
+            None,
+            None,
+            &format!($($arg)+),
+        )
+    };
+}
+
+/*
+ * Print a warning like "error!()".
+ */
+macro_rules! warning {
+    ($code:literal, file = $file:expr, $($arg:tt)+) => {
+        $crate::diag::emit(
+            $crate::diag::Severity::Warning,
+            $code,
+            Some($file),
+            None,
+            &format!($($arg)+),
+        )
+    };
+    ($code:literal, $($arg:tt)+) => {
+        $crate::diag::emit(
+            $crate::diag::Severity::Warning,
+            $code,
+            None,
+            None,
+            &format!($($arg)+),
+        )
+    };
+}
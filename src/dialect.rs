@@ -0,0 +1,370 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::{mem, str};
+
+use crate::dependency::DependencyBuf;
+
+/*
+ * The dependency files of embedded toolchains contain a single rule per
+ * line, usually with only one prerequisite. Paths with spaces are
+ * enclosed in double quotes. The TI and Arm compilers as well as swiftc
+ * additionally escape spaces with a backslash and continue long lines
+ * with a trailing backslash. Windows paths and a missing space after the
+ * colon of a rule are accepted in any case. makedepend writes its rules
+ * without quotes or escapes into a Makefile, see "MAKEDEPEND_MARKER".
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dialect {
+    Iar,
+    Ti,
+    Arm,
+    Swift,
+    Makedepend,
+}
+
+/*
+ * makedepend appends its rules to a Makefile after a line starting with
+ * this marker, e.g. "# DO NOT DELETE THIS LINE -- make depend depends on
+ * it.". The Makefile before it is not part of the dependencies.
+ */
+const MAKEDEPEND_MARKER: &str = "# DO NOT DELETE";
+
+/*
+ * Return the number of lines before the rules of makedepend. If there is
+ * no marker, e.g. for the output of "makedepend -f-", all lines are rules.
+ */
+fn skip_makefile(data: &str) -> usize {
+    data.lines()
+        .position(|x| x.starts_with(MAKEDEPEND_MARKER))
+        .map_or(0, |i| i + 1)
+}
+
+/*
+ * Return the position of the colon which separates the target from the
+ * prerequisites. Colons within quotes and the colon of a drive letter,
+ * e.g. "C:\", are skipped.
+ */
+fn find_separator(line: &str) -> Option<usize> {
+    let bytes = line.as_bytes();
+    let mut quoted = false;
+
+    for (i, &x) in bytes.iter().enumerate() {
+        match x {
+            b'"' => quoted = !quoted,
+            b':' if !quoted => {
+                let drive = i > 0
+                    && bytes[i - 1].is_ascii_alphabetic()
+                    && (i == 1 || matches!(bytes[i - 2], b' ' | b'\t' | b'"'))
+                    && matches!(bytes.get(i + 1), Some(b'\\' | b'/'));
+
+                if !drive {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/*
+ * Split a list of paths separated by whitespace. A path which contains
+ * spaces is enclosed in double quotes or, if "escapes" is set, the spaces
+ * are escaped with a backslash. In this case, "\#" and "$$" are unescaped
+ * as well.
+ */
+fn split_paths(data: &str, escapes: bool) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut path = String::new();
+    let mut quoted = false;
+    let mut chars = data.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' if escapes
+                && matches!(chars.peek(), Some(' ' | '\t' | '#')) =>
+            {
+                path.extend(chars.next());
+            }
+            '$' if escapes && chars.peek() == Some(&'$') => {
+                path.extend(chars.next());
+            }
+            ' ' | '\t' if !quoted => {
+                if !path.is_empty() {
+                    paths.push(mem::take(&mut path));
+                }
+            }
+            _ => path.push(c),
+        }
+    }
+
+    if !path.is_empty() {
+        paths.push(path);
+    }
+
+    paths
+}
+
+/*
+ * Read the dependency files of a toolchain with its own dialect.
+ * Consecutive rules for the same target are combined.
+ */
+pub fn parse_dependencies(
+    data: &[u8],
+    dialect: Dialect,
+) -> Result<Vec<DependencyBuf>, String> {
+    let data = str::from_utf8(data)
+        .map_err(|err| format!("invalid UTF-8 input: {err}"))?;
+    let escapes = !matches!(dialect, Dialect::Iar | Dialect::Makedepend);
+    let mut deps: Vec<DependencyBuf> = Vec::new();
+    let mut line = String::new();
+    let mut number = 0;
+
+    let skip = match dialect {
+        Dialect::Makedepend => skip_makefile(data),
+        _ => 0,
+    };
+
+    for (i, part) in data.lines().enumerate().skip(skip) {
+        let part = part.trim();
+
+        if line.is_empty() {
+            number = i + 1;
+        }
+
+        /* Continue the line with the next one */
+        if let (true, Some(part)) = (escapes, part.strip_suffix('\\')) {
+            line.push_str(part);
+            line.push(' ');
+            continue;
+        }
+
+        line.push_str(part);
+
+        let line = mem::take(&mut line);
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (target, prerequisites) = match find_separator(line) {
+            Some(pos) => (&line[..pos], &line[pos + 1..]),
+            None => return Err(format!("line {number}: missing ':'")),
+        };
+
+        let targets = split_paths(target, escapes);
+        let prerequisites = split_paths(prerequisites, escapes);
+
+        match deps.last_mut() {
+            Some(last) if targets == [last.target.as_str()] => {
+                last.prerequisites.extend(prerequisites);
+            }
+            _ => deps.extend(targets.into_iter().map(|target| DependencyBuf {
+                target,
+                prerequisites: prerequisites.clone(),
+            })),
+        }
+    }
+
+    Ok(deps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * find_separator()
+     *
+     * Verify that drive letters and quoted colons are skipped.
+     */
+    #[test]
+    fn find_separator_001() {
+        assert_eq!(Some(6), find_separator("main.o: main.c"));
+        assert_eq!(Some(11), find_separator("C:\\x\\main.o: C:\\x\\main.c"));
+        assert_eq!(Some(11), find_separator("\"C:\\a:b.o\" : x.h"));
+        assert_eq!(Some(1), find_separator("a:b"));
+        assert_eq!(None, find_separator("C:\\x\\main.c"));
+    }
+
+    /**
+     * parse_dependencies()
+     *
+     * Verify that rules with one prerequisite per line are combined and
+     * that quoted paths are supported.
+     */
+    #[test]
+    fn parse_dependencies_001() {
+        let data = "\
+# IAR
+main.o: C:\\src\\main.c\r
+main.o: \"C:\\Program Files\\IAR\\inc\\stdio.h\"\r
+\r
+\"obj dir\\util.o\": util.c util.h
+main.o: main.h
+";
+
+        let deps = parse_dependencies(data.as_bytes(), Dialect::Iar).unwrap();
+
+        assert_eq!(3, deps.len());
+        assert_eq!("main.o", deps[0].target);
+        assert_eq!(
+            Vec::from([
+                "C:\\src\\main.c",
+                "C:\\Program Files\\IAR\\inc\\stdio.h"
+            ]),
+            deps[0].prerequisites
+        );
+        assert_eq!("obj dir\\util.o", deps[1].target);
+        assert_eq!(Vec::from(["util.c", "util.h"]), deps[1].prerequisites);
+        assert_eq!("main.o", deps[2].target);
+
+        assert!(parse_dependencies(b"main.o\n", Dialect::Iar).is_err());
+    }
+
+    /**
+     * parse_dependencies()
+     *
+     * Verify that escaped spaces and continued lines of the TI compilers
+     * are supported.
+     */
+    #[test]
+    fn parse_dependencies_002() {
+        let data = "\
+# FIXED
+
+main.obj: ../main.c
+main.obj: C:/ti/ccs\\ 12/include/stdint.h \\
+  C:/ti/include/_ti_config.h
+
+../main.c:
+";
+
+        let deps = parse_dependencies(data.as_bytes(), Dialect::Ti).unwrap();
+
+        assert_eq!(2, deps.len());
+        assert_eq!("main.obj", deps[0].target);
+        assert_eq!(
+            Vec::from([
+                "../main.c",
+                "C:/ti/ccs 12/include/stdint.h",
+                "C:/ti/include/_ti_config.h"
+            ]),
+            deps[0].prerequisites
+        );
+        assert_eq!("../main.c", deps[1].target);
+        assert!(deps[1].prerequisites.is_empty());
+
+        let result = parse_dependencies(b"a.obj: \\\n\nb.h\n", Dialect::Ti);
+        assert_eq!(Err(String::from("line 3: missing ':'")), result);
+    }
+
+    /**
+     * parse_dependencies()
+     *
+     * Verify that the output of "armcc --depend" and "armclang -MD" on
+     * Windows is supported.
+     */
+    #[test]
+    fn parse_dependencies_003() {
+        let data = "\
+.\\Objects\\main.o: ..\\src\\main.c\r
+.\\Objects\\main.o:C:\\Keil_v5\\ARM\\ARMCC\\Bin\\..\\include\\stdio.h\r
+.\\Objects\\main.o: ..\\inc\\board.h\r
+C:\\build\\util.o: C:\\src\\util.c \\\r
+  C:\\Program\\ Files\\ArmCompiler6\\include\\stdint.h\r
+";
+
+        let deps = parse_dependencies(data.as_bytes(), Dialect::Arm).unwrap();
+
+        assert_eq!(2, deps.len());
+        assert_eq!(".\\Objects\\main.o", deps[0].target);
+        assert_eq!(
+            Vec::from([
+                "..\\src\\main.c",
+                "C:\\Keil_v5\\ARM\\ARMCC\\Bin\\..\\include\\stdio.h",
+                "..\\inc\\board.h"
+            ]),
+            deps[0].prerequisites
+        );
+        assert_eq!("C:\\build\\util.o", deps[1].target);
+        assert_eq!(
+            Vec::from([
+                "C:\\src\\util.c",
+                "C:\\Program Files\\ArmCompiler6\\include\\stdint.h"
+            ]),
+            deps[1].prerequisites
+        );
+    }
+
+    /**
+     * parse_dependencies()
+     *
+     * Verify that only the rules after the marker of makedepend are read
+     * and that the rules of a target split over several lines are
+     * combined.
+     */
+    #[test]
+    fn parse_dependencies_004() {
+        let data = "\
+CFLAGS = -O2
+all: app
+\t$(CC) -o app main.o # not: a rule
+
+# DO NOT DELETE THIS LINE -- make depend depends on it.
+
+main.o: main.c /usr/include/stdio.h
+main.o: /usr/include/features.h defs.h
+util.o: util.c defs.h
+";
+
+        let deps = parse_dependencies(data.as_bytes(), Dialect::Makedepend);
+
+        assert_eq!(
+            Ok(Vec::from([
+                DependencyBuf {
+                    target: String::from("main.o"),
+                    prerequisites: Vec::from([
+                        String::from("main.c"),
+                        String::from("/usr/include/stdio.h"),
+                        String::from("/usr/include/features.h"),
+                        String::from("defs.h"),
+                    ]),
+                },
+                DependencyBuf {
+                    target: String::from("util.o"),
+                    prerequisites: Vec::from([
+                        String::from("util.c"),
+                        String::from("defs.h"),
+                    ]),
+                },
+            ])),
+            deps
+        );
+
+        let deps = parse_dependencies(b"a.o: a.c\n", Dialect::Makedepend);
+        assert_eq!(1, deps.unwrap().len());
+
+        let deps =
+            parse_dependencies(b"# DO NOT DELETE\nx\n", Dialect::Makedepend);
+        assert_eq!(Err(String::from("line 2: missing ':'")), deps);
+    }
+}
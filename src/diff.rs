@@ -0,0 +1,143 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::{HashMap, HashSet};
+
+use crate::dependency::Dependency;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Change<'a> {
+    pub target: &'a str,
+    pub added: Vec<&'a str>,
+    pub removed: Vec<&'a str>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Diff<'a> {
+    pub added: Vec<Dependency<'a>>,
+    pub removed: Vec<Dependency<'a>>,
+    pub changed: Vec<Change<'a>>,
+}
+
+/*
+ * Compare two sets of merged dependencies. A rule is changed if its
+ * prerequisites differ, regardless of their order. Added and changed rules
+ * are reported in the order of "new", removed rules in the order of "old".
+ */
+pub fn diff<'a>(old: &[Dependency<'a>], new: &[Dependency<'a>]) -> Diff<'a> {
+    let old_map: HashMap<&str, &Dependency> =
+        old.iter().map(|dep| (dep.target, dep)).collect();
+    let new_map: HashMap<&str, &Dependency> =
+        new.iter().map(|dep| (dep.target, dep)).collect();
+
+    let mut result = Diff::default();
+
+    for dep in new {
+        let old_dep = match old_map.get(dep.target) {
+            Some(old_dep) => old_dep,
+            None => {
+                result.added.push(dep.clone());
+                continue;
+            }
+        };
+
+        let old_set: HashSet<&str> =
+            old_dep.prerequisites.iter().copied().collect();
+        let new_set: HashSet<&str> =
+            dep.prerequisites.iter().copied().collect();
+
+        if old_set == new_set {
+            continue;
+        }
+
+        result.changed.push(Change {
+            target: dep.target,
+            added: dep
+                .prerequisites
+                .iter()
+                .filter(|x| !old_set.contains(*x))
+                .copied()
+                .collect(),
+            removed: old_dep
+                .prerequisites
+                .iter()
+                .filter(|x| !new_set.contains(*x))
+                .copied()
+                .collect(),
+        });
+    }
+
+    result.removed = old
+        .iter()
+        .filter(|dep| !new_map.contains_key(dep.target))
+        .cloned()
+        .collect();
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_dep<'a>(target: &'a str, prereqs: &[&'a str]) -> Dependency<'a> {
+        Dependency {
+            target,
+            prerequisites: Vec::from(prereqs),
+        }
+    }
+
+    /**
+     * diff()
+     *
+     * Verify that identical dependencies do not produce any changes, even
+     * if the order of the prerequisites differs.
+     */
+    #[test]
+    fn diff_001() {
+        let old = Vec::from([make_dep("a", &["b", "c"])]);
+        let new = Vec::from([make_dep("a", &["c", "b"])]);
+
+        assert_eq!(Diff::default(), diff(&old, &new));
+    }
+
+    /**
+     * diff()
+     *
+     * Verify that added, removed, and changed rules are detected.
+     */
+    #[test]
+    fn diff_002() {
+        let old =
+            Vec::from([make_dep("a", &["x", "y"]), make_dep("b", &["x"])]);
+        let new =
+            Vec::from([make_dep("c", &["x"]), make_dep("a", &["y", "z"])]);
+
+        let diff = diff(&old, &new);
+
+        assert_eq!(Vec::from([new[0].clone()]), diff.added);
+        assert_eq!(Vec::from([old[1].clone()]), diff.removed);
+        assert_eq!(
+            Vec::from([Change {
+                target: "a",
+                added: Vec::from(["z"]),
+                removed: Vec::from(["x"]),
+            }]),
+            diff.changed
+        );
+    }
+}
@@ -0,0 +1,111 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dependency::Dependency;
+
+/*
+ * Generate a directed graph in the DOT language of Graphviz. Each target
+ * has an edge to each of its prerequisites. Targets without prerequisites
+ * are declared as nodes, so they are not missing from the graph.
+ */
+pub struct DotSerializer {
+    buf: Vec<u8>,
+}
+
+impl DotSerializer {
+    pub fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    pub fn get_dot(&self) -> &[u8] {
+        self.buf.as_slice()
+    }
+
+    pub fn write_vec(&mut self, vec: &[Dependency]) {
+        self.buf.reserve(256 * vec.len());
+        self.buf.extend_from_slice(b"digraph dependencies {\n");
+
+        for dep in vec {
+            if dep.prerequisites.is_empty() {
+                self.buf.extend_from_slice(b"    ");
+                self.write_str(dep.target);
+                self.buf.extend_from_slice(b";\n");
+            }
+
+            for prereq in &dep.prerequisites {
+                self.buf.extend_from_slice(b"    ");
+                self.write_str(dep.target);
+                self.buf.extend_from_slice(b" -> ");
+                self.write_str(prereq);
+                self.buf.extend_from_slice(b";\n");
+            }
+        }
+
+        self.buf.extend_from_slice(b"}\n");
+    }
+
+    fn write_str(&mut self, data: &str) {
+        self.buf.push(b'"');
+
+        for &byte in data.as_bytes() {
+            if byte == b'"' || byte == b'\\' {
+                self.buf.push(b'\\');
+            }
+
+            self.buf.push(byte);
+        }
+
+        self.buf.push(b'"');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * DotSerializer::write_vec()
+     *
+     * Verify that each prerequisite is written as an edge, that the IDs are
+     * escaped, and that a target without prerequisites is written as a node.
+     */
+    #[test]
+    fn write_vec_001() {
+        let vec = Vec::from([
+            Dependency {
+                target: "a.o",
+                prerequisites: Vec::from(["a.c", "dir\\\"b\".h"]),
+            },
+            Dependency {
+                target: "b.o",
+                prerequisites: Vec::new(),
+            },
+        ]);
+
+        let mut serializer = DotSerializer::new();
+        serializer.write_vec(&vec);
+
+        assert_eq!(
+            "digraph dependencies {\n    \
+            \"a.o\" -> \"a.c\";\n    \
+            \"a.o\" -> \"dir\\\\\\\"b\\\".h\";\n    \
+            \"b.o\";\n\
+            }\n",
+            String::from_utf8_lossy(serializer.get_dot())
+        );
+    }
+}
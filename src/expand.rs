@@ -0,0 +1,157 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::borrow::Cow;
+
+fn is_name_byte(x: u8) -> bool {
+    x.is_ascii_alphanumeric() || x == b'_'
+}
+
+/*
+ * Return the name of the variable referenced at the start of "data", i.e.
+ * after a '$', and the length of the reference without the '$'.
+ */
+fn reference(data: &str) -> Option<(&str, usize)> {
+    let close = match data.as_bytes().first()? {
+        b'(' => ')',
+        b'{' => '}',
+        _ => {
+            let len = data.bytes().take_while(|&x| is_name_byte(x)).count();
+
+            return (len != 0).then(|| (&data[..len], len));
+        }
+    };
+
+    let len = data.find(close)?;
+    let name = &data[1..len];
+
+    (!name.is_empty() && name.bytes().all(is_name_byte))
+        .then_some((name, len + 1))
+}
+
+/*
+ * Expand the references to environment variables in "path", i.e. "$NAME",
+ * "${NAME}", and "$(NAME)", with the values returned by "lookup". The
+ * references to unset variables and an escaped "$$" are kept.
+ */
+pub fn expand_env<F>(path: &str, lookup: F) -> Cow<'_, str>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if !path.contains('$') {
+        return Cow::Borrowed(path);
+    }
+
+    let mut result = String::with_capacity(path.len());
+    let mut rest = path;
+
+    while let Some(pos) = rest.find('$') {
+        result.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+
+        if let Some(tail) = rest.strip_prefix('$') {
+            result.push_str("$$");
+            rest = tail;
+            continue;
+        }
+
+        let value =
+            reference(rest).and_then(|(name, len)| Some((lookup(name)?, len)));
+
+        match value {
+            Some((value, len)) => {
+                result.push_str(&value);
+                rest = &rest[len..];
+            }
+            None => result.push('$'),
+        }
+    }
+
+    result.push_str(rest);
+
+    Cow::Owned(result)
+}
+
+/*
+ * Replace a leading "~" of "path" with the home directory if it is the
+ * whole path or followed by '/'. The home directories of other users, i.e.
+ * "~user", are not expanded.
+ */
+pub fn expand_tilde<'a>(path: &'a str, home: Option<&str>) -> Cow<'a, str> {
+    let (home, rest) = match (home, path.strip_prefix('~')) {
+        (Some(home), Some(rest))
+            if rest.is_empty() || rest.starts_with('/') =>
+        {
+            (home, rest)
+        }
+        _ => return Cow::Borrowed(path),
+    };
+
+    Cow::Owned(format!("{}{rest}", home.trim_end_matches('/')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * expand_env()
+     *
+     * Verify that all forms of references are expanded and that unset
+     * variables, escaped and invalid references are kept.
+     */
+    #[test]
+    fn expand_env_001() {
+        let lookup = |name: &str| match name {
+            "HOME" => Some(String::from("/home/u")),
+            "SDK_DIR" => Some(String::from("/opt/sdk")),
+            _ => None,
+        };
+
+        let data = [
+            ("a.h", "a.h"),
+            ("$HOME/a.h", "/home/u/a.h"),
+            ("${SDK_DIR}/inc/b.h", "/opt/sdk/inc/b.h"),
+            ("$(SDK_DIR)/c.h", "/opt/sdk/c.h"),
+            ("$HOME.d/x", "/home/u.d/x"),
+            ("$UNSET/a.h", "$UNSET/a.h"),
+            ("$$HOME/a.h", "$$HOME/a.h"),
+            ("${HOME/a.h", "${HOME/a.h"),
+            ("a$", "a$"),
+        ];
+
+        for (path, expected) in data {
+            assert_eq!(expected, expand_env(path, lookup));
+        }
+    }
+
+    /**
+     * expand_tilde()
+     *
+     * Verify that only a leading "~" followed by '/' is expanded.
+     */
+    #[test]
+    fn expand_tilde_001() {
+        let home = Some("/home/u/");
+
+        assert_eq!("/home/u/a.h", expand_tilde("~/a.h", home));
+        assert_eq!("/home/u", expand_tilde("~", home));
+        assert_eq!("~user/a.h", expand_tilde("~user/a.h", home));
+        assert_eq!("a/~/b.h", expand_tilde("a/~/b.h", home));
+        assert_eq!("~/a.h", expand_tilde("~/a.h", None));
+    }
+}
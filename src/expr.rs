@@ -0,0 +1,413 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dependency::Dependency;
+use crate::regex::Regex;
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(usize),
+    Op(&'static str),
+}
+
+/* Longer operators first, so "!~" is not read as "!" */
+const OPERATORS: &[&str] = &[
+    "&&", "||", "==", "!=", "<=", ">=", "!~", "~", "<", ">", "!", "(", ")",
+];
+
+fn tokenize(data: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = data.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        if c == '"' {
+            let (value, len) = read_string(rest)?;
+            tokens.push(Token::Str(value));
+            rest = &rest[len..];
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|x: char| !x.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let num = rest[..len]
+                .parse()
+                .map_err(|_| format!("invalid number \"{}\"", &rest[..len]))?;
+
+            tokens.push(Token::Num(num));
+            rest = &rest[len..];
+        } else if c.is_ascii_alphabetic() {
+            let len = rest
+                .find(|x: char| !x.is_ascii_alphanumeric() && x != '_')
+                .unwrap_or(rest.len());
+
+            tokens.push(Token::Ident(String::from(&rest[..len])));
+            rest = &rest[len..];
+        } else {
+            let op = OPERATORS
+                .iter()
+                .find(|x| rest.starts_with(*x))
+                .ok_or_else(|| format!("unexpected character '{c}'"))?;
+
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        }
+
+        rest = rest.trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/*
+ * Read a string in double quotes which starts at the beginning of "data".
+ * Only quotes and backslashes are unescaped, so the escape sequences of
+ * regular expressions like "\." are kept as they are.
+ */
+fn read_string(data: &str) -> Result<(String, usize), String> {
+    let mut value = String::new();
+    let mut chars = data.char_indices().skip(1);
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, i + 1)),
+            '\\' => match chars.next() {
+                Some((_, c)) if c == '"' || c == '\\' => value.push(c),
+                Some((_, c)) => {
+                    value.push('\\');
+                    value.push(c);
+                }
+                None => break,
+            },
+            _ => value.push(c),
+        }
+    }
+
+    Err(String::from("unterminated string"))
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Field {
+    Target,
+    Prereq,
+}
+
+#[derive(Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Match(Field, Regex),
+    Equals(Field, String),
+    StartsWith(Field, String),
+    EndsWith(Field, String),
+    Contains(Field, String),
+    Count(&'static str, usize),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        self.pos += 1;
+        self.tokens.get(self.pos - 1)
+    }
+
+    fn accept(&mut self, op: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Op(x)) if *x == op);
+        self.pos += found as usize;
+        found
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.parse_and()?;
+
+        while self.accept("||") {
+            lhs = Expr::Or(Box::new(lhs), Box::new(self.parse_and()?));
+        }
+
+        Ok(lhs)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.parse_unary()?;
+
+        while self.accept("&&") {
+            lhs = Expr::And(Box::new(lhs), Box::new(self.parse_unary()?));
+        }
+
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        if self.accept("!") {
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+
+        if self.accept("(") {
+            let expr = self.parse_or()?;
+
+            if !self.accept(")") {
+                return Err(String::from("expected \")\""));
+            }
+
+            return Ok(expr);
+        }
+
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, String> {
+        let field = match self.next() {
+            Some(Token::Ident(name)) => name.clone(),
+            _ => {
+                return Err(String::from(
+                    "expected \"target\", \"prereq\", or \"count\"",
+                ))
+            }
+        };
+
+        let op = match self.next() {
+            Some(Token::Op(op)) => String::from(*op),
+            Some(Token::Ident(op)) => op.clone(),
+            _ => return Err(format!("expected an operator after \"{field}\"")),
+        };
+
+        if field == "count" {
+            let op = ["==", "!=", "<", "<=", ">", ">="]
+                .into_iter()
+                .find(|x| *x == op)
+                .ok_or_else(|| {
+                    format!("invalid operator \"{op}\" for \"count\"")
+                })?;
+
+            return match self.next() {
+                Some(Token::Num(num)) => Ok(Expr::Count(op, *num)),
+                _ => Err(String::from("expected a number after \"count\"")),
+            };
+        }
+
+        let field = match field.as_str() {
+            "target" => Field::Target,
+            "prereq" => Field::Prereq,
+            _ => return Err(format!("unknown field \"{field}\"")),
+        };
+
+        let value = match self.next() {
+            Some(Token::Str(value)) => value.clone(),
+            _ => return Err(format!("expected a string after \"{op}\"")),
+        };
+
+        let expr = match op.as_str() {
+            "~" | "!~" => Expr::Match(field, Regex::new(&value)?),
+            "==" | "!=" => Expr::Equals(field, value),
+            "startswith" => Expr::StartsWith(field, value),
+            "endswith" => Expr::EndsWith(field, value),
+            "contains" => Expr::Contains(field, value),
+            _ => return Err(format!("unknown operator \"{op}\"")),
+        };
+
+        match op.as_str() {
+            "!~" | "!=" => Ok(Expr::Not(Box::new(expr))),
+            _ => Ok(expr),
+        }
+    }
+}
+
+impl Expr {
+    fn eval(&self, target: &str, prereq: &str, count: usize) -> bool {
+        let get = |field| match field {
+            Field::Target => target,
+            Field::Prereq => prereq,
+        };
+
+        match self {
+            Expr::And(lhs, rhs) => {
+                lhs.eval(target, prereq, count)
+                    && rhs.eval(target, prereq, count)
+            }
+            Expr::Or(lhs, rhs) => {
+                lhs.eval(target, prereq, count)
+                    || rhs.eval(target, prereq, count)
+            }
+            Expr::Not(expr) => !expr.eval(target, prereq, count),
+            Expr::Match(field, regex) => {
+                regex.captures(get(*field), 0).is_some()
+            }
+            Expr::Equals(field, value) => get(*field) == value,
+            Expr::StartsWith(field, value) => {
+                get(*field).starts_with(value.as_str())
+            }
+            Expr::EndsWith(field, value) => {
+                get(*field).ends_with(value.as_str())
+            }
+            Expr::Contains(field, value) => {
+                get(*field).contains(value.as_str())
+            }
+            Expr::Count(op, num) => match *op {
+                "==" => count == *num,
+                "!=" => count != *num,
+                "<" => count < *num,
+                "<=" => count <= *num,
+                ">" => count > *num,
+                _ => count >= *num,
+            },
+        }
+    }
+}
+
+/*
+ * Filter the dependencies with an expression like
+ * 'prereq ~ "\.h$" && target startswith "src/"'. The expression is
+ * evaluated for each pair of target and prerequisite, where "count" is the
+ * number of prerequisites of the target. Rules without a remaining
+ * prerequisite are removed.
+ */
+pub struct Query {
+    expr: Expr,
+}
+
+impl Query {
+    pub fn parse(data: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(data)?,
+            pos: 0,
+        };
+
+        let expr = parser.parse_or()?;
+
+        if parser.peek().is_some() {
+            return Err(String::from("unexpected input after the expression"));
+        }
+
+        Ok(Self { expr })
+    }
+
+    pub fn filter<'a>(&self, deps: &[Dependency<'a>]) -> Vec<Dependency<'a>> {
+        let mut result = Vec::with_capacity(deps.len());
+
+        for dep in deps {
+            let count = dep.prerequisites.len();
+            let prerequisites: Vec<_> = dep
+                .prerequisites
+                .iter()
+                .filter(|x| self.expr.eval(dep.target, x, count))
+                .copied()
+                .collect();
+
+            if !prerequisites.is_empty() {
+                result.push(Dependency {
+                    target: dep.target,
+                    prerequisites,
+                });
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn do_filter(query: &str) -> Vec<(&'static str, Vec<&'static str>)> {
+        let vec = Vec::from([
+            Dependency {
+                target: "src/a.o",
+                prerequisites: Vec::from(["src/a.c", "src/a.h", "inc/b.h"]),
+            },
+            Dependency {
+                target: "lib/b.o",
+                prerequisites: Vec::from(["lib/b.c", "inc/b.h"]),
+            },
+        ]);
+
+        Query::parse(query)
+            .unwrap()
+            .filter(&vec)
+            .into_iter()
+            .map(|x| (x.target, x.prerequisites))
+            .collect()
+    }
+
+    /**
+     * Query::filter()
+     *
+     * Verify that the expression is evaluated for each pair of target and
+     * prerequisite.
+     */
+    #[test]
+    fn filter_001() {
+        let tests = [
+            (
+                r#"prereq ~ "\.h$" && target startswith "src/""#,
+                Vec::from([("src/a.o", Vec::from(["src/a.h", "inc/b.h"]))]),
+            ),
+            (
+                r#"count >= 3 || prereq == "lib/b.c""#,
+                Vec::from([
+                    ("src/a.o", Vec::from(["src/a.c", "src/a.h", "inc/b.h"])),
+                    ("lib/b.o", Vec::from(["lib/b.c"])),
+                ]),
+            ),
+            (
+                r#"!(prereq contains "inc/") && prereq !~ "\.c$""#,
+                Vec::from([("src/a.o", Vec::from(["src/a.h"]))]),
+            ),
+            (r#"target endswith ".a""#, Vec::new()),
+        ];
+
+        for (query, expected) in tests {
+            assert_eq!(expected, do_filter(query), "{query}");
+        }
+    }
+
+    /**
+     * Query::parse()
+     *
+     * Verify that invalid expressions are rejected.
+     */
+    #[test]
+    fn parse_001() {
+        let tests = [
+            "",
+            "target",
+            "target ~",
+            "target ~ 3",
+            "count ~ \"a\"",
+            "count > \"a\"",
+            "path == \"a\"",
+            "prereq == \"a",
+            "(prereq == \"a\"",
+            "prereq == \"a\" prereq",
+            "prereq ~ \"(\"",
+            "prereq like \"a\"",
+        ];
+
+        for query in tests {
+            assert!(Query::parse(query).is_err(), "{query}");
+        }
+    }
+}
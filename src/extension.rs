@@ -0,0 +1,110 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dependency::Dependency;
+
+/*
+ * Return the extension of the file name of "path" without the '.'. Files
+ * without an extension and hidden files like ".clang-format" have none.
+ */
+fn extension(path: &str) -> Option<&str> {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+
+    match name.rsplit_once('.') {
+        Some(("", _)) | None => None,
+        Some((_, ext)) => Some(ext),
+    }
+}
+
+/*
+ * Keep or remove the prerequisites with one of the given extensions. The
+ * extensions are given as comma-separated lists, e.g. "h,hpp,.inc", and
+ * are compared case-sensitively.
+ */
+pub struct ExtensionFilter {
+    extensions: Vec<String>,
+    keep: bool,
+}
+
+impl ExtensionFilter {
+    pub fn new(lists: &[String], keep: bool) -> Self {
+        let extensions = lists
+            .iter()
+            .flat_map(|x| x.split(','))
+            .map(|x| x.trim().trim_start_matches('.'))
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect();
+
+        Self { extensions, keep }
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        let found = match extension(path) {
+            Some(ext) => self.extensions.iter().any(|x| x == ext),
+            None => false,
+        };
+
+        found == self.keep
+    }
+
+    pub fn filter<'a>(&self, deps: &[Dependency<'a>]) -> Vec<Dependency<'a>> {
+        deps.iter()
+            .map(|dep| Dependency {
+                target: dep.target,
+                prerequisites: dep
+                    .prerequisites
+                    .iter()
+                    .filter(|x| self.matches(x))
+                    .copied()
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * ExtensionFilter::matches()
+     *
+     * Verify that only the last extension of the file name is compared.
+     */
+    #[test]
+    fn matches_001() {
+        let only = ExtensionFilter::new(&[String::from("h, .hpp,inc")], true);
+        let drop = ExtensionFilter::new(&[String::from("h")], false);
+
+        let tests = [
+            ("include/a.h", true, false),
+            ("a.hpp", true, true),
+            ("gen/table.inc", true, true),
+            ("a.H", false, true),
+            ("a.h.in", false, true),
+            ("dir.h/Makefile", false, true),
+            ("C:\\dir.h\\file", false, true),
+            (".h", false, true),
+        ];
+
+        for (path, expected_only, expected_drop) in tests {
+            assert_eq!(expected_only, only.matches(path), "{path}");
+            assert_eq!(expected_drop, drop.matches(path), "{path}");
+        }
+    }
+}
@@ -0,0 +1,97 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dependency::Dependency;
+use crate::hash;
+
+/*
+ * Calculate the SHA-256 digest of "deps" in hexadecimal. The rules and
+ * their prerequisites are sorted first, so the fingerprint only changes
+ * if the dependencies do and not if they are just found in a different
+ * order. Each path is terminated by a NUL character and each rule by
+ * another one, which keeps the encoding unambiguous.
+ */
+pub fn compute(deps: &[Dependency]) -> String {
+    let mut rules: Vec<_> = deps.iter().collect();
+    rules.sort_unstable_by_key(|x| x.target);
+
+    let mut buf = Vec::new();
+    let mut prereqs = Vec::new();
+
+    for dep in rules {
+        prereqs.clear();
+        prereqs.extend_from_slice(&dep.prerequisites);
+        prereqs.sort_unstable();
+        prereqs.dedup();
+
+        for path in [dep.target].iter().chain(&prereqs) {
+            buf.extend_from_slice(path.as_bytes());
+            buf.push(0);
+        }
+
+        buf.push(0);
+    }
+
+    hash::to_hex(&hash::sha256(&buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dep<'a>(target: &'a str, prereqs: &[&'a str]) -> Dependency<'a> {
+        Dependency {
+            target,
+            prerequisites: Vec::from(prereqs),
+        }
+    }
+
+    /**
+     * compute()
+     *
+     * Verify that the fingerprint does not depend on the order of the
+     * rules and prerequisites, but on the dependencies themselves.
+     */
+    #[test]
+    fn compute_001() {
+        let a = compute(&[dep("a.o", &["a.c", "a.h"]), dep("b.o", &["b.c"])]);
+        let b = compute(&[dep("b.o", &["b.c"]), dep("a.o", &["a.h", "a.c"])]);
+
+        assert_eq!(64, a.len());
+        assert_eq!(a, b);
+
+        let c = compute(&[dep("a.o", &["a.c"]), dep("b.o", &["a.h", "b.c"])]);
+        assert_ne!(a, c);
+
+        let d = compute(&[dep("a.o", &["a.c", "a.h", "b.o", "b.c"])]);
+        assert_ne!(a, d);
+    }
+
+    /**
+     * compute()
+     *
+     * Verify that the fingerprint of no dependencies is the digest of the
+     * empty input.
+     */
+    #[test]
+    fn compute_002() {
+        assert_eq!(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            compute(&[])
+        );
+    }
+}
@@ -0,0 +1,145 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::str;
+
+use crate::dependency::DependencyBuf;
+
+/*
+ * The recorder writes "./" in front of some relative paths, but not all,
+ * so it is removed to find duplicates.
+ */
+fn normalize(path: &str) -> &str {
+    path.strip_prefix("./").unwrap_or(path)
+}
+
+/*
+ * Read the dependencies from a ".fls" file written by TeX engines with
+ * "-recorder", e.g. by latexmk. An "INPUT" line names a file which was
+ * read and an "OUTPUT" line a file which was written. Each output becomes
+ * a target with the inputs as prerequisites, except for files which are
+ * written as well, like the ".aux" file, as they would depend on
+ * themselves. The "PWD" line is skipped, so relative paths stay relative.
+ */
+pub fn parse_dependencies(data: &[u8]) -> Result<Vec<DependencyBuf>, String> {
+    let data = str::from_utf8(data)
+        .map_err(|err| format!("invalid UTF-8 input: {err}"))?;
+
+    let mut inputs: Vec<&str> = Vec::new();
+    let mut outputs: Vec<&str> = Vec::new();
+
+    for (i, line) in data.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (kind, path) = line.split_once(' ').unwrap_or((line, ""));
+        let path = normalize(path);
+
+        if path.is_empty() {
+            return Err(format!("line {}: missing path", i + 1));
+        }
+
+        let list = match kind {
+            "INPUT" => &mut inputs,
+            "OUTPUT" => &mut outputs,
+            "PWD" => continue,
+            _ => {
+                return Err(format!("line {}: invalid entry \"{kind}\"", i + 1))
+            }
+        };
+
+        if !list.contains(&path) {
+            list.push(path);
+        }
+    }
+
+    let prerequisites: Vec<_> = inputs
+        .into_iter()
+        .filter(|x| !outputs.contains(x))
+        .map(String::from)
+        .collect();
+
+    let deps = outputs
+        .into_iter()
+        .map(|x| DependencyBuf {
+            target: String::from(x),
+            prerequisites: prerequisites.clone(),
+        })
+        .collect();
+
+    Ok(deps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * parse_dependencies()
+     *
+     * Verify that each output depends on the inputs which are not written
+     * as well and that duplicates are removed.
+     */
+    #[test]
+    fn parse_dependencies_001() {
+        let data = "\
+PWD /home/user/doc
+INPUT /usr/share/texmf/tex/latex/base/article.cls
+INPUT ./main.tex
+INPUT main.tex
+OUTPUT main.log\r
+INPUT main.aux
+OUTPUT main.aux
+INPUT figures/my plot.pdf
+OUTPUT main.pdf
+";
+
+        let deps = parse_dependencies(data.as_bytes()).unwrap();
+        let prerequisites = Vec::from([
+            "/usr/share/texmf/tex/latex/base/article.cls",
+            "main.tex",
+            "figures/my plot.pdf",
+        ]);
+
+        assert_eq!(3, deps.len());
+        assert_eq!("main.log", deps[0].target);
+        assert_eq!("main.aux", deps[1].target);
+        assert_eq!("main.pdf", deps[2].target);
+
+        for dep in &deps {
+            assert_eq!(prerequisites, dep.prerequisites);
+        }
+    }
+
+    /**
+     * parse_dependencies()
+     *
+     * Verify that lines which are not written by the recorder are
+     * rejected.
+     */
+    #[test]
+    fn parse_dependencies_002() {
+        let result = parse_dependencies(b"INPUT a.tex\nREAD b.tex\n");
+        assert_eq!(Err(String::from("line 2: invalid entry \"READ\"")), result);
+
+        let result = parse_dependencies(b"OUTPUT\n");
+        assert_eq!(Err(String::from("line 1: missing path")), result);
+    }
+}
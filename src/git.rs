@@ -0,0 +1,138 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/*
+ * Run git with "args" in the directory "dir" and return its output.
+ */
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|err| format!("failed to run git: {err}"))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        return Err(format!("git failed: {}", stderr.trim()));
+    }
+
+    String::from_utf8(output.stdout)
+        .map_err(|_| String::from("git returned invalid UTF-8"))
+}
+
+/*
+ * Return the absolute "path" relative to the absolute directory "base",
+ * e.g. "../lib/a.h" for "/src/lib/a.h" and "/src/app".
+ */
+fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let mut path = path.components().peekable();
+    let mut base = base.components().peekable();
+
+    while path.peek().is_some() && path.peek() == base.peek() {
+        path.next();
+        base.next();
+    }
+
+    let mut result: PathBuf = base.map(|_| Component::ParentDir).collect();
+    result.extend(path);
+
+    result
+}
+
+/*
+ * Ask git for the files which were modified since "reference", including
+ * uncommitted changes and untracked files in the working tree. The files
+ * of the whole repository are considered, not only those below the
+ * current working directory. Each path is returned relative to the current
+ * working directory and in its absolute form to match dependency files
+ * using either.
+ */
+pub fn changed_since(reference: &str) -> Result<Vec<String>, String> {
+    let cwd = env::current_dir()
+        .and_then(fs::canonicalize)
+        .map_err(|err| format!("failed to get the current directory: {err}"))?;
+
+    let root = git(&cwd, &["rev-parse", "--show-toplevel"])?;
+    let root = PathBuf::from(root.trim_end_matches('\n'));
+
+    let args = [
+        "diff",
+        "-z",
+        "--name-only",
+        "--no-relative",
+        reference,
+        "--",
+    ];
+    let changed = git(&root, &args)?;
+
+    let args = ["ls-files", "-z", "--others", "--exclude-standard"];
+    let untracked = git(&root, &args)?;
+
+    let mut result = Vec::new();
+
+    for line in changed.split('\0').chain(untracked.split('\0')) {
+        if line.is_empty() {
+            continue;
+        }
+
+        let path = root.join(line);
+
+        if let Some(path) = path.to_str() {
+            result.push(String::from(path));
+        }
+
+        if let Some(path) = relative_to(&path, &cwd).to_str() {
+            result.push(String::from(path));
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * relative_to()
+     *
+     * Verify that paths inside and outside of the base directory are
+     * returned relative to it.
+     */
+    #[test]
+    fn relative_to_001() {
+        let tests = [
+            ("/src/app/a.c", "/src/app", "a.c"),
+            ("/src/app/x/a.c", "/src/app", "x/a.c"),
+            ("/src/lib/a.h", "/src/app", "../lib/a.h"),
+            ("/src/lib/a.h", "/src/app/x", "../../lib/a.h"),
+        ];
+
+        for (path, base, expected) in tests {
+            let result = relative_to(Path::new(path), Path::new(base));
+
+            assert_eq!(Path::new(expected), result, "{path}");
+        }
+    }
+}
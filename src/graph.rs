@@ -0,0 +1,1291 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::cmp;
+use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
+use std::hash::BuildHasherDefault;
+use std::mem;
+
+use crate::dependency::Dependency;
+use crate::hash::PathHasher;
+
+type NodeMap<'a> = HashMap<&'a str, usize, BuildHasherDefault<PathHasher>>;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Component<'a> {
+    pub size: usize,
+    pub targets: Vec<&'a str>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Orphans<'a> {
+    pub prerequisites: Vec<&'a str>,
+    pub targets: Vec<&'a str>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Metrics<'a> {
+    pub node: &'a str,
+    pub fan_in: usize,
+    pub fan_out: usize,
+    pub transitive_fan_in: Option<usize>,
+    pub transitive_fan_out: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Hotspot<'a> {
+    pub prerequisite: &'a str,
+    pub direct: usize,
+    pub transitive: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CriticalPath<'a> {
+    pub length: usize,
+    pub chains: Vec<Vec<&'a str>>,
+    pub depths: Vec<(&'a str, usize)>,
+}
+
+/*
+ * Every target and every prerequisite is a node within the graph. An edge
+ * points from a target to each of its prerequisites. Nodes are numbered
+ * in the order of their first appearance within the dependencies, which
+ * keeps the results of all analyses deterministic.
+ */
+pub struct Graph<'a> {
+    nodes: Vec<&'a str>,
+    index: NodeMap<'a>,
+    edges: Vec<Vec<usize>>,
+    targets: Vec<usize>,
+}
+
+impl<'a> Graph<'a> {
+    pub fn new(deps: &[Dependency<'a>]) -> Self {
+        let capacity = 2 * deps.len();
+        let hasher = BuildHasherDefault::<PathHasher>::default();
+
+        let mut graph = Self {
+            nodes: Vec::with_capacity(capacity),
+            index: HashMap::with_capacity_and_hasher(capacity, hasher),
+            edges: Vec::with_capacity(capacity),
+            targets: Vec::with_capacity(deps.len()),
+        };
+
+        for dep in deps {
+            let id = graph.insert(dep.target);
+            graph.targets.push(id);
+
+            for prereq in &dep.prerequisites {
+                let prereq_id = graph.insert(prereq);
+                graph.edges[id].push(prereq_id);
+            }
+        }
+
+        graph
+    }
+
+    fn insert(&mut self, name: &'a str) -> usize {
+        match self.index.entry(name) {
+            Entry::Occupied(entry) => *entry.get(),
+            Entry::Vacant(entry) => {
+                let id = self.nodes.len();
+
+                entry.insert(id);
+                self.nodes.push(name);
+                self.edges.push(Vec::new());
+
+                id
+            }
+        }
+    }
+
+    pub fn nodes(&self) -> &[&'a str] {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &[Vec<usize>] {
+        &self.edges
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.index.contains_key(name)
+    }
+
+    /*
+     * Find the chains of dependencies leading from node "from" to node "to".
+     * By default only one of the shortest chains is returned. If "all" is
+     * set, every chain without a repeated node is returned instead. Be aware
+     * that the number of these chains can grow exponentially.
+     */
+    pub fn paths(&self, from: &str, to: &str, all: bool) -> Vec<Vec<&'a str>> {
+        let (from, to) = match (self.index.get(from), self.index.get(to)) {
+            (Some(&from), Some(&to)) => (from, to),
+            _ => return Vec::new(),
+        };
+
+        let paths = if all {
+            self.all_paths(from, to)
+        } else {
+            self.shortest_path(from, to).into_iter().collect()
+        };
+
+        paths
+            .into_iter()
+            .map(|path| path.into_iter().map(|id| self.nodes[id]).collect())
+            .collect()
+    }
+
+    fn shortest_path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let mut parents = vec![usize::MAX; self.nodes.len()];
+        let mut queue = VecDeque::from([from]);
+
+        parents[from] = from;
+
+        while let Some(id) = queue.pop_front() {
+            if id == to {
+                let mut path = Vec::from([to]);
+                let mut id = to;
+
+                while id != from {
+                    id = parents[id];
+                    path.push(id);
+                }
+
+                path.reverse();
+
+                return Some(path);
+            }
+
+            for &next in &self.edges[id] {
+                if parents[next] == usize::MAX {
+                    parents[next] = id;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn all_paths(&self, from: usize, to: usize) -> Vec<Vec<usize>> {
+        let mut result = Vec::new();
+        let mut visited = vec![false; self.nodes.len()];
+        let mut path = Vec::from([from]);
+
+        /* Each stack entry holds the index of the next edge to follow. */
+        let mut stack = Vec::from([0]);
+
+        visited[from] = true;
+
+        while let Some(next) = stack.last_mut() {
+            let id = *path.last().unwrap();
+
+            if id == to || *next >= self.edges[id].len() {
+                if id == to {
+                    result.push(path.clone());
+                }
+
+                visited[id] = false;
+                path.pop();
+                stack.pop();
+                continue;
+            }
+
+            let prereq = self.edges[id][*next];
+            *next += 1;
+
+            if !visited[prereq] {
+                visited[prereq] = true;
+                path.push(prereq);
+                stack.push(0);
+            }
+        }
+
+        result
+    }
+
+    fn referenced(&self) -> Vec<bool> {
+        let mut referenced = vec![false; self.nodes.len()];
+
+        for edges in &self.edges {
+            for &prereq in edges {
+                referenced[prereq] = true;
+            }
+        }
+
+        referenced
+    }
+
+    /*
+     * Return the targets which are not a prerequisite of any other rule,
+     * e.g. the final binaries.
+     */
+    pub fn roots(&self) -> Vec<&'a str> {
+        let referenced = self.referenced();
+
+        self.targets
+            .iter()
+            .filter(|&&id| !referenced[id])
+            .map(|&id| self.nodes[id])
+            .collect()
+    }
+
+    /*
+     * Report the prerequisites which are not reachable from any of the
+     * given roots and the targets which are not a prerequisite of anything.
+     * Without roots, the unreferenced targets are used as the root set.
+     */
+    pub fn orphans(&self, roots: &[String]) -> Orphans<'a> {
+        let referenced = self.referenced();
+        let unreferenced = self.targets.iter().filter(|&&id| !referenced[id]);
+
+        let roots: Vec<usize> = if roots.is_empty() {
+            unreferenced.clone().copied().collect()
+        } else {
+            roots
+                .iter()
+                .filter_map(|x| self.index.get(x.as_str()))
+                .copied()
+                .collect()
+        };
+
+        let reachable = self.reachable(&roots);
+
+        let prerequisites = (0..self.nodes.len())
+            .filter(|&id| referenced[id] && !reachable[id])
+            .map(|id| self.nodes[id])
+            .collect();
+
+        Orphans {
+            prerequisites,
+            targets: unreferenced.map(|&id| self.nodes[id]).collect(),
+        }
+    }
+
+    /*
+     * Calculate the number of direct predecessors (fan-in) and successors
+     * (fan-out) of each node. With "transitive" set, the number of nodes
+     * from which a node can be reached and the number of nodes reachable
+     * from it are calculated as well.
+     */
+    pub fn metrics(&self, transitive: bool) -> Vec<Metrics<'a>> {
+        let reversed = self.reversed();
+        let mut marks = vec![usize::MAX; self.nodes.len()];
+        let mut stack = Vec::new();
+
+        let mut count = |edges: &[Vec<usize>], id: usize, mark: usize| {
+            Self::count_reachable(edges, id, mark, &mut marks, &mut stack)
+        };
+
+        let mut result = Vec::with_capacity(self.nodes.len());
+
+        for (id, &node) in self.nodes.iter().enumerate() {
+            let (transitive_fan_in, transitive_fan_out) = if transitive {
+                let fan_in = count(&reversed, id, 2 * id);
+                let fan_out = count(&self.edges, id, 2 * id + 1);
+
+                (Some(fan_in), Some(fan_out))
+            } else {
+                (None, None)
+            };
+
+            result.push(Metrics {
+                node,
+                fan_in: reversed[id].len(),
+                fan_out: self.edges[id].len(),
+                transitive_fan_in,
+                transitive_fan_out,
+            });
+        }
+
+        result
+    }
+
+    /*
+     * Rank the prerequisites by the number of targets which depend on
+     * them transitively. Ties are resolved by the number of targets which
+     * depend on them directly and then by the order of their appearance.
+     */
+    pub fn hotspots(&self) -> Vec<Hotspot<'a>> {
+        let reversed = self.reversed();
+        let mut marks = vec![usize::MAX; self.nodes.len()];
+        let mut stack = Vec::new();
+
+        /* Only the fan-in is needed, so the fan-out is not calculated */
+        let mut result: Vec<Hotspot> = reversed
+            .iter()
+            .enumerate()
+            .filter(|(_, edges)| !edges.is_empty())
+            .map(|(id, edges)| Hotspot {
+                prerequisite: self.nodes[id],
+                direct: edges.len(),
+                transitive: Self::count_reachable(
+                    &reversed, id, id, &mut marks, &mut stack,
+                ),
+            })
+            .collect();
+
+        result.sort_by(|a, b| {
+            b.transitive
+                .cmp(&a.transitive)
+                .then(b.direct.cmp(&a.direct))
+        });
+
+        result
+    }
+
+    /*
+     * Calculate the depth of each target, i.e. the length of the longest
+     * chain of dependencies starting at the target, and report the longest
+     * chains within the graph. Edges closing a cycle are ignored.
+     */
+    pub fn critical_path(&self) -> CriticalPath<'a> {
+        const UNVISITED: u8 = 0;
+        const ACTIVE: u8 = 1;
+        const DONE: u8 = 2;
+
+        let len = self.nodes.len();
+        let mut states = vec![UNVISITED; len];
+        let mut depths = vec![0; len];
+        let mut next = vec![usize::MAX; len];
+        let mut stack = Vec::new();
+
+        for &root in &self.targets {
+            if states[root] != UNVISITED {
+                continue;
+            }
+
+            states[root] = ACTIVE;
+            stack.push((root, 0));
+
+            while let Some((id, index)) = stack.last_mut() {
+                let id = *id;
+
+                if let Some(&prereq) = self.edges[id].get(*index) {
+                    *index += 1;
+
+                    if states[prereq] == UNVISITED {
+                        states[prereq] = ACTIVE;
+                        stack.push((prereq, 0));
+                    }
+
+                    continue;
+                }
+
+                for &prereq in &self.edges[id] {
+                    if states[prereq] == DONE && depths[prereq] + 1 > depths[id]
+                    {
+                        depths[id] = depths[prereq] + 1;
+                        next[id] = prereq;
+                    }
+                }
+
+                states[id] = DONE;
+                stack.pop();
+            }
+        }
+
+        let length = self
+            .targets
+            .iter()
+            .map(|&id| depths[id])
+            .max()
+            .unwrap_or_default();
+
+        let chains = self
+            .targets
+            .iter()
+            .filter(|&&id| length != 0 && depths[id] == length)
+            .map(|&id| {
+                let mut chain = Vec::from([self.nodes[id]]);
+                let mut id = id;
+
+                while next[id] != usize::MAX {
+                    id = next[id];
+                    chain.push(self.nodes[id]);
+                }
+
+                chain
+            })
+            .collect();
+
+        CriticalPath {
+            length,
+            chains,
+            depths: self
+                .targets
+                .iter()
+                .map(|&id| (self.nodes[id], depths[id]))
+                .collect(),
+        }
+    }
+
+    /*
+     * Group the targets into batches which can be built in parallel. A
+     * target is in the first batch after those of all targets it depends
+     * on, so the batches can be built one after another. The targets of a
+     * batch are ordered like the nodes. Targets within a cycle cannot be
+     * ordered, so the targets of the first cycle are returned as error.
+     */
+    pub fn batches(&self) -> Result<Vec<Vec<&'a str>>, Vec<&'a str>> {
+        let len = self.nodes.len();
+        let mut is_target = vec![false; len];
+
+        for &id in &self.targets {
+            is_target[id] = true;
+        }
+
+        let mut counts = vec![0; len];
+        let mut dependents = vec![Vec::new(); len];
+
+        for &id in &self.targets {
+            let mut prereqs: Vec<_> = self.edges[id]
+                .iter()
+                .copied()
+                .filter(|&x| is_target[x])
+                .collect();
+
+            prereqs.sort_unstable();
+            prereqs.dedup();
+
+            counts[id] = prereqs.len();
+
+            for prereq in prereqs {
+                dependents[prereq].push(id);
+            }
+        }
+
+        let mut batch: Vec<usize> = (0..len)
+            .filter(|&id| is_target[id] && counts[id] == 0)
+            .collect();
+        let mut result = Vec::new();
+        let mut done = 0;
+
+        while !batch.is_empty() {
+            let mut next = Vec::new();
+
+            for &id in &batch {
+                for &dependent in &dependents[id] {
+                    counts[dependent] -= 1;
+
+                    if counts[dependent] == 0 {
+                        next.push(dependent);
+                    }
+                }
+            }
+
+            next.sort_unstable();
+            done += batch.len();
+
+            result.push(batch.iter().map(|&id| self.nodes[id]).collect());
+            batch = next;
+        }
+
+        if done != is_target.iter().filter(|&&x| x).count() {
+            let left: Vec<_> = (0..len)
+                .map(|id| is_target[id] && counts[id] != 0)
+                .collect();
+
+            let cycle = self
+                .find_cycle(&left)
+                .into_iter()
+                .map(|id| self.nodes[id])
+                .collect();
+
+            return Err(cycle);
+        }
+
+        Ok(result)
+    }
+
+    /*
+     * Find the strongly connected components of the nodes in "subset" and
+     * return the first one with a cycle, i.e. with several nodes or a node
+     * depending on itself. Nodes which only depend on a cycle are not part
+     * of it. The nodes of the cycle are ordered like the nodes.
+     */
+    fn find_cycle(&self, subset: &[bool]) -> Vec<usize> {
+        let len = self.nodes.len();
+        let mut visited = vec![false; len];
+        let mut order = Vec::new();
+
+        /* Record the nodes in the order their search finishes */
+        for root in (0..len).filter(|&id| subset[id]) {
+            if visited[root] {
+                continue;
+            }
+
+            visited[root] = true;
+
+            let mut stack = Vec::from([(root, 0)]);
+
+            while let Some((id, index)) = stack.last_mut() {
+                let id = *id;
+
+                if let Some(&prereq) = self.edges[id].get(*index) {
+                    *index += 1;
+
+                    if subset[prereq] && !visited[prereq] {
+                        visited[prereq] = true;
+                        stack.push((prereq, 0));
+                    }
+
+                    continue;
+                }
+
+                order.push(id);
+                stack.pop();
+            }
+        }
+
+        /*
+         * Each search on the reversed edges in the reverse order visits
+         * exactly one strongly connected component.
+         */
+        let reversed = self.reversed();
+        let mut done = vec![false; len];
+        let mut result: Vec<usize> = Vec::new();
+
+        for &root in order.iter().rev() {
+            if done[root] {
+                continue;
+            }
+
+            done[root] = true;
+
+            let mut stack = Vec::from([root]);
+            let mut component = Vec::new();
+
+            while let Some(id) = stack.pop() {
+                component.push(id);
+
+                for &dependent in &reversed[id] {
+                    if subset[dependent] && !done[dependent] {
+                        done[dependent] = true;
+                        stack.push(dependent);
+                    }
+                }
+            }
+
+            component.sort_unstable();
+
+            let cycle = component.len() > 1 || self.edges[root].contains(&root);
+
+            if cycle && (result.is_empty() || component[0] < result[0]) {
+                result = component;
+            }
+        }
+
+        result
+    }
+
+    fn reversed(&self) -> Vec<Vec<usize>> {
+        let mut result = vec![Vec::new(); self.nodes.len()];
+
+        for (id, edges) in self.edges.iter().enumerate() {
+            for &prereq in edges {
+                result[prereq].push(id);
+            }
+        }
+
+        result
+    }
+
+    /*
+     * Return the targets which directly or transitively depend on any of
+     * the given files. A leading "./" of a node is ignored when comparing
+     * it with the files.
+     */
+    pub fn affected(&self, files: &[String]) -> Vec<&'a str> {
+        let files: HashSet<&str> = files.iter().map(|x| x.as_str()).collect();
+
+        let changed: Vec<usize> = (0..self.nodes.len())
+            .filter(|&id| {
+                let node = self.nodes[id];
+
+                files.contains(node.strip_prefix("./").unwrap_or(node))
+            })
+            .collect();
+
+        let affected = Self::reachable_in(&self.reversed(), &changed);
+
+        self.targets
+            .iter()
+            .filter(|&&id| affected[id])
+            .map(|&id| self.nodes[id])
+            .collect()
+    }
+
+    /*
+     * Restrict the dependencies to the rules whose targets are reachable
+     * from the given roots.
+     */
+    pub fn subgraph(
+        &self,
+        deps: &[Dependency<'a>],
+        roots: &[String],
+    ) -> Vec<Dependency<'a>> {
+        let roots: Vec<usize> = roots
+            .iter()
+            .filter_map(|x| self.index.get(x.as_str()))
+            .copied()
+            .collect();
+
+        let reachable = self.reachable(&roots);
+
+        deps.iter()
+            .filter(|dep| reachable[self.index[dep.target]])
+            .cloned()
+            .collect()
+    }
+
+    fn reachable(&self, roots: &[usize]) -> Vec<bool> {
+        Self::reachable_in(&self.edges, roots)
+    }
+
+    /*
+     * Count the nodes reachable from "id" via "edges". The nodes are marked
+     * with "mark" in "marks", which has to be unique for each call, so the
+     * marks do not have to be reset between calls.
+     */
+    fn count_reachable(
+        edges: &[Vec<usize>],
+        id: usize,
+        mark: usize,
+        marks: &mut [usize],
+        stack: &mut Vec<usize>,
+    ) -> usize {
+        let mut result = 0;
+
+        stack.push(id);
+        marks[id] = mark;
+
+        while let Some(id) = stack.pop() {
+            for &next in &edges[id] {
+                if marks[next] != mark {
+                    marks[next] = mark;
+                    stack.push(next);
+                    result += 1;
+                }
+            }
+        }
+
+        result
+    }
+
+    fn reachable_in(edges: &[Vec<usize>], roots: &[usize]) -> Vec<bool> {
+        let mut visited = vec![false; edges.len()];
+        let mut stack = Vec::from(roots);
+
+        while let Some(id) = stack.pop() {
+            if !mem::replace(&mut visited[id], true) {
+                stack.extend_from_slice(&edges[id]);
+            }
+        }
+
+        visited
+    }
+
+    /*
+     * Group the targets into weakly connected components, i.e. the
+     * direction of the edges is ignored.
+     */
+    pub fn components(&self) -> Vec<Component<'a>> {
+        let mut sets = DisjointSet::new(self.nodes.len());
+
+        for (id, edges) in self.edges.iter().enumerate() {
+            for &prereq in edges {
+                sets.union(id, prereq);
+            }
+        }
+
+        let mut sizes = vec![0; self.nodes.len()];
+
+        for id in 0..self.nodes.len() {
+            sizes[sets.find(id)] += 1;
+        }
+
+        let mut map: HashMap<usize, usize> = HashMap::new();
+        let mut result: Vec<Component> = Vec::new();
+
+        for &id in &self.targets {
+            let root = sets.find(id);
+
+            let index = *map.entry(root).or_insert_with(|| {
+                result.push(Component {
+                    size: sizes[root],
+                    targets: Vec::new(),
+                });
+
+                result.len() - 1
+            });
+
+            result[index].targets.push(self.nodes[id]);
+        }
+
+        result
+    }
+}
+
+struct DisjointSet {
+    parents: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+        }
+    }
+
+    fn find(&mut self, id: usize) -> usize {
+        let mut root = id;
+
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+
+        /* Path compression for subsequent lookups */
+        let mut id = id;
+
+        while self.parents[id] != root {
+            let next = self.parents[id];
+            self.parents[id] = root;
+            id = next;
+        }
+
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let a = self.find(a);
+        let b = self.find(b);
+
+        if a != b {
+            self.parents[cmp::max(a, b)] = cmp::min(a, b);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_dep<'a>(target: &'a str, prereqs: &[&'a str]) -> Dependency<'a> {
+        Dependency {
+            target,
+            prerequisites: Vec::from(prereqs),
+        }
+    }
+
+    /**
+     * Graph::new()
+     *
+     * Verify that targets and prerequisites are only inserted once.
+     */
+    #[test]
+    fn new_001() {
+        let deps =
+            Vec::from([make_dep("a", &["c", "d"]), make_dep("b", &["d"])]);
+
+        let graph = Graph::new(&deps);
+
+        assert_eq!(Vec::from(["a", "c", "d", "b"]), graph.nodes);
+        assert_eq!(Vec::from([0, 3]), graph.targets);
+        assert_eq!(Vec::from([1, 2]), graph.edges[0]);
+        assert_eq!(Vec::from([2]), graph.edges[3]);
+    }
+
+    /**
+     * Graph::paths()
+     *
+     * Verify that the function correctly deals with unknown nodes.
+     */
+    #[test]
+    fn paths_001() {
+        let deps = Vec::from([make_dep("a", &["b"])]);
+
+        let graph = Graph::new(&deps);
+
+        assert!(graph.paths("a", "x", false).is_empty());
+        assert!(graph.paths("x", "b", true).is_empty());
+    }
+
+    /**
+     * Graph::paths()
+     *
+     * Verify that the function returns the shortest chain by default.
+     */
+    #[test]
+    fn paths_002() {
+        let deps = Vec::from([
+            make_dep("a", &["b", "c"]),
+            make_dep("b", &["d"]),
+            make_dep("d", &["c"]),
+        ]);
+
+        let graph = Graph::new(&deps);
+        let paths = graph.paths("a", "c", false);
+
+        assert_eq!(1, paths.len());
+        assert_eq!(Vec::from(["a", "c"]), paths[0]);
+    }
+
+    /**
+     * Graph::paths()
+     *
+     * Verify that the function returns every chain if requested and is
+     * not caught in a cycle.
+     */
+    #[test]
+    fn paths_003() {
+        let deps = Vec::from([
+            make_dep("a", &["b", "c"]),
+            make_dep("b", &["d", "a"]),
+            make_dep("d", &["c"]),
+        ]);
+
+        let graph = Graph::new(&deps);
+        let paths = graph.paths("a", "c", true);
+
+        assert_eq!(2, paths.len());
+        assert_eq!(Vec::from(["a", "b", "d", "c"]), paths[0]);
+        assert_eq!(Vec::from(["a", "c"]), paths[1]);
+    }
+
+    /**
+     * Graph::paths()
+     *
+     * Verify that no chain is returned if the nodes are not connected.
+     */
+    #[test]
+    fn paths_004() {
+        let deps = Vec::from([make_dep("a", &["b"]), make_dep("c", &["d"])]);
+
+        let graph = Graph::new(&deps);
+
+        assert!(graph.paths("a", "d", false).is_empty());
+        assert!(graph.paths("b", "a", true).is_empty());
+    }
+
+    /**
+     * Graph::orphans()
+     *
+     * Verify that the unreferenced targets are used as roots if none
+     * are specified.
+     */
+    #[test]
+    fn orphans_001() {
+        let deps = Vec::from([
+            make_dep("a", &["b", "c"]),
+            make_dep("b", &["d"]),
+            make_dep("e", &["f"]),
+            make_dep("f", &["e"]),
+        ]);
+
+        let graph = Graph::new(&deps);
+        let orphans = graph.orphans(&[]);
+
+        assert_eq!(Vec::from(["a"]), orphans.targets);
+        assert_eq!(Vec::from(["e", "f"]), orphans.prerequisites);
+    }
+
+    /**
+     * Graph::orphans()
+     *
+     * Verify that only prerequisites reachable from the given roots are
+     * not reported.
+     */
+    #[test]
+    fn orphans_002() {
+        let deps =
+            Vec::from([make_dep("a", &["c"]), make_dep("b", &["c", "d"])]);
+
+        let graph = Graph::new(&deps);
+        let orphans = graph.orphans(&[String::from("a")]);
+
+        assert_eq!(Vec::from(["a", "b"]), orphans.targets);
+        assert_eq!(Vec::from(["d"]), orphans.prerequisites);
+    }
+
+    /**
+     * Graph::affected()
+     *
+     * Verify that all targets transitively depending on a changed file
+     * are returned in order.
+     */
+    #[test]
+    fn affected_001() {
+        let deps = Vec::from([
+            make_dep("a", &["./b.h", "c.h"]),
+            make_dep("d", &["e.h"]),
+            make_dep("f", &["a"]),
+        ]);
+
+        let graph = Graph::new(&deps);
+        let affected = graph.affected(&[String::from("b.h")]);
+
+        assert_eq!(Vec::from(["a", "f"]), affected);
+    }
+
+    /**
+     * Graph::affected()
+     *
+     * Verify that the function correctly deals with unknown files.
+     */
+    #[test]
+    fn affected_002() {
+        let deps = Vec::from([make_dep("a", &["b"])]);
+
+        let graph = Graph::new(&deps);
+
+        assert!(graph.affected(&[String::from("x")]).is_empty());
+    }
+
+    /**
+     * Graph::subgraph()
+     *
+     * Verify that only the rules reachable from the roots are kept.
+     */
+    #[test]
+    fn subgraph_001() {
+        let deps = Vec::from([
+            make_dep("a", &["b"]),
+            make_dep("b", &["c"]),
+            make_dep("d", &["c"]),
+            make_dep("e", &["a"]),
+        ]);
+
+        let graph = Graph::new(&deps);
+        let subgraph = graph.subgraph(&deps, &[String::from("a")]);
+
+        assert_eq!(2, subgraph.len());
+        assert_eq!(deps[0], subgraph[0]);
+        assert_eq!(deps[1], subgraph[1]);
+    }
+
+    /**
+     * Graph::subgraph()
+     *
+     * Verify that the function correctly deals with unknown roots.
+     */
+    #[test]
+    fn subgraph_002() {
+        let deps = Vec::from([make_dep("a", &["b"])]);
+
+        let graph = Graph::new(&deps);
+
+        assert!(graph.subgraph(&deps, &[String::from("x")]).is_empty());
+    }
+
+    /**
+     * Graph::metrics()
+     *
+     * Verify that only the direct fan-in and fan-out are calculated by
+     * default.
+     */
+    #[test]
+    fn metrics_001() {
+        let deps =
+            Vec::from([make_dep("a", &["b", "c"]), make_dep("b", &["c"])]);
+
+        let graph = Graph::new(&deps);
+        let metrics = graph.metrics(false);
+
+        assert_eq!(3, metrics.len());
+
+        assert_eq!("a", metrics[0].node);
+        assert_eq!(0, metrics[0].fan_in);
+        assert_eq!(2, metrics[0].fan_out);
+        assert_eq!(None, metrics[0].transitive_fan_in);
+        assert_eq!(None, metrics[0].transitive_fan_out);
+
+        assert_eq!("c", metrics[2].node);
+        assert_eq!(2, metrics[2].fan_in);
+        assert_eq!(0, metrics[2].fan_out);
+    }
+
+    /**
+     * Graph::metrics()
+     *
+     * Verify that the transitive fan-in and fan-out are calculated
+     * correctly, even in the presence of a cycle.
+     */
+    #[test]
+    fn metrics_002() {
+        let deps = Vec::from([
+            make_dep("a", &["b"]),
+            make_dep("b", &["c"]),
+            make_dep("c", &["b", "d"]),
+        ]);
+
+        let graph = Graph::new(&deps);
+        let metrics = graph.metrics(true);
+
+        assert_eq!(Some(0), metrics[0].transitive_fan_in);
+        assert_eq!(Some(3), metrics[0].transitive_fan_out);
+
+        assert_eq!(Some(2), metrics[1].transitive_fan_in);
+        assert_eq!(Some(2), metrics[1].transitive_fan_out);
+
+        assert_eq!(Some(3), metrics[3].transitive_fan_in);
+        assert_eq!(Some(0), metrics[3].transitive_fan_out);
+    }
+
+    /**
+     * Graph::hotspots()
+     *
+     * Verify that the prerequisites are ranked by the number of targets
+     * depending on them.
+     */
+    #[test]
+    fn hotspots_001() {
+        let deps = Vec::from([
+            make_dep("a", &["x", "y"]),
+            make_dep("b", &["y", "z"]),
+            make_dep("z", &["x"]),
+        ]);
+
+        let graph = Graph::new(&deps);
+        let hotspots = graph.hotspots();
+
+        assert_eq!(3, hotspots.len());
+
+        assert_eq!("x", hotspots[0].prerequisite);
+        assert_eq!(2, hotspots[0].direct);
+        assert_eq!(3, hotspots[0].transitive);
+
+        assert_eq!("y", hotspots[1].prerequisite);
+        assert_eq!(2, hotspots[1].direct);
+        assert_eq!(2, hotspots[1].transitive);
+
+        assert_eq!("z", hotspots[2].prerequisite);
+        assert_eq!(1, hotspots[2].direct);
+        assert_eq!(1, hotspots[2].transitive);
+    }
+
+    /**
+     * Graph::critical_path()
+     *
+     * Verify that the function correctly deals with an empty graph.
+     */
+    #[test]
+    fn critical_path_001() {
+        let graph = Graph::new(&[]);
+        let path = graph.critical_path();
+
+        assert_eq!(0, path.length);
+        assert!(path.chains.is_empty());
+        assert!(path.depths.is_empty());
+    }
+
+    /**
+     * Graph::critical_path()
+     *
+     * Verify that the longest chain and the depth of each target are
+     * reported correctly.
+     */
+    #[test]
+    fn critical_path_002() {
+        let deps = Vec::from([
+            make_dep("a", &["x", "b"]),
+            make_dep("b", &["c"]),
+            make_dep("c", &["x"]),
+            make_dep("d", &["x"]),
+        ]);
+
+        let graph = Graph::new(&deps);
+        let path = graph.critical_path();
+
+        assert_eq!(3, path.length);
+        assert_eq!(Vec::from([Vec::from(["a", "b", "c", "x"])]), path.chains);
+        assert_eq!(
+            Vec::from([("a", 3), ("b", 2), ("c", 1), ("d", 1)]),
+            path.depths
+        );
+    }
+
+    /**
+     * Graph::critical_path()
+     *
+     * Verify that the function terminates in the presence of a cycle.
+     */
+    #[test]
+    fn critical_path_003() {
+        let deps = Vec::from([make_dep("a", &["b"]), make_dep("b", &["a"])]);
+
+        let graph = Graph::new(&deps);
+        let path = graph.critical_path();
+
+        assert_eq!(1, path.length);
+        assert_eq!(Vec::from([Vec::from(["a", "b"])]), path.chains);
+        assert_eq!(Vec::from([("a", 1), ("b", 0)]), path.depths);
+    }
+
+    /**
+     * Graph::components()
+     *
+     * Verify that the function correctly deals with an empty graph.
+     */
+    #[test]
+    fn components_001() {
+        let graph = Graph::new(&[]);
+
+        assert!(graph.components().is_empty());
+    }
+
+    /**
+     * Graph::components()
+     *
+     * Verify that targets sharing a prerequisite end up in the same
+     * component while unrelated targets do not.
+     */
+    #[test]
+    fn components_002() {
+        let deps = Vec::from([
+            make_dep("a", &["x"]),
+            make_dep("b", &["y"]),
+            make_dep("c", &["x", "z"]),
+        ]);
+
+        let graph = Graph::new(&deps);
+        let components = graph.components();
+
+        assert_eq!(2, components.len());
+        assert_eq!(4, components[0].size);
+        assert_eq!(Vec::from(["a", "c"]), components[0].targets);
+        assert_eq!(2, components[1].size);
+        assert_eq!(Vec::from(["b"]), components[1].targets);
+    }
+
+    /**
+     * Graph::components()
+     *
+     * Verify that a prerequisite which is a target itself links the
+     * components of both rules.
+     */
+    #[test]
+    fn components_003() {
+        let deps = Vec::from([
+            make_dep("a", &["b"]),
+            make_dep("c", &["d"]),
+            make_dep("b", &["d"]),
+        ]);
+
+        let graph = Graph::new(&deps);
+        let components = graph.components();
+
+        assert_eq!(1, components.len());
+        assert_eq!(4, components[0].size);
+        assert_eq!(Vec::from(["a", "c", "b"]), components[0].targets);
+    }
+
+    /**
+     * Graph::batches()
+     *
+     * Verify that each target is placed in the batch after those of the
+     * targets it depends on.
+     */
+    #[test]
+    fn batches_001() {
+        let deps = Vec::from([
+            make_dep("app", &["a.o", "b.o", "lib.a"]),
+            make_dep("lib.a", &["c.o"]),
+            make_dep("a.o", &["a.c", "gen.h"]),
+            make_dep("b.o", &["b.c"]),
+            make_dep("c.o", &["c.c"]),
+            make_dep("gen.h", &[]),
+        ]);
+
+        let graph = Graph::new(&deps);
+
+        let expected = Vec::from([
+            Vec::from(["b.o", "c.o", "gen.h"]),
+            Vec::from(["a.o", "lib.a"]),
+            Vec::from(["app"]),
+        ]);
+
+        assert_eq!(Ok(expected), graph.batches());
+        assert_eq!(Ok(Vec::new()), Graph::new(&[]).batches());
+    }
+
+    /**
+     * Graph::batches()
+     *
+     * Verify that the targets within a cycle are returned as error.
+     */
+    #[test]
+    fn batches_002() {
+        let deps = Vec::from([
+            make_dep("a", &["b"]),
+            make_dep("b", &["c"]),
+            make_dep("c", &["b"]),
+            make_dep("d", &["x"]),
+        ]);
+
+        let graph = Graph::new(&deps);
+
+        assert_eq!(Err(Vec::from(["b", "c"])), graph.batches());
+    }
+
+    /**
+     * Graph::batches()
+     *
+     * Verify that only the targets of a cycle are returned as error and
+     * not those depending on it, and that a target depending on itself is
+     * a cycle.
+     */
+    #[test]
+    fn batches_003() {
+        let deps = Vec::from([
+            make_dep("a", &["b"]),
+            make_dep("b", &["c"]),
+            make_dep("c", &["a"]),
+            make_dep("d", &["a"]),
+        ]);
+
+        let graph = Graph::new(&deps);
+
+        assert_eq!(Err(Vec::from(["a", "b", "c"])), graph.batches());
+
+        let deps = Vec::from([make_dep("e", &["e"]), make_dep("f", &["e"])]);
+        let graph = Graph::new(&deps);
+
+        assert_eq!(Err(Vec::from(["e"])), graph.batches());
+    }
+
+    /**
+     * Graph::roots()
+     *
+     * Verify that the function returns the targets which are not a
+     * prerequisite of another rule.
+     */
+    #[test]
+    fn roots_001() {
+        let deps = Vec::from([
+            make_dep("a.o", &["a.c"]),
+            make_dep("app", &["a.o", "lib.a"]),
+            make_dep("lib.a", &["b.o"]),
+            make_dep("test", &["a.o"]),
+            make_dep("loop", &["loop"]),
+        ]);
+
+        let graph = Graph::new(&deps);
+
+        assert_eq!(Vec::from(["app", "test"]), graph.roots());
+    }
+}
@@ -0,0 +1,306 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::graph::Graph;
+use crate::json::JsonSerializer;
+
+const HEADER: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>dep2j</title>
+<style>
+html, body { margin: 0; height: 100%; overflow: hidden; font: 14px sans-serif; }
+#search { position: absolute; top: 10px; left: 10px; width: 300px; padding: 4px; }
+#info { position: absolute; bottom: 10px; left: 10px; background: #fffd; }
+canvas { display: block; }
+</style>
+</head>
+<body>
+<canvas id="canvas"></canvas>
+<input id="search" type="search" placeholder="Search...">
+<div id="info"></div>
+<script>
+const graph = "##;
+
+const FOOTER: &str = r##";
+const canvas = document.getElementById("canvas");
+const search = document.getElementById("search");
+const info = document.getElementById("info");
+const ctx = canvas.getContext("2d");
+const nodes = graph.nodes;
+const links = graph.links;
+const view = { x: 0, y: 0, scale: 1 };
+let highlight = null;
+let hover = null;
+let alpha = 1;
+
+nodes.forEach((node, i) => {
+    const angle = 0.5 * i;
+    const radius = 10 * Math.sqrt(i);
+
+    node.x = radius * Math.cos(angle);
+    node.y = radius * Math.sin(angle);
+    node.vx = 0;
+    node.vy = 0;
+    node.links = [];
+});
+
+links.forEach((link) => {
+    nodes[link.source].links.push(link.target);
+    nodes[link.target].links.push(link.source);
+});
+
+function resize() {
+    canvas.width = window.innerWidth;
+    canvas.height = window.innerHeight;
+}
+
+function tick() {
+    const sample = Math.min(nodes.length, 64);
+
+    for (const node of nodes) {
+        for (let i = 0; i < sample; ++i) {
+            const other = nodes[Math.floor(Math.random() * nodes.length)];
+            const dx = node.x - other.x;
+            const dy = node.y - other.y;
+            const dist = dx * dx + dy * dy + 0.01;
+            const force = 50 * nodes.length / sample / dist;
+
+            node.vx += dx * force * alpha;
+            node.vy += dy * force * alpha;
+        }
+
+        node.vx -= 0.01 * node.x * alpha;
+        node.vy -= 0.01 * node.y * alpha;
+    }
+
+    for (const link of links) {
+        const a = nodes[link.source];
+        const b = nodes[link.target];
+        const dx = b.x - a.x;
+        const dy = b.y - a.y;
+        const dist = Math.sqrt(dx * dx + dy * dy) + 0.01;
+        const force = 0.05 * (dist - 30) / dist * alpha;
+
+        a.vx += dx * force;
+        a.vy += dy * force;
+        b.vx -= dx * force;
+        b.vy -= dy * force;
+    }
+
+    for (const node of nodes) {
+        node.vx *= 0.6;
+        node.vy *= 0.6;
+        node.x += node.vx;
+        node.y += node.vy;
+    }
+
+    alpha = Math.max(0.995 * alpha, 0.02);
+}
+
+function isVisible(node) {
+    return !highlight || highlight.has(node);
+}
+
+function draw() {
+    ctx.setTransform(1, 0, 0, 1, 0, 0);
+    ctx.clearRect(0, 0, canvas.width, canvas.height);
+    ctx.setTransform(view.scale, 0, 0, view.scale,
+                     canvas.width / 2 + view.x, canvas.height / 2 + view.y);
+
+    ctx.lineWidth = 0.5 / view.scale;
+
+    for (const link of links) {
+        const a = nodes[link.source];
+        const b = nodes[link.target];
+
+        ctx.strokeStyle = isVisible(a) && isVisible(b) ? "#999" : "#eee";
+        ctx.beginPath();
+        ctx.moveTo(a.x, a.y);
+        ctx.lineTo(b.x, b.y);
+        ctx.stroke();
+    }
+
+    for (const node of nodes) {
+        ctx.fillStyle = isVisible(node) ? "#1f77b4" : "#ddd";
+        ctx.beginPath();
+        ctx.arc(node.x, node.y, 3 + Math.sqrt(node.links.length), 0, 6.3);
+        ctx.fill();
+    }
+
+    if (hover) {
+        ctx.fillStyle = "#000";
+        ctx.font = 14 / view.scale + "px sans-serif";
+        ctx.fillText(hover.id, hover.x + 8, hover.y - 8);
+    }
+}
+
+function frame() {
+    if (alpha > 0.02) {
+        tick();
+    }
+
+    draw();
+    window.requestAnimationFrame(frame);
+}
+
+function toGraph(event) {
+    return {
+        x: (event.clientX - canvas.width / 2 - view.x) / view.scale,
+        y: (event.clientY - canvas.height / 2 - view.y) / view.scale,
+    };
+}
+
+let drag = null;
+
+canvas.addEventListener("mousedown", (event) => {
+    drag = { x: event.clientX - view.x, y: event.clientY - view.y };
+});
+
+window.addEventListener("mouseup", () => {
+    drag = null;
+});
+
+canvas.addEventListener("mousemove", (event) => {
+    if (drag) {
+        view.x = event.clientX - drag.x;
+        view.y = event.clientY - drag.y;
+        return;
+    }
+
+    const pos = toGraph(event);
+    const limit = 64 / (view.scale * view.scale);
+
+    hover = null;
+
+    for (const node of nodes) {
+        const dx = node.x - pos.x;
+        const dy = node.y - pos.y;
+
+        if (dx * dx + dy * dy < limit) {
+            hover = node;
+        }
+    }
+
+    info.textContent = hover ? hover.id + " (" + hover.links.length + ")" : "";
+});
+
+canvas.addEventListener("wheel", (event) => {
+    event.preventDefault();
+    view.scale *= event.deltaY < 0 ? 1.1 : 1 / 1.1;
+});
+
+search.addEventListener("input", () => {
+    const text = search.value.trim();
+
+    if (!text) {
+        highlight = null;
+        return;
+    }
+
+    highlight = new Set();
+
+    for (const node of nodes) {
+        if (node.id.includes(text)) {
+            highlight.add(node);
+            node.links.forEach((i) => highlight.add(nodes[i]));
+        }
+    }
+});
+
+window.addEventListener("resize", resize);
+
+resize();
+frame();
+</script>
+</body>
+</html>
+"##;
+
+/*
+ * Generate a standalone HTML document which contains the graph and
+ * a small viewer using a force-directed layout.
+ */
+pub struct HtmlSerializer {
+    buf: Vec<u8>,
+}
+
+impl HtmlSerializer {
+    pub fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    pub fn get_html(&self) -> &[u8] {
+        self.buf.as_slice()
+    }
+
+    pub fn write_graph(&mut self, graph: &Graph) {
+        let mut serializer = JsonSerializer::new();
+        serializer.write_d3(graph);
+
+        let json = serializer.get_json();
+
+        self.buf.reserve(HEADER.len() + json.len() + FOOTER.len());
+        self.buf.extend_from_slice(HEADER.as_bytes());
+
+        /*
+         * The graph is embedded in a script element. Escaping '<' ensures
+         * that a path like "</script>" cannot terminate the element.
+         */
+        for &byte in json {
+            if byte == b'<' {
+                self.buf.extend_from_slice(b"\\u003c");
+            } else {
+                self.buf.push(byte);
+            }
+        }
+
+        self.buf.extend_from_slice(FOOTER.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dependency::Dependency;
+
+    /**
+     * HtmlSerializer::write_graph()
+     *
+     * Verify that the graph is embedded into the page and that a path cannot
+     * close the script element.
+     */
+    #[test]
+    fn write_graph_001() {
+        let vec = Vec::from([Dependency {
+            target: "</script>",
+            prerequisites: Vec::from(["b"]),
+        }]);
+
+        let graph = Graph::new(&vec);
+
+        let mut serializer = HtmlSerializer::new();
+        serializer.write_graph(&graph);
+
+        let html = String::from_utf8_lossy(serializer.get_html());
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("{\"id\":\"\\u003c/script>\"}"));
+        assert_eq!(1, html.matches("</script>").count());
+    }
+}
@@ -0,0 +1,227 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str;
+
+const DIRECTIVES: &[(&[u8], bool)] = &[
+    (b"include", false),
+    (b"-include", true),
+    (b"sinclude", true),
+];
+
+fn split_directive(line: &[u8]) -> Option<(bool, &[u8])> {
+    let start = line.iter().position(|&x| x != b' ')?;
+    let line = &line[start..];
+
+    let (optional, rest) = DIRECTIVES.iter().find_map(|(name, optional)| {
+        line.strip_prefix(*name).map(|rest| (*optional, rest))
+    })?;
+
+    matches!(rest.first(), Some(b' ' | b'\t')).then_some((optional, rest))
+}
+
+/*
+ * Return whether "line" starts with an include directive. It is
+ * sufficient to pass the first few bytes of a line.
+ */
+pub fn is_directive(line: &[u8]) -> bool {
+    split_directive(line).is_some()
+}
+
+/*
+ * Return whether "data" might contain an include directive. This is much
+ * faster than looking for the directives and rules out most input.
+ */
+pub fn has_directives(data: &[u8]) -> bool {
+    data.windows(7).any(|x| x == b"include")
+}
+
+/*
+ * Return whether the included files are optional and the included files
+ * if "line" is an include directive.
+ */
+pub fn parse_directive(line: &[u8]) -> Option<(bool, &str)> {
+    let (optional, rest) = split_directive(line)?;
+
+    Some((optional, str::from_utf8(rest).ok()?.trim()))
+}
+
+/*
+ * Replace the include directives of Makefile-style input with the content
+ * of the included files. Relative paths are resolved relative to the
+ * directory of the including file. Each file is included at most once,
+ * which also protects against cycles. Files included with "-include" or
+ * "sinclude" are ignored if they do not exist.
+ */
+pub struct Includer {
+    visited: HashSet<PathBuf>,
+}
+
+impl Includer {
+    pub fn new() -> Self {
+        Self {
+            visited: HashSet::new(),
+        }
+    }
+
+    pub fn expand(
+        &mut self,
+        path: &Path,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, String> {
+        if !has_directives(&data) {
+            return Ok(data);
+        }
+
+        if let Ok(path) = fs::canonicalize(path) {
+            self.visited.insert(path);
+        }
+
+        let mut buf = Vec::with_capacity(data.len());
+        self.expand_into(path, &data, &mut buf)?;
+
+        Ok(buf)
+    }
+
+    fn expand_into(
+        &mut self,
+        path: &Path,
+        data: &[u8],
+        buf: &mut Vec<u8>,
+    ) -> Result<(), String> {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let mut continued = false;
+
+        for line in data.split_inclusive(|&x| x == b'\n') {
+            let directive = match continued {
+                true => None,
+                false => parse_directive(line),
+            };
+
+            continued = line.ends_with(b"\\\n") || line.ends_with(b"\\\r\n");
+
+            let (optional, files) = match directive {
+                Some(val) => val,
+                None => {
+                    buf.extend_from_slice(line);
+                    continue;
+                }
+            };
+
+            for file in files.split_ascii_whitespace() {
+                let file = dir.join(file);
+
+                let canonical = match fs::canonicalize(&file) {
+                    Ok(val) => val,
+                    Err(_) if optional => continue,
+                    Err(err) => {
+                        let file = file.display();
+                        return Err(format!(
+                            "failed to include \"{file}\": {err}"
+                        ));
+                    }
+                };
+
+                if !self.visited.insert(canonical) {
+                    continue;
+                }
+
+                let data = fs::read(&file).map_err(|err| {
+                    let file = file.display();
+                    format!("failed to include \"{file}\": {err}")
+                })?;
+
+                if !buf.is_empty() && !buf.ends_with(b"\n") {
+                    buf.push(b'\n');
+                }
+
+                self.expand_into(&file, &data, buf)?;
+
+                if !buf.ends_with(b"\n") {
+                    buf.push(b'\n');
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    /**
+     * parse_directive()
+     *
+     * Verify that include directives are recognized and that rules with
+     * similar targets are not.
+     */
+    #[test]
+    fn parse_directive_001() {
+        fn do_parse(line: &str) -> Option<(bool, &str)> {
+            parse_directive(line.as_bytes())
+        }
+
+        assert_eq!(Some((false, "a.d b.d")), do_parse("include a.d b.d\n"));
+        assert_eq!(Some((true, "a.d")), do_parse("  -include a.d"));
+        assert_eq!(Some((true, "a.d")), do_parse("sinclude\ta.d\n"));
+        assert_eq!(None, do_parse("include: a.c\n"));
+        assert_eq!(None, do_parse("includes.o: a.c\n"));
+        assert_eq!(None, do_parse("\tinclude a.d\n"));
+    }
+
+    /**
+     * Includer::expand()
+     *
+     * Verify that included files are inserted at the location of the
+     * directive, relative to the including file, and only once.
+     */
+    #[test]
+    fn expand_001() {
+        let mut dir = env::temp_dir();
+        dir.push(format!("dep2j-include-{}", process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+
+        fs::write(dir.join("sub/b.d"), "b.o: b.c\ninclude ../c.d\n").unwrap();
+        fs::write(dir.join("c.d"), "c.o: c.c\n-include sub/b.d none.d")
+            .unwrap();
+
+        let data = Vec::from(
+            "a.o: a.c \\\ninclude x.h\ninclude sub/b.d\n-include none.d\nd.o:\n",
+        );
+
+        let mut includer = Includer::new();
+        let result = includer.expand(&dir.join("a.d"), data);
+
+        assert_eq!(
+            Ok(Vec::from(
+                "a.o: a.c \\\ninclude x.h\nb.o: b.c\nc.o: c.c\nd.o:\n"
+            )),
+            result
+        );
+
+        let result = includer.expand(&dir.join("a.d"), Vec::from("include x"));
+        assert!(result.is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,206 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+#[cfg(not(unix))]
+use std::path::PathBuf;
+
+use crate::dependency::{Dependency, DependencyBuf};
+use crate::json::{Attributes, Value};
+
+/*
+ * The device and the inode number of a file.
+ */
+#[cfg(unix)]
+type FileId = (u64, u64);
+
+#[cfg(unix)]
+fn file_id(path: &str) -> Option<FileId> {
+    fs::metadata(path).ok().map(|x| (x.dev(), x.ino()))
+}
+
+/*
+ * Without inode numbers, a file is identified by its canonical path, so
+ * only symbolic links are merged.
+ */
+#[cfg(not(unix))]
+type FileId = PathBuf;
+
+#[cfg(not(unix))]
+fn file_id(path: &str) -> Option<FileId> {
+    fs::canonicalize(path).ok()
+}
+
+/*
+ * The prerequisites after merging the paths which refer to the same file
+ * and the paths merged into each canonical path.
+ */
+pub struct Deduped {
+    pub deps: Vec<DependencyBuf>,
+    pub aliases: HashMap<String, Vec<String>>,
+}
+
+/*
+ * Merge the prerequisites which are hard links or symbolic links to the
+ * same file. Paths with the same device and inode number are replaced by
+ * the canonical path of the first one, duplicates within a rule are
+ * removed. A file reached by a single path is kept as it is, as are paths
+ * which cannot be accessed.
+ */
+pub fn dedupe(deps: &[Dependency]) -> Deduped {
+    let mut inodes: HashMap<FileId, Vec<&str>> = HashMap::new();
+    let mut keys = HashSet::new();
+
+    for &prereq in deps.iter().flat_map(|dep| &dep.prerequisites) {
+        if keys.contains(prereq) {
+            continue;
+        }
+
+        if let Some(key) = file_id(prereq) {
+            inodes.entry(key).or_default().push(prereq);
+        }
+
+        keys.insert(prereq);
+    }
+
+    let mut canonical = HashMap::new();
+    let mut aliases = HashMap::new();
+
+    for paths in inodes.into_values().filter(|x| x.len() > 1) {
+        let path = match fs::canonicalize(paths[0]) {
+            Ok(path) => path.to_string_lossy().into_owned(),
+            Err(_) => String::from(paths[0]),
+        };
+
+        for &alias in &paths {
+            canonical.insert(alias, path.clone());
+        }
+
+        aliases.insert(path, paths.into_iter().map(String::from).collect());
+    }
+
+    let deps = deps
+        .iter()
+        .map(|dep| {
+            let mut prerequisites: Vec<String> = Vec::new();
+
+            for &prereq in &dep.prerequisites {
+                let path = match canonical.get(prereq) {
+                    Some(path) => path.clone(),
+                    None => String::from(prereq),
+                };
+
+                if !prerequisites.contains(&path) {
+                    prerequisites.push(path);
+                }
+            }
+
+            DependencyBuf {
+                target: String::from(dep.target),
+                prerequisites,
+            }
+        })
+        .collect();
+
+    Deduped { deps, aliases }
+}
+
+/*
+ * The "aliases" of each canonical path in the order they were found.
+ */
+pub fn attributes(
+    aliases: &HashMap<String, Vec<String>>,
+) -> HashMap<String, Attributes> {
+    aliases
+        .iter()
+        .map(|(path, vec)| {
+            let values = vec.iter().cloned().map(Value::String).collect();
+
+            (path.clone(), Vec::from([("aliases", Value::Array(values))]))
+        })
+        .collect()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::os::unix::fs::symlink;
+    use std::process;
+
+    /**
+     * dedupe(), attributes()
+     *
+     * Verify that hard links and symbolic links to the same file are
+     * replaced by its canonical path, while other and missing files are
+     * kept as they are.
+     */
+    #[test]
+    fn dedupe_001() {
+        let mut dir = env::temp_dir();
+        dir.push(format!("dep2j-inode-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let dir = fs::canonicalize(&dir).unwrap();
+        let file = dir.join("a.h");
+        let link = dir.join("b.h");
+        let hard = dir.join("c.h");
+        let other = dir.join("d.h");
+
+        fs::write(&file, b"a").unwrap();
+        fs::write(&other, b"d").unwrap();
+        symlink(&file, &link).unwrap();
+        fs::hard_link(&file, &hard).unwrap();
+
+        let file = file.to_str().unwrap();
+        let link = link.to_str().unwrap();
+        let hard = hard.to_str().unwrap();
+        let other = other.to_str().unwrap();
+
+        let vec = Vec::from([
+            Dependency {
+                target: "x.o",
+                prerequisites: Vec::from([link, other, file, "/nonexistent"]),
+            },
+            Dependency {
+                target: "y.o",
+                prerequisites: Vec::from([hard]),
+            },
+        ]);
+
+        let result = dedupe(&vec);
+
+        assert_eq!(2, result.deps.len());
+        assert_eq!(
+            Vec::from([file, other, "/nonexistent"]),
+            result.deps[0].prerequisites
+        );
+        assert_eq!(Vec::from([file]), result.deps[1].prerequisites);
+        assert_eq!(1, result.aliases.len());
+        assert_eq!(Vec::from([link, file, hard]), result.aliases[file]);
+
+        let map = attributes(&result.aliases);
+        assert!(
+            matches!(&map[file][0], ("aliases", Value::Array(x)) if x.len() == 3)
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
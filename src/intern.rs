@@ -0,0 +1,75 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+
+use crate::hash::PathHasher;
+
+/*
+ * Assign a unique id to each distinct path. The paths themselves are not
+ * copied since they are borrowed from the parsed data. Using the ids as
+ * keys keeps sets of paths small, e.g. a header referenced by thousands of
+ * rules is hashed only once and each reference is stored in four bytes.
+ */
+pub struct Interner<'a> {
+    ids: HashMap<&'a str, u32, BuildHasherDefault<PathHasher>>,
+}
+
+impl<'a> Interner<'a> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        let hasher = BuildHasherDefault::<PathHasher>::default();
+
+        Self {
+            ids: HashMap::with_capacity_and_hasher(capacity, hasher),
+        }
+    }
+
+    pub fn intern(&mut self, path: &'a str) -> u32 {
+        if let Some(&id) = self.ids.get(path) {
+            return id;
+        }
+
+        let id = u32::try_from(self.ids.len()).expect("too many paths");
+
+        self.ids.insert(path, id);
+
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * Interner::intern()
+     *
+     * Verify that identical paths are mapped to the same id and that the
+     * ids are assigned in the order of the first occurrence.
+     */
+    #[test]
+    fn intern_001() {
+        let data = String::from("a.h b.h a.h");
+        let mut interner = Interner::with_capacity(0);
+
+        let ids: Vec<u32> =
+            data.split(' ').map(|x| interner.intern(x)).collect();
+
+        assert_eq!(Vec::from([0, 1, 0]), ids);
+    }
+}
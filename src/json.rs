@@ -15,24 +15,302 @@
  * along with this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
-use crate::dependency::Dependency;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::{self, Write};
+use std::{mem, str, thread};
+
+use crate::conflict::Conflict;
+use crate::dependency::{Dependency, DependencyBuf};
+use crate::diag::Severity;
+use crate::diff::Diff;
+use crate::graph::{Component, CriticalPath, Graph, Hotspot, Metrics, Orphans};
+
+/*
+ * Additional members which are written into the JSON object of a
+ * dependency.
+ */
+pub type Attributes = Vec<(&'static str, Value)>;
+
+/*
+ * The minimum number of dependencies for which "write_vec()" uses multiple
+ * threads. Below, starting the threads takes longer than the serialization.
+ */
+const PARALLEL_THRESHOLD: usize = 4096;
 
 pub struct JsonSerializer {
     buf: Vec<u8>,
+    attributes: Vec<Attributes>,
+    target_attributes: HashMap<String, Attributes>,
+    prerequisite_attributes: Option<HashMap<String, Attributes>>,
 }
 
 impl JsonSerializer {
     pub fn new() -> Self {
-        Self { buf: Vec::new() }
+        Self {
+            buf: Vec::new(),
+            attributes: Vec::new(),
+            target_attributes: HashMap::new(),
+            prerequisite_attributes: None,
+        }
+    }
+
+    /*
+     * Add attributes to the dependencies written by "write_vec()". The
+     * attributes are matched with the dependencies by their index.
+     */
+    pub fn add_attributes(&mut self, attributes: Vec<Attributes>) {
+        if self.attributes.is_empty() {
+            self.attributes = attributes;
+            return;
+        }
+
+        for (attrs, new) in self.attributes.iter_mut().zip(attributes) {
+            attrs.extend(new);
+        }
+    }
+
+    /*
+     * Add attributes to the dependencies written by "write_vec()" which
+     * are matched with the dependencies by their target.
+     */
+    pub fn add_target_attributes(
+        &mut self,
+        attributes: HashMap<String, Attributes>,
+    ) {
+        for (target, attrs) in attributes {
+            self.target_attributes
+                .entry(target)
+                .or_default()
+                .extend(attrs);
+        }
+    }
+
+    /*
+     * Add attributes to the prerequisites written by "write_vec()". Once
+     * attributes are added, each prerequisite is written as an object
+     * with its path and the attributes which were added for it.
+     */
+    pub fn add_prerequisite_attributes(
+        &mut self,
+        attributes: HashMap<String, Attributes>,
+    ) {
+        let map = self
+            .prerequisite_attributes
+            .get_or_insert_with(HashMap::new);
+
+        for (path, attrs) in attributes {
+            map.entry(path).or_default().extend(attrs);
+        }
     }
 
     pub fn get_json(&self) -> &[u8] {
         self.buf.as_slice()
     }
 
-    pub fn write_vec(&mut self, vec: &Vec<Dependency>) {
+    pub fn write_vec(&mut self, vec: &[Dependency]) {
+        let threads = thread::available_parallelism().map_or(1, |x| x.get());
+
+        if threads > 1 && vec.len() >= PARALLEL_THRESHOLD {
+            self.write_vec_parallel(vec, threads);
+            return;
+        }
+
         self.buf.reserve(4096 * vec.len());
 
+        let result = self.write_vec_with(vec, |_| Ok(()));
+        debug_assert!(result.is_ok());
+    }
+
+    /*
+     * Write the dependencies like "write_vec()", but split them into one
+     * chunk per thread. Each thread serializes its chunk into a buffer of
+     * its own and the buffers are joined in order.
+     */
+    fn write_vec_parallel(&mut self, vec: &[Dependency], threads: usize) {
+        let attributes = mem::take(&mut self.attributes);
+        let target_attributes = mem::take(&mut self.target_attributes);
+        let prerequisite_attributes = self.prerequisite_attributes.take();
+
+        let chunk_size = vec.len().div_ceil(threads).max(1);
+
+        let bufs: Vec<Vec<u8>> = thread::scope(|scope| {
+            let handles: Vec<_> = vec
+                .chunks(chunk_size)
+                .enumerate()
+                .map(|(n, chunk)| {
+                    let attributes = &attributes;
+                    let target_attributes = &target_attributes;
+                    let prerequisite_attributes = &prerequisite_attributes;
+
+                    scope.spawn(move || {
+                        let mut serializer = JsonSerializer::new();
+                        serializer.buf.reserve(4096 * chunk.len());
+
+                        for (i, dep) in chunk.iter().enumerate() {
+                            let index = n * chunk_size + i;
+
+                            if index != 0 {
+                                serializer.buf.push(b',');
+                            }
+
+                            serializer.write_dependency(
+                                dep,
+                                attributes.get(index),
+                                target_attributes,
+                                prerequisite_attributes,
+                            );
+                        }
+
+                        serializer.buf
+                    })
+                })
+                .collect();
+
+            handles.into_iter().map(|x| x.join().unwrap()).collect()
+        });
+
+        let len = bufs.iter().map(Vec::len).sum::<usize>();
+        self.buf.reserve(len + 2);
+
+        self.buf.push(b'[');
+
+        for buf in bufs {
+            self.buf.extend_from_slice(&buf);
+        }
+
+        self.buf.push(b']');
+
+        self.attributes = attributes;
+        self.target_attributes = target_attributes;
+        self.prerequisite_attributes = prerequisite_attributes;
+    }
+
+    /*
+     * Write the dependencies like "write_vec()", but pass the generated
+     * JSON to "out" in pieces instead of keeping all of it in memory.
+     */
+    pub fn stream_vec<W: Write>(
+        &mut self,
+        vec: &[Dependency],
+        out: &mut W,
+    ) -> io::Result<()> {
+        const THRESHOLD: usize = 1 << 16;
+
+        self.buf.clear();
+        self.write_vec_with(vec, |buf| {
+            if buf.len() >= THRESHOLD {
+                out.write_all(buf)?;
+                buf.clear();
+            }
+
+            Ok(())
+        })?;
+
+        out.write_all(&self.buf)?;
+        self.buf.clear();
+
+        Ok(())
+    }
+
+    /*
+     * Write each dependency on its own as a list with one element and pass
+     * its index and the generated JSON to "out", e.g. to write one file per
+     * target.
+     */
+    pub fn write_each<F>(&mut self, vec: &[Dependency], mut out: F)
+    where
+        F: FnMut(usize, &[u8]),
+    {
+        let attributes = mem::take(&mut self.attributes);
+        let target_attributes = mem::take(&mut self.target_attributes);
+        let prerequisite_attributes = self.prerequisite_attributes.take();
+
+        for (i, dep) in vec.iter().enumerate() {
+            self.buf.clear();
+            self.buf.push(b'[');
+            self.write_dependency(
+                dep,
+                attributes.get(i),
+                &target_attributes,
+                &prerequisite_attributes,
+            );
+            self.buf.push(b']');
+
+            out(i, &self.buf);
+        }
+
+        self.buf.clear();
+        self.attributes = attributes;
+        self.target_attributes = target_attributes;
+        self.prerequisite_attributes = prerequisite_attributes;
+    }
+
+    /*
+     * Write the dependencies to "out" as elements of a list which is opened
+     * and closed by the caller, e.g. to append the rules of each input to
+     * the same list as soon as they are parsed. "first" tells whether no
+     * element was written to the list so far.
+     */
+    pub fn stream_elements<W: Write + ?Sized>(
+        &mut self,
+        vec: &[Dependency],
+        first: bool,
+        out: &mut W,
+    ) -> io::Result<()> {
+        let mut first = first;
+        let mut result = Ok(());
+
+        self.write_each(vec, |_, data| {
+            if result.is_err() {
+                return;
+            }
+
+            /* Strip the brackets of the list with the single element */
+            let data = &data[1..data.len() - 1];
+
+            result = match mem::take(&mut first) {
+                true => out.write_all(data),
+                false => out.write_all(b",").and_then(|_| out.write_all(data)),
+            };
+        });
+
+        result
+    }
+
+    /*
+     * Write the key of a member of an object to "out" in pieces, preceded
+     * by a comma unless it is the "first" member.
+     */
+    pub fn stream_key<W: Write + ?Sized>(
+        &mut self,
+        key: &str,
+        first: bool,
+        out: &mut W,
+    ) -> io::Result<()> {
+        self.buf.clear();
+
+        if !first {
+            self.buf.push(b',');
+        }
+
+        self.write_str(key);
+        self.buf.push(b':');
+
+        out.write_all(&self.buf)
+    }
+
+    fn write_vec_with<F>(
+        &mut self,
+        vec: &[Dependency],
+        mut flush: F,
+    ) -> io::Result<()>
+    where
+        F: FnMut(&mut Vec<u8>) -> io::Result<()>,
+    {
+        let attributes = mem::take(&mut self.attributes);
+        let target_attributes = mem::take(&mut self.target_attributes);
+        let prerequisite_attributes = self.prerequisite_attributes.take();
+
         self.buf.push(b'[');
 
         for (i, dep) in vec.iter().enumerate() {
@@ -40,55 +318,1003 @@ impl JsonSerializer {
                 self.buf.push(b',');
             }
 
-            self.buf.push(b'{');
+            self.write_dependency(
+                dep,
+                attributes.get(i),
+                &target_attributes,
+                &prerequisite_attributes,
+            );
+
+            if let Err(err) = flush(&mut self.buf) {
+                self.attributes = attributes;
+                self.target_attributes = target_attributes;
+                self.prerequisite_attributes = prerequisite_attributes;
+
+                return Err(err);
+            }
+        }
+
+        self.buf.push(b']');
+
+        self.attributes = attributes;
+        self.target_attributes = target_attributes;
+        self.prerequisite_attributes = prerequisite_attributes;
+
+        Ok(())
+    }
+
+    fn write_dependency(
+        &mut self,
+        dep: &Dependency,
+        attributes: Option<&Attributes>,
+        target_attributes: &HashMap<String, Attributes>,
+        prerequisite_attributes: &Option<HashMap<String, Attributes>>,
+    ) {
+        self.buf.push(b'{');
+
+        self.buf.extend_from_slice(b"\"target\":");
+        self.write_str(dep.target);
+        self.buf.extend_from_slice(b",\"prerequisites\":[");
+
+        for (j, val) in dep.prerequisites.iter().enumerate() {
+            if j != 0 {
+                self.buf.push(b',');
+            }
+
+            self.write_prerequisite(val, prerequisite_attributes);
+        }
+
+        self.buf.push(b']');
+
+        if let Some(attrs) = attributes {
+            self.write_attributes(attrs);
+        }
+
+        if let Some(attrs) = target_attributes.get(dep.target) {
+            self.write_attributes(attrs);
+        }
+
+        self.buf.push(b'}');
+    }
+
+    fn write_prerequisite(
+        &mut self,
+        path: &str,
+        prerequisite_attributes: &Option<HashMap<String, Attributes>>,
+    ) {
+        match prerequisite_attributes {
+            Some(map) => {
+                self.buf.extend_from_slice(b"{\"path\":");
+                self.write_str(path);
+
+                if let Some(attrs) = map.get(path) {
+                    self.write_attributes(attrs);
+                }
+
+                self.buf.push(b'}');
+            }
+            None => self.write_str(path),
+        }
+    }
+
+    fn write_attributes(&mut self, attrs: &Attributes) {
+        for (key, value) in attrs {
+            self.buf.push(b',');
+            self.write_str(key);
+            self.buf.push(b':');
+            self.write_value(value);
+        }
+    }
+
+    /*
+     * Write the graph in the format of the "elements" which can be
+     * loaded by Cytoscape.js. The path of a node is used as its id.
+     */
+    pub fn write_cytoscape(&mut self, graph: &Graph) {
+        let nodes = graph.nodes();
+
+        self.buf.reserve(64 * nodes.len());
+
+        self.buf.extend_from_slice(b"{\"elements\":{\"nodes\":[");
+
+        for (i, node) in nodes.iter().enumerate() {
+            if i != 0 {
+                self.buf.push(b',');
+            }
+
+            self.buf.extend_from_slice(b"{\"data\":{\"id\":");
+            self.write_str(node);
+            self.buf.extend_from_slice(b"}}");
+        }
+
+        self.buf.extend_from_slice(b"],\"edges\":[");
+
+        let mut first = true;
+
+        for (id, edges) in graph.edges().iter().enumerate() {
+            for &prereq in edges {
+                if !mem::take(&mut first) {
+                    self.buf.push(b',');
+                }
+
+                self.buf.extend_from_slice(b"{\"data\":{\"source\":");
+                self.write_str(nodes[id]);
+                self.buf.extend_from_slice(b",\"target\":");
+                self.write_str(nodes[prereq]);
+                self.buf.extend_from_slice(b"}}");
+            }
+        }
+
+        self.buf.extend_from_slice(b"]}}");
+    }
+
+    /*
+     * Write the graph as nodes and links for D3.js. Links refer to the
+     * nodes by their index to keep the output compact.
+     */
+    pub fn write_d3(&mut self, graph: &Graph) {
+        let nodes = graph.nodes();
+
+        self.buf.reserve(64 * nodes.len());
+
+        self.buf.extend_from_slice(b"{\"nodes\":[");
+
+        for (i, node) in nodes.iter().enumerate() {
+            if i != 0 {
+                self.buf.push(b',');
+            }
+
+            self.buf.extend_from_slice(b"{\"id\":");
+            self.write_str(node);
+            self.buf.push(b'}');
+        }
+
+        self.buf.extend_from_slice(b"],\"links\":[");
+
+        let mut first = true;
+
+        for (id, edges) in graph.edges().iter().enumerate() {
+            for &prereq in edges {
+                if !mem::take(&mut first) {
+                    self.buf.push(b',');
+                }
+
+                self.buf.extend_from_slice(b"{\"source\":");
+                self.write_usize(id);
+                self.buf.extend_from_slice(b",\"target\":");
+                self.write_usize(prereq);
+                self.buf.push(b'}');
+            }
+        }
+
+        self.buf.extend_from_slice(b"]}");
+    }
+
+    pub fn write_components(&mut self, vec: &[Component]) {
+        self.buf.reserve(64 * vec.len());
+
+        self.buf.push(b'[');
+
+        for (i, component) in vec.iter().enumerate() {
+            if i != 0 {
+                self.buf.push(b',');
+            }
+
+            self.buf.extend_from_slice(b"{\"component\":");
+            self.write_usize(i);
+            self.buf.extend_from_slice(b",\"size\":");
+            self.write_usize(component.size);
+            self.buf.extend_from_slice(b",\"targets\":");
+            self.write_str_list(&component.targets);
+            self.buf.push(b'}');
+        }
+
+        self.buf.push(b']');
+    }
+
+    pub fn write_critical_path(&mut self, path: &CriticalPath) {
+        self.buf.reserve(64 * path.depths.len());
+
+        self.buf.extend_from_slice(b"{\"length\":");
+        self.write_usize(path.length);
+        self.buf.extend_from_slice(b",\"chains\":");
+        self.write_paths(&path.chains);
+        self.buf.extend_from_slice(b",\"depths\":[");
+
+        for (i, (target, depth)) in path.depths.iter().enumerate() {
+            if i != 0 {
+                self.buf.push(b',');
+            }
+
+            self.buf.extend_from_slice(b"{\"target\":");
+            self.write_str(target);
+            self.buf.extend_from_slice(b",\"depth\":");
+            self.write_usize(*depth);
+            self.buf.push(b'}');
+        }
+
+        self.buf.extend_from_slice(b"]}");
+    }
+
+    /*
+     * Write the prerequisites as a tree of directories. Each directory is
+     * an object with its subdirectories in "dirs" and the names of its
+     * files in "files". Absolute paths are placed in the directory "/".
+     */
+    pub fn write_tree(&mut self, vec: &[Dependency]) {
+        #[derive(Default)]
+        struct Dir<'a> {
+            dirs: BTreeMap<&'a str, Dir<'a>>,
+            files: BTreeSet<&'a str>,
+        }
 
-            self.buf.extend_from_slice(b"\"target\":");
+        fn write_dir(serializer: &mut JsonSerializer, dir: &Dir) {
+            serializer.buf.extend_from_slice(b"{\"dirs\":{");
+
+            for (i, (name, subdir)) in dir.dirs.iter().enumerate() {
+                if i != 0 {
+                    serializer.buf.push(b',');
+                }
+
+                serializer.write_str(name);
+                serializer.buf.push(b':');
+                write_dir(serializer, subdir);
+            }
+
+            serializer.buf.extend_from_slice(b"},\"files\":");
+
+            let files: Vec<&str> = dir.files.iter().copied().collect();
+            serializer.write_str_list(&files);
+            serializer.buf.push(b'}');
+        }
+
+        let mut root = Dir::default();
+
+        for &prereq in vec.iter().flat_map(|dep| &dep.prerequisites) {
+            let mut dir = &mut root;
+
+            if prereq.starts_with('/') {
+                dir = dir.dirs.entry("/").or_default();
+            }
+
+            let mut components = prereq
+                .split('/')
+                .filter(|x| !x.is_empty() && *x != ".")
+                .peekable();
+
+            while let Some(name) = components.next() {
+                if components.peek().is_none() {
+                    dir.files.insert(name);
+                } else {
+                    dir = dir.dirs.entry(name).or_default();
+                }
+            }
+        }
+
+        write_dir(self, &root);
+    }
+
+    pub fn write_diff(&mut self, diff: &Diff) {
+        self.buf.extend_from_slice(b"{\"added\":");
+        self.write_vec(&diff.added);
+        self.buf.extend_from_slice(b",\"removed\":");
+        self.write_vec(&diff.removed);
+        self.buf.extend_from_slice(b",\"changed\":[");
+
+        for (i, change) in diff.changed.iter().enumerate() {
+            if i != 0 {
+                self.buf.push(b',');
+            }
+
+            self.buf.extend_from_slice(b"{\"target\":");
+            self.write_str(change.target);
+            self.buf.extend_from_slice(b",\"added\":");
+            self.write_str_list(&change.added);
+            self.buf.extend_from_slice(b",\"removed\":");
+            self.write_str_list(&change.removed);
+            self.buf.push(b'}');
+        }
+
+        self.buf.extend_from_slice(b"]}");
+    }
+
+    /*
+     * Write the differences as a stream of events with one JSON object per
+     * line, i.e. as NDJSON.
+     */
+    pub fn write_events(&mut self, diff: &Diff) {
+        for dep in &diff.added {
+            self.buf
+                .extend_from_slice(b"{\"event\":\"rule-added\",\"target\":");
             self.write_str(dep.target);
+            self.buf.extend_from_slice(b",\"prerequisites\":");
+            self.write_str_list(&dep.prerequisites);
+            self.buf.extend_from_slice(b"}\n");
+        }
+
+        for dep in &diff.removed {
+            self.buf
+                .extend_from_slice(b"{\"event\":\"rule-removed\",\"target\":");
+            self.write_str(dep.target);
+            self.buf.extend_from_slice(b",\"prerequisites\":");
+            self.write_str_list(&dep.prerequisites);
+            self.buf.extend_from_slice(b"}\n");
+        }
+
+        for change in &diff.changed {
+            self.buf
+                .extend_from_slice(b"{\"event\":\"rule-changed\",\"target\":");
+            self.write_str(change.target);
+            self.buf.extend_from_slice(b",\"added\":");
+            self.write_str_list(&change.added);
+            self.buf.extend_from_slice(b",\"removed\":");
+            self.write_str_list(&change.removed);
+            self.buf.extend_from_slice(b"}\n");
+        }
+    }
+
+    pub fn write_conflicts(&mut self, vec: &[Conflict]) {
+        self.buf.push(b'[');
+
+        for (i, conflict) in vec.iter().enumerate() {
+            if i != 0 {
+                self.buf.push(b',');
+            }
+
+            self.buf.extend_from_slice(b"{\"target\":");
+            self.write_str(&conflict.target);
+            self.buf.extend_from_slice(b",\"inputs\":");
+            let inputs: Vec<_> =
+                conflict.inputs.iter().map(String::as_str).collect();
+            self.write_str_list(&inputs);
+            self.buf.extend_from_slice(b",\"difference\":");
+            let difference: Vec<_> =
+                conflict.difference.iter().map(String::as_str).collect();
+            self.write_str_list(&difference);
+            self.buf.push(b'}');
+        }
+
+        self.buf.push(b']');
+    }
+
+    pub fn write_hotspots(&mut self, vec: &[Hotspot]) {
+        self.buf.reserve(128 * vec.len());
+
+        self.buf.push(b'[');
+
+        for (i, hotspot) in vec.iter().enumerate() {
+            if i != 0 {
+                self.buf.push(b',');
+            }
+
+            self.buf.extend_from_slice(b"{\"prerequisite\":");
+            self.write_str(hotspot.prerequisite);
+            self.buf.extend_from_slice(b",\"direct\":");
+            self.write_usize(hotspot.direct);
+            self.buf.extend_from_slice(b",\"transitive\":");
+            self.write_usize(hotspot.transitive);
+            self.buf.push(b'}');
+        }
+
+        self.buf.push(b']');
+    }
+
+    pub fn write_metrics(&mut self, vec: &[Metrics]) {
+        self.buf.reserve(128 * vec.len());
+
+        self.buf.push(b'[');
+
+        for (i, metrics) in vec.iter().enumerate() {
+            if i != 0 {
+                self.buf.push(b',');
+            }
+
+            self.buf.extend_from_slice(b"{\"node\":");
+            self.write_str(metrics.node);
+            self.buf.extend_from_slice(b",\"fan_in\":");
+            self.write_usize(metrics.fan_in);
+            self.buf.extend_from_slice(b",\"fan_out\":");
+            self.write_usize(metrics.fan_out);
+
+            if let Some(value) = metrics.transitive_fan_in {
+                self.buf.extend_from_slice(b",\"transitive_fan_in\":");
+                self.write_usize(value);
+            }
+
+            if let Some(value) = metrics.transitive_fan_out {
+                self.buf.extend_from_slice(b",\"transitive_fan_out\":");
+                self.write_usize(value);
+            }
+
+            self.buf.push(b'}');
+        }
+
+        self.buf.push(b']');
+    }
+
+    pub fn write_orphans(&mut self, orphans: &Orphans) {
+        self.buf.extend_from_slice(b"{\"prerequisites\":");
+        self.write_str_list(&orphans.prerequisites);
+        self.buf.extend_from_slice(b",\"targets\":");
+        self.write_str_list(&orphans.targets);
+        self.buf.push(b'}');
+    }
+
+    /*
+     * Write the prerequisites of all dependencies once in the order of
+     * their first appearance, e.g. as the input manifest of a remote build
+     * cache. Like with "write_vec()", the prerequisites are written as
+     * objects if attributes were added for them.
+     */
+    pub fn write_manifest(&mut self, vec: &[Dependency]) {
+        let prerequisite_attributes = self.prerequisite_attributes.take();
+        let mut seen = HashSet::new();
+
+        self.buf.push(b'[');
+
+        for path in vec.iter().flat_map(|x| &x.prerequisites) {
+            if !seen.insert(*path) {
+                continue;
+            }
+
+            if seen.len() != 1 {
+                self.buf.push(b',');
+            }
+
+            self.write_prerequisite(path, &prerequisite_attributes);
+        }
+
+        self.buf.push(b']');
+
+        self.prerequisite_attributes = prerequisite_attributes;
+    }
+
+    /*
+     * Write the dependencies like "write_vec()", but combine the targets
+     * with the same prerequisites in the same order into one object with
+     * a list of "targets". The objects are ordered by their first target.
+     */
+    pub fn write_coalesced(&mut self, vec: &[Dependency]) {
+        let prerequisite_attributes = self.prerequisite_attributes.take();
+        let mut groups: Vec<(Vec<&str>, &[&str])> = Vec::new();
+        let mut index: HashMap<&[&str], usize> = HashMap::new();
+
+        for dep in vec {
+            let prereqs = dep.prerequisites.as_slice();
+
+            match index.get(prereqs) {
+                Some(&i) => groups[i].0.push(dep.target),
+                None => {
+                    index.insert(prereqs, groups.len());
+                    groups.push((Vec::from([dep.target]), prereqs));
+                }
+            }
+        }
+
+        self.buf.push(b'[');
+
+        for (i, (targets, prereqs)) in groups.iter().enumerate() {
+            if i != 0 {
+                self.buf.push(b',');
+            }
+
+            self.buf.extend_from_slice(b"{\"targets\":");
+            self.write_str_list(targets);
             self.buf.extend_from_slice(b",\"prerequisites\":[");
 
-            for (j, val) in dep.prerequisites.iter().enumerate() {
+            for (j, path) in prereqs.iter().enumerate() {
                 if j != 0 {
                     self.buf.push(b',');
                 }
 
-                self.write_str(val);
+                self.write_prerequisite(path, &prerequisite_attributes);
             }
 
-            self.buf.push(b']');
-            self.buf.push(b'}');
+            self.buf.extend_from_slice(b"]}");
+        }
+
+        self.buf.push(b']');
+
+        self.prerequisite_attributes = prerequisite_attributes;
+    }
+
+    pub fn write_paths(&mut self, vec: &[Vec<&str>]) {
+        self.buf.push(b'[');
+
+        for (i, path) in vec.iter().enumerate() {
+            if i != 0 {
+                self.buf.push(b',');
+            }
+
+            self.write_str_list(path);
         }
 
         self.buf.push(b']');
     }
 
+    pub fn write_str_list(&mut self, list: &[&str]) {
+        self.buf.push(b'[');
+
+        for (i, val) in list.iter().enumerate() {
+            if i != 0 {
+                self.buf.push(b',');
+            }
+
+            self.write_str(val);
+        }
+
+        self.buf.push(b']');
+    }
+
+    pub fn write_value(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.buf.extend_from_slice(b"null"),
+            Value::Bool(true) => self.buf.extend_from_slice(b"true"),
+            Value::Bool(false) => self.buf.extend_from_slice(b"false"),
+            Value::Number(num) if num.is_finite() => {
+                self.buf.extend_from_slice(num.to_string().as_bytes());
+            }
+            Value::Number(_) => self.buf.extend_from_slice(b"null"),
+            Value::String(data) => self.write_str(data),
+            Value::Array(values) => {
+                self.buf.push(b'[');
+
+                for (i, value) in values.iter().enumerate() {
+                    if i != 0 {
+                        self.buf.push(b',');
+                    }
+
+                    self.write_value(value);
+                }
+
+                self.buf.push(b']');
+            }
+            Value::Object(members) => {
+                self.buf.push(b'{');
+
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i != 0 {
+                        self.buf.push(b',');
+                    }
+
+                    self.write_str(key);
+                    self.buf.push(b':');
+                    self.write_value(value);
+                }
+
+                self.buf.push(b'}');
+            }
+        }
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.buf.extend_from_slice(value.to_string().as_bytes());
+    }
+
     fn write_str(&mut self, data: &str) {
         let bytes = data.as_bytes();
         let mut i = 0;
 
-        self.buf.push(b'\"');
+        self.buf.push(b'\"');
+
+        for (j, &byte) in bytes.iter().enumerate() {
+            let escape: &[u8] = match byte {
+                b'\\' => b"\\\\",
+                b'"' => b"\\\"",
+                b'\n' => b"\\n",
+                b'\r' => b"\\r",
+                b'\t' => b"\\t",
+                0x08 => b"\\b",
+                0x0c => b"\\f",
+                0x00..=0x1f => b"",
+                _ => continue,
+            };
+
+            self.buf.extend_from_slice(&bytes[i..j]);
+
+            /* The other control characters have no short escape */
+            if escape.is_empty() {
+                let hex = format!("\\u{byte:04x}");
+                self.buf.extend_from_slice(hex.as_bytes());
+            } else {
+                self.buf.extend_from_slice(escape);
+            }
+
+            i = j + 1;
+        }
+
+        self.buf.extend_from_slice(&bytes[i..]);
+
+        self.buf.push(b'\"');
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+/*
+ * Format a diagnostic as a single line of JSON for "--diagnostics json".
+ * The "file" and the "line" are null if the diagnostic does not refer to a
+ * location.
+ */
+pub fn format_diagnostic(
+    severity: Severity,
+    code: &str,
+    file: Option<&str>,
+    line: Option<usize>,
+    message: &str,
+) -> String {
+    let members = Vec::from([
+        (
+            String::from("severity"),
+            Value::String(severity.to_string()),
+        ),
+        (String::from("code"), Value::String(code.into())),
+        (
+            String::from("file"),
+            file.map_or(Value::Null, |x| Value::String(x.into())),
+        ),
+        (
+            String::from("line"),
+            line.map_or(Value::Null, |x| Value::Number(x as f64)),
+        ),
+        (String::from("message"), Value::String(message.into())),
+    ]);
+
+    let mut serializer = JsonSerializer::new();
+    serializer.write_value(&Value::Object(members));
+
+    String::from_utf8_lossy(serializer.get_json()).into_owned()
+}
+
+/*
+ * Read dependencies as written by "JsonSerializer::write_vec()". The
+ * prerequisites may be strings or objects with a "path" and attributes.
+ * The attributes are dropped.
+ */
+pub fn parse_dependencies(data: &[u8]) -> Result<Vec<DependencyBuf>, String> {
+    let value = JsonParser::new(data).parse()?;
+
+    let list = value
+        .as_array()
+        .ok_or_else(|| String::from("expected an array of dependencies"))?;
+
+    let mut result = Vec::with_capacity(list.len());
+
+    for (i, item) in list.iter().enumerate() {
+        let invalid = |key| format!("entry {i}: invalid \"{key}\"");
+
+        let target = item
+            .get("target")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid("target"))?;
+
+        let prerequisites = item
+            .get("prerequisites")
+            .and_then(Value::as_array)
+            .ok_or_else(|| invalid("prerequisites"))?
+            .iter()
+            .map(|x| {
+                x.as_str()
+                    .or_else(|| x.get("path").and_then(Value::as_str))
+                    .map(String::from)
+                    .ok_or_else(|| invalid("prerequisites"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        result.push(DependencyBuf {
+            target: String::from(target),
+            prerequisites,
+        });
+    }
+
+    Ok(result)
+}
+
+/*
+ * A small parser for JSON documents as specified in RFC 8259. It is used
+ * to read data produced by other tools, e.g. compilation databases.
+ */
+pub struct JsonParser<'a> {
+    data: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    /*
+     * Limit the nesting of arrays and objects to protect the stack
+     * against malicious input.
+     */
+    const MAX_DEPTH: usize = 256;
+
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    pub fn parse(&mut self) -> Result<Value, String> {
+        let value = self.parse_value()?;
+
+        self.skip_whitespace();
+
+        if self.pos != self.data.len() {
+            return Err(self.error("unexpected trailing data"));
+        }
+
+        Ok(value)
+    }
+
+    fn error(&self, msg: &str) -> String {
+        format!("{msg} at offset {}", self.pos)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, literal: &[u8]) -> Result<(), String> {
+        if !self.data[self.pos..].starts_with(literal) {
+            return Err(self.error("invalid literal"));
+        }
+
+        self.pos += literal.len();
+
+        Ok(())
+    }
+
+    fn parse_value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(b'n') => self.expect(b"null").map(|_| Value::Null),
+            Some(b't') => self.expect(b"true").map(|_| Value::Bool(true)),
+            Some(b'f') => self.expect(b"false").map(|_| Value::Bool(false)),
+            Some(b'"') => self.parse_string().map(Value::String),
+            Some(b'[') => self.parse_array(),
+            Some(b'{') => self.parse_object(),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of data")),
+        }
+    }
+
+    fn enter(&mut self) -> Result<(), String> {
+        self.depth += 1;
+
+        if self.depth > Self::MAX_DEPTH {
+            return Err(self.error("maximum nesting depth exceeded"));
+        }
+
+        /* Skip the opening bracket */
+        self.pos += 1;
+
+        Ok(())
+    }
+
+    fn parse_array(&mut self) -> Result<Value, String> {
+        let mut values = Vec::new();
+
+        self.enter()?;
+        self.skip_whitespace();
+
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            self.depth -= 1;
+
+            return Ok(Value::Array(values));
+        }
+
+        loop {
+            values.push(self.parse_value()?);
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => break,
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+
+        self.pos += 1;
+        self.depth -= 1;
+
+        Ok(Value::Array(values))
+    }
+
+    fn parse_object(&mut self) -> Result<Value, String> {
+        let mut members = Vec::new();
+
+        self.enter()?;
+        self.skip_whitespace();
+
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            self.depth -= 1;
+
+            return Ok(Value::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected string"));
+            }
+
+            let name = self.parse_string()?;
+
+            self.skip_whitespace();
+
+            if self.peek() != Some(b':') {
+                return Err(self.error("expected ':'"));
+            }
+
+            self.pos += 1;
+
+            members.push((name, self.parse_value()?));
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => break,
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+
+        self.pos += 1;
+        self.depth -= 1;
+
+        Ok(Value::Object(members))
+    }
+
+    fn parse_number(&mut self) -> Result<Value, String> {
+        let begin = self.pos;
+
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') =
+            self.peek()
+        {
+            self.pos += 1;
+        }
+
+        str::from_utf8(&self.data[begin..self.pos])
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .map(Value::Number)
+            .ok_or_else(|| self.error("invalid number"))
+    }
+
+    fn parse_hex(&mut self) -> Result<u32, String> {
+        let digits = self
+            .data
+            .get(self.pos..self.pos + 4)
+            .and_then(|x| str::from_utf8(x).ok())
+            .and_then(|x| u32::from_str_radix(x, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+
+        self.pos += 4;
+
+        Ok(digits)
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        let mut buf = Vec::new();
+
+        /* Skip the opening quote */
+        self.pos += 1;
+
+        loop {
+            let begin = self.pos;
+
+            while let Some(byte) = self.peek() {
+                if byte == b'"' || byte == b'\\' || byte < 0x20 {
+                    break;
+                }
+
+                self.pos += 1;
+            }
+
+            buf.extend_from_slice(&self.data[begin..self.pos]);
+
+            match self.peek() {
+                Some(b'"') => break,
+                Some(b'\\') => self.pos += 1,
+                Some(_) => return Err(self.error("invalid control character")),
+                None => return Err(self.error("unterminated string")),
+            }
+
+            let byte =
+                self.peek().ok_or_else(|| self.error("invalid escape"))?;
+            self.pos += 1;
+
+            match byte {
+                b'"' | b'\\' | b'/' => buf.push(byte),
+                b'b' => buf.push(0x08),
+                b'f' => buf.push(0x0c),
+                b'n' => buf.push(b'\n'),
+                b'r' => buf.push(b'\r'),
+                b't' => buf.push(b'\t'),
+                b'u' => {
+                    let mut code = self.parse_hex()?;
+
+                    if (0xd800..0xdc00).contains(&code)
+                        && self.data[self.pos..].starts_with(b"\\u")
+                    {
+                        self.pos += 2;
+
+                        let low = self.parse_hex()?;
+
+                        if !(0xdc00..0xe000).contains(&low) {
+                            return Err(self.error("invalid surrogate pair"));
+                        }
+
+                        code =
+                            0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                    }
 
-        for (j, _) in bytes.iter().enumerate() {
-            match bytes[j] {
-                b'\\' | b'"' => {
-                    self.buf.extend_from_slice(&bytes[i..j]);
-                    self.buf.push(b'\\');
-                    self.buf.push(bytes[j]);
+                    let c = char::from_u32(code)
+                        .ok_or_else(|| self.error("invalid unicode escape"))?;
 
-                    i = j + 1;
+                    let mut tmp = [0; 4];
+                    buf.extend_from_slice(c.encode_utf8(&mut tmp).as_bytes());
                 }
-                _ => {}
+                _ => return Err(self.error("invalid escape")),
             }
         }
 
-        self.buf.extend_from_slice(&bytes[i..]);
+        /* Skip the closing quote */
+        self.pos += 1;
 
-        self.buf.push(b'\"');
+        String::from_utf8(buf).map_err(|_| self.error("invalid UTF-8"))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::diff::Change;
 
     #[test]
     fn write_vec_001() {
@@ -136,6 +1362,562 @@ mod tests {
         );
     }
 
+    #[test]
+    fn write_vec_004() {
+        let vec = Vec::from([
+            Dependency {
+                target: "a",
+                prerequisites: Vec::new(),
+            },
+            Dependency {
+                target: "b",
+                prerequisites: Vec::new(),
+            },
+        ]);
+
+        let attributes = Vec::from([Vec::from([
+            ("x", Value::Number(1.0)),
+            ("y", Value::Array(Vec::from([Value::Null]))),
+        ])]);
+
+        let mut serializer = JsonSerializer::new();
+        serializer.add_attributes(attributes);
+        serializer.write_vec(&vec);
+
+        assert_eq!(
+            b"[{\"target\":\"a\",\"prerequisites\":[],\"x\":1,\"y\":[null]},\
+            {\"target\":\"b\",\"prerequisites\":[]}]",
+            serializer.buf.as_slice()
+        );
+    }
+
+    #[test]
+    fn write_vec_005() {
+        let vec = Vec::from([Dependency {
+            target: "a",
+            prerequisites: Vec::from(["b", "c"]),
+        }]);
+
+        let mut serializer = JsonSerializer::new();
+        serializer.add_prerequisite_attributes(HashMap::from([(
+            String::from("b"),
+            Vec::from([("size", Value::Number(1.0))]),
+        )]));
+        serializer.add_prerequisite_attributes(HashMap::from([(
+            String::from("b"),
+            Vec::from([("kind", Value::String("header".into()))]),
+        )]));
+        serializer.write_vec(&vec);
+
+        assert_eq!(
+            b"[{\"target\":\"a\",\"prerequisites\":[\
+            {\"path\":\"b\",\"size\":1,\"kind\":\"header\"},\
+            {\"path\":\"c\"}]}]",
+            serializer.buf.as_slice()
+        );
+    }
+
+    #[test]
+    fn write_vec_006() {
+        let vec = Vec::from([
+            Dependency {
+                target: "a",
+                prerequisites: Vec::new(),
+            },
+            Dependency {
+                target: "b",
+                prerequisites: Vec::new(),
+            },
+        ]);
+
+        let mut serializer = JsonSerializer::new();
+        serializer.add_attributes(Vec::from([
+            Vec::new(),
+            Vec::from([("x", Value::Number(1.0))]),
+        ]));
+        serializer.add_target_attributes(HashMap::from([(
+            String::from("b"),
+            Vec::from([("y", Value::Null)]),
+        )]));
+        serializer.write_vec(&vec);
+
+        assert_eq!(
+            b"[{\"target\":\"a\",\"prerequisites\":[]},\
+            {\"target\":\"b\",\"prerequisites\":[],\"x\":1,\"y\":null}]",
+            serializer.buf.as_slice()
+        );
+    }
+
+    #[test]
+    fn write_vec_parallel_001() {
+        let names: Vec<String> = (0..10).map(|x| format!("t{x}")).collect();
+
+        let vec: Vec<Dependency> = names
+            .iter()
+            .map(|x| Dependency {
+                target: x,
+                prerequisites: Vec::from(["a", x]),
+            })
+            .collect();
+
+        let attributes = || {
+            let list =
+                (0..10).map(|x| Vec::from([("x", Value::Number(x as f64))]));
+
+            list.collect()
+        };
+
+        let mut expected = JsonSerializer::new();
+        expected.add_attributes(attributes());
+        expected.write_vec_with(&vec, |_| Ok(())).unwrap();
+
+        for threads in [1, 3, 4, 16] {
+            let mut serializer = JsonSerializer::new();
+            serializer.add_attributes(attributes());
+            serializer.write_vec_parallel(&vec, threads);
+
+            assert_eq!(expected.get_json(), serializer.get_json());
+        }
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_vec_parallel(&[], 4);
+
+        assert_eq!(b"[]", serializer.get_json());
+    }
+
+    #[test]
+    fn write_manifest_001() {
+        let vec = Vec::from([
+            Dependency {
+                target: "a.o",
+                prerequisites: Vec::from(["a.c", "x.h"]),
+            },
+            Dependency {
+                target: "b.o",
+                prerequisites: Vec::from(["b.c", "x.h"]),
+            },
+            Dependency {
+                target: "c.o",
+                prerequisites: Vec::new(),
+            },
+        ]);
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_manifest(&vec);
+
+        assert_eq!(b"[\"a.c\",\"x.h\",\"b.c\"]", serializer.get_json());
+
+        let attrs = HashMap::from([(
+            String::from("x.h"),
+            Vec::from([("size", Value::Number(3.0))]),
+        )]);
+
+        let mut serializer = JsonSerializer::new();
+        serializer.add_prerequisite_attributes(attrs);
+        serializer.write_manifest(&vec[1..]);
+
+        let expected = b"[{\"path\":\"b.c\"},{\"path\":\"x.h\",\"size\":3}]";
+        assert_eq!(expected, serializer.get_json());
+    }
+
+    #[test]
+    fn write_coalesced_001() {
+        let vec = Vec::from([
+            Dependency {
+                target: "x86/a.o",
+                prerequisites: Vec::from(["a.c", "a.h"]),
+            },
+            Dependency {
+                target: "b.o",
+                prerequisites: Vec::from(["a.h", "a.c"]),
+            },
+            Dependency {
+                target: "arm/a.o",
+                prerequisites: Vec::from(["a.c", "a.h"]),
+            },
+            Dependency {
+                target: "c.o",
+                prerequisites: Vec::new(),
+            },
+        ]);
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_coalesced(&vec);
+
+        let expected = "[{\"targets\":[\"x86/a.o\",\"arm/a.o\"],\
+                        \"prerequisites\":[\"a.c\",\"a.h\"]},\
+                        {\"targets\":[\"b.o\"],\
+                        \"prerequisites\":[\"a.h\",\"a.c\"]},\
+                        {\"targets\":[\"c.o\"],\"prerequisites\":[]}]";
+
+        assert_eq!(expected.as_bytes(), serializer.get_json());
+    }
+
+    #[test]
+    fn stream_key_001() {
+        let mut serializer = JsonSerializer::new();
+        let mut out = Vec::from(*b"{");
+
+        serializer.stream_key("a \"b\".d", true, &mut out).unwrap();
+        out.extend_from_slice(b"[]");
+        serializer.stream_key("c.d", false, &mut out).unwrap();
+        out.extend_from_slice(b"[]}");
+
+        assert_eq!(b"{\"a \\\"b\\\".d\":[],\"c.d\":[]}", out.as_slice());
+    }
+
+    #[test]
+    fn stream_elements_001() {
+        let vec = Vec::from([
+            Dependency {
+                target: "a",
+                prerequisites: Vec::from(["b"]),
+            },
+            Dependency {
+                target: "c",
+                prerequisites: Vec::new(),
+            },
+        ]);
+
+        let mut serializer = JsonSerializer::new();
+        let mut out = Vec::from(*b"[");
+
+        serializer
+            .stream_elements(&vec[..1], true, &mut out)
+            .unwrap();
+        serializer.stream_elements(&[], false, &mut out).unwrap();
+        serializer
+            .stream_elements(&vec[1..], false, &mut out)
+            .unwrap();
+        out.push(b']');
+
+        serializer.write_vec(&vec);
+
+        assert_eq!(serializer.get_json(), out.as_slice());
+    }
+
+    #[test]
+    fn write_tree_001() {
+        let vec = Vec::from([
+            Dependency {
+                target: "a.o",
+                prerequisites: Vec::from(["./src/a.c", "/usr/include/a.h"]),
+            },
+            Dependency {
+                target: "b.o",
+                prerequisites: Vec::from(["src/b.c", "src/a.c", "b.h"]),
+            },
+        ]);
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_tree(&vec);
+
+        assert_eq!(
+            concat!(
+                "{\"dirs\":{",
+                "\"/\":{\"dirs\":{\"usr\":{\"dirs\":{",
+                "\"include\":{\"dirs\":{},\"files\":[\"a.h\"]}",
+                "},\"files\":[]}},\"files\":[]},",
+                "\"src\":{\"dirs\":{},\"files\":[\"a.c\",\"b.c\"]}",
+                "},\"files\":[\"b.h\"]}"
+            )
+            .as_bytes(),
+            serializer.buf.as_slice()
+        );
+    }
+
+    #[test]
+    fn parse_dependencies_001() {
+        let data = br#"[
+            {"target": "a.o", "prerequisites": ["a.c", {"path": "a.h"}]},
+            {"target": "b.o", "prerequisites": [], "command": "cc"}
+        ]"#;
+
+        let deps = parse_dependencies(data).unwrap();
+
+        assert_eq!(2, deps.len());
+        assert_eq!("a.o", deps[0].target);
+        assert_eq!(Vec::from(["a.c", "a.h"]), deps[0].prerequisites);
+        assert_eq!("b.o", deps[1].target);
+        assert!(deps[1].prerequisites.is_empty());
+    }
+
+    #[test]
+    fn parse_dependencies_002() {
+        let inputs: [&[u8]; 4] = [
+            b"{}",
+            br#"[{"prerequisites": []}]"#,
+            br#"[{"target": "a"}]"#,
+            br#"[{"target": "a", "prerequisites": [1]}]"#,
+        ];
+
+        for data in inputs {
+            assert!(parse_dependencies(data).is_err());
+        }
+    }
+
+    #[test]
+    fn write_value_001() {
+        let value = Value::Object(Vec::from([
+            ("a".into(), Value::Number(-1.5)),
+            ("b".into(), Value::Bool(false)),
+            ("c".into(), Value::String("\"".into())),
+            ("d".into(), Value::Number(f64::NAN)),
+        ]));
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_value(&value);
+
+        assert_eq!(
+            b"{\"a\":-1.5,\"b\":false,\"c\":\"\\\"\",\"d\":null}",
+            serializer.buf.as_slice()
+        );
+    }
+
+    #[test]
+    fn write_cytoscape_001() {
+        let vec = Vec::from([Dependency {
+            target: "a",
+            prerequisites: Vec::from(["b", "c"]),
+        }]);
+
+        let graph = Graph::new(&vec);
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_cytoscape(&graph);
+
+        assert_eq!(
+            b"{\"elements\":{\"nodes\":[{\"data\":{\"id\":\"a\"}},\
+            {\"data\":{\"id\":\"b\"}},{\"data\":{\"id\":\"c\"}}],\
+            \"edges\":[{\"data\":{\"source\":\"a\",\"target\":\"b\"}},\
+            {\"data\":{\"source\":\"a\",\"target\":\"c\"}}]}}",
+            serializer.buf.as_slice()
+        );
+    }
+
+    #[test]
+    fn write_d3_001() {
+        let vec = Vec::from([
+            Dependency {
+                target: "a",
+                prerequisites: Vec::from(["b"]),
+            },
+            Dependency {
+                target: "c",
+                prerequisites: Vec::from(["b"]),
+            },
+        ]);
+
+        let graph = Graph::new(&vec);
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_d3(&graph);
+
+        assert_eq!(
+            b"{\"nodes\":[{\"id\":\"a\"},{\"id\":\"b\"},{\"id\":\"c\"}],\
+            \"links\":[{\"source\":0,\"target\":1},\
+            {\"source\":2,\"target\":1}]}",
+            serializer.buf.as_slice()
+        );
+    }
+
+    #[test]
+    fn write_components_001() {
+        let vec = Vec::from([
+            Component {
+                size: 3,
+                targets: Vec::from(["a", "b"]),
+            },
+            Component {
+                size: 2,
+                targets: Vec::from(["c"]),
+            },
+        ]);
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_components(&vec);
+
+        assert_eq!(
+            b"[{\"component\":0,\"size\":3,\"targets\":[\"a\",\"b\"]},\
+            {\"component\":1,\"size\":2,\"targets\":[\"c\"]}]",
+            serializer.buf.as_slice()
+        );
+    }
+
+    #[test]
+    fn write_critical_path_001() {
+        let path = CriticalPath {
+            length: 1,
+            chains: Vec::from([Vec::from(["a", "b"])]),
+            depths: Vec::from([("a", 1)]),
+        };
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_critical_path(&path);
+
+        assert_eq!(
+            b"{\"length\":1,\"chains\":[[\"a\",\"b\"]],\
+            \"depths\":[{\"target\":\"a\",\"depth\":1}]}",
+            serializer.buf.as_slice()
+        );
+    }
+
+    #[test]
+    fn write_diff_001() {
+        let diff = Diff {
+            added: Vec::from([Dependency {
+                target: "a",
+                prerequisites: Vec::from(["b"]),
+            }]),
+            removed: Vec::new(),
+            changed: Vec::from([Change {
+                target: "c",
+                added: Vec::from(["d"]),
+                removed: Vec::new(),
+            }]),
+        };
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_diff(&diff);
+
+        assert_eq!(
+            concat!(
+                "{\"added\":[{\"target\":\"a\",\"prerequisites\":[\"b\"]}],",
+                "\"removed\":[],",
+                "\"changed\":[{\"target\":\"c\",\"added\":[\"d\"],",
+                "\"removed\":[]}]}"
+            )
+            .as_bytes(),
+            serializer.buf.as_slice()
+        );
+    }
+
+    #[test]
+    fn write_events_001() {
+        let diff = Diff {
+            added: Vec::from([Dependency {
+                target: "a",
+                prerequisites: Vec::from(["b"]),
+            }]),
+            removed: Vec::from([Dependency {
+                target: "e",
+                prerequisites: Vec::from(["f", "g"]),
+            }]),
+            changed: Vec::from([Change {
+                target: "c",
+                added: Vec::from(["d"]),
+                removed: Vec::new(),
+            }]),
+        };
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_events(&diff);
+
+        assert_eq!(
+            concat!(
+                "{\"event\":\"rule-added\",\"target\":\"a\",",
+                "\"prerequisites\":[\"b\"]}\n",
+                "{\"event\":\"rule-removed\",\"target\":\"e\",",
+                "\"prerequisites\":[\"f\",\"g\"]}\n",
+                "{\"event\":\"rule-changed\",\"target\":\"c\",",
+                "\"added\":[\"d\"],\"removed\":[]}\n"
+            )
+            .as_bytes(),
+            serializer.buf.as_slice()
+        );
+    }
+
+    #[test]
+    fn write_conflicts_001() {
+        let vec = Vec::from([Conflict {
+            target: String::from("a.o"),
+            inputs: Vec::from([String::from("x.d"), String::from("y.d")]),
+            difference: Vec::from([String::from("a.h")]),
+        }]);
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_conflicts(&vec);
+
+        assert_eq!(
+            b"[{\"target\":\"a.o\",\"inputs\":[\"x.d\",\"y.d\"],\
+            \"difference\":[\"a.h\"]}]",
+            serializer.buf.as_slice()
+        );
+    }
+
+    #[test]
+    fn write_hotspots_001() {
+        let vec = Vec::from([Hotspot {
+            prerequisite: "a",
+            direct: 1,
+            transitive: 2,
+        }]);
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_hotspots(&vec);
+
+        assert_eq!(
+            b"[{\"prerequisite\":\"a\",\"direct\":1,\"transitive\":2}]",
+            serializer.buf.as_slice()
+        );
+    }
+
+    #[test]
+    fn write_metrics_001() {
+        let vec = Vec::from([
+            Metrics {
+                node: "a",
+                fan_in: 0,
+                fan_out: 1,
+                transitive_fan_in: None,
+                transitive_fan_out: None,
+            },
+            Metrics {
+                node: "b",
+                fan_in: 1,
+                fan_out: 0,
+                transitive_fan_in: Some(1),
+                transitive_fan_out: Some(0),
+            },
+        ]);
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_metrics(&vec);
+
+        assert_eq!(
+            b"[{\"node\":\"a\",\"fan_in\":0,\"fan_out\":1},\
+            {\"node\":\"b\",\"fan_in\":1,\"fan_out\":0,\
+            \"transitive_fan_in\":1,\"transitive_fan_out\":0}]",
+            serializer.buf.as_slice()
+        );
+    }
+
+    #[test]
+    fn write_orphans_001() {
+        let orphans = Orphans {
+            prerequisites: Vec::from(["a", "b"]),
+            targets: Vec::new(),
+        };
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_orphans(&orphans);
+
+        assert_eq!(
+            b"{\"prerequisites\":[\"a\",\"b\"],\"targets\":[]}",
+            serializer.buf.as_slice()
+        );
+    }
+
+    #[test]
+    fn write_paths_001() {
+        let vec = Vec::from([Vec::from(["a", "b"]), Vec::from(["a"])]);
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_paths(&vec);
+
+        assert_eq!(b"[[\"a\",\"b\"],[\"a\"]]", serializer.buf.as_slice());
+    }
+
     #[test]
     fn write_str_001() {
         let mut serializer = JsonSerializer::new();
@@ -162,4 +1944,103 @@ mod tests {
         }
         assert_eq!(b"\"\\\"e\\\\z\\\"\"", serializer.buf.as_slice());
     }
+
+    #[test]
+    fn write_str_004() {
+        let mut serializer = JsonSerializer::new();
+        serializer.write_str("a\tb\nc\r\u{8}\u{c}\u{0}\u{1f}");
+
+        assert_eq!(
+            b"\"a\\tb\\nc\\r\\b\\f\\u0000\\u001f\"",
+            serializer.buf.as_slice()
+        );
+
+        let mut parser = JsonParser::new(serializer.get_json());
+        let value = parser.parse().unwrap();
+
+        assert_eq!(
+            Value::String(String::from("a\tb\nc\r\u{8}\u{c}\u{0}\u{1f}")),
+            value
+        );
+    }
+
+    #[test]
+    fn parse_001() {
+        let value = JsonParser::new(b" [1, -2.5e1, true, false, null] ")
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            Value::Array(Vec::from([
+                Value::Number(1.0),
+                Value::Number(-25.0),
+                Value::Bool(true),
+                Value::Bool(false),
+                Value::Null,
+            ])),
+            value
+        );
+    }
+
+    #[test]
+    fn parse_002() {
+        let data =
+            b"{\"a\": {\"b\": \"x\\\"\\n\\u00e4\\ud83d\\ude00\"}, \"c\": []}";
+        let value = JsonParser::new(data).parse().unwrap();
+
+        let inner = value.get("a").and_then(|x| x.get("b"));
+
+        assert_eq!(Some("x\"\n\u{e4}\u{1f600}"), inner.and_then(Value::as_str));
+        assert_eq!(Some(&[][..]), value.get("c").and_then(Value::as_array));
+        assert_eq!(None, value.get("d"));
+    }
+
+    #[test]
+    fn parse_003() {
+        for data in [
+            &b""[..],
+            b"[1,]",
+            b"{\"a\" 1}",
+            b"\"abc",
+            b"[1] x",
+            b"tru",
+            b"\"\\x\"",
+            b"\"\\ud800\\u0041\"",
+        ] {
+            assert!(JsonParser::new(data).parse().is_err());
+        }
+    }
+
+    #[test]
+    fn parse_004() {
+        let data = "[".repeat(1024);
+
+        assert!(JsonParser::new(data.as_bytes()).parse().is_err());
+    }
+
+    #[test]
+    fn format_diagnostic_001() {
+        let json = format_diagnostic(
+            Severity::Warning,
+            "invalid-utf8",
+            Some("a \"b\".d"),
+            Some(3),
+            "invalid sequence",
+        );
+
+        assert_eq!(
+            "{\"severity\":\"warning\",\"code\":\"invalid-utf8\",\
+            \"file\":\"a \\\"b\\\".d\",\"line\":3,\
+            \"message\":\"invalid sequence\"}",
+            json
+        );
+
+        let json = format_diagnostic(Severity::Error, "usage", None, None, "x");
+
+        assert_eq!(
+            "{\"severity\":\"error\",\"code\":\"usage\",\"file\":null,\
+            \"line\":null,\"message\":\"x\"}",
+            json
+        );
+    }
 }
@@ -0,0 +1,143 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+fn escape(data: &str) -> String {
+    let mut result = String::with_capacity(data.len());
+
+    for c in data.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+/*
+ * A test case fails if it has at least one failure. Its output is shown
+ * without failing it, e.g. for warnings.
+ */
+pub struct TestCase {
+    pub name: String,
+    pub failures: Vec<String>,
+    pub output: Vec<String>,
+}
+
+/*
+ * Generate a JUnit XML report with a test suite "suite" of "cases". The
+ * first failure of a test case is its message, all of them are its text.
+ */
+pub fn report(suite: &str, cases: &[TestCase]) -> Vec<u8> {
+    let tests = cases.len();
+    let failures = cases.iter().filter(|x| !x.failures.is_empty()).count();
+    let counts = format!("tests=\"{tests}\" failures=\"{failures}\"");
+
+    let mut buf = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    buf.push_str(&format!("<testsuites name=\"dep2j\" {counts}>\n"));
+    buf.push_str(&format!("  <testsuite name=\"{suite}\" {counts}>\n"));
+
+    for case in cases {
+        let name = escape(&case.name);
+
+        buf.push_str(&format!(
+            "    <testcase classname=\"dep2j.{suite}\" name=\"{name}\""
+        ));
+
+        if case.failures.is_empty() && case.output.is_empty() {
+            buf.push_str("/>\n");
+            continue;
+        }
+
+        buf.push_str(">\n");
+
+        if let Some(first) = case.failures.first() {
+            let message = escape(first);
+            let text = escape(&case.failures.join("\n"));
+
+            buf.push_str(&format!(
+                "      <failure message=\"{message}\" type=\"error\">\
+                {text}</failure>\n",
+            ));
+        }
+
+        if !case.output.is_empty() {
+            let text = escape(&case.output.join("\n"));
+
+            buf.push_str(&format!("      <system-out>{text}</system-out>\n"));
+        }
+
+        buf.push_str("    </testcase>\n");
+    }
+
+    buf.push_str("  </testsuite>\n</testsuites>\n");
+    buf.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * report()
+     *
+     * Verify that a test case with failures fails and that the names,
+     * the failures, and the output are escaped.
+     */
+    #[test]
+    fn report_001() {
+        let cases = [
+            TestCase {
+                name: String::from("ok.d"),
+                failures: Vec::new(),
+                output: Vec::new(),
+            },
+            TestCase {
+                name: String::from("a&b.d"),
+                failures: Vec::from([
+                    String::from("a&b.d:2:1: error: missing \":\""),
+                    String::from("a&b.d:3:1: error: stray ':'"),
+                ]),
+                output: Vec::from([String::from("duplicate <rule>")]),
+            },
+        ];
+
+        let expected = "\
+<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<testsuites name=\"dep2j\" tests=\"2\" failures=\"1\">
+  <testsuite name=\"lint\" tests=\"2\" failures=\"1\">
+    <testcase classname=\"dep2j.lint\" name=\"ok.d\"/>
+    <testcase classname=\"dep2j.lint\" name=\"a&amp;b.d\">
+      <failure message=\"a&amp;b.d:2:1: error: missing &quot;:&quot;\" \
+type=\"error\">a&amp;b.d:2:1: error: missing &quot;:&quot;
+a&amp;b.d:3:1: error: stray &apos;:&apos;</failure>
+      <system-out>duplicate &lt;rule&gt;</system-out>
+    </testcase>
+  </testsuite>
+</testsuites>
+";
+
+        assert_eq!(
+            expected,
+            String::from_utf8(report("lint", &cases)).unwrap()
+        );
+    }
+}
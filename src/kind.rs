@@ -0,0 +1,254 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::{HashMap, HashSet};
+
+use crate::dependency::Dependency;
+use crate::json::{Attributes, Value};
+
+pub const KINDS: &[&str] = &["header", "source", "generated", "other"];
+
+/*
+ * The default rules. Generated files are listed first since they usually
+ * share the extension of a header or source file.
+ */
+const DEFAULT_RULES: &[(&str, &str)] = &[
+    ("generated", "generated/"),
+    ("generated", "gen/"),
+    ("generated", ".pb.h"),
+    ("generated", ".pb.cc"),
+    ("header", ".h"),
+    ("header", ".hh"),
+    ("header", ".hpp"),
+    ("header", ".hxx"),
+    ("header", ".h++"),
+    ("header", ".inc"),
+    ("header", ".inl"),
+    ("header", ".ipp"),
+    ("header", ".tcc"),
+    ("source", ".c"),
+    ("source", ".cc"),
+    ("source", ".cpp"),
+    ("source", ".cxx"),
+    ("source", ".c++"),
+    ("source", ".C"),
+    ("source", ".m"),
+    ("source", ".mm"),
+    ("source", ".s"),
+    ("source", ".S"),
+];
+
+/*
+ * Determine the kind of a file with the first matching rule. A pattern
+ * ending with '/' matches files within a directory of that name, any other
+ * pattern matches the end of the path. Files not matching any rule are of
+ * the kind "other".
+ */
+pub struct KindRules {
+    rules: Vec<(String, String)>,
+}
+
+impl KindRules {
+    /*
+     * Create the rules from the default rules and "rules". The latter are
+     * given as "<kind>=<pattern>" and take precedence over the defaults.
+     */
+    pub fn new(rules: &[String]) -> Result<Self, String> {
+        let mut result = Self {
+            rules: Vec::with_capacity(rules.len() + DEFAULT_RULES.len()),
+        };
+
+        for rule in rules {
+            let (kind, pattern) = rule
+                .split_once('=')
+                .filter(|(kind, pattern)| {
+                    KINDS.contains(kind) && !pattern.is_empty()
+                })
+                .ok_or_else(|| format!("invalid kind rule \"{rule}\""))?;
+
+            result.rules.push((kind.into(), pattern.into()));
+        }
+
+        for (kind, pattern) in DEFAULT_RULES {
+            result
+                .rules
+                .push((String::from(*kind), String::from(*pattern)));
+        }
+
+        Ok(result)
+    }
+
+    pub fn kind(&self, path: &str) -> &str {
+        let matches = |pattern: &str| match pattern.strip_suffix('/') {
+            Some(dir) => path
+                .split('/')
+                .rev()
+                .skip(1)
+                .any(|component| component == dir),
+            None => path.ends_with(pattern),
+        };
+
+        self.rules
+            .iter()
+            .find(|(_, pattern)| matches(pattern))
+            .map_or("other", |(kind, _)| kind)
+    }
+
+    pub fn attributes(
+        &self,
+        deps: &[Dependency],
+    ) -> HashMap<String, Attributes> {
+        let mut result = HashMap::new();
+
+        for &prereq in deps.iter().flat_map(|dep| &dep.prerequisites) {
+            if !result.contains_key(prereq) {
+                let kind = Value::String(self.kind(prereq).into());
+
+                result
+                    .insert(String::from(prereq), Vec::from([("kind", kind)]));
+            }
+        }
+
+        result
+    }
+}
+
+/*
+ * Tag each prerequisite with whether it is "generated", i.e. whether it is
+ * the target of a rule as well. Rules without prerequisites do not count,
+ * since compilers emit them for every header with "-MP".
+ */
+pub fn generated(deps: &[Dependency]) -> HashMap<String, Attributes> {
+    let targets: HashSet<&str> = deps
+        .iter()
+        .filter(|dep| !dep.prerequisites.is_empty())
+        .map(|dep| dep.target)
+        .collect();
+
+    let mut result = HashMap::new();
+
+    for &prereq in deps.iter().flat_map(|dep| &dep.prerequisites) {
+        if !result.contains_key(prereq) {
+            let value = Value::Bool(targets.contains(prereq));
+
+            result.insert(
+                String::from(prereq),
+                Vec::from([("generated", value)]),
+            );
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * KindRules::kind()
+     *
+     * Verify that the default rules classify common files.
+     */
+    #[test]
+    fn kind_001() {
+        let rules = KindRules::new(&[]).unwrap();
+
+        assert_eq!("header", rules.kind("include/foo.h"));
+        assert_eq!("header", rules.kind("foo.hpp"));
+        assert_eq!("source", rules.kind("src/main.cc"));
+        assert_eq!("generated", rules.kind("proto/foo.pb.h"));
+        assert_eq!("generated", rules.kind("build/generated/config.h"));
+        assert_eq!("header", rules.kind("generated.h"));
+        assert_eq!("other", rules.kind("Makefile"));
+    }
+
+    /**
+     * KindRules::kind()
+     *
+     * Verify that custom rules take precedence over the default rules.
+     */
+    #[test]
+    fn kind_002() {
+        let vec = Vec::from([
+            String::from("generated=out/"),
+            String::from("source=.rs"),
+        ]);
+
+        let rules = KindRules::new(&vec).unwrap();
+
+        assert_eq!("generated", rules.kind("out/config.h"));
+        assert_eq!("source", rules.kind("main.rs"));
+        assert_eq!("header", rules.kind("config.h"));
+    }
+
+    /**
+     * KindRules::new()
+     *
+     * Verify that invalid rules are rejected.
+     */
+    #[test]
+    fn new_001() {
+        for rule in ["header", "unknown=.h", "header="] {
+            assert!(KindRules::new(&[String::from(rule)]).is_err());
+        }
+    }
+
+    /**
+     * generated()
+     *
+     * Verify that only the prerequisites which are the target of a rule
+     * with prerequisites are tagged as generated.
+     */
+    #[test]
+    fn generated_001() {
+        let deps = [
+            Dependency {
+                target: "main.o",
+                prerequisites: Vec::from(["main.c", "config.h", "util.h"]),
+            },
+            Dependency {
+                target: "config.h",
+                prerequisites: Vec::from(["config.h.in"]),
+            },
+            Dependency {
+                target: "util.h",
+                prerequisites: Vec::new(),
+            },
+        ];
+
+        let attrs = generated(&deps);
+
+        assert_eq!(4, attrs.len());
+        assert_eq!(
+            Vec::from([("generated", Value::Bool(false))]),
+            attrs["main.c"]
+        );
+        assert_eq!(
+            Vec::from([("generated", Value::Bool(true))]),
+            attrs["config.h"]
+        );
+        assert_eq!(
+            Vec::from([("generated", Value::Bool(false))]),
+            attrs["util.h"]
+        );
+        assert_eq!(
+            Vec::from([("generated", Value::Bool(false))]),
+            attrs["config.h.in"]
+        );
+    }
+}
@@ -0,0 +1,48 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*
+ * The library shares the modules of the command-line tool, but only
+ * exposes the helpers enabled with features, e.g. "build-script", and
+ * "parse_checked()" for untrusted input. It only compiles the modules the
+ * helpers need and uses a part of each, so their unused items are not
+ * reported.
+ */
+#![cfg(feature = "build-script")]
+
+#[macro_use]
+#[allow(dead_code)]
+mod diag;
+
+#[allow(dead_code)]
+mod cond;
+#[allow(dead_code)]
+mod dependency;
+#[allow(dead_code)]
+mod hash;
+#[allow(dead_code)]
+mod include;
+#[allow(dead_code)]
+mod intern;
+#[allow(dead_code)]
+mod separators;
+#[allow(dead_code)]
+mod vars;
+
+pub mod cargo;
+
+pub use dependency::{parse_checked, DependencyBuf, ParseError, Parsed};
@@ -0,0 +1,562 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::{HashMap, HashSet};
+
+pub use crate::diag::Severity;
+use crate::{cond, include};
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+struct Token {
+    text: Vec<u8>,
+    line: usize,
+    column: usize,
+}
+
+struct Rule {
+    prerequisites: HashSet<Vec<u8>>,
+    line: usize,
+}
+
+/*
+ * Check dependency files for syntax errors and for constructs which are
+ * valid but likely not intended. Lines and columns start at 1. Include
+ * directives are skipped, the included files are not checked. Conditional
+ * directives are only checked for being balanced.
+ */
+pub struct Linter<'a> {
+    data: &'a [u8],
+    pos: usize,
+    line: usize,
+    column: usize,
+    rules: HashMap<Vec<u8>, Rule>,
+    diagnostics: Vec<Diagnostic>,
+    conditionals: Vec<(usize, usize)>,
+    strict: bool,
+}
+
+impl<'a> Linter<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            line: 1,
+            column: 1,
+            rules: HashMap::new(),
+            diagnostics: Vec::new(),
+            conditionals: Vec::new(),
+            strict: false,
+        }
+    }
+
+    /*
+     * Report the recipe lines of a Makefile as errors, like the parser
+     * rejects them with "--strict".
+     */
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn lint(mut self) -> Vec<Diagnostic> {
+        while self.pos < self.data.len() {
+            match self.peek(0) {
+                Some(b'\t') => self.lint_recipe(),
+                _ if include::is_directive(&self.data[self.pos..]) => {
+                    self.skip_continued_line()
+                }
+                _ if cond::is_directive(&self.data[self.pos..]) => {
+                    self.lint_conditional()
+                }
+                _ => self.lint_rule(),
+            }
+        }
+
+        for (line, column) in std::mem::take(&mut self.conditionals) {
+            let msg = String::from("missing \"endif\"");
+            self.report(line, column, Severity::Error, msg);
+        }
+
+        self.diagnostics
+    }
+
+    fn report(
+        &mut self,
+        line: usize,
+        column: usize,
+        severity: Severity,
+        msg: String,
+    ) {
+        self.diagnostics.push(Diagnostic {
+            line,
+            column,
+            severity,
+            message: msg,
+        });
+    }
+
+    fn advance(&mut self) -> u8 {
+        let byte = self.data[self.pos];
+
+        self.pos += 1;
+
+        if byte == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        byte
+    }
+
+    fn peek(&self, offset: usize) -> Option<u8> {
+        self.data.get(self.pos + offset).copied()
+    }
+
+    /*
+     * Check whether the remainder of the current line is whitespace only.
+     */
+    fn is_blank_line(&self) -> bool {
+        self.data[self.pos..]
+            .iter()
+            .take_while(|&&x| x != b'\n')
+            .all(|x| x.is_ascii_whitespace())
+    }
+
+    fn skip_line(&mut self) {
+        while self.pos < self.data.len() && self.advance() != b'\n' {}
+    }
+
+    /*
+     * Skip a line and its continuation lines.
+     */
+    fn skip_continued_line(&mut self) {
+        while self.pos < self.data.len() {
+            let start = self.pos;
+
+            self.skip_line();
+
+            let line = &self.data[start..self.pos];
+            let line = line.strip_suffix(b"\n").unwrap_or(line);
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+            if !line.ends_with(b"\\") {
+                break;
+            }
+        }
+    }
+
+    /*
+     * A line starting with a tab is a recipe line of a Makefile, which
+     * does not contain any dependencies.
+     */
+    fn lint_recipe(&mut self) {
+        let (line, column) = (self.line, self.column);
+
+        self.skip_continued_line();
+
+        if self.strict {
+            let msg = String::from("unexpected recipe line");
+            self.report(line, column, Severity::Error, msg);
+        }
+    }
+
+    /*
+     * Check that each "else" and "endif" belongs to a conditional. The
+     * conditions themselves are not checked.
+     */
+    fn lint_conditional(&mut self) {
+        let (line, column) = (self.line, self.column);
+        let keyword = cond::keyword(&self.data[self.pos..]);
+
+        self.skip_continued_line();
+
+        let msg = match keyword {
+            Some(b"else") if self.conditionals.is_empty() => {
+                "\"else\" without conditional"
+            }
+            Some(b"endif") if self.conditionals.pop().is_none() => {
+                "\"endif\" without conditional"
+            }
+            Some(b"else" | b"endif") => return,
+            _ => {
+                self.conditionals.push((line, column));
+                return;
+            }
+        };
+
+        self.report(line, column, Severity::Error, msg.into());
+    }
+
+    /*
+     * Read one logical line, i.e. a line including its continuation lines,
+     * and check the contained rule.
+     */
+    fn lint_rule(&mut self) {
+        let start = (self.line, self.column);
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut token: Option<Token> = None;
+        let mut colon: Option<usize> = None;
+
+        while let Some(byte) = self.peek(0) {
+            let (line, column) = (self.line, self.column);
+
+            match byte {
+                b'\n' => {
+                    self.advance();
+                    break;
+                }
+                b'#' if token.is_none() => {
+                    self.skip_line();
+                    break;
+                }
+                b' ' | b'\t' | b'\r' => {
+                    self.advance();
+                    tokens.extend(token.take());
+                }
+                b'\\' => {
+                    self.advance();
+
+                    match self.peek(0) {
+                        None => {
+                            let msg = "trailing backslash at end of file";
+                            self.report(
+                                line,
+                                column,
+                                Severity::Error,
+                                msg.into(),
+                            );
+                        }
+                        Some(b'\n') => {
+                            self.advance();
+                            tokens.extend(token.take());
+                        }
+                        Some(b'\r') if self.peek(1) == Some(b'\n') => {
+                            self.advance();
+                            self.advance();
+                            tokens.extend(token.take());
+                        }
+                        Some(b' ' | b'\t') if self.is_blank_line() => {
+                            let msg = "whitespace after line continuation";
+                            self.report(
+                                line,
+                                column,
+                                Severity::Warning,
+                                msg.into(),
+                            );
+
+                            self.skip_line();
+                            tokens.extend(token.take());
+                        }
+                        Some(_) => {
+                            let next = self.advance();
+                            let token = token.get_or_insert(Token {
+                                text: Vec::new(),
+                                line,
+                                column,
+                            });
+
+                            token.text.push(b'\\');
+                            token.text.push(next);
+                        }
+                    }
+                }
+                b':' if Self::is_drive_letter(&token, self.peek(1)) => {
+                    self.advance();
+                    token.as_mut().unwrap().text.push(b':');
+                }
+                b':' if colon.is_none() => {
+                    self.advance();
+                    tokens.extend(token.take());
+                    colon = Some(tokens.len());
+                }
+                b':' => {
+                    self.advance();
+
+                    let msg = "stray ':' in list of prerequisites";
+                    self.report(line, column, Severity::Warning, msg.into());
+                }
+                _ => {
+                    self.advance();
+
+                    let token = token.get_or_insert(Token {
+                        text: Vec::new(),
+                        line,
+                        column,
+                    });
+
+                    token.text.push(byte);
+                }
+            }
+        }
+
+        tokens.extend(token.take());
+
+        self.check_rule(start, tokens, colon);
+    }
+
+    /*
+     * A colon following a single letter and preceding a path separator is
+     * part of a path like "C:\foo" or "C:/foo".
+     */
+    fn is_drive_letter(token: &Option<Token>, next: Option<u8>) -> bool {
+        match token {
+            Some(token) => {
+                token.text.len() == 1
+                    && token.text[0].is_ascii_alphabetic()
+                    && matches!(next, Some(b'\\' | b'/'))
+            }
+            None => false,
+        }
+    }
+
+    /*
+     * Check a rule whose tokens in front of the index "colon" are the
+     * targets and whose remaining tokens are the prerequisites.
+     */
+    fn check_rule(
+        &mut self,
+        start: (usize, usize),
+        mut targets: Vec<Token>,
+        colon: Option<usize>,
+    ) {
+        let (line, column) = start;
+
+        let colon = match colon {
+            Some(colon) => colon,
+            None if targets.is_empty() => return,
+            None => {
+                let msg = String::from("missing ':' after target");
+                self.report(line, column, Severity::Error, msg);
+                return;
+            }
+        };
+
+        let prereqs = targets.split_off(colon);
+
+        if targets.is_empty() {
+            let msg = String::from("rule without target");
+            self.report(line, column, Severity::Error, msg);
+            return;
+        }
+
+        if let Some(second) = targets.get(1) {
+            let msg = format!(
+                "multiple targets \"{}\" and \"{}\", possibly an unescaped space",
+                String::from_utf8_lossy(&targets[0].text),
+                String::from_utf8_lossy(&second.text)
+            );
+
+            self.report(second.line, second.column, Severity::Warning, msg);
+        }
+
+        let set: HashSet<Vec<u8>> =
+            prereqs.into_iter().map(|x| x.text).collect();
+
+        for target in targets {
+            match self.rules.get(&target.text) {
+                Some(rule) => {
+                    /* The branches of a conditional are alternatives */
+                    let conflict = self.conditionals.is_empty()
+                        && !rule.prerequisites.is_empty()
+                        && !set.is_empty()
+                        && rule.prerequisites != set;
+
+                    if conflict {
+                        let msg = format!(
+                            "conflicting prerequisites for \"{}\", previous \
+                             rule at line {}",
+                            String::from_utf8_lossy(&target.text),
+                            rule.line
+                        );
+
+                        self.report(
+                            target.line,
+                            target.column,
+                            Severity::Warning,
+                            msg,
+                        );
+                    }
+                }
+                None => {
+                    let rule = Rule {
+                        prerequisites: set.clone(),
+                        line: target.line,
+                    };
+
+                    self.rules.insert(target.text, rule);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn do_lint(data: &str) -> Vec<(usize, usize, Severity)> {
+        Linter::new(data.as_bytes())
+            .lint()
+            .into_iter()
+            .map(|x| (x.line, x.column, x.severity))
+            .collect()
+    }
+
+    /**
+     * Linter::lint()
+     *
+     * Verify that valid dependency files do not produce any diagnostics.
+     */
+    #[test]
+    fn lint_001() {
+        let data = "\
+# comment
+a.o: a.c \\
+  a.h b\\ c.h C:\\foo.h
+a.h:
+";
+
+        assert!(do_lint(data).is_empty());
+    }
+
+    /**
+     * Linter::lint()
+     *
+     * Verify that syntax errors are reported with their locations.
+     */
+    #[test]
+    fn lint_002() {
+        let data = "a.o a.c\n: b.c\nb.o: b.c \\";
+
+        assert_eq!(
+            Vec::from([
+                (1, 1, Severity::Error),
+                (2, 1, Severity::Error),
+                (3, 10, Severity::Error),
+            ]),
+            do_lint(data)
+        );
+    }
+
+    /**
+     * Linter::lint()
+     *
+     * Verify that suspicious constructs, e.g. duplicated rules with
+     * different prerequisites, are reported as warnings.
+     */
+    #[test]
+    fn lint_003() {
+        let data = "\
+a.o: a.c: b.c
+my file.o: x.c
+a.o: c.c
+b.o: b.c \\ \n  b.h
+";
+
+        assert_eq!(
+            Vec::from([
+                (1, 9, Severity::Warning),
+                (2, 4, Severity::Warning),
+                (3, 1, Severity::Warning),
+                (4, 10, Severity::Warning),
+            ]),
+            do_lint(data)
+        );
+    }
+
+    /**
+     * Linter::lint()
+     *
+     * Verify that the recipe lines of a Makefile, including their
+     * continuation lines, are skipped and only reported in strict mode.
+     */
+    #[test]
+    fn lint_004() {
+        let data = "all: a.o\n\tcc -o all \\\n  a.o\n\techo: done\nb.o: b.c\n";
+
+        assert!(do_lint(data).is_empty());
+
+        let mut linter = Linter::new(data.as_bytes());
+        linter.set_strict(true);
+
+        let result: Vec<_> = linter
+            .lint()
+            .into_iter()
+            .map(|x| (x.line, x.column, x.severity))
+            .collect();
+
+        assert_eq!(
+            Vec::from([(2, 1, Severity::Error), (4, 1, Severity::Error)]),
+            result
+        );
+    }
+
+    /**
+     * Linter::lint()
+     *
+     * Verify that include directives are not reported as rules.
+     */
+    #[test]
+    fn lint_005() {
+        let data = "\
+include a.mk b.mk
+-include c.mk
+  sinclude \\
+    d.mk
+a.o: a.c
+includes: a.o
+include
+";
+
+        assert_eq!(Vec::from([(7, 1, Severity::Error)]), do_lint(data));
+    }
+
+    /**
+     * Linter::lint()
+     *
+     * Verify that conditional directives are not reported as rules and
+     * that unbalanced conditionals are reported.
+     */
+    #[test]
+    fn lint_006() {
+        let data = "\
+ifeq ($(ARCH),x86)
+a.o: x86.h
+else ifdef DEBUG
+a.o: debug.h
+else
+a.o: arm.h
+endif
+endif
+  ifndef A
+else: a
+";
+
+        assert_eq!(
+            Vec::from([(8, 1, Severity::Error), (9, 1, Severity::Error)]),
+            do_lint(data)
+        );
+    }
+}
@@ -0,0 +1,334 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+
+use crate::cond;
+use crate::json::{Attributes, Value};
+
+/*
+ * The position of a rule in a dependency file. The line starts at 1 and
+ * the byte range "start..end" covers the rule including its continuation
+ * lines, but not the final newline. The text and the prerequisites of the
+ * rule are only recorded on request, see "Locations::set_record_rules()".
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    pub rule: String,
+    pub prerequisites: Vec<String>,
+}
+
+impl Location {
+    fn to_value(&self) -> Value {
+        Value::Object(Vec::from([
+            (String::from("file"), Value::String(self.file.clone())),
+            (String::from("line"), Value::Number(self.line as f64)),
+            (String::from("start"), Value::Number(self.start as f64)),
+            (String::from("end"), Value::Number(self.end as f64)),
+        ]))
+    }
+}
+
+/*
+ * Find the end of the logical line starting at "pos", i.e. the next
+ * newline which is not escaped by a backslash.
+ */
+fn line_end(data: &[u8], pos: usize) -> usize {
+    let mut i = pos;
+
+    while i < data.len() {
+        if data[i] == b'\n' && (i == 0 || data[i - 1] != b'\\') {
+            break;
+        }
+
+        i += 1;
+    }
+
+    i
+}
+
+/*
+ * Find the colon which separates the targets from the prerequisites. Like
+ * the parser, only a colon followed by whitespace counts, so drive letters
+ * are part of a path.
+ */
+fn separator(rule: &[u8]) -> Option<usize> {
+    (0..rule.len()).find(|&i| {
+        rule[i] == b':'
+            && matches!(
+                &rule[i + 1..],
+                [] | [b' ' | b'\t' | b'\r' | b'\n', ..]
+                    | [b'\\', b'\n', ..]
+                    | [b'\\', b'\r', b'\n', ..]
+            )
+    })
+}
+
+/*
+ * Split the targets or the prerequisites of a rule at unescaped whitespace.
+ * Escaped newlines separate paths, too.
+ */
+fn split_paths(paths: &[u8]) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut begin = 0;
+
+    for i in 0..=paths.len() {
+        let split = match paths.get(i) {
+            None => true,
+            Some(b'\n' | b'\r') => true,
+            Some(b' ' | b'\t') => i == 0 || paths[i - 1] != b'\\',
+            Some(b'\\') => matches!(paths.get(i + 1), Some(b'\n' | b'\r')),
+            _ => false,
+        };
+
+        if split {
+            if i > begin {
+                result.push(String::from_utf8_lossy(&paths[begin..i]).into());
+            }
+
+            begin = i + 1;
+        }
+    }
+
+    result
+}
+
+fn is_include(line: &[u8]) -> bool {
+    [&b"include "[..], b"-include ", b"sinclude "]
+        .iter()
+        .any(|x| line.starts_with(x))
+}
+
+/*
+ * Record where the rules of each target are defined. A target can have
+ * several locations since rules for the same target are merged.
+ */
+#[derive(Default)]
+pub struct Locations {
+    map: HashMap<String, Vec<Location>>,
+    record_rules: bool,
+}
+
+impl Locations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /*
+     * Record the text and the prerequisites of each rule, too, e.g. to
+     * find the rules which are responsible for a dependency.
+     */
+    pub fn set_record_rules(&mut self, record_rules: bool) {
+        self.record_rules = record_rules;
+    }
+
+    /*
+     * Record the rules of the dependency file "path" with the content
+     * "data". Recipe lines, comments, and directives are skipped.
+     */
+    pub fn scan(&mut self, path: &str, data: &[u8]) {
+        let mut pos = 0;
+        let mut line = 1;
+
+        while pos < data.len() {
+            let end = line_end(data, pos);
+            let first_line = line;
+            let rule = &data[pos..end];
+            let start = pos;
+
+            line += rule.iter().filter(|&&x| x == b'\n').count() + 1;
+            pos = end + 1;
+
+            let rule = rule.strip_suffix(b"\r").unwrap_or(rule);
+            let text = rule.trim_ascii_start();
+
+            if rule.starts_with(b"\t")
+                || text.is_empty()
+                || text.starts_with(b"#")
+                || is_include(text)
+                || cond::is_directive(text)
+            {
+                continue;
+            }
+
+            let sep = match separator(rule) {
+                Some(val) => val,
+                None => continue,
+            };
+
+            let (text, prerequisites) = if self.record_rules {
+                let text = String::from_utf8_lossy(rule).into_owned();
+
+                (text, split_paths(&rule[sep + 1..]))
+            } else {
+                (String::new(), Vec::new())
+            };
+
+            for target in split_paths(&rule[..sep]) {
+                self.map.entry(target).or_default().push(Location {
+                    file: String::from(path),
+                    line: first_line,
+                    start,
+                    end: start + rule.len(),
+                    rule: text.clone(),
+                    prerequisites: prerequisites.clone(),
+                });
+            }
+        }
+    }
+
+    /*
+     * Map the recorded targets and prerequisites like the paths of the
+     * dependencies, so the locations are found for the mapped targets.
+     */
+    pub fn map_targets<F>(&mut self, f: F)
+    where
+        F: Fn(&str) -> String,
+    {
+        let mut map: HashMap<String, Vec<Location>> = HashMap::new();
+
+        for (target, mut locations) in self.map.drain() {
+            for location in &mut locations {
+                for prereq in &mut location.prerequisites {
+                    *prereq = f(prereq);
+                }
+            }
+
+            map.entry(f(&target)).or_default().extend(locations);
+        }
+
+        self.map = map;
+    }
+
+    /*
+     * Return the first location of "target", if it was found.
+     */
+    pub fn get(&self, target: &str) -> Option<&Location> {
+        self.map.get(target).and_then(|x| x.first())
+    }
+
+    /*
+     * Return the locations of all rules of "target".
+     */
+    pub fn get_all(&self, target: &str) -> &[Location] {
+        self.map.get(target).map_or(&[], |x| x.as_slice())
+    }
+
+    pub fn attributes(&self) -> HashMap<String, Attributes> {
+        self.map
+            .iter()
+            .map(|(target, locations)| {
+                let list = locations.iter().map(Location::to_value).collect();
+
+                (
+                    target.clone(),
+                    Vec::from([("locations", Value::Array(list))]),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * Locations::scan()
+     *
+     * Verify that each target of a rule is located at the first line of
+     * the rule and that the byte range covers its continuation lines.
+     */
+    #[test]
+    fn scan_001() {
+        let data = b"\
+# comment
+a.o b.o: a.c \\
+ a.h
+\techo a
+include x.d
+
+c\\ d.o: c.c\r
+c.o: C:\\c.c
+";
+        let mut locations = Locations::new();
+        locations.scan("x.d", data);
+
+        let loc = |line, start, end| {
+            Vec::from([Location {
+                file: String::from("x.d"),
+                line,
+                start,
+                end,
+                rule: String::new(),
+                prerequisites: Vec::new(),
+            }])
+        };
+
+        assert_eq!(4, locations.map.len());
+        assert_eq!(Some(&loc(2, 10, 29)), locations.map.get("a.o"));
+        assert_eq!(Some(&loc(2, 10, 29)), locations.map.get("b.o"));
+        assert_eq!(Some(&loc(7, 51, 62)), locations.map.get("c\\ d.o"));
+        assert_eq!(Some(&loc(8, 64, 75)), locations.map.get("c.o"));
+    }
+
+    /**
+     * Locations::map_targets()
+     *
+     * Verify that the locations of targets which are mapped to the same
+     * path are combined.
+     */
+    #[test]
+    fn map_targets_001() {
+        let mut locations = Locations::new();
+        locations.scan("x.d", b"./a.o: a.c\na.o: a.h\n");
+        locations.map_targets(|x| String::from(x.trim_start_matches("./")));
+
+        let attrs = locations.attributes();
+
+        match &attrs["a.o"][..] {
+            [("locations", Value::Array(list))] => assert_eq!(2, list.len()),
+            _ => panic!("unexpected attributes"),
+        }
+    }
+
+    /**
+     * Locations::scan()
+     *
+     * Verify that the text and the prerequisites of the rules are recorded
+     * on request and mapped like the targets.
+     */
+    #[test]
+    fn scan_002() {
+        let mut locations = Locations::new();
+        locations.set_record_rules(true);
+        locations.scan("x.d", b"a.o: a.c \\\n ./a.h\na.o: b.h\n");
+        locations.map_targets(|x| String::from(x.trim_start_matches("./")));
+
+        let list = locations.get_all("a.o");
+
+        assert_eq!(2, list.len());
+        assert_eq!("a.o: a.c \\\n ./a.h", list[0].rule);
+        assert_eq!(Vec::from(["a.c", "a.h"]), list[0].prerequisites);
+        assert_eq!(Vec::from(["b.h"]), list[1].prerequisites);
+        assert!(locations.get_all("b.o").is_empty());
+    }
+}
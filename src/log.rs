@@ -0,0 +1,201 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crate::diag;
+use crate::json::{JsonSerializer, Value};
+use crate::lint::Severity;
+
+fn millis(duration: Duration) -> Value {
+    Value::Number((duration.as_secs_f64() * 1e6).round() / 1e3)
+}
+
+/*
+ * Format an entry of the log as a single line of JSON. Each entry starts
+ * with the "event" and the "time" in milliseconds since the start.
+ */
+fn format_entry(
+    event: &str,
+    time: Duration,
+    fields: Vec<(&str, Value)>,
+) -> Vec<u8> {
+    let mut members = Vec::with_capacity(fields.len() + 2);
+    members.push((String::from("event"), Value::String(event.into())));
+    members.push((String::from("time"), millis(time)));
+    members.extend(fields.into_iter().map(|(k, v)| (String::from(k), v)));
+
+    let mut serializer = JsonSerializer::new();
+    serializer.write_value(&Value::Object(members));
+
+    let mut line = Vec::from(serializer.get_json());
+    line.push(b'\n');
+    line
+}
+
+/*
+ * Record the processing of the input files in a file with one JSON object
+ * per line. The entries are written immediately, so the log is complete
+ * even if dep2j exits with an error. Warnings are printed to stderr unless
+ * "quiet" is set, regardless of the log.
+ */
+pub struct Logger {
+    file: Option<File>,
+    start: Instant,
+    warnings: usize,
+    failures: usize,
+    quiet: bool,
+}
+
+impl Logger {
+    pub fn new(quiet: bool) -> Self {
+        Self {
+            file: None,
+            start: Instant::now(),
+            warnings: 0,
+            failures: 0,
+            quiet,
+        }
+    }
+
+    pub fn open(&mut self, path: &str) -> io::Result<()> {
+        self.file = Some(File::create(path)?);
+
+        Ok(())
+    }
+
+    fn write(&mut self, event: &str, fields: Vec<(&str, Value)>) {
+        let file = match &mut self.file {
+            Some(val) => val,
+            None => return,
+        };
+
+        let line = format_entry(event, self.start.elapsed(), fields);
+
+        if let Err(err) = file.write_all(&line) {
+            warning!("write-failed", "failed to write the log: {err}");
+            self.file = None;
+        }
+    }
+
+    /*
+     * Record that the input "path" of "bytes" was read in "elapsed".
+     */
+    pub fn input(&mut self, path: &str, bytes: u64, elapsed: Duration) {
+        self.write(
+            "input",
+            Vec::from([
+                ("path", Value::String(path.into())),
+                ("bytes", Value::Number(bytes as f64)),
+                ("duration", millis(elapsed)),
+            ]),
+        );
+    }
+
+    /*
+     * Record a warning with the "code" of "diag" and the file and the line
+     * it refers to, if any.
+     */
+    pub fn warning(
+        &mut self,
+        code: &str,
+        location: Option<(&str, usize)>,
+        message: &str,
+    ) {
+        if !self.quiet {
+            let (file, line) = location.unzip();
+
+            diag::emit(Severity::Warning, code, file, line, message);
+        }
+
+        self.warnings += 1;
+        self.write(
+            "warning",
+            Vec::from([("message", Value::String(message.into()))]),
+        );
+    }
+
+    /*
+     * Record that the input "path" could not be read or parsed. Unlike a
+     * warning, the error is printed even if "quiet" is set.
+     */
+    pub fn failure(&mut self, code: &str, path: &str, message: &str) {
+        diag::emit(Severity::Error, code, Some(path), None, message);
+
+        self.failures += 1;
+        self.write(
+            "failure",
+            Vec::from([
+                ("path", Value::String(path.into())),
+                ("message", Value::String(message.into())),
+            ]),
+        );
+    }
+
+    pub fn summary(
+        &mut self,
+        inputs: usize,
+        bytes: u64,
+        targets: usize,
+        prerequisites: usize,
+    ) {
+        let count = |x| Value::Number(x as f64);
+        let warnings = self.warnings;
+        let failures = self.failures;
+
+        self.write(
+            "summary",
+            Vec::from([
+                ("inputs", count(inputs)),
+                ("bytes", Value::Number(bytes as f64)),
+                ("targets", count(targets)),
+                ("prerequisites", count(prerequisites)),
+                ("warnings", count(warnings)),
+                ("failures", count(failures)),
+            ]),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * format_entry()
+     *
+     * Verify that an entry is written as a single line of JSON which
+     * starts with the event and the time.
+     */
+    #[test]
+    fn format_entry_001() {
+        let fields = Vec::from([
+            ("path", Value::String(String::from("a \"b\".d"))),
+            ("bytes", Value::Number(42.0)),
+            ("duration", millis(Duration::from_micros(1500))),
+        ]);
+
+        let line = format_entry("input", Duration::from_millis(3), fields);
+        let expected =
+            "{\"event\":\"input\",\"time\":3,\"path\":\"a \\\"b\\\".d\",\
+                        \"bytes\":42,\"duration\":1.5}\n";
+
+        assert_eq!(expected, String::from_utf8(line).unwrap());
+    }
+}
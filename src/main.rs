@@ -15,33 +15,619 @@
  * along with this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
+#[macro_use]
+mod diag;
+
 mod args;
+mod bazel;
+mod budget;
+mod cache;
+mod chunk;
+mod classify;
+mod compdb;
+mod cond;
+mod conflict;
+mod csv;
 mod dependency;
+mod dialect;
+mod diff;
+mod dot;
+mod expand;
+mod expr;
+mod extension;
+mod fingerprint;
+mod fls;
+mod git;
+mod graph;
 mod hash;
+mod html;
+mod include;
+mod inode;
+mod intern;
 mod json;
+mod junit;
+mod kind;
+mod lint;
+mod location;
+mod log;
+mod memory;
+mod metadata;
+mod naming;
+mod ninja;
+mod normalize;
+mod output;
+mod p1689;
+mod package;
+mod pipe;
+mod plantuml;
+mod policy;
+mod prefix;
+mod progress;
+mod regex;
+mod rewrite;
+mod sandbox;
+mod sarif;
+mod separators;
+mod server;
+mod source;
+mod summary;
+mod swift;
+mod template;
+mod timings;
+mod tlog;
+mod unicode;
+mod unicode_data;
+mod uri;
+mod utf8;
+mod vars;
+mod zstd;
 
-use std::{env, str};
-use std::fs::File;
-use std::io::{self, Read, Write, IsTerminal};
-use std::process::exit;
+use std::borrow::Cow;
+use std::{env, mem, str, thread};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, StdoutLock, Write, IsTerminal};
+use std::path::Path;
+use std::process::{self, exit, ChildStdin, Stdio};
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::dependency::DependencyParser;
-use crate::json::JsonSerializer;
+use crate::args::{Args, Command, Format, InputFormat, Query, Report, Stats};
+use crate::bazel::BazelMap;
+use crate::budget::Budget;
+use crate::cache::Cache;
+use crate::chunk::ChunkParser;
+use crate::classify::{Class, Classifier};
+use crate::compdb::CompileDb;
+use crate::conflict::ConflictFinder;
+use crate::csv::CsvSerializer;
+use crate::dependency::{
+    Dependency, DependencyBuf, DependencyParser, MergePolicy,
+};
+use crate::dialect::Dialect;
+use crate::dot::DotSerializer;
+use crate::extension::ExtensionFilter;
+use crate::graph::Graph;
+use crate::html::HtmlSerializer;
+use crate::include::Includer;
+use crate::json::{Attributes, JsonSerializer, Value};
+use crate::junit::TestCase;
+use crate::kind::KindRules;
+use crate::lint::{Linter, Severity};
+use crate::location::Locations;
+use crate::log::Logger;
+use crate::naming::NameTemplate;
+use crate::ninja::NinjaSerializer;
+use crate::package::{Backend, PackageResolver};
+use crate::plantuml::PlantUmlSerializer;
+use crate::policy::Policy;
+use crate::prefix::PrefixMap;
+use crate::progress::Progress;
+use crate::rewrite::Rewriter;
+use crate::sandbox::Sandbox;
+use crate::sarif::Finding;
+use crate::separators::Separators;
+use crate::server::Server;
+use crate::source::SourceRules;
+use crate::template::Template;
+use crate::timings::Phase;
+use crate::unicode::Normalizer;
+use crate::utf8::{Utf8Checker, Utf8Policy, Utf8Reader};
+use crate::vars::Variables;
+use crate::zstd::Decoder;
 
 fn help() {
     println!(
         "\
-USAGE: dep2j [options] <file1> [... <fileN>]
+USAGE: dep2j [<command>] [options] <file1> [... <fileN>]
+       dep2j diff [options] <old> <new>
+
+COMMANDS:
+
+    convert         Convert the dependency files. This is the default if
+                    no command is given.
+    graph           Write the dependency graph in the format given with
+                    \"--format\", which is one of \"cytoscape\"
+                    (default), \"d3\", \"dot\", \"html\", and
+                    \"plantuml\".
+    query path <from> <to>
+                    Print the chain of dependencies which leads from
+                    <from> to <to>.
+    query affected <ref>
+                    Print the targets affected by the files which were
+                    modified since the git revision <ref>, including
+                    uncommitted changes and untracked files anywhere in
+                    the repository.
+    query targets   Print the targets.
+    query roots     Print the targets which are not a prerequisite of any
+                    other rule, e.g. the final binaries.
+    query prerequisites
+                    Print each prerequisite once.
+    query orphans   Print the prerequisites which are not reachable from
+                    the root set and the targets which are not referenced
+                    by any other rule. Without a root set, the unreferenced
+                    targets are used as roots.
+    stats [summary] Print tables of the targets with the most
+                    prerequisites and the prerequisites with the most
+                    targets depending on them directly. The tables have
+                    10 rows unless \"--top\" is given.
+    stats metrics   Print the number of direct predecessors (fan-in) and
+                    successors (fan-out) of each target and prerequisite.
+    stats hotspots  Rank the prerequisites by the number of targets which
+                    depend on them directly and transitively.
+    stats critical-path
+                    Print the longest chains of dependencies and the
+                    depth of each target.
+    stats components
+                    Group the targets into weakly connected components
+                    and output each component with its size.
+    diff <old> <new>
+                    Print the rules which were added, removed, or changed
+                    between the files <old> and <new>.
+    why <target> <prerequisite>
+                    Print the rules and the dependency files which make
+                    <target> depend on <prerequisite>. With
+                    \"--transitive\", the rules of each dependency in
+                    the chain from <target> to <prerequisite> are printed
+                    if there is no direct dependency.
+    check           Check the dependencies against the policy given with
+                    \"--policy\". The exit status is non-zero if a
+                    dependency is denied by the policy.
+    lint            Check the dependency files for syntax errors and
+                    suspicious constructs. The exit status is non-zero
+                    if an error is found. Recipe lines are skipped
+                    unless \"--strict\" is given.
+    watch           Convert the dependency files again whenever one of
+                    them is modified.
+
+    The commands \"path\" and \"hotspots\" and the options
+    \"--changed-since <ref>\", \"--orphans\", \"--metrics\",
+    \"--critical-path\", and \"--components\" are kept as aliases of
+    the respective queries and statistics. The option \"--list <name>\"
+    is an alias of \"query <name>\" for \"roots\", \"targets\", and
+    \"prerequisites\".
 
 OPTIONS:
 
-    -o <file>       Write generated output to <file>.
+    -o <file>       Write generated output to <file>. An existing file
+                    is only overwritten if it looks like the output of
+                    dep2j. This option may be specified multiple times
+                    to write several files from a single run. The files
+                    ending with \".csv\", \".dot\", \".gv\", \".html\",
+                    and \".puml\" are written in the respective format,
+                    any other file in the format given with \"--format\".
+                    The file \"-\" is stdout, e.g. to write it in
+                    addition to other files.
+    --force         Overwrite the files given with \"-o\" in any case.
+    --name-template <template>
+                    Write the JSON output of each target to its own file
+                    named by <template> instead of a single output. The
+                    placeholders \"{{path}}\", \"{{dir}}\", \"{{name}}\",
+                    \"{{stem}}\", and \"{{ext}}\" expand to the parts of the
+                    target, e.g. \"src/main.o\", \"src\", \"main.o\",
+                    \"main\", and \"o\". Empty and \".\" segments are
+                    dropped, \"..\" is replaced with \"__\", and control
+                    characters and '<', '>', ':', '\"', '\\', '|', '?', and
+                    '*' are replaced with '_'. Missing directories are
+                    created.
+    --pipe <command>
+                    Write the output to the stdin of the shell command
+                    <command> instead of stdout, e.g. to compress or
+                    upload it. If the command fails, dep2j exits with
+                    its exit status.
+    --compress <name>
+                    Compress each output. The only supported compression
+                    is \"zstd\", which requires the zstd program. Input
+                    files ending with \".zst\" are always decompressed.
+    --diagnostics <format>
+                    Print the warnings and errors on stderr as \"text\"
+                    (default) or as \"json\" with one object per line.
+                    Each object has the \"severity\", a \"code\" for the
+                    kind of the diagnostic, e.g. \"read-failed\", the
+                    \"file\" and the \"line\", which may be null, and
+                    the \"message\".
+    --quiet, -q     Do not show the progress and the warnings on stderr.
+                    By default, the number of processed files, the
+                    number of parsed bytes, and the estimated remaining
+                    time are shown if stderr is a terminal.
+    --log-file <file>
+                    Write the size and the reading time of each input
+                    file, the warnings, and a summary with the number of
+                    inputs, bytes, targets, and prerequisites to <file>.
+                    Each entry is a JSON object on a separate line.
+    --timings       Print the wall time and the throughput of reading and
+                    parsing each input file and the total of each phase,
+                    i.e. read, parse, merge, transform, serialize, and
+                    write, to stderr after the conversion.
+    --stats         Print the peak resident set size to stderr after the
+                    conversion. If built with the \"alloc-stats\"
+                    feature, the peak and the total of the allocated
+                    bytes and the number of allocations are printed,
+                    too.
+    --format <name> Write the dependencies in the format <name>.
+                    Supported formats are \"json\" (default), \"csv\",
+                    \"cytoscape\", \"d3\", \"dot\", \"html\", \"ninja\",
+                    \"plantuml\", \"template\", \"tree-json\",
+                    \"batches\", and \"manifest\". The \"html\" format
+                    generates a standalone page to explore the graph,
+                    the \"ninja\" format matches \"ninja -t deps\", the
+                    \"tree-json\" format nests the prerequisites by
+                    their directories, the \"batches\" format lists the
+                    targets which can be built in parallel in batches,
+                    which have to be built in order, and the
+                    \"manifest\" format lists each prerequisite of all
+                    rules once, with the attributes of \"--hash\" and
+                    \"--with-metadata\".
+    --template <text>
+                    Write each rule or each pair of target and
+                    prerequisite with the \"template\" format as <text>.
+                    The placeholders \"{{{{target}}}}\", \"{{{{prereq}}}}\",
+                    \"{{{{prereqs}}}}\", and \"{{{{count}}}}\" are replaced with the
+                    target, the prerequisite, all prerequisites
+                    separated by spaces, and their number. A template
+                    with \"{{{{prereq}}}}\" is expanded for each pair, any
+                    other template for each rule. The sequences \"\\n\"
+                    and \"\\t\" are replaced with a newline and a tab.
+    --input <file>[:dir=<dir>]
+                    Read <file> like a positional input. With \":dir=\",
+                    relative targets and prerequisites of <file> are
+                    resolved against <dir>, e.g. for dependency files
+                    written in different directories. The joined paths
+                    are not normalized, see \"--normalize-paths\".
+    --input-format <name>
+                    Read the input in the format <name>. Supported
+                    formats are \"make\" (default) for dependency
+                    files, \"json\" for the output of dep2j, and
+                    \"tlog\" for the tracking logs of MSBuild, e.g.
+                    \"CL.read.1.tlog\", in UTF-16 or UTF-8. The
+                    formats \"iar\" and \"keil\" read dependency files
+                    with one rule per line and quoted paths, \"ti\" the
+                    \".pp\" files of the TI compilers, \"arm\" the
+                    output of armcc and armclang on Windows, and
+                    \"swift\" the \".d\" and \".swiftdeps\" files of
+                    swiftc. The format \"p1689\" reads the module
+                    dependencies written by clang-scan-deps, which are
+                    tagged with \"module\" in the output, and \"fls\"
+                    the recorder files of TeX engines, where each
+                    output depends on the inputs. The format
+                    \"makedepend\" reads the rules which makedepend
+                    appends to a Makefile after \"# DO NOT DELETE\".
+    --stream        Write the rules of each input file as soon as it is
+                    parsed instead of collecting the rules of all input
+                    files first, so the memory use stays constant and
+                    the output starts early. Rules are only merged
+                    within each input file. This is not supported with
+                    commands, other formats, several outputs, and the
+                    options which need all rules, e.g. \"--sort\" and
+                    \"--root\".
+    --group-by-input
+                    Write an object which maps each input file to the
+                    rules parsed from it instead of a list. Like with
+                    \"--stream\", rules are only merged within each
+                    input file and the same options are supported.
+    --coalesce-identical
+                    Combine the targets with the same prerequisites in
+                    the same order into one rule with a list of
+                    \"targets\" in the JSON output, e.g. the objects of
+                    several architectures built from the same sources.
+    --dedupe-by-inode
+                    Replace the prerequisites which are hard links or
+                    symbolic links to the same file by its canonical
+                    path and write the merged paths as its \"aliases\".
+                    Files reached by a single path are kept as they are.
+                    Hard links are only recognized on unix platforms.
+    --print0        Print the paths of \"query targets\", \"query
+                    roots\", \"query prerequisites\", and \"query affected\"
+                    each followed by a NUL character instead of as
+                    JSON, e.g. for \"xargs -0\".
+    --all           Print all chains instead of only the shortest one
+                    with \"query path\".
+    --compile-db <file>
+                    Attach the command, directory, and output of the
+                    matching entry of the compilation database <file>
+                    to each dependency.
+    --with-metadata Write each prerequisite as an object with its \"path\",
+                    its \"size\" in bytes, and its modification time
+                    \"mtime\" in seconds since the epoch.
+    --with-packages Write each prerequisite as an object with its \"path\"
+                    and, if it is a system file, see \"--system-dir\",
+                    the \"package\" which owns it or null. The package
+                    is looked up with the first of dpkg, rpm, and
+                    pkg-config which is installed.
+    --package-manager <name>
+                    Like \"--with-packages\", but look up the packages
+                    with <name>, which is \"dpkg\", \"rpm\", or
+                    \"pkg-config\".
+    --with-fingerprint
+                    Write the JSON output as an object with the
+                    \"fingerprint\" of the dependencies and the
+                    \"dependencies\" themselves. The fingerprint is the
+                    SHA-256 digest of the sorted rules and prerequisites,
+                    so it only changes if the dependencies do.
+    --print-fingerprint
+                    Write only the fingerprint of the dependencies
+                    instead of the output.
+    --with-locations
+                    Write the \"locations\" of the rules of each target,
+                    i.e. the dependency \"file\", the \"line\", and the
+                    byte range from \"start\" to \"end\" of each rule.
+                    This requires Makefile input which is not parsed in
+                    chunks.
+    --infer-source  Write the \"source\" of each target, which is given
+                    by the rules of \"--source-rule\" or is the first
+                    prerequisite unless it is a header, or null.
+    --strip-source  Like \"--infer-source\", but also remove the source
+                    from the prerequisites.
+    --source-rule <target>=<source>
+                    Map the targets matching the pattern <target> to the
+                    source <source> with \"--infer-source\", e.g.
+                    \"build/%.o=src/%.c\". The '%' of <source> is
+                    replaced with the part matched by the '%' of
+                    <target>. A source which is a prerequisite of the
+                    target is preferred. This option may be specified
+                    multiple times.
+    --hash <name>   Write each prerequisite as an object with its \"path\"
+                    and the digest of its content. The only supported
+                    hash function is \"sha256\".
+    --classify      Write each prerequisite as an object with its \"path\"
+                    and its \"class\", which is either \"system\",
+                    \"framework\", or \"project\". Files within
+                    /usr/include, the compiler resource directories, the
+                    system framework directories, and the Xcode SDKs are
+                    system files. The headers of the frameworks among
+                    them, e.g. \"Foo.framework/Headers/foo.h\", are
+                    framework files.
+    --system-dir <dir>
+                    Classify the files within <dir> as system files.
+                    This option may be specified multiple times.
+    --drop-system, --no-system
+                    Remove the prerequisites which are system files.
+                    Besides the standard locations and the directories
+                    given with \"--system-dir\", the directories of the
+                    compiler reported by \"$CC -print-search-dirs\" are
+                    system directories.
+    --drop-frameworks
+                    Remove the prerequisites which are framework files,
+                    but keep the other system files.
+    --drop-project  Remove the prerequisites which are project files.
+    --only-ext <list>
+                    Keep only the prerequisites with one of the
+                    extensions in the comma-separated <list>, e.g.
+                    \"h,hpp,inc\". This option may be specified multiple
+                    times.
+    --drop-ext <list>
+                    Remove the prerequisites with one of the extensions
+                    in the comma-separated <list>.
+    --query <expr>  Keep only the pairs of target and prerequisite for
+                    which the expression <expr> is true. The fields
+                    \"target\" and \"prereq\" are compared to strings
+                    with \"==\", \"!=\", \"startswith\", \"endswith\",
+                    \"contains\", and the regular expression matches
+                    \"~\" and \"!~\". The field \"count\" is the number
+                    of prerequisites of the target and is compared to
+                    numbers with \"==\", \"!=\", \"<\", \"<=\", \">\",
+                    and \">=\". Comparisons are combined with \"&&\",
+                    \"||\", \"!\", and parentheses. Rules without a
+                    remaining prerequisite are removed.
+    --report <kind>=<file>
+                    Write a report of the \"lint\" or \"check\" command
+                    to <file>. The kind \"junit\" writes a JUnit XML
+                    report with a test case for each input file or deny
+                    rule, respectively. The kind \"sarif\" writes a SARIF
+                    log with the location of each finding, which is the
+                    rule of the target with \"--with-locations\" for the
+                    \"check\" command. This option may be specified
+                    multiple times.
+    --policy <file> Read the rules of the \"check\" command from <file>.
+                    Each line is either \"allow <target> -> <prereq>\"
+                    or \"deny <target> -> <prereq>\" with glob patterns
+                    where '*' and '?' do not match '/', \"**\" matches
+                    any path, and a trailing '/' matches the files within
+                    a directory. The last matching rule decides and
+                    dependencies without a matching rule are allowed.
+    --report-conflicts
+                    Instead of the dependencies, write the targets whose
+                    rules in different input files have different
+                    prerequisites with the \"inputs\" and the
+                    prerequisites which are missing in any of them as
+                    the \"difference\". The exit status is non-zero if
+                    there is a conflict.
+    --keep-going    Skip input files which cannot be read or parsed
+                    instead of exiting at the first one. The remaining
+                    files are converted, the failed files are listed at
+                    the end, and the exit status is 2 if any failed.
+    --kind          Write each prerequisite as an object with its \"path\"
+                    and its \"kind\", which is either \"header\",
+                    \"source\", \"generated\", or \"other\".
+    --tag-generated Write each prerequisite as an object with its \"path\"
+                    and whether it is \"generated\", i.e. whether it is
+                    the target of a rule with prerequisites as well.
+    --kind-rule <kind>=<pattern>
+                    Classify the files matching <pattern> as <kind>. A
+                    pattern ending with '/' matches the files within a
+                    directory of that name, any other pattern matches
+                    the end of a path. This option may be specified
+                    multiple times and takes precedence over the
+                    builtin rules.
+    --root <target> Add <target> to the root set used by the analyses.
+                    This option may be specified multiple times.
+    --roots-file <file>
+                    Add each line of <file> to the root set. Empty lines
+                    and lines starting with '#' are ignored.
+                    If a root set is specified, the output is restricted
+                    to the rules reachable from the root set.
+    --transitive    Additionally calculate the transitive fan-in and
+                    fan-out with \"stats metrics\" and explain indirect
+                    dependencies with \"why\".
+    --top <n>       Limit the output of \"stats hotspots\" and \"stats
+                    summary\" to the first <n> entries.
+    --table         Print \"stats hotspots\" as an aligned table instead
+                    of JSON.
+    --interval <ms> Check the input files for modifications every <ms>
+                    milliseconds with the \"watch\" command. The default
+                    is 500.
+    --events        Print the rules which were added, removed, or changed
+                    as a stream of JSON objects, one per line, with the
+                    \"watch\" command instead of writing the complete
+                    output again. The first event of each rule is
+                    \"rule-added\", later events are \"rule-changed\"
+                    and \"rule-removed\".
+    --max-prereqs-per-target <n>
+                    Warn about each target with more than <n>
+                    prerequisites.
+    --max-total-edges <n>
+                    Warn if there are more than <n> pairs of target and
+                    prerequisite in total.
+    --fail-on-budget
+                    Exit with an error instead of writing the output if
+                    \"--max-prereqs-per-target\" or
+                    \"--max-total-edges\" is exceeded.
+    --sandbox-root <dir>
+                    Exit with an error instead of writing the output if
+                    a prerequisite resolves to a path outside of <dir>,
+                    e.g. to keep a build hermetic. Relative paths are
+                    resolved against the working directory and the
+                    symbolic links of existing files are followed.
+                    Repeat the option to allow several directories.
+    --sandbox-warn  Warn about the prerequisites outside of
+                    \"--sandbox-root\" instead of exiting with an error.
+    --merge <name>  Remove duplicated prerequisites with the strategy
+                    <name>. The \"hash\" strategy (default) preserves
+                    the order of the prerequisites. The \"sort\" strategy
+                    sorts them and is faster for targets with many
+                    prerequisites.
+    --merge-policy <name>
+                    Handle a target with several rules according to the
+                    policy <name>. The policy \"union\" (default)
+                    combines the prerequisites of all rules, \"first\"
+                    and \"last\" keep only the first or the last rule,
+                    and \"error\" fails if the rules of a target have
+                    different prerequisites.
+    --sort          Sort the targets and the prerequisites of each target
+                    bytewise. By default, the order of the input is
+                    preserved.
+    --server        Answer newline-delimited JSON-RPC 2.0 requests read
+                    from stdin. Supported methods are \"parse\",
+                    \"rdeps\", \"diff\", and \"exit\". Parsed files
+                    are kept in memory between requests.
+    --daemon <socket>
+                    Answer the requests of \"--server\" on connections
+                    to the unix socket <socket>. Parsed files are kept
+                    in memory for all connections. The daemon stops
+                    and removes the socket on \"exit\". A socket left
+                    behind by a killed daemon is replaced. Only
+                    supported on unix platforms.
+    --cache-dir <dir>
+                    Store the parsed dependencies of each input file in
+                    <dir>. Files whose content did not change since the
+                    last run are not parsed again.
+    --max-memory <bytes>
+                    Parse the input in chunks or, if this is not
+                    possible, fail early if the estimated memory usage
+                    exceeds <bytes>. The suffixes 'K', 'M', and 'G' multiply
+                    <bytes> by powers of 1024.
+    --define <name>=<value>
+                    Replace the references \"$(<name>)\" and
+                    \"${{<name>}}\" in the targets and prerequisites with
+                    <value>. This option may be specified multiple times.
+    --expand-env    Replace the references \"$<name>\", \"$(<name>)\",
+                    and \"${{<name>}}\" in the targets and prerequisites
+                    with the value of the environment variable <name>.
+                    References to unset variables are kept.
+    --expand-tilde  Replace a leading '~' of the targets and
+                    prerequisites with the home directory.
+    --normalize-paths
+                    Resolve the \".\" and \"..\" segments of the targets
+                    and prerequisites and collapse repeated separators
+                    without accessing the file system, so different
+                    spellings of the same path are written the same way.
+    --bazel         Remap the paths of dependency files written by Bazel
+                    to paths relative to the workspace: the execroot
+                    and sandbox directories are removed, \"bazel-out/
+                    <config>/bin/\" is replaced by \"bazel-bin/\", and
+                    \"external/\" by \"bazel-<workspace>/external/\",
+                    where <workspace> is the name of the current
+                    directory.
+    --bazel-execroot <dir>
+                    Like \"--bazel\", but remove the execroot <dir>,
+                    e.g. if it is not in Bazel's output base.
+    --prefix-map-file <file>
+                    Replace the prefixes of paths with the mappings in
+                    <file>, which contains one \"<old>=<new>\" pair per
+                    line like \"-ffile-prefix-map\". The last matching
+                    mapping is applied.
+    --rewrite <rule>
+                    Rewrite the targets and prerequisites with <rule>,
+                    which is given as \"s/<regex>/<replacement>/[g]\"
+                    like the \"s\" command of sed with extended regular
+                    expressions. Rules may be specified multiple times
+                    and are applied in order.
+    --unicode-normalize <nfc|nfd>
+                    Convert all paths to the Unicode normalization form
+                    <form> before they are merged and written, i.e.
+                    \"nfc\" for composed or \"nfd\" for decomposed
+                    characters, e.g. for the paths written on macOS.
+    --output-separators <windows|posix>
+                    Write the targets and prerequisites with backslash
+                    or slash separators, e.g. for a consumer on another
+                    platform. Backslashes which escape a space, a tab,
+                    or '#' are kept. The conversion is applied after
+                    all other path options.
+    --as-uri        Write the targets and prerequisites as percent-
+                    encoded \"file://\" URIs. Relative paths are
+                    resolved against the current directory.
+    --strict        Reject input which is not a plain dependency file. By
+                    default, the recipe lines of a Makefile, i.e. lines
+                    starting with a tab, are skipped.
+                    Include directives are replaced with the content of
+                    the included files, except with \"--chunk-size\".
+                    Conditional directives are evaluated with the
+                    variables given with \"--define\". The branches of
+                    undecidable conditions are kept.
+    --require-utf8  Reject input which is not valid UTF-8 and print the
+                    location of the first invalid sequence.
+    --lossy-utf8    Replace invalid UTF-8 sequences in the input with
+                    U+FFFD without a warning. By default, a warning with
+                    the location of the first invalid sequence is
+                    printed.
+    --chunk-size <bytes>
+                    Parse the input in chunks of about <bytes> instead of
+                    reading all of it into memory at once. Only the
+                    distinct paths are kept in memory and the JSON
+                    output is written in pieces. Input exceeding the
+                    limit of \"--max-memory\" is parsed in chunks
+                    automatically. Include and conditional directives
+                    cannot be parsed in chunks.
     --              Intepret the remaining arguments as input files.
                     This is useful if a file name starts with a '-'.
+    -               Read stdin at this position of the input files. By
+                    default, stdin is read after all input files if it
+                    is not a terminal.
 Generic Options:
 
     --help, -h      Print this help message and exit.
     --version       Print version information and exit.
+    --version-json  Print the version, the git commit, and the supported
+                    input and output formats as JSON and exit.
 "
     );
 }
@@ -52,78 +638,2731 @@ fn version() {
     println!("dep2j {version}");
 }
 
-fn main() {
-    let argv = env::args();
-    let argc = argv.len();
-    let args = args::parse(argv);
+fn version_json() {
+    let string = |x: &str| Value::String(String::from(x));
+    let commit = option_env!("DEP2J_GIT_COMMIT").map_or(Value::Null, string);
 
-    let mut stdin = io::stdin();
-    let isatty = stdin.is_terminal();
+    let value = Value::Object(Vec::from([
+        (String::from("version"), string(env!("CARGO_PKG_VERSION"))),
+        (String::from("commit"), commit),
+        (
+            String::from("input_formats"),
+            Value::Array(InputFormat::names().map(string).collect()),
+        ),
+        (
+            String::from("output_formats"),
+            Value::Array(Format::names().map(string).collect()),
+        ),
+    ]));
 
-    if args.help || (isatty && argc < 2) {
-        help();
-        exit(0)
+    let mut serializer = JsonSerializer::new();
+    serializer.write_value(&value);
+
+    write_output("", "", false, serializer.get_json());
+}
+
+fn require_nodes<'a, I>(graph: &Graph, names: I)
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    for name in names {
+        if !graph.contains(name) {
+            error!(
+                "unknown-node",
+                "\"{name}\" is not part of the dependencies"
+            );
+            exit(1);
+        }
     }
+}
 
-    if args.version {
-        version();
-        exit(0)
+fn read_roots(path: &str, roots: &mut Vec<String>) {
+    let content = fs::read_to_string(path).unwrap_or_else(|err| {
+        error!("read-failed", "failed to read roots from \"{path}\": {err}");
+        exit(1);
+    });
+
+    let lines = content
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+    roots.extend(lines.map(String::from));
+}
+
+fn read_prefix_map(path: &str) -> PrefixMap {
+    let data = fs::read_to_string(path).unwrap_or_else(|err| {
+        error!(
+            "read-failed",
+            file = path,
+            "failed to read \"{path}\": {err}"
+        );
+        exit(1);
+    });
+
+    PrefixMap::parse(&data).unwrap_or_else(|err| {
+        error!(
+            "invalid-input",
+            file = path,
+            "failed to parse \"{path}\": {err}"
+        );
+        exit(1);
+    })
+}
+
+fn read_template(template: &str) -> Template {
+    if template.is_empty() {
+        error!(
+            "invalid-argument",
+            "the \"template\" format requires \"--template\""
+        );
+        exit(1);
     }
 
-    if isatty && args.input.is_empty() {
-        eprintln!("error: no input data available");
+    Template::parse(template).unwrap_or_else(|err| {
+        error!("invalid-input", "invalid template: {err}");
+        exit(1);
+    })
+}
+
+fn read_compile_db(path: &str) -> CompileDb {
+    let data = fs::read(path).unwrap_or_else(|err| {
+        error!(
+            "read-failed",
+            file = path,
+            "failed to read \"{path}\": {err}"
+        );
+        exit(1);
+    });
+
+    CompileDb::parse(&data).unwrap_or_else(|err| {
+        error!(
+            "invalid-input",
+            file = path,
+            "failed to parse \"{path}\": {err}"
+        );
+        exit(1);
+    })
+}
+
+/*
+ * Read the dependencies of an input which is not a dependency file. The
+ * attributes of tagged prerequisites are added to "tags".
+ */
+fn read_deps(
+    format: InputFormat,
+    path: &str,
+    data: &[u8],
+    tags: &mut HashMap<String, Attributes>,
+) -> Result<Vec<DependencyBuf>, String> {
+    let result = match format {
+        InputFormat::Tlog => tlog::parse_dependencies(data),
+        InputFormat::Iar => dialect::parse_dependencies(data, Dialect::Iar),
+        InputFormat::Ti => dialect::parse_dependencies(data, Dialect::Ti),
+        InputFormat::Arm => dialect::parse_dependencies(data, Dialect::Arm),
+        InputFormat::Swift => swift::parse_dependencies(path, data),
+        InputFormat::P1689 => p1689::parse_dependencies(data, tags),
+        InputFormat::Fls => fls::parse_dependencies(data),
+        InputFormat::Makedepend => {
+            dialect::parse_dependencies(data, Dialect::Makedepend)
+        }
+        _ => json::parse_dependencies(data),
+    };
+
+    result.map_err(|err| format!("failed to parse \"{path}\": {err}"))
+}
+
+/*
+ * Report that the input "path" cannot be read or parsed. Unless
+ * "--keep-going" is given, this is fatal.
+ */
+fn input_failed(
+    args: &Args,
+    log: &mut Logger,
+    code: &str,
+    path: &str,
+    msg: &str,
+) {
+    log.failure(code, path, msg);
+
+    if !args.keep_going {
         exit(1);
     }
+}
 
-    let mut data = Vec::with_capacity(4096 * args.input.len());
+/*
+ * Print the diagnostics of each input in the style of a compiler and
+ * exit with a non-zero status if any error was found.
+ */
+fn lint(args: &Args, inputs: &[(&str, Vec<u8>)]) -> ! {
+    let mut errors = false;
+    let mut cases = Vec::with_capacity(inputs.len());
+    let mut findings = Vec::new();
 
-    for path in &args.input {
-        let mut file = match File::open(path) {
-            Ok(val) => val,
-            Err(err) => {
-                eprintln!("error: failed to open \"{path}\": {err}");
-                exit(1);
+    for (path, data) in inputs {
+        let mut case = TestCase {
+            name: String::from(*path),
+            failures: Vec::new(),
+            output: Vec::new(),
+        };
+
+        let mut linter = Linter::new(data);
+        linter.set_strict(args.strict);
+
+        for diag in linter.lint() {
+            let (line, column) = (diag.line, diag.column);
+            let (severity, message) = (diag.severity, &diag.message);
+            let msg = format!("{path}:{line}:{column}: {severity}: {message}");
+
+            println!("{msg}");
+
+            findings.push(Finding {
+                rule: String::from("lint"),
+                severity,
+                message: message.clone(),
+                file: String::from(*path),
+                line,
+                column: Some(column),
+            });
+
+            if severity == Severity::Error {
+                case.failures.push(msg);
+            } else {
+                case.output.push(msg);
             }
+
+            errors |= severity == Severity::Error;
+        }
+
+        cases.push(case);
+    }
+
+    write_reports(&args.reports, "lint", &cases, &findings);
+
+    exit(errors as i32)
+}
+
+/*
+ * Write the results of the "lint" or "check" command as JUnit test "cases"
+ * or SARIF "findings", depending on the kind of each report.
+ */
+fn write_reports(
+    reports: &[(Report, String)],
+    suite: &str,
+    cases: &[TestCase],
+    findings: &[Finding],
+) {
+    for (report, path) in reports {
+        let data = match report {
+            Report::Junit => junit::report(suite, cases),
+            Report::Sarif => sarif::report(findings),
         };
 
-        if let Ok(attr) = file.metadata() {
-            data.reserve(attr.len() as usize);
+        write_output(path, "", false, &data);
+    }
+}
+
+/*
+ * Print the dependencies which violate the policy in "path" and exit with
+ * a non-zero status if there are any. Each rule which denies dependencies
+ * is a test case of the reports. A finding refers to the rule of the
+ * target if its location is known and to the policy otherwise.
+ */
+fn check(args: &Args, deps: &[Dependency], locations: &Locations) -> ! {
+    let path = &args.policy;
+
+    if path.is_empty() {
+        error!(
+            "invalid-argument",
+            "the \"check\" command requires \"--policy\""
+        );
+        exit(1);
+    }
+
+    let data = fs::read_to_string(path).unwrap_or_else(|err| {
+        error!(
+            "read-failed",
+            file = path,
+            "failed to read \"{path}\": {err}"
+        );
+        exit(1);
+    });
+
+    let policy = Policy::parse(&data).unwrap_or_else(|err| {
+        error!(
+            "invalid-input",
+            file = path,
+            "failed to parse \"{path}\": {err}"
+        );
+        exit(1);
+    });
+
+    let violations = policy.check(deps);
+
+    let mut cases: Vec<_> = policy
+        .deny_rules()
+        .map(|(line, rule)| TestCase {
+            name: format!("{path}:{line}: {rule}"),
+            failures: Vec::new(),
+            output: Vec::new(),
+        })
+        .collect();
+
+    let mut findings = Vec::with_capacity(violations.len());
+
+    for x in &violations {
+        let (target, prereq, line) = (x.target, x.prereq, x.line);
+        let msg = format!(
+            "{path}:{line}: error: \"{target}\" must not depend on \"{prereq}\""
+        );
+
+        println!("{msg}");
+
+        let (file, line) = locations
+            .get(target)
+            .map_or((path.as_str(), line), |x| (x.file.as_str(), x.line));
+
+        findings.push(Finding {
+            rule: x.rule.to_string(),
+            severity: Severity::Error,
+            message: format!("\"{target}\" must not depend on \"{prereq}\""),
+            file: String::from(file),
+            line,
+            column: None,
+        });
+
+        let name = format!("{path}:{line}: {}", x.rule);
+        if let Some(case) = cases.iter_mut().find(|x| x.name == name) {
+            case.failures.push(msg);
         }
+    }
+
+    write_reports(&args.reports, "check", &cases, &findings);
+
+    exit(!violations.is_empty() as i32)
+}
+
+/*
+ * Find the rules which make "target" depend on "prereq". Without a direct
+ * dependency, the rules of each dependency in the shortest chain between
+ * both are returned if "--transitive" is given.
+ */
+fn why_rules(
+    args: &Args,
+    deps: &[Dependency],
+    locations: &Locations,
+    target: &String,
+    prereq: &String,
+) -> Value {
+    let graph = Graph::new(deps);
+    require_nodes(&graph, [target, prereq]);
+
+    let direct = deps.iter().any(|dep| {
+        dep.target == target && dep.prerequisites.contains(&prereq.as_str())
+    });
+
+    let chain = if direct {
+        Vec::from([target.as_str(), prereq.as_str()])
+    } else if args.transitive {
+        graph.paths(target, prereq, false).pop().unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+
+    if chain.is_empty() {
+        let how = if args.transitive { "" } else { " directly" };
+
+        error!(
+            "unknown-edge",
+            "\"{target}\" does not depend on \"{prereq}\"{how}"
+        );
+        exit(1);
+    }
+
+    let edges = chain.windows(2).map(|edge| {
+        let rules = locations
+            .get_all(edge[0])
+            .iter()
+            .filter(|x| x.prerequisites.iter().any(|p| p == edge[1]))
+            .map(|x| {
+                Value::Object(Vec::from([
+                    (String::from("file"), Value::String(x.file.clone())),
+                    (String::from("line"), Value::Number(x.line as f64)),
+                    (String::from("rule"), Value::String(x.rule.clone())),
+                ]))
+            })
+            .collect();
+
+        Value::Object(Vec::from([
+            (String::from("target"), Value::String(edge[0].into())),
+            (String::from("prerequisite"), Value::String(edge[1].into())),
+            (String::from("rules"), Value::Array(rules)),
+        ]))
+    });
+
+    Value::Array(edges.collect())
+}
+
+/*
+ * The working set consists of the input data, the parsed dependencies, and
+ * the generated output. For typical dependency files, it stays below this
+ * multiple of the input size.
+ */
+const MEMORY_FACTOR: u64 = 5;
+
+fn check_memory(size: u64, limit: u64) {
+    let estimate = size.saturating_mul(MEMORY_FACTOR);
+
+    if estimate > limit {
+        error!(
+            "memory-limit",
+            "estimated memory usage of {estimate} bytes exceeds the \
+             limit of {limit} bytes set with \"--max-memory\""
+        );
+        exit(1);
+    }
+}
+
+/*
+ * Replace the include directives in the input read from "path", which
+ * starts at "start" within "buf", with the content of the included files.
+ */
+fn expand_includes(
+    includer: &mut Includer,
+    path: &str,
+    buf: &mut Vec<u8>,
+    start: usize,
+) -> Result<(), String> {
+    if !include::has_directives(&buf[start..]) {
+        return Ok(());
+    }
+
+    let data = buf.split_off(start);
+    let data = includer.expand(Path::new(path), data)?;
+
+    buf.extend_from_slice(&data);
+
+    Ok(())
+}
+
+/*
+ * Evaluate the conditional directives in the input read from "path", which
+ * starts at "start" within "buf".
+ */
+fn expand_conditionals(
+    vars: Option<&Variables>,
+    path: &str,
+    buf: &mut Vec<u8>,
+    start: usize,
+) -> Result<(), String> {
+    if !cond::has_directives(&buf[start..]) {
+        return Ok(());
+    }
+
+    let data = cond::preprocess(&buf[start..], vars)
+        .map_err(|err| format!("{path}: {err}"))?;
+
+    buf.truncate(start);
+    buf.extend_from_slice(&data);
+
+    Ok(())
+}
+
+/*
+ * Read the content of the file "path" or of stdin if "path" is "-".
+ */
+fn read_input(path: &str) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+
+    if path == "-" {
+        io::stdin().read_to_end(&mut data).map(|_| data)
+    } else if zstd::is_compressed(path) {
+        Decoder::open(path)
+            .and_then(|mut x| x.read_to_end(&mut data))
+            .map(|_| data)
+    } else {
+        fs::read(path)
+    }
+}
+
+fn read_file(path: &str) -> Vec<u8> {
+    read_input(path).unwrap_or_else(|err| {
+        error!(
+            "read-failed",
+            file = path,
+            "failed to read file \"{path}\": {err}"
+        );
+        exit(1);
+    })
+}
 
-        if let Err(err) = file.read_to_end(&mut data) {
-            eprintln!("error: failed to read file \"{path}\": {err}");
+/*
+ * Read the dependencies of a single file for the "diff" command.
+ */
+fn read_file_deps(args: &Args, path: &str) -> Vec<DependencyBuf> {
+    let mut data = read_file(path);
+
+    let result = if args.input_format != InputFormat::Make {
+        read_deps(args.input_format, path, &data, &mut HashMap::new()).map(Some)
+    } else {
+        expand_includes(&mut Includer::new(), path, &mut data, 0)
+            .and_then(|_| expand_conditionals(None, path, &mut data, 0))
+            .map(|_| None)
+    };
+
+    match result {
+        Ok(Some(deps)) => return deps,
+        Ok(None) => (),
+        Err(err) => {
+            error!("invalid-input", file = path, "{err}");
             exit(1);
         }
     }
 
-    if !isatty {
-        data.reserve(4096);
+    let mut parser = DependencyParser::new();
+    parser.set_strict(args.strict);
+
+    parser.parse(data).iter().map(Dependency::to_buf).collect()
+}
 
-        if let Err(err) = stdin.read_to_end(&mut data) {
-            eprintln!("error: failed to read stdin: {err}");
+/*
+ * The "graph" command only writes the formats which describe a graph and
+ * defaults to "cytoscape".
+ */
+fn graph_format(format: Format) -> Format {
+    match format {
+        Format::Json => Format::Cytoscape,
+        Format::Csv
+        | Format::Ninja
+        | Format::Template
+        | Format::TreeJson
+        | Format::Batches
+        | Format::Manifest => {
+            error!(
+                "invalid-argument",
+                "invalid format for the \"graph\" command"
+            );
             exit(1);
         }
+        _ => format,
     }
+}
 
-    let mut parser = DependencyParser::new();
-    let deps = parser.parse(data);
+/*
+ * Pair each output file with its format, which is inferred from the
+ * extension of the file or given by "format". Without an output file, the
+ * output is written to stdout.
+ */
+fn output_formats(args: &Args, format: Format) -> Vec<(&str, Format)> {
+    let mut outputs: Vec<_> = args
+        .outputs
+        .iter()
+        .map(|path| (path.as_str(), Format::from_extension(path)))
+        .map(|(path, ext)| (path, ext.unwrap_or(format)))
+        .collect();
+
+    if outputs.is_empty() || !args.pipe.is_empty() {
+        outputs.push(("", format));
+    }
+
+    if args.command == Command::Graph {
+        for (_, format) in &mut outputs {
+            *format = graph_format(*format);
+        }
+    }
+
+    outputs
+}
+
+/*
+ * Write the dependencies to each output in its format. The dependencies
+ * are serialized only once for all outputs of the same format.
+ */
+fn write_formats(
+    args: &Args,
+    outputs: &[(&str, Format)],
+    deps: &[Dependency],
+    tags: HashMap<String, Attributes>,
+    target_tags: HashMap<String, Attributes>,
+    classifier: &Classifier,
+    streaming: bool,
+) {
+    let mut manifest_tags = outputs
+        .iter()
+        .any(|(_, format)| *format == Format::Manifest)
+        .then(|| tags.clone());
+    let mut tags = Some((tags, target_tags));
+    let mut graph = None;
+    let mut cache: HashMap<Format, Vec<u8>> = HashMap::new();
+    let mut serializer = None;
+    let mut envelope = None;
+
+    for &(path, format) in outputs {
+        if format == Format::Json {
+            let serializer = serializer.get_or_insert_with(|| {
+                let (tags, target_tags) = tags.take().unwrap();
+
+                json_serializer(args, deps, tags, target_tags, classifier)
+            });
+
+            if streaming && !args.coalesce_identical {
+                let compress = !args.compress.is_empty();
+
+                stream_output(path, &args.pipe, compress, serializer, deps);
+                continue;
+            }
+
+            if serializer.get_json().is_empty() {
+                match args.coalesce_identical {
+                    true => serializer.write_coalesced(deps),
+                    false => serializer.write_vec(deps),
+                }
+            }
+
+            let compress = !args.compress.is_empty();
+
+            if args.with_fingerprint {
+                let data = envelope.get_or_insert_with(|| {
+                    let hex = fingerprint::compute(deps);
+                    let mut data = Vec::from(format!(
+                        "{{\"fingerprint\":\"{hex}\",\"dependencies\":"
+                    ));
+
+                    data.extend_from_slice(serializer.get_json());
+                    data.push(b'}');
+                    data
+                });
+
+                write_output(path, &args.pipe, compress, data);
+                continue;
+            }
+
+            write_output(path, &args.pipe, compress, serializer.get_json());
+            continue;
+        }
+
+        let data = cache.entry(format).or_insert_with(|| {
+            let graph = graph.get_or_insert_with(|| Graph::new(deps));
+
+            match format {
+                Format::Csv => {
+                    let mut serializer = CsvSerializer::new();
+                    serializer.write_vec(deps);
+                    Vec::from(serializer.get_csv())
+                }
+                Format::Cytoscape => {
+                    let mut serializer = JsonSerializer::new();
+                    serializer.write_cytoscape(graph);
+                    Vec::from(serializer.get_json())
+                }
+                Format::D3 => {
+                    let mut serializer = JsonSerializer::new();
+                    serializer.write_d3(graph);
+                    Vec::from(serializer.get_json())
+                }
+                Format::Dot => {
+                    let mut serializer = DotSerializer::new();
+                    serializer.write_vec(deps);
+                    Vec::from(serializer.get_dot())
+                }
+                Format::Html => {
+                    let mut serializer = HtmlSerializer::new();
+                    serializer.write_graph(graph);
+                    Vec::from(serializer.get_html())
+                }
+                Format::Ninja => {
+                    let mut serializer = NinjaSerializer::new();
+                    serializer.write_vec(deps);
+                    Vec::from(serializer.get_text())
+                }
+                Format::PlantUml => {
+                    let mut serializer = PlantUmlSerializer::new();
+                    serializer.write_graph(graph);
+                    Vec::from(serializer.get_plantuml())
+                }
+                Format::Template => read_template(&args.template).render(deps),
+                Format::TreeJson => {
+                    let mut serializer = JsonSerializer::new();
+                    serializer.write_tree(deps);
+                    Vec::from(serializer.get_json())
+                }
+                Format::Batches => {
+                    let batches = graph.batches().unwrap_or_else(|cycle| {
+                        let list = cycle.join("\", \"");
+
+                        error!(
+                            "invalid-input",
+                            "cannot order the targets of a cycle: \"{list}\""
+                        );
+                        exit(1);
+                    });
+
+                    let mut serializer = JsonSerializer::new();
+                    serializer.write_paths(&batches);
+                    Vec::from(serializer.get_json())
+                }
+                Format::Manifest => {
+                    let tags = manifest_tags.take().unwrap_or_default();
+                    let mut serializer = JsonSerializer::new();
+
+                    add_prerequisite_attributes(
+                        args,
+                        deps,
+                        tags,
+                        classifier,
+                        &mut serializer,
+                    );
+                    serializer.write_manifest(deps);
+                    Vec::from(serializer.get_json())
+                }
+                Format::Json => unreachable!(),
+            }
+        });
+
+        write_output(path, &args.pipe, !args.compress.is_empty(), data);
+    }
+}
 
+/*
+ * Create the serializer for the "json" format with the attributes of the
+ * targets and prerequisites requested by the options.
+ */
+fn json_serializer(
+    args: &Args,
+    deps: &[Dependency],
+    tags: HashMap<String, Attributes>,
+    target_tags: HashMap<String, Attributes>,
+    classifier: &Classifier,
+) -> JsonSerializer {
     let mut serializer = JsonSerializer::new();
-    serializer.write_vec(deps);
 
-    let json = serializer.get_json();
+    if !args.compile_db.is_empty() {
+        let db = read_compile_db(&args.compile_db);
+        serializer.add_attributes(db.attributes(deps));
+    }
 
-    if args.output.is_empty() {
-        println!("{}", unsafe{str::from_utf8_unchecked(json)});
-        return;
+    if !target_tags.is_empty() {
+        serializer.add_target_attributes(target_tags);
     }
 
-    File::create(&args.output)
-        .and_then(|mut file| {
-            file.write_all(json)
-        })
-        .unwrap_or_else(|err| {
-            eprintln!("error: failed to write to \"{}\": {err}", args.output);
+    add_prerequisite_attributes(args, deps, tags, classifier, &mut serializer);
+
+    serializer
+}
+
+/*
+ * Write the JSON output of each target to the file named by "template".
+ * All names are checked before any file is written, so two targets with
+ * the same name do not overwrite each other.
+ */
+fn write_per_target(
+    args: &Args,
+    template: &NameTemplate,
+    serializer: &mut JsonSerializer,
+    deps: &[Dependency],
+) {
+    let mut targets: HashMap<String, &str> = HashMap::new();
+    let mut names = Vec::with_capacity(deps.len());
+
+    for dep in deps {
+        let name = template.expand(dep.target);
+
+        if let Some(other) = targets.insert(name.clone(), dep.target) {
+            let target = dep.target;
+
+            error!(
+                "invalid-argument",
+                "targets \"{other}\" and \"{target}\" are both written to \
+                 \"{name}\""
+            );
+            exit(1);
+        }
+
+        if !args.force {
+            check_overwrite(&name);
+        }
+
+        names.push(name);
+    }
+
+    let compress = !args.compress.is_empty();
+
+    serializer.write_each(deps, |i, data| {
+        let path = &names[i];
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+
+        if let Err(err) = fs::create_dir_all(dir) {
+            let dir = dir.display();
+
+            error!(
+                "write-failed",
+                file = path,
+                "failed to create directory \"{dir}\": {err}"
+            );
+            exit(1);
+        }
+
+        write_output(path, "", compress, data);
+    });
+}
+
+/*
+ * Add the attributes of the prerequisites requested by the options, which
+ * are shared by the "json" and the "manifest" format.
+ */
+fn add_prerequisite_attributes(
+    args: &Args,
+    deps: &[Dependency],
+    tags: HashMap<String, Attributes>,
+    classifier: &Classifier,
+    serializer: &mut JsonSerializer,
+) {
+    if !tags.is_empty() {
+        serializer.add_prerequisite_attributes(tags);
+    }
+
+    if args.with_metadata {
+        serializer.add_prerequisite_attributes(metadata::attributes(deps));
+    }
+
+    if args.classify {
+        serializer.add_prerequisite_attributes(classifier.attributes(deps));
+    }
+
+    if args.kind {
+        let rules = KindRules::new(&args.kind_rules).unwrap_or_else(|err| {
+            error!("invalid-argument", "{err}");
+            exit(1);
+        });
+
+        serializer.add_prerequisite_attributes(rules.attributes(deps));
+    }
+
+    if args.tag_generated {
+        serializer.add_prerequisite_attributes(kind::generated(deps));
+    }
+
+    if !args.hash.is_empty() {
+        serializer.add_prerequisite_attributes(metadata::digests(deps));
+    }
+
+    if args.with_packages {
+        let backend = args.package_manager.or_else(Backend::detect);
+        let backend = backend.unwrap_or_else(|| {
+            error!(
+                "invalid-argument",
+                "\"--with-packages\" found no package manager, use \
+                 \"--package-manager\""
+            );
             exit(1);
         });
+
+        let mut resolver = PackageResolver::new(backend);
+        let attributes = resolver.attributes(deps, classifier);
+
+        serializer.add_prerequisite_attributes(attributes.unwrap_or_else(
+            |err| {
+                error!("package-failed", "{err}");
+                exit(1);
+            },
+        ));
+    }
+}
+
+/*
+ * Convert the input files again whenever one of them is modified. Each
+ * conversion runs the "convert" command in a new process, so errors in the
+ * input files are reported without ending the watch.
+ */
+fn watch(args: &Args, argv: &[String]) -> ! {
+    if args.input.is_empty() {
+        error!("invalid-argument", "no input files to watch");
+        exit(1);
+    }
+
+    let program = env::current_exe().unwrap_or_else(|err| {
+        error!("watch-failed", "failed to locate the executable: {err}");
+        exit(1);
+    });
+
+    let interval = Duration::from_millis(args.interval);
+    let mut last = Vec::new();
+    let mut deps = Vec::new();
+
+    loop {
+        let mtimes: Vec<Option<SystemTime>> = args
+            .input
+            .iter()
+            .map(|path| fs::metadata(path).and_then(|x| x.modified()).ok())
+            .collect();
+
+        if mtimes != last && args.events {
+            deps = watch_events(&program, argv, deps);
+            last = mtimes;
+        } else if mtimes != last {
+            let status = process::Command::new(&program)
+                .arg("convert")
+                .args(argv)
+                .stdin(Stdio::null())
+                .status();
+
+            match status {
+                Ok(status) if !status.success() => {
+                    warning!(
+                        "watch-failed",
+                        "failed to convert the input files"
+                    );
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    error!("watch-failed", "failed to run \"convert\": {err}");
+                    exit(1);
+                }
+            }
+
+            last = mtimes;
+        }
+
+        thread::sleep(interval);
+    }
+}
+
+/*
+ * Convert the input files and print the differences to the previous
+ * dependencies "old" as events. The first conversion reports all rules as
+ * added. The previous dependencies are kept if the conversion fails.
+ */
+fn watch_events(
+    program: &Path,
+    argv: &[String],
+    old: Vec<DependencyBuf>,
+) -> Vec<DependencyBuf> {
+    let output = process::Command::new(program)
+        .arg("convert")
+        .args(argv)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .unwrap_or_else(|err| {
+            error!("watch-failed", "failed to run \"convert\": {err}");
+            exit(1);
+        });
+
+    if !output.status.success() {
+        warning!("watch-failed", "failed to convert the input files");
+        return old;
+    }
+
+    let new = match json::parse_dependencies(&output.stdout) {
+        Ok(val) => val,
+        Err(err) => {
+            warning!(
+                "watch-failed",
+                "failed to parse the converted files: {err}"
+            );
+            return old;
+        }
+    };
+
+    let mut serializer = JsonSerializer::new();
+    serializer.write_events(&diff::diff(
+        &old.iter().map(|x| x.as_dependency()).collect::<Vec<_>>(),
+        &new.iter().map(|x| x.as_dependency()).collect::<Vec<_>>(),
+    ));
+
+    let mut out = io::stdout().lock();
+
+    if let Err(err) = out.write_all(serializer.get_json()).and(out.flush()) {
+        error!("write-failed", "failed to write the events: {err}");
+        exit(1);
+    }
+
+    new
+}
+
+/*
+ * Report the first invalid UTF-8 sequence of an input according to the
+ * policy. The parser replaces invalid sequences in any case.
+ */
+fn check_utf8(
+    path: &str,
+    checker: &Utf8Checker,
+    policy: Utf8Policy,
+    log: &mut Logger,
+) {
+    if let Some((line, column)) = checker.finish() {
+        let msg = format!(
+            "\"{path}\" is not valid UTF-8 at line {line}, column {column}"
+        );
+
+        match policy {
+            Utf8Policy::Warn => {
+                let msg = format!("{msg}, invalid sequences are replaced");
+
+                log.warning("invalid-utf8", Some((path, line)), &msg);
+            }
+            Utf8Policy::Require => {
+                error!("invalid-utf8", file = path, line = line, "{msg}");
+                exit(1);
+            }
+            Utf8Policy::Lossy => {}
+        }
+    }
+}
+
+fn open_log(args: &Args) -> Logger {
+    let mut log = Logger::new(args.quiet);
+
+    if !args.log_file.is_empty() {
+        if let Err(err) = log.open(&args.log_file) {
+            let path = &args.log_file;
+
+            error!(
+                "write-failed",
+                file = path,
+                "failed to create log \"{path}\": {err}"
+            );
+            exit(1);
+        }
+    }
+
+    log
+}
+
+/*
+ * Write the rules of each input as soon as it is parsed instead of
+ * collecting the rules of all inputs first. The rules are only merged
+ * within each input, so the memory use does not grow with the number of
+ * inputs and the output starts with the first input. With
+ * "--group-by-input", the rules are written as an object with a member
+ * for each input.
+ */
+fn stream(args: &Args, implicit_stdin: bool) -> ! {
+    let mut inputs: Vec<&str> = args.input.iter().map(String::as_str).collect();
+
+    if implicit_stdin {
+        inputs.push("-");
+    }
+
+    /* Each input is a key of the object and must not appear twice */
+    if args.group_by_input {
+        let mut seen = HashSet::new();
+        inputs.retain(|x| seen.insert(*x));
+    }
+
+    let mut log = open_log(args);
+    let mut failed = Vec::new();
+    let mut totals = (0, 0, 0);
+
+    let path = args.outputs.first().map_or("", String::as_str);
+    let mut write = |out: &mut dyn Write| {
+        stream_inputs(args, &inputs, &mut log, &mut failed, &mut totals, out)
+    };
+
+    let result = if !args.compress.is_empty() {
+        compress_output(path, |out| write(out));
+        Ok(())
+    } else if path.is_empty() && !args.pipe.is_empty() {
+        pipe_output(&args.pipe, |out| write(out));
+        Ok(())
+    } else if is_stdout(path) {
+        write_stdout(true, |out| write(out))
+    } else {
+        File::create(path).and_then(|mut file| write(&mut file))
+    };
+
+    if let Err(err) = result {
+        let path = if is_stdout(path) { "stdout" } else { path };
+
+        error!(
+            "write-failed",
+            file = path,
+            "failed to write to \"{path}\": {err}"
+        );
+        exit(1);
+    }
+
+    let (bytes, targets, prereqs) = totals;
+    log.summary(inputs.len(), bytes, targets, prereqs);
+
+    if failed.is_empty() {
+        exit(0);
+    }
+
+    error!(
+        "read-failed",
+        "{} of {} input files failed: {}",
+        failed.len(),
+        inputs.len(),
+        failed.join(", ")
+    );
+    exit(2);
+}
+
+/*
+ * Parse the inputs one by one for "stream()" and write their rules to
+ * "out". The failed inputs are added to "failed" and the number of bytes,
+ * targets, and prerequisites to "totals".
+ */
+fn stream_inputs<'a>(
+    args: &Args,
+    inputs: &[&'a str],
+    log: &mut Logger,
+    failed: &mut Vec<&'a str>,
+    totals: &mut (u64, usize, usize),
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let make_input = args.input_format == InputFormat::Make;
+    let group = args.group_by_input;
+    let mut parser = DependencyParser::new();
+    let mut includer = Includer::new();
+    let mut first = true;
+
+    parser.set_merge_strategy(args.merge);
+    parser.set_strict(args.strict);
+    parser.set_keep_going(args.keep_going);
+
+    out.write_all(if group { b"{" } else { b"[" })?;
+
+    for &path in inputs {
+        let begin = Instant::now();
+        let name = if path == "-" { "stdin" } else { path };
+
+        let mut data = match read_input(path) {
+            Ok(val) => val,
+            Err(err) => {
+                let msg = format!("failed to read file \"{name}\": {err}");
+
+                input_failed(args, log, "read-failed", name, &msg);
+                failed.push(name);
+                continue;
+            }
+        };
+
+        /* Empty stdin which was not given explicitly is no input */
+        if group && data.is_empty() && !args.input.iter().any(|x| x == path) {
+            continue;
+        }
+
+        let len = data.len() as u64;
+        let mut tags = HashMap::new();
+        let bufs;
+        let merged;
+
+        let deps: &[Dependency] = if make_input {
+            let mut checker = Utf8Checker::new();
+            checker.update(&data);
+            check_utf8(name, &checker, args.utf8, log);
+
+            let result = expand_includes(&mut includer, name, &mut data, 0)
+                .and_then(|_| expand_conditionals(None, name, &mut data, 0));
+
+            if let Err(err) = result {
+                input_failed(args, log, "invalid-input", name, &err);
+                failed.push(name);
+                continue;
+            }
+
+            parser.reset();
+
+            if let Err(err) = parser.feed(data) {
+                let msg = format!("failed to parse \"{name}\": {err}");
+
+                input_failed(args, log, "invalid-input", name, &msg);
+                failed.push(name);
+                continue;
+            }
+
+            parser.finish()
+        } else {
+            bufs = match read_deps(args.input_format, name, &data, &mut tags) {
+                Ok(val) => val,
+                Err(err) => {
+                    input_failed(args, log, "invalid-input", name, &err);
+                    failed.push(name);
+                    continue;
+                }
+            };
+
+            merged = args
+                .merge
+                .merge(bufs.iter().map(|x| x.as_dependency()).collect());
+            &merged
+        };
+
+        let mut serializer = JsonSerializer::new();
+
+        if !tags.is_empty() {
+            serializer.add_prerequisite_attributes(tags);
+        }
+
+        if group {
+            serializer.stream_key(name, first, out)?;
+            out.write_all(b"[")?;
+            serializer.stream_elements(deps, true, out)?;
+            out.write_all(b"]")?;
+            first = false;
+        } else {
+            serializer.stream_elements(deps, first, out)?;
+            first &= deps.is_empty();
+        }
+
+        totals.0 += len;
+        totals.1 += deps.len();
+        totals.2 += deps.iter().map(|x| x.prerequisites.len()).sum::<usize>();
+
+        log.input(name, len, begin.elapsed());
+    }
+
+    out.write_all(if group { b"}" } else { b"]" })
+}
+
+/*
+ * Write the dependencies as JSON without keeping all of the generated
+ * output in memory.
+ */
+fn stream_output(
+    path: &str,
+    pipe: &str,
+    compress: bool,
+    serializer: &mut JsonSerializer,
+    deps: &[Dependency],
+) {
+    let result = if compress {
+        compress_output(path, |out| serializer.stream_vec(deps, out));
+        return;
+    } else if path.is_empty() && !pipe.is_empty() {
+        pipe_output(pipe, |out| serializer.stream_vec(deps, out));
+        return;
+    } else if is_stdout(path) {
+        write_stdout(true, |out| serializer.stream_vec(deps, out))
+    } else {
+        File::create(path)
+            .and_then(|mut file| serializer.stream_vec(deps, &mut file))
+    };
+
+    if let Err(err) = result {
+        let path = if is_stdout(path) { "stdout" } else { path };
+
+        error!(
+            "write-failed",
+            file = path,
+            "failed to write to \"{path}\": {err}"
+        );
+        exit(1);
+    }
+}
+
+/*
+ * Refuse to overwrite an existing file which was not written by dep2j,
+ * e.g. a dependency file given to "-o" by mistake.
+ */
+fn check_overwrite(path: &str) {
+    if is_stdout(path) {
+        return;
+    }
+
+    let data = match fs::read(path) {
+        Ok(val) => val,
+        Err(_) => return,
+    };
+
+    let data = if zstd::is_compressed(path) {
+        let mut content = Vec::new();
+
+        /* A file which cannot be decompressed is no output of dep2j */
+        let _ =
+            Decoder::open(path).and_then(|mut x| x.read_to_end(&mut content));
+        content
+    } else {
+        data
+    };
+
+    if !output::is_generated(&data) {
+        let msg = "not written by dep2j, use \"--force\" to overwrite it";
+
+        error!(
+            "write-failed",
+            file = path,
+            "refusing to overwrite \"{path}\": {msg}"
+        );
+        exit(1);
+    }
+}
+
+/*
+ * Write the output to the stdin of the shell command "pipe" and exit with
+ * the status of the command if it fails.
+ */
+fn pipe_output<F>(pipe: &str, write: F)
+where
+    F: FnOnce(&mut ChildStdin) -> io::Result<()>,
+{
+    let status = pipe::run(pipe, write).unwrap_or_else(|err| {
+        error!(
+            "write-failed",
+            "failed to pipe the output to \"{pipe}\": {err}"
+        );
+        exit(1);
+    });
+
+    if !status.success() {
+        error!("write-failed", "\"{pipe}\" failed: {status}");
+        exit(status.code().unwrap_or(1));
+    }
+}
+
+/*
+ * Compress the output with zstd into the file "path" or to stdout and exit
+ * if this fails.
+ */
+fn compress_output<F>(path: &str, write: F)
+where
+    F: FnOnce(&mut ChildStdin) -> io::Result<()>,
+{
+    let (path, name) = match is_stdout(path) {
+        true => ("", "stdout"),
+        false => (path, path),
+    };
+
+    let status = zstd::compress(path, write).unwrap_or_else(|err| {
+        error!(
+            "write-failed",
+            "failed to compress the output to \"{name}\": {err}"
+        );
+        exit(1);
+    });
+
+    if !status.success() {
+        error!(
+            "write-failed",
+            "failed to compress the output to \"{name}\": {status}"
+        );
+        exit(status.code().unwrap_or(1));
+    }
+}
+
+/*
+ * The output is written to stdout if no file is given or if the file is
+ * "-". Unlike no file, "-" is not replaced by the command of "--pipe".
+ */
+fn is_stdout(path: &str) -> bool {
+    path.is_empty() || path == "-"
+}
+
+/*
+ * Write the output with "write" and a final newline if "newline" is set to
+ * stdout. The output is buffered and written as is, so it does not have to
+ * be valid UTF-8.
+ */
+fn write_stdout<F>(newline: bool, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut BufWriter<StdoutLock>) -> io::Result<()>,
+{
+    let mut out = BufWriter::new(io::stdout().lock());
+
+    write(&mut out)?;
+
+    if newline {
+        out.write_all(b"\n")?;
+    }
+
+    out.flush()
+}
+
+fn write_output(path: &str, pipe: &str, compress: bool, data: &[u8]) {
+    let begin = Instant::now();
+
+    do_write_output(path, pipe, compress, data);
+    timings::record(Phase::Write, None, data.len() as u64, begin.elapsed());
+}
+
+fn do_write_output(path: &str, pipe: &str, compress: bool, data: &[u8]) {
+    if compress {
+        compress_output(path, |out| out.write_all(data));
+        return;
+    }
+
+    if path.is_empty() && !pipe.is_empty() {
+        pipe_output(pipe, |out| out.write_all(data));
+        return;
+    }
+
+    let newline = output::needs_newline(data);
+
+    let result = if is_stdout(path) {
+        write_stdout(newline, |out| out.write_all(data))
+    } else {
+        File::create(path).and_then(|mut file| file.write_all(data))
+    };
+
+    if let Err(err) = result {
+        let path = if is_stdout(path) { "stdout" } else { path };
+
+        error!(
+            "write-failed",
+            file = path,
+            "failed to write to \"{path}\": {err}"
+        );
+        exit(1);
+    }
+}
+
+/*
+ * Print the timings and the memory usage of the conversion, if requested,
+ * before exiting with "status". The serialization of the outputs started
+ * at "output".
+ */
+fn finish(status: i32, output: Instant, bytes: u64) -> ! {
+    timings::record_output(output);
+    timings::print(bytes);
+    memory::print();
+    exit(status);
+}
+
+/*
+ * The rules of the input after all transformations, which are written by
+ * the command. The serialization of the outputs started at "output".
+ */
+struct Conversion<'a> {
+    args: &'a Args,
+    deps: &'a [Dependency<'a>],
+    tags: HashMap<String, Attributes>,
+    locations: Locations,
+    sources: Option<HashMap<&'a str, String>>,
+    name_template: Option<NameTemplate>,
+    classifier: Classifier,
+    streaming: bool,
+    outputs: Vec<(&'a str, Format)>,
+    status: i32,
+    output: Instant,
+    size: u64,
+}
+
+impl Conversion<'_> {
+    /*
+     * Write "data" to each output and exit.
+     */
+    fn write(&self, data: &[u8]) -> ! {
+        let compress = !self.args.compress.is_empty();
+
+        for (path, _) in &self.outputs {
+            write_output(path, &self.args.pipe, compress, data);
+        }
+
+        finish(self.status, self.output, self.size);
+    }
+}
+
+/*
+ * Write the differences between the rules of the files "old" and "new".
+ */
+fn diff(args: &Args, old: &str, new: &str) -> ! {
+    let old = read_file_deps(args, old);
+    let new = read_file_deps(args, new);
+
+    let old =
+        dependency::merge(old.iter().map(|x| x.as_dependency()).collect());
+    let new =
+        dependency::merge(new.iter().map(|x| x.as_dependency()).collect());
+
+    let mut serializer = JsonSerializer::new();
+    serializer.write_diff(&diff::diff(&old, &new));
+
+    for (path, _) in output_formats(args, Format::Json) {
+        write_output(
+            path,
+            &args.pipe,
+            !args.compress.is_empty(),
+            serializer.get_json(),
+        );
+    }
+
+    exit(0)
+}
+
+/*
+ * Write the answer to "query" about the graph of the rules.
+ */
+fn query(conversion: &Conversion, query: &Query) -> ! {
+    let Conversion { args, deps, .. } = *conversion;
+    let graph = Graph::new(deps);
+    let mut serializer = JsonSerializer::new();
+
+    let list = match query {
+        Query::Path(from, to) => {
+            require_nodes(&graph, [from, to]);
+            serializer.write_paths(&graph.paths(from, to, args.all));
+            Vec::new()
+        }
+        Query::Affected(rev) => {
+            let files = git::changed_since(rev).unwrap_or_else(|err| {
+                error!("git-failed", "{err}");
+                exit(1);
+            });
+
+            graph.affected(&files)
+        }
+        Query::Orphans => {
+            require_nodes(&graph, &args.roots);
+            serializer.write_orphans(&graph.orphans(&args.roots));
+            Vec::new()
+        }
+        Query::Prerequisites => {
+            let mut seen = HashSet::new();
+
+            deps.iter()
+                .flat_map(|dep| &dep.prerequisites)
+                .copied()
+                .filter(|x| seen.insert(*x))
+                .collect()
+        }
+        Query::Roots => graph.roots(),
+        Query::Targets => deps.iter().map(|dep| dep.target).collect(),
+    };
+
+    if args.print0 {
+        conversion.write(&output::records(&list));
+    }
+
+    if query.is_list() {
+        serializer.write_str_list(&list);
+    }
+
+    conversion.write(serializer.get_json());
+}
+
+/*
+ * Write why "target" depends on "prereq".
+ */
+fn why(conversion: &Conversion, target: &String, prereq: &String) -> ! {
+    let Conversion { args, deps, .. } = *conversion;
+    let locations = &conversion.locations;
+
+    let mut serializer = JsonSerializer::new();
+    serializer.write_value(&why_rules(args, deps, locations, target, prereq));
+
+    conversion.write(serializer.get_json());
+}
+
+/*
+ * Write the statistics "stats" about the graph of the rules.
+ */
+fn stats(conversion: &Conversion, stats: Stats) -> ! {
+    let Conversion { args, deps, .. } = *conversion;
+    let graph = Graph::new(deps);
+    let mut serializer = JsonSerializer::new();
+
+    match stats {
+        Stats::Components => serializer.write_components(&graph.components()),
+        Stats::CriticalPath => {
+            serializer.write_critical_path(&graph.critical_path())
+        }
+        Stats::Hotspots => {
+            let mut hotspots = graph.hotspots();
+
+            hotspots.truncate(args.top);
+
+            if args.table {
+                let table = summary::render_hotspots(&hotspots);
+                conversion.write(table.as_bytes());
+            }
+
+            serializer.write_hotspots(&hotspots);
+        }
+        Stats::Metrics => {
+            serializer.write_metrics(&graph.metrics(args.transitive))
+        }
+        Stats::Summary => {
+            let top = if args.top == usize::MAX { 10 } else { args.top };
+            let table = summary::render(deps, top);
+
+            conversion.write(table.as_bytes());
+        }
+    }
+
+    conversion.write(serializer.get_json());
+}
+
+/*
+ * Write the rules in the output formats, or a file for each target with
+ * "--name-template".
+ */
+fn convert(conversion: Conversion) -> ! {
+    let Conversion {
+        args,
+        deps,
+        tags,
+        locations,
+        sources,
+        name_template,
+        classifier,
+        streaming,
+        outputs,
+        status,
+        output,
+        size,
+    } = conversion;
+
+    let mut target_tags = HashMap::new();
+
+    if args.with_locations {
+        target_tags = locations.attributes();
+    }
+
+    if let Some(sources) = &sources {
+        for (target, attrs) in source::attributes(deps, sources) {
+            target_tags
+                .entry(target)
+                .or_insert_with(Vec::new)
+                .extend(attrs);
+        }
+    }
+
+    if let Some(template) = &name_template {
+        let mut serializer =
+            json_serializer(args, deps, tags, target_tags, &classifier);
+
+        write_per_target(args, template, &mut serializer, deps);
+        finish(status, output, size);
+    }
+
+    write_formats(
+        args,
+        &outputs,
+        deps,
+        tags,
+        target_tags,
+        &classifier,
+        streaming,
+    );
+    finish(status, output, size);
+}
+
+/*
+ * The rules read from the input. The rules of Makefile input are
+ * accumulated in a single parser, unless the input is parsed in chunks.
+ * With "per_file", each input is parsed on its own and its rules are kept
+ * in "bufs".
+ */
+struct Rules {
+    parser: DependencyParser<'static>,
+    chunker: Option<ChunkParser>,
+    bufs: Vec<DependencyBuf>,
+    per_file: bool,
+}
+
+impl Rules {
+    /*
+     * Merge the rules of all inputs. The rules of the parser are borrowed
+     * if they do not have to be merged with other rules.
+     */
+    fn finish(&mut self, args: &Args) -> Cow<'_, [Dependency<'_>]> {
+        let mut deps = Cow::Borrowed(self.parser.finish().as_slice());
+
+        if let Some(chunker) = &mut self.chunker {
+            deps = Cow::Owned(chunker.finish());
+        }
+
+        if self.per_file {
+            let mut vec: Vec<_> =
+                self.bufs.iter().map(|x| x.as_dependency()).collect();
+            vec.extend_from_slice(&deps);
+
+            let vec = args.merge_policy.apply(vec).unwrap_or_else(|err| {
+                error!("invalid-input", "{err}");
+                exit(1);
+            });
+
+            deps = Cow::Owned(args.merge.merge(vec));
+        }
+
+        deps
+    }
+}
+
+/*
+ * Read the input files and stdin, see "read_inputs()".
+ */
+struct Input<'a> {
+    args: &'a Args,
+    vars: Option<&'a Variables>,
+    cache: Option<Cache>,
+    make_input: bool,
+    with_locations: bool,
+    size: u64,
+    budget: u64,
+    data: Vec<u8>,
+    rules: Rules,
+    tags: HashMap<String, Attributes>,
+    includer: Includer,
+    locations: Locations,
+    conflicts: Option<ConflictFinder>,
+    failed: Vec<&'a str>,
+    log: Logger,
+}
+
+impl<'a> Input<'a> {
+    fn new(args: &'a Args, vars: Option<&'a Variables>) -> Self {
+        let cache = (!args.cache_dir.is_empty()).then(|| {
+            let mut cache = Cache::new(&args.cache_dir).unwrap_or_else(|err| {
+                let dir = &args.cache_dir;
+
+                error!(
+                    "write-failed",
+                    "failed to create cache \"{dir}\": {err}"
+                );
+                exit(1);
+            });
+
+            cache.set_strict(args.strict);
+            cache.set_keep_going(args.keep_going);
+            cache
+        });
+
+        let make_input = args.input_format == InputFormat::Make;
+        /*
+         * Conflicts are found by comparing the rules of each input and a
+         * broken input or one with its own base directory can only be
+         * handled if it is parsed on its own.
+         */
+        let per_file = cache.is_some()
+            || !make_input
+            || args.report_conflicts
+            || args.keep_going
+            || !args.input_dirs.is_empty();
+
+        /*
+         * Check the size of the input before reading it. Input which is too
+         * large is parsed in chunks. If this is not possible, fail early
+         * instead of running out of memory halfway through.
+         */
+        let size: u64 = args
+            .input
+            .iter()
+            .filter(|path| *path != "-")
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|attr| attr.len())
+            .sum();
+
+        let budget = args.max_memory / MEMORY_FACTOR;
+        let mut chunker = None;
+
+        if !per_file && args.chunk_size != 0 {
+            chunker = Some(Self::new_chunker(args, args.chunk_size));
+        } else if !per_file && size > budget {
+            chunker = Some(Self::new_chunker(args, budget));
+        } else {
+            check_memory(size, args.max_memory);
+        }
+
+        let mut parser = DependencyParser::new();
+        parser.set_merge_strategy(args.merge);
+        parser.set_merge_policy(args.merge_policy);
+        parser.set_strict(args.strict);
+        parser.set_keep_going(args.keep_going);
+
+        let is_why = matches!(args.command, Command::Why(..));
+        let mut locations = Locations::new();
+
+        locations.set_record_rules(is_why);
+
+        Self {
+            args,
+            vars,
+            cache,
+            make_input,
+            with_locations: args.with_locations || is_why,
+            size,
+            budget,
+            data: Vec::with_capacity(4096 * args.input.len()),
+            rules: Rules {
+                parser,
+                chunker,
+                bufs: Vec::new(),
+                per_file,
+            },
+            tags: HashMap::new(),
+            includer: Includer::new(),
+            locations,
+            conflicts: args.report_conflicts.then(ConflictFinder::new),
+            failed: Vec::new(),
+            log: open_log(args),
+        }
+    }
+
+    fn new_chunker(args: &Args, chunk_size: u64) -> ChunkParser {
+        let mut chunker = ChunkParser::new(chunk_size, args.merge);
+        chunker.set_strict(args.strict);
+        chunker
+    }
+
+    fn fail(&mut self, code: &str, path: &'a str, msg: &str) {
+        input_failed(self.args, &mut self.log, code, path, msg);
+        self.failed.push(path);
+    }
+
+    fn read_files(&mut self) {
+        let args = self.args;
+        let show_progress = !args.quiet && io::stderr().is_terminal();
+        let mut progress =
+            Progress::new(args.input.len(), self.size, show_progress);
+
+        for (index, path) in args.input.iter().enumerate() {
+            if let Some(len) = self.read_file(index, path) {
+                progress.update(len);
+            }
+        }
+
+        progress.finish();
+    }
+
+    /*
+     * Read the input file "path" and return its size if it was read.
+     */
+    fn read_file(&mut self, index: usize, path: &'a str) -> Option<u64> {
+        let args = self.args;
+        let begin = Instant::now();
+        let base = args
+            .input_dirs
+            .iter()
+            .find(|(i, _)| *i == index)
+            .map(|(_, dir)| dir.as_str());
+
+        let (mut file, len): (Box<dyn Read>, u64) = if path == "-" {
+            (Box::new(io::stdin().lock()), 0)
+        } else if zstd::is_compressed(path) {
+            let file = match Decoder::open(path) {
+                Ok(val) => val,
+                Err(err) => {
+                    let msg = format!("failed to open \"{path}\": {err}");
+
+                    self.fail("read-failed", path, &msg);
+                    return None;
+                }
+            };
+
+            let len = fs::metadata(path).map_or(0, |attr| attr.len());
+            (Box::new(file), len)
+        } else {
+            let file = match File::open(path) {
+                Ok(val) => val,
+                Err(err) => {
+                    let msg = format!("failed to open \"{path}\": {err}");
+
+                    self.fail("read-failed", path, &msg);
+                    return None;
+                }
+            };
+
+            let len = file.metadata().map_or(0, |attr| attr.len());
+            (Box::new(file), len)
+        };
+
+        let path = if path == "-" { "stdin" } else { path };
+
+        let mut checker = Utf8Checker::new();
+
+        if let Some(chunker) = &mut self.rules.chunker {
+            if let Err(err) = chunker.read(Utf8Reader::new(file, &mut checker))
+            {
+                error!(
+                    "read-failed",
+                    file = path,
+                    "failed to read file \"{path}\": {err}"
+                );
+                exit(1);
+            }
+
+            check_utf8(path, &checker, args.utf8, &mut self.log);
+            self.log.input(path, len, begin.elapsed());
+            timings::record(Phase::Read, Some(path), len, begin.elapsed());
+            return Some(len);
+        }
+
+        /* Cached and JSON files have to be parsed one by one */
+        let per_file = self.rules.per_file;
+        let mut content = Vec::new();
+        let data = &mut self.data;
+        let buf = if per_file { &mut content } else { data };
+
+        buf.reserve(len as usize);
+        let start = buf.len();
+
+        if let Err(err) = file.read_to_end(buf) {
+            let msg = format!("failed to read file \"{path}\": {err}");
+
+            self.fail("read-failed", path, &msg);
+            return None;
+        }
+
+        if self.with_locations {
+            self.locations.scan(path, &buf[start..]);
+        }
+
+        /* The other parsers reject invalid input on their own */
+        if self.make_input {
+            checker.update(&buf[start..]);
+            check_utf8(path, &checker, args.utf8, &mut self.log);
+
+            let result = expand_includes(&mut self.includer, path, buf, start)
+                .and_then(|_| expand_conditionals(self.vars, path, buf, start));
+
+            if let Err(err) = result {
+                self.fail("invalid-input", path, &err);
+                return None;
+            }
+        }
+
+        let bytes = (buf.len() - start) as u64;
+        let parsing = Instant::now();
+        timings::record(Phase::Read, Some(path), bytes, begin.elapsed());
+
+        let bufs = &mut self.rules.bufs;
+        let first = bufs.len();
+
+        if !self.make_input {
+            let format = args.input_format;
+
+            match read_deps(format, path, &content, &mut self.tags) {
+                Ok(deps) => bufs.extend(deps),
+                Err(err) => {
+                    self.fail("invalid-input", path, &err);
+                    return None;
+                }
+            }
+        } else if let Some(cache) = &self.cache {
+            match cache.parse(content) {
+                Ok(deps) => bufs.extend(deps),
+                Err(err) => {
+                    let msg = format!("failed to parse \"{path}\": {err}");
+
+                    self.fail("invalid-input", path, &msg);
+                    return None;
+                }
+            }
+        } else if base.is_some() {
+            /* The rules are rebased below, so keep them apart */
+            let mut parser = DependencyParser::new();
+            parser.set_strict(args.strict);
+            parser.set_keep_going(args.keep_going);
+
+            match parser.feed(content) {
+                Ok(rules) => bufs.extend(rules.iter().map(Dependency::to_buf)),
+                Err(err) => {
+                    let msg = format!("failed to parse \"{path}\": {err}");
+
+                    self.fail("invalid-input", path, &msg);
+                    return None;
+                }
+            }
+        } else if per_file {
+            match self.rules.parser.feed(content) {
+                Ok(rules) => {
+                    if let Some(conflicts) = &mut self.conflicts {
+                        conflicts.add(path, rules);
+                    }
+                }
+                Err(err) => {
+                    let msg = format!("failed to parse \"{path}\": {err}");
+
+                    self.fail("invalid-input", path, &msg);
+                    return None;
+                }
+            }
+        }
+
+        let bufs = &mut self.rules.bufs;
+
+        if let Some(base) = base {
+            for buf in &mut bufs[first..] {
+                buf.target = normalize::join(base, &buf.target).into_owned();
+
+                for x in &mut buf.prerequisites {
+                    *x = normalize::join(base, x).into_owned();
+                }
+            }
+        }
+
+        /* The rules fed to the parser are added to the conflicts above */
+        let fed = self.make_input && self.cache.is_none() && base.is_none();
+
+        if let (false, Some(conflicts)) = (fed, &mut self.conflicts) {
+            let deps: Vec<_> =
+                bufs[first..].iter().map(|x| x.as_dependency()).collect();
+
+            conflicts.add(path, &deps);
+        }
+
+        /* Without "per_file", all inputs are parsed at once later on */
+        if per_file {
+            let elapsed = parsing.elapsed();
+            timings::record(Phase::Parse, Some(path), bytes, elapsed);
+        }
+
+        self.log.input(path, len, begin.elapsed());
+        Some(len)
+    }
+
+    /*
+     * Read stdin after all input files if it is redirected.
+     */
+    fn read_stdin(&mut self) {
+        let args = self.args;
+        let stdin = io::stdin();
+        let mut checker = Utf8Checker::new();
+
+        if let Some(chunker) = &mut self.rules.chunker {
+            if let Err(err) = chunker.read(Utf8Reader::new(stdin, &mut checker))
+            {
+                error!("read-failed", "failed to read stdin: {err}");
+                exit(1);
+            }
+
+            check_utf8("stdin", &checker, args.utf8, &mut self.log);
+            return;
+        }
+
+        let data = &mut self.data;
+        data.reserve(4096);
+
+        let begin = Instant::now();
+        let start = data.len();
+        let prev_size = self.size;
+
+        /* Stop reading stdin as soon as the limit is exceeded */
+        let remaining = self.budget.saturating_sub(self.size);
+
+        match stdin
+            .lock()
+            .take(remaining.saturating_add(1))
+            .read_to_end(data)
+        {
+            Ok(count) => self.size += count as u64,
+            Err(err) => {
+                error!("read-failed", "failed to read stdin: {err}");
+                exit(1);
+            }
+        }
+
+        checker.update(&data[start..]);
+
+        if self.with_locations {
+            self.locations.scan("stdin", &data[start..]);
+        }
+
+        if !self.rules.per_file && self.size > self.budget {
+            /* Parse the data read so far and the rest of stdin in chunks */
+            let mut parser = Self::new_chunker(args, self.budget);
+            let stdin = Utf8Reader::new(stdin.lock(), &mut checker);
+            let input = data.as_slice().chain(stdin);
+
+            if let Err(err) = parser.read(input) {
+                error!("read-failed", "failed to read stdin: {err}");
+                exit(1);
+            }
+
+            *data = Vec::new();
+            self.rules.chunker = Some(parser);
+        } else {
+            check_memory(self.size, args.max_memory);
+        }
+
+        if self.make_input {
+            check_utf8("stdin", &checker, args.utf8, &mut self.log);
+
+            /* Paths included by stdin are relative to the current directory */
+            let result = expand_includes(&mut self.includer, "", data, start)
+                .and_then(|_| {
+                    expand_conditionals(self.vars, "stdin", data, start)
+                });
+
+            if let Err(err) = result {
+                error!("invalid-input", file = "stdin", "{err}");
+                exit(1);
+            }
+        }
+
+        if !self.make_input && !data.is_empty() {
+            let format = args.input_format;
+            let bufs = &mut self.rules.bufs;
+            let first = bufs.len();
+
+            let result = read_deps(format, "stdin", data, &mut self.tags);
+
+            bufs.extend(result.unwrap_or_else(|err| {
+                error!("invalid-input", file = "stdin", "{err}");
+                exit(1);
+            }));
+            data.clear();
+
+            if let Some(conflicts) = &mut self.conflicts {
+                let deps: Vec<_> =
+                    bufs[first..].iter().map(|x| x.as_dependency()).collect();
+
+                conflicts.add("stdin", &deps);
+            }
+        }
+
+        let size = self.size - prev_size;
+
+        self.log.input("stdin", size, begin.elapsed());
+        timings::record(Phase::Read, Some("stdin"), size, begin.elapsed());
+    }
+
+    /*
+     * Reject the options which need all rules at once if the input is
+     * parsed in chunks.
+     */
+    fn check_chunks(&self) {
+        let args = self.args;
+
+        if self.rules.chunker.is_none() {
+            return;
+        }
+
+        if args.merge_policy != MergePolicy::Union {
+            error!(
+                "invalid-argument",
+                "\"--merge-policy\" cannot be used in chunks"
+            );
+            exit(1);
+        }
+
+        if args.with_fingerprint {
+            error!(
+                "invalid-argument",
+                "\"--with-fingerprint\" cannot be used in chunks, \
+                 use \"--print-fingerprint\" instead"
+            );
+            exit(1);
+        }
+
+        if self.with_locations {
+            let is_why = matches!(args.command, Command::Why(..));
+            let option = if is_why { "why" } else { "--with-locations" };
+
+            error!("invalid-argument", "\"{option}\" cannot be used in chunks");
+            exit(1);
+        }
+    }
+
+    /*
+     * Parse the data which holds stdin or, without "per_file", all inputs
+     * concatenated.
+     */
+    fn parse_data(&mut self) {
+        if self.data.is_empty() {
+            return;
+        }
+
+        let parsing = Instant::now();
+        let data = mem::take(&mut self.data);
+        let bytes = data.len() as u64;
+
+        let rules = self.rules.parser.feed(data).unwrap_or_else(|err| {
+            error!("invalid-input", "{err}");
+            exit(1);
+        });
+
+        if let Some(conflicts) = &mut self.conflicts {
+            conflicts.add("stdin", rules);
+        }
+
+        timings::record(Phase::Parse, None, bytes, parsing.elapsed());
+    }
+}
+
+/*
+ * Read and parse the input files and, if "stdin" is set, stdin.
+ */
+fn read_inputs<'a>(
+    args: &'a Args,
+    vars: Option<&'a Variables>,
+    stdin: bool,
+) -> Input<'a> {
+    let mut input = Input::new(args, vars);
+
+    input.read_files();
+
+    if stdin {
+        input.read_stdin();
+    }
+
+    input.check_chunks();
+    input.parse_data();
+    input
+}
+
+/*
+ * Write the rules which differ between the inputs and exit with a non-zero
+ * status if there are any.
+ */
+fn write_conflicts(args: &Args, conflicts: ConflictFinder) -> ! {
+    let conflicts = conflicts.finish();
+
+    let mut serializer = JsonSerializer::new();
+    serializer.write_conflicts(&conflicts);
+
+    for (path, _) in output_formats(args, Format::Json) {
+        let compress = !args.compress.is_empty();
+
+        write_output(path, &args.pipe, compress, serializer.get_json());
+    }
+
+    exit(!conflicts.is_empty() as i32);
+}
+
+/*
+ * Rewrite the paths of the rules as requested by the options. The paths
+ * are converted to the Unicode normalization form first, so the other
+ * options see them in a single form. Variables are expanded next, the
+ * defined ones before those of the environment. Afterwards, the paths are
+ * normalized, the Bazel paths are remapped, the prefixes are mapped like
+ * the compiler does, and the rewrite rules are applied. The separators are
+ * converted last, unless the paths are turned into URIs.
+ */
+struct PathMapper<'a> {
+    args: &'a Args,
+    vars: Option<&'a Variables>,
+    unicode: Option<Normalizer>,
+    rewriter: Option<Rewriter>,
+    prefix_map: Option<PrefixMap>,
+    bazel: Option<BazelMap>,
+    home: Option<String>,
+    cwd: Option<String>,
+}
+
+impl<'a> PathMapper<'a> {
+    fn new(args: &'a Args, vars: Option<&'a Variables>) -> Self {
+        let rewriter = (!args.rewrites.is_empty()).then(|| {
+            Rewriter::new(&args.rewrites).unwrap_or_else(|err| {
+                error!("invalid-argument", "{err}");
+                exit(1);
+            })
+        });
+
+        let prefix_map = (!args.prefix_map_file.is_empty())
+            .then(|| read_prefix_map(&args.prefix_map_file));
+
+        let bazel = args.bazel.then(|| {
+            let dir = env::current_dir().unwrap_or_default();
+            let workspace =
+                dir.file_name().unwrap_or_default().to_string_lossy();
+
+            BazelMap::new(&args.bazel_execroot, &workspace)
+        });
+
+        let cwd = args.as_uri.then(|| {
+            let dir = env::current_dir().unwrap_or_default();
+
+            dir.to_string_lossy().into_owned()
+        });
+
+        Self {
+            args,
+            vars,
+            unicode: args.unicode_normalize.map(Normalizer::new),
+            rewriter,
+            prefix_map,
+            bazel,
+            home: env::var("HOME").ok(),
+            cwd,
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        let args = self.args;
+
+        self.unicode.is_some()
+            || self.vars.is_some()
+            || args.expand_env
+            || args.expand_tilde
+            || args.normalize_paths
+            || self.bazel.is_some()
+            || self.prefix_map.is_some()
+            || self.rewriter.is_some()
+            || args.output_separators != Separators::Keep
+            || args.as_uri
+    }
+
+    fn apply(&self, path: &str) -> String {
+        let args = self.args;
+        let mut path = String::from(path);
+
+        if let Some(unicode) = &self.unicode {
+            path = unicode.apply(&path).into_owned();
+        }
+
+        if let Some(vars) = self.vars {
+            path = vars.expand(&path).into_owned();
+        }
+
+        if args.expand_env {
+            let lookup = |name: &str| env::var(name).ok();
+            path = expand::expand_env(&path, lookup).into_owned();
+        }
+
+        if args.expand_tilde {
+            let home = self.home.as_deref();
+            path = expand::expand_tilde(&path, home).into_owned();
+        }
+
+        if args.normalize_paths {
+            path = normalize::normalize(&path).into_owned();
+        }
+
+        if let Some(bazel) = &self.bazel {
+            path = bazel.apply(&path).into_owned();
+        }
+
+        if let Some(prefix_map) = &self.prefix_map {
+            path = prefix_map.apply(&path).into_owned();
+        }
+
+        if let Some(rewriter) = &self.rewriter {
+            path = rewriter.rewrite(&path).into_owned();
+        }
+
+        path = args.output_separators.apply(&path).into_owned();
+
+        if let Some(cwd) = &self.cwd {
+            path = uri::file_uri(&path, cwd);
+        }
+
+        path
+    }
+
+    /*
+     * Rewrite the paths of "deps" and the targets of "locations". Nothing
+     * is returned if no option rewrites the paths. The rules of targets
+     * whose paths became identical still have to be merged.
+     */
+    fn map(
+        &self,
+        deps: &[Dependency],
+        locations: &mut Locations,
+    ) -> Option<Vec<DependencyBuf>> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let mapped = dependency::map_paths(deps, |x| self.apply(x));
+        locations.map_targets(|x| self.apply(x));
+
+        Some(mapped)
+    }
+}
+
+/*
+ * Replace the files which are hard links to the same file with a single
+ * path. The other paths are added to the attributes in "tags". The files
+ * are looked up by their final paths.
+ */
+fn dedupe_by_inode(
+    deps: &[Dependency],
+    tags: &mut HashMap<String, Attributes>,
+) -> Vec<DependencyBuf> {
+    let result = inode::dedupe(deps);
+
+    for (path, attrs) in inode::attributes(&result.aliases) {
+        tags.entry(path).or_default().extend(attrs);
+    }
+
+    result.deps
+}
+
+fn new_classifier(args: &Args) -> Classifier {
+    let mut system_dirs = args.system_dirs.clone();
+
+    if args.drop_system || args.drop_project || args.classify {
+        system_dirs.extend(classify::compiler_dirs());
+    }
+
+    Classifier::new(&system_dirs)
+}
+
+/*
+ * Select the rules requested by the options: the rules reachable from the
+ * roots and those which pass the filters. The sources of the targets are
+ * inferred and removed if requested, before the rules are sorted.
+ */
+fn select_rules<'a>(
+    args: &Args,
+    mut deps: Cow<'a, [Dependency<'a>]>,
+    classifier: &Classifier,
+) -> (Cow<'a, [Dependency<'a>]>, Option<HashMap<&'a str, String>>) {
+    let orphans = args.command == Command::Query(Query::Orphans);
+
+    if !args.roots.is_empty() && !orphans {
+        let graph = Graph::new(&deps);
+        require_nodes(&graph, &args.roots);
+
+        let subgraph = graph.subgraph(&deps, &args.roots);
+        deps = Cow::Owned(subgraph);
+    }
+
+    let only_ext = !args.only_ext.is_empty();
+    let drop_ext = !args.drop_ext.is_empty();
+
+    let expr = (!args.query.is_empty()).then(|| {
+        expr::Query::parse(&args.query).unwrap_or_else(|err| {
+            error!("invalid-input", "invalid query: {err}");
+            exit(1);
+        })
+    });
+
+    if args.drop_system
+        || args.drop_project
+        || args.drop_frameworks
+        || only_ext
+        || drop_ext
+        || expr.is_some()
+    {
+        let mut vec = deps.into_owned();
+
+        if args.drop_system {
+            let classes = [Class::System, Class::Framework];
+            vec = classifier.filter(&vec, &classes);
+        }
+
+        if args.drop_frameworks {
+            vec = classifier.filter(&vec, &[Class::Framework]);
+        }
+
+        if args.drop_project {
+            vec = classifier.filter(&vec, &[Class::Project]);
+        }
+
+        if only_ext {
+            vec = ExtensionFilter::new(&args.only_ext, true).filter(&vec);
+        }
+
+        if drop_ext {
+            vec = ExtensionFilter::new(&args.drop_ext, false).filter(&vec);
+        }
+
+        if let Some(expr) = &expr {
+            vec = expr.filter(&vec);
+        }
+
+        deps = Cow::Owned(vec);
+    }
+
+    /* The source is the first prerequisite, so infer it before sorting */
+    let sources = (args.infer_source || args.strip_source).then(|| {
+        let kinds = KindRules::new(&args.kind_rules).unwrap_or_else(|err| {
+            error!("invalid-argument", "{err}");
+            exit(1);
+        });
+
+        let rules =
+            SourceRules::new(&args.source_rules).unwrap_or_else(|err| {
+                error!("invalid-argument", "{err}");
+                exit(1);
+            });
+
+        source::infer(&kinds, &rules, &deps)
+    });
+
+    if let (true, Some(sources)) = (args.strip_source, &sources) {
+        deps = Cow::Owned(source::strip(&deps, sources));
+    }
+
+    if args.sort {
+        let mut vec = deps.into_owned();
+        dependency::sort(&mut vec);
+
+        deps = Cow::Owned(vec);
+    }
+
+    (deps, sources)
+}
+
+/*
+ * Report the rules which exceed "--max-prereqs" or "--max-edges". They
+ * are warnings unless "--fail-on-budget" is given.
+ */
+fn check_budget(args: &Args, deps: &[Dependency], log: &mut Logger) {
+    let budget = Budget {
+        max_prereqs: args.max_prereqs,
+        max_edges: args.max_edges,
+    };
+
+    let exceeded = budget.check(deps);
+
+    for msg in &exceeded {
+        if args.fail_on_budget {
+            error!("budget-exceeded", "{msg}");
+        } else {
+            log.warning("budget-exceeded", None, msg);
+        }
+    }
+
+    if args.fail_on_budget && !exceeded.is_empty() {
+        exit(1);
+    }
+}
+
+/*
+ * Report the paths outside of the roots given with "--sandbox-root". They
+ * are errors unless "--sandbox-warn" is given.
+ */
+fn check_sandbox(args: &Args, deps: &[Dependency], log: &mut Logger) {
+    if args.sandbox_roots.is_empty() {
+        return;
+    }
+
+    let cwd = env::current_dir().unwrap_or_default();
+    let sandbox = Sandbox::new(&args.sandbox_roots, &cwd.to_string_lossy());
+    let outside = sandbox.check(deps);
+
+    for msg in &outside {
+        if args.sandbox_warn {
+            log.warning("sandbox-violation", None, msg);
+        } else {
+            error!("sandbox-violation", "{msg}");
+        }
+    }
+
+    if !args.sandbox_warn && !outside.is_empty() {
+        exit(1);
+    }
+}
+
+fn main() {
+    let argv: Vec<String> = env::args().collect();
+    let argc = argv.len();
+
+    /* Report invalid arguments in the requested format, too */
+    let json: diag::Format = json::format_diagnostic;
+    let pre = argv.windows(2).any(|x| x == ["--diagnostics", "json"]);
+    diag::set_format(pre.then_some(json));
+
+    let mut args = args::parse(argv.iter().cloned());
+    diag::set_format(args.json_diagnostics.then_some(json));
+
+    let mut stdin = io::stdin();
+    let isatty = stdin.is_terminal();
+
+    if args.help || (isatty && argc < 2) {
+        help();
+        exit(0)
+    }
+
+    if args.version_json {
+        version_json();
+        exit(0)
+    }
+
+    if args.version {
+        version();
+        exit(0)
+    }
+
+    if args.server {
+        let result = Server::new().run(stdin.lock(), io::stdout().lock());
+
+        if let Err(err) = result {
+            error!("server-failed", "server failed: {err}");
+            exit(1);
+        }
+
+        exit(0)
+    }
+
+    if !args.daemon.is_empty() {
+        let path = &args.daemon;
+
+        if let Err(err) = Server::new().listen(path) {
+            error!("server-failed", "failed to listen on \"{path}\": {err}");
+            exit(1);
+        }
+
+        exit(0)
+    }
+
+    if let Some(msg) = args.conflict() {
+        error!("invalid-argument", "{msg}");
+        exit(1);
+    }
+
+    let name_template = (!args.name_template.is_empty()).then(|| {
+        NameTemplate::parse(&args.name_template).unwrap_or_else(|err| {
+            error!("invalid-argument", "invalid name template: {err}");
+            exit(1);
+        })
+    });
+
+    if !args.force && args.command != Command::Lint {
+        for path in &args.outputs {
+            check_overwrite(path);
+        }
+    }
+
+    if args.command == Command::Watch {
+        watch(&args, &argv[2..]);
+    }
+
+    if let Command::Diff(old, new) = &args.command {
+        diff(&args, old, new);
+    }
+
+    if isatty && args.input.is_empty() {
+        error!("read-failed", "no input data available");
+        exit(1);
+    }
+
+    /* Read stdin in place of "-" or after all input files if redirected */
+    let explicit_stdin = args.input.iter().any(|path| path == "-");
+    let implicit_stdin = !isatty && !explicit_stdin;
+
+    if args.stream || args.group_by_input {
+        stream(&args, implicit_stdin);
+    }
+
+    if args.command == Command::Lint {
+        let mut inputs = Vec::with_capacity(args.input.len() + 1);
+
+        for path in &args.input {
+            let data = read_file(path);
+            let path = if path == "-" { "<stdin>" } else { path };
+
+            inputs.push((path, data));
+        }
+
+        if implicit_stdin {
+            let mut data = Vec::new();
+
+            if let Err(err) = stdin.read_to_end(&mut data) {
+                error!("read-failed", "failed to read stdin: {err}");
+                exit(1);
+            }
+
+            inputs.push(("<stdin>", data));
+        }
+
+        lint(&args, &inputs);
+    }
+
+    if !args.roots_file.is_empty() {
+        read_roots(&args.roots_file, &mut args.roots);
+    }
+
+    if args.timings {
+        timings::enable();
+    }
+
+    if args.stats {
+        memory::enable();
+    }
+
+    let vars = (!args.defines.is_empty()).then(|| {
+        Variables::new(&args.defines).unwrap_or_else(|err| {
+            error!("invalid-argument", "{err}");
+            exit(1);
+        })
+    });
+
+    let mapper = PathMapper::new(&args, vars.as_ref());
+    let mut input = read_inputs(&args, vars.as_ref(), implicit_stdin);
+
+    if let Some(conflicts) = input.conflicts.take() {
+        write_conflicts(&args, conflicts);
+    }
+
+    let Input {
+        mut rules,
+        mut tags,
+        mut locations,
+        failed,
+        mut log,
+        size,
+        ..
+    } = input;
+
+    let streaming = rules.chunker.is_some();
+    let merging = Instant::now();
+    let deps = rules.finish(&args);
+
+    timings::record(Phase::Merge, None, size, merging.elapsed());
+
+    let transforming = Instant::now();
+
+    let mapped = mapper.map(&deps, &mut locations);
+    let deps = match &mapped {
+        Some(bufs) => {
+            let vec = bufs.iter().map(|x| x.as_dependency()).collect();
+            Cow::Owned(args.merge.merge(vec))
+        }
+        None => deps,
+    };
+
+    /* The files are looked up by their final paths */
+    let deduped = args
+        .dedupe_by_inode
+        .then(|| dedupe_by_inode(&deps, &mut tags));
+
+    let deps = match &deduped {
+        Some(bufs) => {
+            Cow::Owned(bufs.iter().map(|x| x.as_dependency()).collect())
+        }
+        None => deps,
+    };
+
+    let classifier = new_classifier(&args);
+    let (deps, sources) = select_rules(&args, deps, &classifier);
+
+    check_budget(&args, &deps, &mut log);
+    check_sandbox(&args, &deps, &mut log);
+
+    timings::record(Phase::Transform, None, size, transforming.elapsed());
+
+    /* The outputs are serialized and written from here on */
+    let output = Instant::now();
+
+    let inputs = args.input.len() + implicit_stdin as usize;
+    let prereqs = deps.iter().map(|x| x.prerequisites.len()).sum();
+    log.summary(inputs, size, deps.len(), prereqs);
+
+    /* The output of a partial conversion is written, but still fails */
+    let status = if failed.is_empty() { 0 } else { 2 };
+
+    if !failed.is_empty() {
+        error!(
+            "read-failed",
+            "{} of {} input files failed: {}",
+            failed.len(),
+            args.input.len(),
+            failed.join(", ")
+        );
+    }
+
+    let conversion = Conversion {
+        args: &args,
+        deps: &deps,
+        tags,
+        locations,
+        sources,
+        name_template,
+        classifier,
+        streaming,
+        outputs: output_formats(&args, args.format),
+        status,
+        output,
+        size,
+    };
+
+    if args.print_fingerprint {
+        let hex = fingerprint::compute(&deps);
+        conversion.write(hex.as_bytes());
+    }
+
+    match &args.command {
+        Command::Query(x) => query(&conversion, x),
+        Command::Why(target, prereq) => why(&conversion, target, prereq),
+        Command::Check => check(&args, &deps, &conversion.locations),
+        Command::Stats(x) => stats(&conversion, *x),
+        _ => convert(conversion),
+    }
 }
@@ -0,0 +1,226 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/*
+ * Count the allocations of the program with the system allocator. The
+ * counters are updated atomically, which slows down allocation, so the
+ * allocator is only used with the "alloc-stats" feature.
+ */
+#[cfg(feature = "alloc-stats")]
+mod counting {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+    static CURRENT: AtomicUsize = AtomicUsize::new(0);
+    static PEAK: AtomicUsize = AtomicUsize::new(0);
+    static TOTAL: AtomicU64 = AtomicU64::new(0);
+    static COUNT: AtomicU64 = AtomicU64::new(0);
+
+    pub struct Counting;
+
+    fn add(size: usize) {
+        let current = CURRENT.fetch_add(size, Ordering::Relaxed) + size;
+
+        PEAK.fetch_max(current, Ordering::Relaxed);
+        TOTAL.fetch_add(size as u64, Ordering::Relaxed);
+        COUNT.fetch_add(1, Ordering::Relaxed);
+    }
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc(layout);
+
+            if !ptr.is_null() {
+                add(layout.size());
+            }
+
+            ptr
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc_zeroed(layout);
+
+            if !ptr.is_null() {
+                add(layout.size());
+            }
+
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout);
+            CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+
+        unsafe fn realloc(
+            &self,
+            ptr: *mut u8,
+            layout: Layout,
+            new_size: usize,
+        ) -> *mut u8 {
+            let new_ptr = System.realloc(ptr, layout, new_size);
+
+            if !new_ptr.is_null() {
+                CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+                add(new_size);
+            }
+
+            new_ptr
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: Counting = Counting;
+
+    /*
+     * The peak of the allocated bytes, the total of all allocated bytes,
+     * and the number of allocations.
+     */
+    pub fn allocations() -> (u64, u64, u64) {
+        (
+            PEAK.load(Ordering::Relaxed) as u64,
+            TOTAL.load(Ordering::Relaxed),
+            COUNT.load(Ordering::Relaxed),
+        )
+    }
+}
+
+#[cfg(feature = "alloc-stats")]
+fn allocations() -> Option<(u64, u64, u64)> {
+    Some(counting::allocations())
+}
+
+#[cfg(not(feature = "alloc-stats"))]
+fn allocations() -> Option<(u64, u64, u64)> {
+    None
+}
+
+/*
+ * Parse the peak resident set size in bytes from the "VmHWM" line of
+ * "/proc/self/status", which is given in kB.
+ */
+fn parse_status(status: &str) -> Option<u64> {
+    let line = status.lines().find(|x| x.starts_with("VmHWM:"))?;
+    let value = line["VmHWM:".len()..].trim().strip_suffix("kB")?;
+
+    value.trim().parse::<u64>().ok().map(|x| x * 1024)
+}
+
+fn peak_rss() -> Option<u64> {
+    parse_status(&fs::read_to_string("/proc/self/status").ok()?)
+}
+
+/*
+ * Print the memory usage to stderr when the conversion is done.
+ */
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+fn line(name: &str, value: Option<u64>, unit: &str) -> String {
+    let text = match value {
+        Some(value) => format!("{name:<16} {value:>14} {unit}"),
+        None => format!("{name:<16} {:>14}", "-"),
+    };
+
+    format!("{}\n", text.trim_end())
+}
+
+/*
+ * Render the peak resident set size and, with the "alloc-stats" feature,
+ * the allocated bytes. Unavailable values are shown as "-".
+ */
+fn render(rss: Option<u64>, allocations: Option<(u64, u64, u64)>) -> String {
+    let mut result = line("peak rss", rss, "bytes");
+
+    if let Some((peak, total, count)) = allocations {
+        result += &line("peak allocated", Some(peak), "bytes");
+        result += &line("total allocated", Some(total), "bytes");
+        result += &line("allocations", Some(count), "");
+    }
+
+    result
+}
+
+pub fn print() {
+    if ENABLED.load(Ordering::Relaxed) {
+        eprint!("{}", render(peak_rss(), allocations()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * parse_status()
+     *
+     * Verify that the peak resident set size is converted to bytes and
+     * that a missing or malformed "VmHWM" line is rejected.
+     */
+    #[test]
+    fn parse_status_001() {
+        let status = "Name:\tdep2j\nVmPeak:\t  20000 kB\nVmHWM:\t    1234 kB\n";
+
+        assert_eq!(Some(1234 * 1024), parse_status(status));
+        assert_eq!(None, parse_status("Name:\tdep2j\n"));
+        assert_eq!(None, parse_status("VmHWM:\t1234 pages\n"));
+    }
+
+    /**
+     * render()
+     *
+     * Verify that the allocations are only listed if they are counted.
+     */
+    #[test]
+    fn render_001() {
+        assert_eq!(
+            "peak rss                   2048 bytes\n",
+            render(Some(2048), None)
+        );
+        assert_eq!(
+            "peak rss                      -\n\
+             peak allocated                1 bytes\n\
+             total allocated               2 bytes\n\
+             allocations                   3\n",
+            render(None, Some((1, 2, 3)))
+        );
+    }
+
+    /**
+     * counting::allocations()
+     *
+     * Verify that allocations are counted with the "alloc-stats" feature.
+     */
+    #[cfg(feature = "alloc-stats")]
+    #[test]
+    fn allocations_001() {
+        let (_, total, count) = counting::allocations();
+        let vec: Vec<u8> = Vec::with_capacity(4096);
+        let (peak, total_after, count_after) = counting::allocations();
+
+        assert!(peak >= 4096);
+        assert!(total_after >= total + 4096);
+        assert!(count_after > count);
+        drop(vec);
+    }
+}
@@ -0,0 +1,190 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::thread;
+use std::time::UNIX_EPOCH;
+
+use crate::dependency::Dependency;
+use crate::hash;
+use crate::json::{Attributes, Value};
+
+/*
+ * Gather the size in bytes and the modification time in seconds since the
+ * epoch of each prerequisite. Both are null if a file cannot be accessed,
+ * e.g. because it was removed after the dependency file was generated.
+ */
+pub fn attributes(deps: &[Dependency]) -> HashMap<String, Attributes> {
+    let mut result = HashMap::new();
+
+    for &prereq in deps.iter().flat_map(|dep| &dep.prerequisites) {
+        if result.contains_key(prereq) {
+            continue;
+        }
+
+        let attr = fs::metadata(prereq).ok();
+
+        let size = attr
+            .as_ref()
+            .map(|x| Value::Number(x.len() as f64))
+            .unwrap_or(Value::Null);
+
+        let mtime = attr
+            .and_then(|x| x.modified().ok())
+            .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+            .map(|x| Value::Number(x.as_secs_f64()))
+            .unwrap_or(Value::Null);
+
+        result.insert(
+            String::from(prereq),
+            Vec::from([("size", size), ("mtime", mtime)]),
+        );
+    }
+
+    result
+}
+
+/*
+ * Calculate the SHA-256 digest of each unique prerequisite. The files are
+ * distributed over one thread per available CPU. The digest is null if a
+ * file cannot be read.
+ */
+pub fn digests(deps: &[Dependency]) -> HashMap<String, Attributes> {
+    let mut seen = HashSet::new();
+
+    let files: Vec<&str> = deps
+        .iter()
+        .flat_map(|dep| &dep.prerequisites)
+        .copied()
+        .filter(|x| seen.insert(*x))
+        .collect();
+
+    let threads = thread::available_parallelism().map_or(1, |x| x.get());
+    let chunk_size = files.len().div_ceil(threads).max(1);
+
+    let digest = |path: &str| {
+        fs::read(path)
+            .map(|data| Value::String(hash::to_hex(&hash::sha256(&data))))
+            .unwrap_or(Value::Null)
+    };
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = files
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|&path| (String::from(path), digest(path)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .map(|(path, digest)| (path, Vec::from([("sha256", digest)])))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    /**
+     * attributes()
+     *
+     * Verify that the size of existing files is returned and that missing
+     * files are reported with null values.
+     */
+    #[test]
+    fn attributes_001() {
+        let mut path = env::temp_dir();
+        path.push(format!("dep2j-metadata-{}", process::id()));
+        fs::write(&path, b"abc").unwrap();
+
+        let path = path.to_str().unwrap();
+
+        let vec = Vec::from([Dependency {
+            target: "a",
+            prerequisites: Vec::from([path, "/nonexistent/file.h"]),
+        }]);
+
+        let map = attributes(&vec);
+
+        assert_eq!(2, map.len());
+        assert_eq!(("size", Value::Number(3.0)), map[path][0]);
+        assert!(matches!(map[path][1], ("mtime", Value::Number(_))));
+        assert_eq!(
+            Vec::from([("size", Value::Null), ("mtime", Value::Null)]),
+            map["/nonexistent/file.h"]
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+
+    /**
+     * digests()
+     *
+     * Verify that each prerequisite is hashed once and that missing files
+     * are reported with a null value.
+     */
+    #[test]
+    fn digests_001() {
+        let mut path = env::temp_dir();
+        path.push(format!("dep2j-digests-{}", process::id()));
+        fs::write(&path, b"abc").unwrap();
+
+        let path = path.to_str().unwrap();
+
+        let vec = Vec::from([
+            Dependency {
+                target: "a",
+                prerequisites: Vec::from([path, "/nonexistent/file.h"]),
+            },
+            Dependency {
+                target: "b",
+                prerequisites: Vec::from([path]),
+            },
+        ]);
+
+        let map = digests(&vec);
+
+        assert_eq!(2, map.len());
+        assert_eq!(
+            Vec::from([(
+                "sha256",
+                Value::String(String::from(
+                    "ba7816bf8f01cfea414140de5dae2223\
+                     b00361a396177a9cb410ff61f20015ad"
+                ))
+            )]),
+            map[path]
+        );
+        assert_eq!(
+            Vec::from([("sha256", Value::Null)]),
+            map["/nonexistent/file.h"]
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,201 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+#[derive(Debug, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    Path,
+    Dir,
+    Name,
+    Stem,
+    Ext,
+}
+
+/*
+ * Replace the characters which are invalid in file names on common file
+ * systems, i.e. control characters and '<', '>', ':', '"', '\', '|', '?',
+ * and '*', with '_'.
+ */
+fn sanitize(segment: &str) -> String {
+    let invalid = |c: char| c.is_control() || "<>:\"\\|?*".contains(c);
+
+    segment
+        .chars()
+        .map(|c| if invalid(c) { '_' } else { c })
+        .collect()
+}
+
+/*
+ * Split "target" into sanitized path segments. Empty and "." segments are
+ * dropped, so an absolute target is placed relative to the current
+ * directory, too, and ".." is replaced with "__" so no file is written
+ * outside of it.
+ */
+fn segments(target: &str) -> Vec<String> {
+    target
+        .split('/')
+        .filter(|x| !x.is_empty() && *x != ".")
+        .map(|x| {
+            if x == ".." {
+                String::from("__")
+            } else {
+                sanitize(x)
+            }
+        })
+        .collect()
+}
+
+/*
+ * A template for the names of the files written for each target with the
+ * placeholders "{path}", "{dir}", "{name}", "{stem}", and "{ext}". For
+ * the target "src/main.o", they expand to "src/main.o", "src", "main.o",
+ * "main", and "o". "{dir}" is "." for a target without a directory and
+ * "{ext}" is empty for a name without an extension. The values are
+ * sanitized with "segments()".
+ */
+pub struct NameTemplate {
+    pieces: Vec<Piece>,
+}
+
+impl NameTemplate {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut pieces = Vec::new();
+        let mut rest = template;
+
+        while let Some(begin) = rest.find('{') {
+            if begin != 0 {
+                pieces.push(Piece::Literal(String::from(&rest[..begin])));
+            }
+
+            let end = rest[begin..]
+                .find('}')
+                .ok_or_else(|| String::from("unterminated placeholder"))?;
+
+            let name = &rest[begin + 1..begin + end];
+            let piece = match name {
+                "path" => Piece::Path,
+                "dir" => Piece::Dir,
+                "name" => Piece::Name,
+                "stem" => Piece::Stem,
+                "ext" => Piece::Ext,
+                _ => return Err(format!("unknown placeholder \"{name}\"")),
+            };
+
+            pieces.push(piece);
+            rest = &rest[begin + end + 1..];
+        }
+
+        if !rest.is_empty() {
+            pieces.push(Piece::Literal(String::from(rest)));
+        }
+
+        if !pieces.iter().any(|x| !matches!(x, Piece::Literal(_))) {
+            return Err(String::from("no placeholder in template"));
+        }
+
+        Ok(Self { pieces })
+    }
+
+    pub fn expand(&self, target: &str) -> String {
+        let mut segments = segments(target);
+        let name = segments.pop().unwrap_or_else(|| String::from("_"));
+        let dir = if segments.is_empty() {
+            String::from(".")
+        } else {
+            segments.join("/")
+        };
+
+        let (stem, ext) = match name.rfind('.') {
+            Some(i) if i != 0 => (&name[..i], &name[i + 1..]),
+            _ => (name.as_str(), ""),
+        };
+
+        let mut result = String::new();
+
+        for piece in &self.pieces {
+            match piece {
+                Piece::Literal(value) => result.push_str(value),
+                Piece::Path if segments.is_empty() => result.push_str(&name),
+                Piece::Path => {
+                    result.push_str(&dir);
+                    result.push('/');
+                    result.push_str(&name);
+                }
+                Piece::Dir => result.push_str(&dir),
+                Piece::Name => result.push_str(&name),
+                Piece::Stem => result.push_str(stem),
+                Piece::Ext => result.push_str(ext),
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * NameTemplate::expand()
+     *
+     * Verify that the function expands the placeholders with the sanitized
+     * parts of the target.
+     */
+    #[test]
+    fn expand_001() {
+        let template = NameTemplate::parse("{dir}/{stem}.deps.json").unwrap();
+
+        let tests = [
+            ("src/main.o", "src/main.deps.json"),
+            ("main.o", "./main.deps.json"),
+            ("./src//main.o", "src/main.deps.json"),
+            ("/abs/lib.a", "abs/lib.deps.json"),
+            ("../x/a.o", "__/x/a.deps.json"),
+            ("src/a:b?.o", "src/a_b_.deps.json"),
+            ("src/.hidden", "src/.hidden.deps.json"),
+        ];
+
+        for (target, expected) in tests {
+            assert_eq!(expected, template.expand(target), "{target}");
+        }
+
+        let template = NameTemplate::parse("out/{path}-{ext}").unwrap();
+
+        assert_eq!("out/a.o-o", template.expand("a.o"));
+        assert_eq!("out/src/a-", template.expand("src/a"));
+    }
+
+    /**
+     * NameTemplate::parse()
+     *
+     * Verify that the function rejects invalid templates.
+     */
+    #[test]
+    fn parse_001() {
+        let tests = [
+            ("{dir/x.json", "unterminated placeholder"),
+            ("{target}.json", "unknown placeholder \"target\""),
+            ("deps.json", "no placeholder in template"),
+        ];
+
+        for (template, expected) in tests {
+            let result = NameTemplate::parse(template).map(|_| ());
+            assert_eq!(Err(String::from(expected)), result);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+use std::time::UNIX_EPOCH;
+
+use crate::dependency::Dependency;
+
+/*
+ * Generate output in the layout of "ninja -t deps". Without a ninja
+ * deps log, the modification time of the target itself is reported.
+ * Targets which do not exist are reported as stale.
+ */
+pub struct NinjaSerializer {
+    buf: Vec<u8>,
+}
+
+impl NinjaSerializer {
+    pub fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    pub fn get_text(&self) -> &[u8] {
+        self.buf.as_slice()
+    }
+
+    pub fn write_vec(&mut self, vec: &[Dependency]) {
+        self.buf.reserve(4096 * vec.len());
+
+        for dep in vec {
+            let mtime = fs::metadata(dep.target)
+                .and_then(|attr| attr.modified())
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_nanos())
+                .unwrap_or_default();
+
+            self.write_dep(dep, mtime);
+        }
+    }
+
+    fn write_dep(&mut self, dep: &Dependency, mtime: u128) {
+        let state = if mtime != 0 { "VALID" } else { "STALE" };
+        let count = dep.prerequisites.len();
+
+        self.buf.extend_from_slice(dep.target.as_bytes());
+        self.buf.extend_from_slice(
+            format!(": #deps {count}, deps mtime {mtime} ({state})\n")
+                .as_bytes(),
+        );
+
+        for prereq in &dep.prerequisites {
+            self.buf.extend_from_slice(b"    ");
+            self.buf.extend_from_slice(prereq.as_bytes());
+            self.buf.push(b'\n');
+        }
+
+        self.buf.push(b'\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * NinjaSerializer::write_dep()
+     *
+     * Verify that a rule is written in the format of "ninja -t deps" with
+     * the given modification time.
+     */
+    #[test]
+    fn write_dep_001() {
+        let dep = Dependency {
+            target: "a.o",
+            prerequisites: Vec::from(["a.c", "b.h"]),
+        };
+
+        let mut serializer = NinjaSerializer::new();
+        serializer.write_dep(&dep, 42);
+
+        assert_eq!(
+            "a.o: #deps 2, deps mtime 42 (VALID)\n    a.c\n    b.h\n\n",
+            String::from_utf8_lossy(serializer.get_text())
+        );
+    }
+
+    /**
+     * NinjaSerializer::write_vec()
+     *
+     * Verify that the rule of a missing target is marked as stale.
+     */
+    #[test]
+    fn write_vec_001() {
+        let vec = Vec::from([Dependency {
+            target: "/nonexistent/a.o",
+            prerequisites: Vec::new(),
+        }]);
+
+        let mut serializer = NinjaSerializer::new();
+        serializer.write_vec(&vec);
+
+        assert_eq!(
+            "/nonexistent/a.o: #deps 0, deps mtime 0 (STALE)\n\n",
+            String::from_utf8_lossy(serializer.get_text())
+        );
+    }
+}
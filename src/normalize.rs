@@ -0,0 +1,143 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::borrow::Cow;
+
+fn is_clean(path: &str) -> bool {
+    if path.is_empty() {
+        return true;
+    }
+
+    let mut segments = path.split('/');
+
+    /* The empty segment before the root of an absolute path */
+    if path.starts_with('/') {
+        segments.next();
+    }
+
+    segments.all(|x| !matches!(x, "" | "." | ".."))
+}
+
+/*
+ * Resolve the "." and ".." segments of "path" and collapse repeated
+ * separators without accessing the file system. A ".." segment removes the
+ * preceding segment, even if it is a symbolic link to another directory,
+ * and is kept if there is none in a relative path. A trailing separator is
+ * removed, too.
+ */
+pub fn normalize(path: &str) -> Cow<'_, str> {
+    if is_clean(path) {
+        return Cow::Borrowed(path);
+    }
+
+    let absolute = path.starts_with('/');
+    let mut segments: Vec<&str> = Vec::new();
+
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => match segments.last() {
+                Some(&last) if last != ".." => {
+                    segments.pop();
+                }
+                _ if !absolute => segments.push(".."),
+                _ => {}
+            },
+            _ => segments.push(segment),
+        }
+    }
+
+    let result = segments.join("/");
+
+    match (absolute, result.is_empty()) {
+        (true, _) => Cow::Owned(format!("/{result}")),
+        (false, true) => Cow::Borrowed("."),
+        (false, false) => Cow::Owned(result),
+    }
+}
+
+/*
+ * Resolve the relative "path" against the directory "base". Absolute and
+ * empty paths are kept. The result is not normalized.
+ */
+pub fn join<'a>(base: &str, path: &'a str) -> Cow<'a, str> {
+    if path.is_empty() || path.starts_with('/') || base.is_empty() {
+        return Cow::Borrowed(path);
+    }
+
+    let path = path.strip_prefix("./").unwrap_or(path);
+
+    match base.trim_end_matches('/') {
+        "" => Cow::Owned(format!("/{path}")),
+        base => Cow::Owned(format!("{base}/{path}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * normalize()
+     *
+     * Verify that the function resolves the "." and ".." segments and
+     * collapses repeated separators.
+     */
+    #[test]
+    fn normalize_001() {
+        let tests = [
+            ("a/b.h", "a/b.h"),
+            ("./a/b.h", "a/b.h"),
+            ("a/./b.h", "a/b.h"),
+            ("a/c/../b.h", "a/b.h"),
+            ("a//b.h", "a/b.h"),
+            ("a/b/", "a/b"),
+            ("../a/../../b.h", "../../b.h"),
+            ("a/..", "."),
+            ("/usr/../../include//stdio.h", "/include/stdio.h"),
+            ("/", "/"),
+            ("", ""),
+        ];
+
+        for (path, expected) in tests {
+            assert_eq!(expected, normalize(path), "{path}");
+        }
+    }
+
+    /**
+     * join()
+     *
+     * Verify that only relative paths are resolved against the base
+     * directory.
+     */
+    #[test]
+    fn join_001() {
+        let tests = [
+            ("/src/a", "a.h", "/src/a/a.h"),
+            ("/src/a/", "./inc/a.h", "/src/a/inc/a.h"),
+            ("/src/a", "../b/b.h", "/src/a/../b/b.h"),
+            ("/src/a", "/usr/include/stdio.h", "/usr/include/stdio.h"),
+            ("/", "a.h", "/a.h"),
+            ("build", "a.o", "build/a.o"),
+            ("/src/a", "", ""),
+        ];
+
+        for (base, path, expected) in tests {
+            assert_eq!(expected, join(base, path), "{base} {path}");
+        }
+    }
+}
@@ -0,0 +1,130 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::str;
+
+use crate::json::JsonParser;
+use crate::separators;
+
+/*
+ * Write each path followed by a NUL character for "--print0". The paths
+ * are unescaped, so tools like "xargs -0" get the actual file names.
+ */
+pub fn records(paths: &[&str]) -> Vec<u8> {
+    let mut data = Vec::new();
+
+    for path in paths {
+        data.extend_from_slice(separators::unescape(path).as_bytes());
+        data.push(0);
+    }
+
+    data
+}
+
+/*
+ * Check whether a newline has to be written after "data" on stdout. Text
+ * which ends with a newline, e.g. of a template, is kept as it is, and a
+ * newline after records separated by NUL characters would be another
+ * record, e.g. for "xargs -0".
+ */
+pub fn needs_newline(data: &[u8]) -> bool {
+    !data.ends_with(b"\n") && !data.ends_with(b"\0")
+}
+
+/*
+ * Check whether "data" looks like the output of dep2j in any of its
+ * formats. An empty file is treated as generated output, too.
+ */
+pub fn is_generated(data: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(data);
+    let first = text.lines().next().unwrap_or_default();
+
+    /* Paths written by "--print0" */
+    let records = data.ends_with(b"\0") && str::from_utf8(data).is_ok();
+
+    text.trim().is_empty()
+        || records
+        || text.starts_with("<!DOCTYPE html>")
+        || text.starts_with("@startuml\n")
+        || text.starts_with("digraph dependencies {\n")
+        || text.starts_with("target,prerequisite\r\n")
+        || first.contains(": #deps ")
+        || JsonParser::new(data).parse().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * needs_newline()
+     *
+     * Verify that a newline is only needed if the output does not end
+     * with a newline or a NUL character.
+     */
+    #[test]
+    fn needs_newline_001() {
+        assert!(needs_newline(b""));
+        assert!(needs_newline(b"[]"));
+        assert!(!needs_newline(b"a.o: a.c\n"));
+        assert!(!needs_newline(b"a.o\0"));
+    }
+
+    /**
+     * records()
+     *
+     * Verify that each path is terminated by a NUL character and that
+     * escaped spaces are written without the backslash.
+     */
+    #[test]
+    fn records_001() {
+        assert_eq!(b"".as_slice(), records(&[]));
+        assert_eq!(
+            b"a.o\0x y.o\0c\\d.o\0".as_slice(),
+            records(&["a.o", "x\\ y.o", "c\\d.o"])
+        );
+    }
+
+    /**
+     * is_generated()
+     *
+     * Verify that the output formats of dep2j are recognized and that
+     * other files are not.
+     */
+    #[test]
+    fn is_generated_001() {
+        let tests = [
+            ("", true),
+            ("[{\"target\":\"a.o\",\"prerequisites\":[]}]\n", true),
+            ("{\"elements\":{\"nodes\":[],\"edges\":[]}}", true),
+            ("<!DOCTYPE html>\n<html>\n", true),
+            ("@startuml\n\"a.o\" --> \"a.c\"\n@enduml\n", true),
+            ("a.o: #deps 1, deps mtime 0 (STALE)\n    a.c\n\n", true),
+            ("digraph dependencies {\n    \"a.o\";\n}\n", true),
+            ("target,prerequisite\r\na.o,a.c\r\n", true),
+            ("a.o\0my dir/b.o\0", true),
+            ("digraph G {\n}\n", false),
+            ("a.o: a.c a.h\n", false),
+            ("all:\n\tcc -o app main.c\n", false),
+            ("# Notes\n", false),
+        ];
+
+        for (data, expected) in tests {
+            assert_eq!(expected, is_generated(data.as_bytes()), "{data}");
+        }
+    }
+}
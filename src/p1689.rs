@@ -0,0 +1,145 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+
+use crate::dependency::DependencyBuf;
+use crate::json::{Attributes, JsonParser, Value};
+
+/*
+ * Read the module dependencies of C++ sources in the format of P1689R5,
+ * e.g. as written by "clang-scan-deps -format=p1689". The primary output
+ * of each rule becomes the target. The logical names of the modules which
+ * the rule provides and requires become its prerequisites and are tagged
+ * in "tags" with the attribute "module". A module which is provided by
+ * any rule is tagged as "provides", otherwise as "requires".
+ */
+pub fn parse_dependencies(
+    data: &[u8],
+    tags: &mut HashMap<String, Attributes>,
+) -> Result<Vec<DependencyBuf>, String> {
+    let value = JsonParser::new(data).parse()?;
+
+    let rules = value
+        .get("rules")
+        .and_then(Value::as_array)
+        .ok_or_else(|| String::from("expected an array of rules"))?;
+
+    let mut result = Vec::with_capacity(rules.len());
+
+    for (i, rule) in rules.iter().enumerate() {
+        let invalid = |key| format!("rule {i}: invalid \"{key}\"");
+
+        let outputs = rule.get("outputs").and_then(Value::as_array);
+        let target = rule
+            .get("primary-output")
+            .or_else(|| outputs.and_then(|x| x.first()))
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid("primary-output"))?;
+
+        let mut prerequisites = Vec::new();
+
+        for kind in ["provides", "requires"] {
+            let modules = match rule.get(kind) {
+                Some(value) => value.as_array().ok_or_else(|| invalid(kind))?,
+                None => continue,
+            };
+
+            for module in modules {
+                let name = module
+                    .get("logical-name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| invalid("logical-name"))?;
+
+                if kind == "provides" || !tags.contains_key(name) {
+                    let tag = Value::String(String::from(kind));
+                    tags.insert(
+                        String::from(name),
+                        Vec::from([("module", tag)]),
+                    );
+                }
+
+                prerequisites.push(String::from(name));
+            }
+        }
+
+        result.push(DependencyBuf {
+            target: String::from(target),
+            prerequisites,
+        });
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * parse_dependencies()
+     *
+     * Verify that the provided and required modules become tagged
+     * prerequisites of the primary output.
+     */
+    #[test]
+    fn parse_dependencies_001() {
+        let data = r#"{
+            "revision": 0,
+            "version": 1,
+            "rules": [
+                {
+                    "primary-output": "main.o",
+                    "requires": [
+                        { "logical-name": "M" },
+                        { "logical-name": "std" }
+                    ]
+                },
+                {
+                    "outputs": ["m.o"],
+                    "provides": [
+                        {
+                            "logical-name": "M",
+                            "source-path": "m.cppm",
+                            "is-interface": true
+                        }
+                    ],
+                    "requires": [{ "logical-name": "M:part" }]
+                }
+            ]
+        }"#;
+
+        let mut tags = HashMap::new();
+        let deps = parse_dependencies(data.as_bytes(), &mut tags).unwrap();
+
+        assert_eq!(2, deps.len());
+        assert_eq!("main.o", deps[0].target);
+        assert_eq!(Vec::from(["M", "std"]), deps[0].prerequisites);
+        assert_eq!("m.o", deps[1].target);
+        assert_eq!(Vec::from(["M", "M:part"]), deps[1].prerequisites);
+
+        let tag = |name: &str| tags[name][0].1.as_str().map(String::from);
+
+        assert_eq!(3, tags.len());
+        assert_eq!(Some(String::from("provides")), tag("M"));
+        assert_eq!(Some(String::from("requires")), tag("std"));
+        assert_eq!(Some(String::from("requires")), tag("M:part"));
+
+        let data = r#"{ "rules": [{ "requires": [] }] }"#;
+        assert!(parse_dependencies(data.as_bytes(), &mut tags).is_err());
+    }
+}
@@ -0,0 +1,404 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+use std::env;
+use std::io;
+use std::process::Command;
+
+use crate::classify::Classifier;
+use crate::dependency::Dependency;
+use crate::json::{Attributes, Value};
+
+/*
+ * The number of paths passed to a single query, which keeps the command
+ * line well below the limits of the system.
+ */
+const BATCH_SIZE: usize = 256;
+
+/*
+ * The package database which is asked for the owner of a file. "dpkg" and
+ * "rpm" know each installed file, "pkg-config" only the include
+ * directories of the packages with a ".pc" file.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Dpkg,
+    Rpm,
+    PkgConfig,
+}
+
+const BACKENDS: &[(&str, Backend)] = &[
+    ("dpkg", Backend::Dpkg),
+    ("rpm", Backend::Rpm),
+    ("pkg-config", Backend::PkgConfig),
+];
+
+impl Backend {
+    pub fn parse(name: &str) -> Option<Self> {
+        BACKENDS
+            .iter()
+            .find(|(x, _)| *x == name)
+            .map(|(_, backend)| *backend)
+    }
+
+    /*
+     * Return the first backend whose program is found in "PATH".
+     */
+    pub fn detect() -> Option<Self> {
+        let path = env::var_os("PATH")?;
+
+        BACKENDS
+            .iter()
+            .find(|(name, _)| {
+                env::split_paths(&path).any(|dir| dir.join(name).is_file())
+            })
+            .map(|(_, backend)| *backend)
+    }
+}
+
+/*
+ * Parse the output of "dpkg -S", e.g. "libc6-dev:amd64: /usr/include/
+ * stdio.h". A file owned by several packages is attributed to the first
+ * one. The lines of diversions are skipped.
+ */
+fn parse_dpkg(output: &str) -> HashMap<String, String> {
+    let mut result = HashMap::new();
+
+    for line in output.lines() {
+        if line.starts_with("diversion by ") {
+            continue;
+        }
+
+        if let Some((packages, path)) = line.split_once(": ") {
+            let package = packages.split(", ").next().unwrap_or_default();
+
+            result
+                .entry(String::from(path))
+                .or_insert_with(|| String::from(package));
+        }
+    }
+
+    result
+}
+
+/*
+ * Parse the output of "rpm -qf --queryformat '%{NAME}\n'", which has one
+ * line for each of "paths" in order. Nothing is returned if the lines do
+ * not match the paths, e.g. for a file owned by several packages.
+ */
+fn parse_rpm(paths: &[&str], output: &str) -> Option<HashMap<String, String>> {
+    let lines: Vec<&str> = output.lines().collect();
+
+    if lines.len() != paths.len() {
+        return None;
+    }
+
+    let result = paths
+        .iter()
+        .zip(lines)
+        .filter(|(_, line)| !line.ends_with(" is not owned by any package"))
+        .filter(|(_, line)| !line.starts_with("error: "))
+        .map(|(path, line)| (String::from(*path), String::from(line)))
+        .collect();
+
+    Some(result)
+}
+
+/*
+ * Return the include directories of the output of "pkg-config
+ * --cflags-only-I".
+ */
+fn parse_include_dirs(output: &str) -> Vec<String> {
+    output
+        .split_whitespace()
+        .filter_map(|x| x.strip_prefix("-I"))
+        .map(|x| String::from(x.trim_end_matches('/')))
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+/*
+ * Return the package whose include directory is the longest prefix of
+ * "path".
+ */
+fn find_owner<'a>(dirs: &'a [(String, String)], path: &str) -> Option<&'a str> {
+    dirs.iter()
+        .filter(|(dir, _)| match path.strip_prefix(dir.as_str()) {
+            Some(rest) => rest.starts_with('/'),
+            None => false,
+        })
+        .max_by_key(|(dir, _)| dir.len())
+        .map(|(_, package)| package.as_str())
+}
+
+/*
+ * Run "program" and return its output. The exit status is ignored, since
+ * a query fails if only one of the files has no owner.
+ */
+fn run(program: &str, args: &[&str]) -> io::Result<String> {
+    let output = Command::new(program).args(args).output().map_err(|err| {
+        let msg = format!("failed to run \"{program}\": {err}");
+        io::Error::new(err.kind(), msg)
+    })?;
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn query_dpkg(paths: &[&str]) -> io::Result<HashMap<String, String>> {
+    let args = [&["-S"], paths].concat();
+
+    run("dpkg", &args).map(|x| parse_dpkg(&x))
+}
+
+fn query_rpm(paths: &[&str]) -> io::Result<HashMap<String, String>> {
+    const QUERY: &[&str] = &["-qf", "--queryformat", "%{NAME}\n"];
+
+    let args = [QUERY, paths].concat();
+
+    if let Some(result) = parse_rpm(paths, &run("rpm", &args)?) {
+        return Ok(result);
+    }
+
+    /* Query each file on its own to keep the owners apart */
+    let mut result = HashMap::new();
+
+    for path in paths {
+        let output = run("rpm", &[QUERY, &[path]].concat())?;
+        let line = output.lines().next().unwrap_or_default();
+
+        result.extend(parse_rpm(&[path], line).unwrap_or_default());
+    }
+
+    Ok(result)
+}
+
+/*
+ * Find the packages which own the system files. Each file is only looked
+ * up once and the files are queried in batches, since starting the
+ * package manager takes much longer than a lookup.
+ */
+pub struct PackageResolver {
+    backend: Backend,
+    cache: HashMap<String, Option<String>>,
+    include_dirs: Option<Vec<(String, String)>>,
+}
+
+impl PackageResolver {
+    pub fn new(backend: Backend) -> Self {
+        Self {
+            backend,
+            cache: HashMap::new(),
+            include_dirs: None,
+        }
+    }
+
+    /*
+     * Look up the owners of the "paths" which are not cached yet.
+     */
+    pub fn resolve(&mut self, paths: &[&str]) -> io::Result<()> {
+        let mut paths: Vec<&str> = paths
+            .iter()
+            .filter(|x| !self.cache.contains_key(**x))
+            .copied()
+            .collect();
+
+        paths.sort_unstable();
+        paths.dedup();
+
+        for batch in paths.chunks(BATCH_SIZE) {
+            let mut owners = match self.backend {
+                Backend::Dpkg => query_dpkg(batch)?,
+                Backend::Rpm => query_rpm(batch)?,
+                Backend::PkgConfig => self.query_pkg_config(batch)?,
+            };
+
+            for path in batch {
+                self.cache.insert(String::from(*path), owners.remove(*path));
+            }
+        }
+
+        Ok(())
+    }
+
+    /*
+     * Return the package which owns "path", or "None" if there is none.
+     */
+    pub fn owner(&mut self, path: &str) -> io::Result<Option<&str>> {
+        self.resolve(&[path])?;
+
+        Ok(self.cache.get(path).and_then(|x| x.as_deref()))
+    }
+
+    /*
+     * Ask for the include directories of all packages known to pkg-config
+     * once, instead of for each file.
+     */
+    fn query_pkg_config(
+        &mut self,
+        paths: &[&str],
+    ) -> io::Result<HashMap<String, String>> {
+        if self.include_dirs.is_none() {
+            let list = run("pkg-config", &["--list-all"])?;
+            let mut dirs = Vec::new();
+
+            for line in list.lines() {
+                let Some(package) = line.split_whitespace().next() else {
+                    continue;
+                };
+
+                let output = run("pkg-config", &["--cflags-only-I", package])?;
+
+                for dir in parse_include_dirs(&output) {
+                    dirs.push((dir, String::from(package)));
+                }
+            }
+
+            self.include_dirs = Some(dirs);
+        }
+
+        let dirs = self.include_dirs.as_deref().unwrap_or_default();
+        let result = paths
+            .iter()
+            .filter_map(|path| {
+                let owner = find_owner(dirs, path)?;
+                Some((String::from(*path), String::from(owner)))
+            })
+            .collect();
+
+        Ok(result)
+    }
+
+    /*
+     * The "package" of each system prerequisite, which is null if no
+     * package owns it. Project files are not looked up.
+     */
+    pub fn attributes(
+        &mut self,
+        deps: &[Dependency],
+        classifier: &Classifier,
+    ) -> io::Result<HashMap<String, Attributes>> {
+        let paths: Vec<&str> = deps
+            .iter()
+            .flat_map(|dep| &dep.prerequisites)
+            .filter(|x| classifier.is_system(x))
+            .copied()
+            .collect();
+
+        self.resolve(&paths)?;
+
+        let mut result = HashMap::new();
+
+        for path in paths {
+            let package = match self.owner(path)? {
+                Some(val) => Value::String(String::from(val)),
+                None => Value::Null,
+            };
+
+            result
+                .insert(String::from(path), Vec::from([("package", package)]));
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * parse_dpkg()
+     *
+     * Verify that the owner of each file is found, that the first of
+     * several owners is taken, and that diversions are skipped.
+     */
+    #[test]
+    fn parse_dpkg_001() {
+        let output = "\
+libc6-dev:amd64: /usr/include/stdio.h
+diversion by dash from: /bin/sh
+linux-libc-dev:amd64, linux-libc-dev:i386: /usr/include/linux/types.h
+";
+        let result = parse_dpkg(output);
+
+        assert_eq!(2, result.len());
+        assert_eq!("libc6-dev:amd64", result["/usr/include/stdio.h"]);
+        assert_eq!(
+            "linux-libc-dev:amd64",
+            result["/usr/include/linux/types.h"]
+        );
+    }
+
+    /**
+     * parse_rpm()
+     *
+     * Verify that the lines are matched with the paths in order and that
+     * files without an owner are left out.
+     */
+    #[test]
+    fn parse_rpm_001() {
+        let paths = ["/usr/include/stdio.h", "/opt/a.h", "/usr/include/zlib.h"];
+        let output = "\
+glibc-headers
+file /opt/a.h is not owned by any package
+zlib-devel
+";
+        let result = parse_rpm(&paths, output).unwrap();
+
+        assert_eq!(2, result.len());
+        assert_eq!("glibc-headers", result["/usr/include/stdio.h"]);
+        assert_eq!("zlib-devel", result["/usr/include/zlib.h"]);
+
+        assert_eq!(None, parse_rpm(&paths, "glibc-headers\n"));
+    }
+
+    /**
+     * find_owner()
+     *
+     * Verify that the package with the most specific include directory
+     * owns a file.
+     */
+    #[test]
+    fn find_owner_001() {
+        let mut dirs = Vec::new();
+
+        for (output, package) in [
+            (
+                "-I/usr/include/glib-2.0 -I/usr/lib/glib-2.0/include ",
+                "glib-2.0",
+            ),
+            ("-I/usr/include/glib-2.0/gio/ -DX", "gio-2.0"),
+        ] {
+            for dir in parse_include_dirs(output) {
+                dirs.push((dir, String::from(package)));
+            }
+        }
+
+        let tests = [
+            ("/usr/include/glib-2.0/glib.h", Some("glib-2.0")),
+            ("/usr/lib/glib-2.0/include/glibconfig.h", Some("glib-2.0")),
+            ("/usr/include/glib-2.0/gio/gio.h", Some("gio-2.0")),
+            ("/usr/include/glib-2.0x/a.h", None),
+            ("/usr/include/stdio.h", None),
+        ];
+
+        for (path, expected) in tests {
+            assert_eq!(expected, find_owner(&dirs, path), "{path}");
+        }
+    }
+}
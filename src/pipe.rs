@@ -0,0 +1,96 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::io::{self, ErrorKind};
+use std::process::{ChildStdin, Command, ExitStatus, Stdio};
+
+fn shell(command: &str) -> Command {
+    let mut result = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+
+    result.arg(command);
+    result
+}
+
+/*
+ * Run "command" with the shell and pass its stdin to "write". A command
+ * which stops reading its input early, e.g. "head", is not an error, so a
+ * broken pipe is ignored. The caller decides what to do with the exit
+ * status of the command.
+ */
+pub fn run<F>(command: &str, write: F) -> io::Result<ExitStatus>
+where
+    F: FnOnce(&mut ChildStdin) -> io::Result<()>,
+{
+    feed(&mut shell(command), write)
+}
+
+/*
+ * Like "run()", but for a program which is not run by the shell.
+ */
+pub fn feed<F>(command: &mut Command, write: F) -> io::Result<ExitStatus>
+where
+    F: FnOnce(&mut ChildStdin) -> io::Result<()>,
+{
+    let mut child = command.stdin(Stdio::piped()).spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+
+    let result = write(&mut stdin);
+
+    /* Close the pipe, so the command sees the end of its input */
+    drop(stdin);
+
+    let status = child.wait()?;
+
+    match result {
+        Err(err) if err.kind() != ErrorKind::BrokenPipe => Err(err),
+        _ => Ok(status),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    /**
+     * run()
+     *
+     * Verify that the output is passed to the command, that a broken
+     * pipe is ignored, and that the exit status is returned.
+     */
+    #[test]
+    fn run_001() {
+        let status = run("grep -q b", |x| x.write_all(b"a\nb\nc\n")).unwrap();
+        assert!(status.success());
+
+        let data = vec![b'x'; 1 << 20];
+        let status = run("head -c 1 >/dev/null", |x| x.write_all(&data));
+        assert!(status.unwrap().success());
+
+        let status = run("cat >/dev/null; exit 3", |x| x.write_all(b"a"));
+        assert_eq!(Some(3), status.unwrap().code());
+    }
+}
@@ -0,0 +1,156 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::graph::Graph;
+
+/*
+ * Generate a PlantUML component diagram. Paths are not valid PlantUML
+ * identifiers, so every node is declared as a component with a quoted
+ * name and a generated alias which is used for the edges.
+ */
+pub struct PlantUmlSerializer {
+    buf: Vec<u8>,
+}
+
+impl PlantUmlSerializer {
+    pub fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    pub fn get_plantuml(&self) -> &[u8] {
+        self.buf.as_slice()
+    }
+
+    pub fn write_graph(&mut self, graph: &Graph) {
+        let nodes = graph.nodes();
+
+        self.buf.reserve(64 * nodes.len());
+        self.buf.extend_from_slice(b"@startuml\n");
+
+        for (id, node) in nodes.iter().enumerate() {
+            self.buf.extend_from_slice(b"component ");
+            self.write_str(node);
+            self.buf.extend_from_slice(b" as ");
+            self.write_alias(id);
+            self.buf.push(b'\n');
+        }
+
+        for (id, edges) in graph.edges().iter().enumerate() {
+            if edges.is_empty() {
+                continue;
+            }
+
+            self.buf.extend_from_slice(b"\n' ");
+            self.buf.extend_from_slice(nodes[id].as_bytes());
+            self.buf.push(b'\n');
+
+            for &prereq in edges {
+                self.write_alias(id);
+                self.buf.extend_from_slice(b" --> ");
+                self.write_alias(prereq);
+                self.buf.push(b'\n');
+            }
+        }
+
+        self.buf.extend_from_slice(b"@enduml\n");
+    }
+
+    fn write_alias(&mut self, id: usize) {
+        self.buf.push(b'n');
+        self.buf.extend_from_slice(id.to_string().as_bytes());
+    }
+
+    /*
+     * PlantUML does not support escape sequences within quoted names.
+     * Characters which would end the name or start an escape sequence
+     * are written as unicode code points instead.
+     */
+    fn write_str(&mut self, data: &str) {
+        self.buf.push(b'"');
+
+        for &byte in data.as_bytes() {
+            match byte {
+                b'"' => self.buf.extend_from_slice(b"<U+0022>"),
+                b'\\' => self.buf.extend_from_slice(b"<U+005C>"),
+                _ => self.buf.push(byte),
+            }
+        }
+
+        self.buf.push(b'"');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dependency::Dependency;
+
+    /**
+     * PlantUmlSerializer::write_graph()
+     *
+     * Verify that each node is declared as a component and that the edges
+     * of each target are grouped below it.
+     */
+    #[test]
+    fn write_graph_001() {
+        let vec = Vec::from([
+            Dependency {
+                target: "a.o",
+                prerequisites: Vec::from(["a.c", "b.h"]),
+            },
+            Dependency {
+                target: "b.h",
+                prerequisites: Vec::new(),
+            },
+        ]);
+
+        let graph = Graph::new(&vec);
+
+        let mut serializer = PlantUmlSerializer::new();
+        serializer.write_graph(&graph);
+
+        assert_eq!(
+            "@startuml\n\
+            component \"a.o\" as n0\n\
+            component \"a.c\" as n1\n\
+            component \"b.h\" as n2\n\
+            \n\
+            ' a.o\n\
+            n0 --> n1\n\
+            n0 --> n2\n\
+            @enduml\n",
+            String::from_utf8_lossy(serializer.get_plantuml())
+        );
+    }
+
+    /**
+     * PlantUmlSerializer::write_str()
+     *
+     * Verify that quotes and backslashes are replaced with their Unicode
+     * code points.
+     */
+    #[test]
+    fn write_str_001() {
+        let mut serializer = PlantUmlSerializer::new();
+        serializer.write_str("a \"b\"\\c");
+
+        assert_eq!(
+            b"\"a <U+0022>b<U+0022><U+005C>c\"",
+            serializer.buf.as_slice()
+        );
+    }
+}
@@ -0,0 +1,277 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dependency::Dependency;
+
+/*
+ * Match "path" against a glob "pattern". A '*' matches any sequence of
+ * characters except '/', "**" matches across directories, and '?' matches
+ * a single character except '/'. A pattern ending with '/' matches all
+ * files within the directory.
+ */
+fn glob_match(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'/'] => path.first() == Some(&b'/') && path.len() > 1,
+        [b'*', b'*', rest @ ..] => {
+            let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+
+            if rest.is_empty() {
+                return true;
+            }
+
+            (0..=path.len()).any(|i| {
+                (i == 0 || path[i - 1] == b'/') && glob_match(rest, &path[i..])
+            })
+        }
+        [b'*', rest @ ..] => {
+            let end =
+                path.iter().position(|&x| x == b'/').unwrap_or(path.len());
+
+            (0..=end).any(|i| glob_match(rest, &path[i..]))
+        }
+        [b'?', rest @ ..] => match path {
+            [c, tail @ ..] if *c != b'/' => glob_match(rest, tail),
+            _ => false,
+        },
+        [c, rest @ ..] => match path {
+            [x, tail @ ..] if x == c => glob_match(rest, tail),
+            _ => false,
+        },
+    }
+}
+
+fn matches(pattern: &str, path: &str) -> bool {
+    let path = path.strip_prefix("./").unwrap_or(path);
+
+    glob_match(pattern.as_bytes(), path.as_bytes())
+}
+
+struct Rule {
+    allow: bool,
+    target: String,
+    prereq: String,
+    line: usize,
+    text: String,
+}
+
+pub struct Violation<'a> {
+    pub target: &'a str,
+    pub prereq: &'a str,
+    pub line: usize,
+    pub rule: &'a str,
+}
+
+/*
+ * Restrict which targets may depend on which prerequisites. Each line of
+ * a policy is a rule "allow <target> -> <prerequisite>" or "deny <target>
+ * -> <prerequisite>" with glob patterns for the paths. The last rule which
+ * matches a pair of target and prerequisite decides whether the pair is
+ * allowed. Pairs without a matching rule are allowed. Empty lines and
+ * lines starting with '#' are ignored.
+ */
+pub struct Policy {
+    rules: Vec<Rule>,
+}
+
+impl Policy {
+    pub fn parse(data: &str) -> Result<Self, String> {
+        let mut rules = Vec::new();
+
+        for (i, line) in data.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || format!("line {}: invalid rule \"{line}\"", i + 1);
+
+            let (action, rest) =
+                line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+            let (target, prereq) = rest.split_once("->").ok_or_else(invalid)?;
+            let (target, prereq) = (target.trim(), prereq.trim());
+
+            let allow = match action {
+                "allow" => true,
+                "deny" => false,
+                _ => return Err(invalid()),
+            };
+
+            if target.is_empty() || prereq.is_empty() {
+                return Err(invalid());
+            }
+
+            rules.push(Rule {
+                allow,
+                target: String::from(target),
+                prereq: String::from(prereq),
+                line: i + 1,
+                text: String::from(line),
+            });
+        }
+
+        Ok(Self { rules })
+    }
+
+    /*
+     * The line and the text of each rule which denies dependencies.
+     */
+    pub fn deny_rules(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.rules
+            .iter()
+            .filter(|rule| !rule.allow)
+            .map(|rule| (rule.line, rule.text.as_str()))
+    }
+
+    pub fn check<'a>(&'a self, deps: &[Dependency<'a>]) -> Vec<Violation<'a>> {
+        let mut result = Vec::new();
+
+        for dep in deps {
+            let rules: Vec<_> = self
+                .rules
+                .iter()
+                .filter(|rule| matches(&rule.target, dep.target))
+                .collect();
+
+            if rules.is_empty() {
+                continue;
+            }
+
+            for prereq in &dep.prerequisites {
+                let rule = rules
+                    .iter()
+                    .rev()
+                    .find(|rule| matches(&rule.prereq, prereq));
+
+                if let Some(rule) = rule.filter(|rule| !rule.allow) {
+                    result.push(Violation {
+                        target: dep.target,
+                        prereq,
+                        line: rule.line,
+                        rule: &rule.text,
+                    });
+                }
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * glob_match()
+     *
+     * Verify that the wildcards do not match across directories, except
+     * for "**".
+     */
+    #[test]
+    fn glob_match_001() {
+        let tests = [
+            ("src/net/", "src/net/a.o", true),
+            ("src/net/", "src/net/x/a.o", true),
+            ("src/net/", "src/network/a.o", false),
+            ("src/*.o", "src/a.o", true),
+            ("src/*.o", "src/net/a.o", false),
+            ("src/**/*.h", "src/a.h", true),
+            ("src/**/*.h", "src/ui/w/b.h", true),
+            ("**/ui/*", "src/ui/b.h", true),
+            ("**/ui/*", "src/gui/b.h", false),
+            ("src/**", "src/a/b.c", true),
+            ("a?.c", "ab.c", true),
+            ("a?.c", "a/.c", false),
+        ];
+
+        for (pattern, path, expected) in tests {
+            let result = glob_match(pattern.as_bytes(), path.as_bytes());
+            assert_eq!(expected, result, "{pattern} {path}");
+        }
+    }
+
+    /**
+     * Policy::check()
+     *
+     * Verify that the last matching rule decides whether a dependency is
+     * allowed.
+     */
+    #[test]
+    fn check_001() {
+        let data = "\
+# The network code must not use the UI
+deny src/net/ -> src/ui/
+allow src/net/bridge.o -> src/ui/api.h
+
+deny ** -> **/internal/*
+";
+
+        let policy = Policy::parse(data).unwrap();
+
+        let deps = Vec::from([
+            Dependency {
+                target: "src/net/a.o",
+                prerequisites: Vec::from(["src/net/a.c", "./src/ui/w.h"]),
+            },
+            Dependency {
+                target: "src/net/bridge.o",
+                prerequisites: Vec::from(["src/ui/api.h", "src/ui/w.h"]),
+            },
+            Dependency {
+                target: "src/ui/w.o",
+                prerequisites: Vec::from(["src/net/a.h", "lib/internal/x.h"]),
+            },
+        ]);
+
+        let result: Vec<_> = policy
+            .check(&deps)
+            .iter()
+            .map(|x| (x.target, x.prereq, x.line))
+            .collect();
+
+        assert_eq!(
+            Vec::from([
+                ("src/net/a.o", "./src/ui/w.h", 2),
+                ("src/net/bridge.o", "src/ui/w.h", 2),
+                ("src/ui/w.o", "lib/internal/x.h", 5),
+            ]),
+            result
+        );
+
+        assert_eq!(2, policy.deny_rules().count());
+    }
+
+    /**
+     * Policy::parse()
+     *
+     * Verify that invalid rules are rejected with their line.
+     */
+    #[test]
+    fn parse_001() {
+        let result = Policy::parse("deny a -> b\nforbid a -> b\n").map(|_| ());
+        assert_eq!(
+            Err(String::from("line 2: invalid rule \"forbid a -> b\"")),
+            result
+        );
+
+        for data in ["deny a b", "deny -> b", "allow a ->", "deny"] {
+            assert!(Policy::parse(data).is_err(), "{data}");
+        }
+    }
+}
@@ -0,0 +1,115 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::borrow::Cow;
+
+/*
+ * Replace the prefixes of paths like the "-ffile-prefix-map=<old>=<new>"
+ * option of GCC and Clang. As with the compilers, the prefixes are
+ * matched as plain strings and the last matching mapping wins.
+ */
+pub struct PrefixMap {
+    maps: Vec<(String, String)>,
+}
+
+impl PrefixMap {
+    /*
+     * Read the mappings from "data" with one "<old>=<new>" pair per line.
+     * Empty lines and lines starting with '#' are ignored.
+     */
+    pub fn parse(data: &str) -> Result<Self, String> {
+        let mut maps = Vec::new();
+
+        for (i, line) in data.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (old, new) = line
+                .split_once('=')
+                .filter(|(old, _)| !old.is_empty())
+                .ok_or_else(|| format!("line {}: invalid mapping", i + 1))?;
+
+            maps.push((String::from(old), String::from(new)));
+        }
+
+        Ok(Self { maps })
+    }
+
+    pub fn apply<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        let map = self
+            .maps
+            .iter()
+            .rev()
+            .find_map(|(old, new)| Some((new, path.strip_prefix(old)?)));
+
+        match map {
+            Some((new, rest)) => Cow::Owned(format!("{new}{rest}")),
+            None => Cow::Borrowed(path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * PrefixMap::apply()
+     *
+     * Verify that the last matching mapping replaces the prefix.
+     */
+    #[test]
+    fn apply_001() {
+        let data = "\
+# Reproducible builds
+/home/ci/src=.
+/home/ci/src/third_party=/opt/vendor
+
+/usr/include=
+";
+
+        let map = PrefixMap::parse(data).unwrap();
+
+        let tests = [
+            ("/home/ci/src/main.c", "./main.c"),
+            ("/home/ci/src/third_party/z.h", "/opt/vendor/z.h"),
+            ("/usr/include/stdio.h", "/stdio.h"),
+            ("/home/ci/other/a.h", "/home/ci/other/a.h"),
+        ];
+
+        for (path, expected) in tests {
+            assert_eq!(expected, map.apply(path));
+        }
+    }
+
+    /**
+     * PrefixMap::parse()
+     *
+     * Verify that invalid mappings are rejected with their line.
+     */
+    #[test]
+    fn parse_001() {
+        let result = PrefixMap::parse("a=b\n\n/src\n").map(|_| ());
+        assert_eq!(Err(String::from("line 3: invalid mapping")), result);
+
+        assert!(PrefixMap::parse("=b\n").is_err());
+        assert!(PrefixMap::parse("a==b\n").is_ok());
+    }
+}
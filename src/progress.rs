@@ -0,0 +1,156 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/* Do not show the progress for runs which finish quickly */
+const DELAY: Duration = Duration::from_millis(500);
+const INTERVAL: Duration = Duration::from_millis(100);
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+fn format_line(
+    done: usize,
+    total: usize,
+    bytes: u64,
+    eta: Option<u64>,
+) -> String {
+    let size = format_size(bytes);
+
+    match eta {
+        Some(secs) => {
+            let (min, sec) = (secs / 60, secs % 60);
+            format!("{done}/{total} files, {size}, ETA {min}:{sec:02}")
+        }
+        None => format!("{done}/{total} files, {size}"),
+    }
+}
+
+/*
+ * Report the number of processed input files, the number of parsed bytes,
+ * and the estimated remaining time on a single line of stderr. The
+ * estimate is based on the sizes of the input files.
+ */
+pub struct Progress {
+    total: usize,
+    total_bytes: u64,
+    done: usize,
+    bytes: u64,
+    start: Instant,
+    last: Option<Instant>,
+    enabled: bool,
+}
+
+impl Progress {
+    pub fn new(total: usize, total_bytes: u64, enabled: bool) -> Self {
+        Self {
+            total,
+            total_bytes,
+            done: 0,
+            bytes: 0,
+            start: Instant::now(),
+            last: None,
+            enabled,
+        }
+    }
+
+    /*
+     * Record an input file of "bytes" as processed.
+     */
+    pub fn update(&mut self, bytes: u64) {
+        self.done += 1;
+        self.bytes += bytes;
+
+        if !self.enabled {
+            return;
+        }
+
+        let now = Instant::now();
+        let elapsed = now - self.start;
+
+        if elapsed < DELAY || self.last.is_some_and(|x| now - x < INTERVAL) {
+            return;
+        }
+
+        self.last = Some(now);
+
+        let remaining = self.total_bytes.saturating_sub(self.bytes);
+        let eta = (self.bytes != 0).then(|| {
+            let rate = self.bytes as f64 / elapsed.as_secs_f64();
+            (remaining as f64 / rate).round() as u64
+        });
+
+        let line = format_line(self.done, self.total, self.bytes, eta);
+        let _ = write!(io::stderr(), "\r\x1b[K{line}");
+    }
+
+    /*
+     * Clear the line if the progress was shown.
+     */
+    pub fn finish(&mut self) {
+        if self.last.take().is_some() {
+            let _ = write!(io::stderr(), "\r\x1b[K");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * format_line()
+     *
+     * Verify that the sizes and the estimated time are formatted in a
+     * readable way.
+     */
+    #[test]
+    fn format_line_001() {
+        let tests = [
+            (1, 10, 512, None, "1/10 files, 512 B"),
+            (2, 10, 1536, Some(5), "2/10 files, 1.5 KiB, ETA 0:05"),
+            (
+                900,
+                1000,
+                3 << 30,
+                Some(125),
+                "900/1000 files, 3.0 GiB, ETA 2:05",
+            ),
+        ];
+
+        for (done, total, bytes, eta, expected) in tests {
+            assert_eq!(expected, format_line(done, total, bytes, eta));
+        }
+    }
+}
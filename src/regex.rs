@@ -0,0 +1,609 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::iter::Peekable;
+use std::mem;
+use std::str::Chars;
+
+#[derive(Clone, Debug)]
+struct Class {
+    negated: bool,
+    ranges: Vec<(char, char)>,
+}
+
+impl Class {
+    fn new(negated: bool, ranges: &[(char, char)]) -> Self {
+        Self {
+            negated,
+            ranges: Vec::from(ranges),
+        }
+    }
+
+    fn matches(&self, c: char) -> bool {
+        let found = self.ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi);
+
+        found != self.negated
+    }
+}
+
+const DIGIT: &[(char, char)] = &[('0', '9')];
+const WORD: &[(char, char)] = &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')];
+const SPACE: &[(char, char)] = &[('\t', '\r'), (' ', ' ')];
+
+#[derive(Debug)]
+enum Node {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat(Box<Node>, u32, Option<u32>, bool),
+}
+
+#[derive(Debug)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    Split(usize, usize),
+    Jump(usize),
+    Save(usize),
+    Match,
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    groups: usize,
+}
+
+impl Parser<'_> {
+    fn parse_alternate(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::from([self.parse_concat()?]);
+
+        while self.chars.next_if_eq(&'|').is_some() {
+            nodes.push(self.parse_concat()?);
+        }
+
+        Ok(match nodes.len() {
+            1 => nodes.pop().unwrap(),
+            _ => Node::Alternate(nodes),
+        })
+    }
+
+    fn parse_concat(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+
+        while !matches!(self.chars.peek(), None | Some('|' | ')')) {
+            let node = self.parse_atom()?;
+            nodes.push(self.parse_repeat(node)?);
+        }
+
+        Ok(Node::Concat(nodes))
+    }
+
+    fn parse_repeat(&mut self, mut node: Node) -> Result<Node, String> {
+        loop {
+            let (min, max) = match self.chars.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                Some('{') => match self.parse_bounds()? {
+                    Some(bounds) => bounds,
+                    None => return Ok(node),
+                },
+                _ => return Ok(node),
+            };
+
+            /* Skip the quantifier or the closing brace */
+            self.chars.next();
+
+            let greedy = self.chars.next_if_eq(&'?').is_none();
+            node = Node::Repeat(Box::new(node), min, max, greedy);
+        }
+    }
+
+    /*
+     * Parse "{n}", "{n,}", or "{n,m}" up to, but excluding, the closing
+     * brace. Anything else is not a quantifier and "{" is a literal.
+     */
+    fn parse_bounds(&mut self) -> Result<Option<(u32, Option<u32>)>, String> {
+        let rest: String = self.chars.clone().skip(1).collect();
+        let spec = match rest.split_once('}') {
+            Some((spec, _)) => spec,
+            None => return Ok(None),
+        };
+
+        let number = |x: &str| x.parse::<u32>().ok();
+
+        let bounds = match spec.split_once(',') {
+            None => number(spec).map(|n| (n, Some(n))),
+            Some((min, "")) => number(min).map(|n| (n, None)),
+            Some((min, max)) => number(min).zip(number(max).map(Some)),
+        };
+
+        let bounds = match bounds {
+            Some(val) => val,
+            None => return Ok(None),
+        };
+
+        if matches!(bounds, (min, Some(max)) if max < min) {
+            return Err(format!("invalid repetition \"{{{spec}}}\""));
+        }
+
+        /* Skip everything but the closing brace */
+        for _ in 0..spec.len() + 1 {
+            self.chars.next();
+        }
+
+        Ok(Some(bounds))
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, String> {
+        let c = self.chars.next().unwrap();
+
+        let node = match c {
+            '(' => {
+                let index = match self.chars.next_if_eq(&'?') {
+                    Some(_) if self.chars.next_if_eq(&':').is_some() => None,
+                    Some(_) => return Err(String::from("unsupported group")),
+                    None => {
+                        self.groups += 1;
+                        Some(self.groups)
+                    }
+                };
+
+                let node = self.parse_alternate()?;
+
+                if self.chars.next() != Some(')') {
+                    return Err(String::from("missing \")\""));
+                }
+
+                Node::Group(Box::new(node), index)
+            }
+            '[' => Node::Class(self.parse_class()?),
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '*' | '+' | '?' => {
+                return Err(format!("nothing to repeat before \"{c}\""));
+            }
+            '\\' => self.parse_escape()?,
+            _ => Node::Char(c),
+        };
+
+        Ok(node)
+    }
+
+    fn parse_escape(&mut self) -> Result<Node, String> {
+        let c = self
+            .chars
+            .next()
+            .ok_or_else(|| String::from("trailing \"\\\""))?;
+
+        let node = match c {
+            'd' => Node::Class(Class::new(false, DIGIT)),
+            'D' => Node::Class(Class::new(true, DIGIT)),
+            'w' => Node::Class(Class::new(false, WORD)),
+            'W' => Node::Class(Class::new(true, WORD)),
+            's' => Node::Class(Class::new(false, SPACE)),
+            'S' => Node::Class(Class::new(true, SPACE)),
+            'n' => Node::Char('\n'),
+            't' => Node::Char('\t'),
+            c if c.is_ascii_alphanumeric() => {
+                return Err(format!("unsupported escape \"\\{c}\""));
+            }
+            c => Node::Char(c),
+        };
+
+        Ok(node)
+    }
+
+    fn parse_class(&mut self) -> Result<Class, String> {
+        let negated = self.chars.next_if_eq(&'^').is_some();
+        let mut ranges = Vec::new();
+        let mut first = true;
+
+        loop {
+            let mut c = match self.chars.next() {
+                Some(']') if !first => break,
+                Some(c) => c,
+                None => return Err(String::from("missing \"]\"")),
+            };
+
+            first = false;
+
+            if c == '\\' {
+                c = match self.chars.next() {
+                    Some('d') => {
+                        ranges.extend_from_slice(DIGIT);
+                        continue;
+                    }
+                    Some('w') => {
+                        ranges.extend_from_slice(WORD);
+                        continue;
+                    }
+                    Some('s') => {
+                        ranges.extend_from_slice(SPACE);
+                        continue;
+                    }
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some(c) if !c.is_ascii_alphanumeric() => c,
+                    Some(c) => {
+                        return Err(format!("unsupported escape \"\\{c}\""));
+                    }
+                    None => return Err(String::from("missing \"]\"")),
+                };
+            }
+
+            let mut lookahead = self.chars.clone();
+
+            match (lookahead.next(), lookahead.next()) {
+                (Some('-'), Some(hi)) if hi != ']' => {
+                    self.chars.next();
+                    self.chars.next();
+
+                    if hi < c {
+                        return Err(format!("invalid range \"{c}-{hi}\""));
+                    }
+
+                    ranges.push((c, hi));
+                }
+                _ => ranges.push((c, c)),
+            }
+        }
+
+        Ok(Class { negated, ranges })
+    }
+}
+
+struct Compiler {
+    prog: Vec<Inst>,
+}
+
+impl Compiler {
+    fn emit(&mut self, inst: Inst) -> usize {
+        self.prog.push(inst);
+        self.prog.len() - 1
+    }
+
+    fn patch(&mut self, pos: usize, target: usize) {
+        match &mut self.prog[pos] {
+            Inst::Split(_, y) => *y = target,
+            Inst::Jump(x) => *x = target,
+            _ => unreachable!(),
+        }
+    }
+
+    fn compile(&mut self, node: &Node) {
+        match node {
+            Node::Char(c) => {
+                self.emit(Inst::Char(*c));
+            }
+            Node::Any => {
+                self.emit(Inst::Any);
+            }
+            Node::Class(class) => {
+                self.emit(Inst::Class(class.clone()));
+            }
+            Node::Start => {
+                self.emit(Inst::Start);
+            }
+            Node::End => {
+                self.emit(Inst::End);
+            }
+            Node::Group(node, None) => self.compile(node),
+            Node::Group(node, Some(index)) => {
+                self.emit(Inst::Save(2 * index));
+                self.compile(node);
+                self.emit(Inst::Save(2 * index + 1));
+            }
+            Node::Concat(nodes) => nodes.iter().for_each(|x| self.compile(x)),
+            Node::Alternate(nodes) => {
+                let mut jumps = Vec::new();
+
+                for (i, node) in nodes.iter().enumerate() {
+                    if i + 1 == nodes.len() {
+                        self.compile(node);
+                        break;
+                    }
+
+                    let split = self.prog.len();
+                    self.emit(Inst::Split(split + 1, 0));
+                    self.compile(node);
+                    jumps.push(self.emit(Inst::Jump(0)));
+
+                    let next = self.prog.len();
+                    self.patch(split, next);
+                }
+
+                let end = self.prog.len();
+                jumps.into_iter().for_each(|x| self.patch(x, end));
+            }
+            Node::Repeat(node, min, max, greedy) => {
+                for _ in 0..*min {
+                    self.compile(node);
+                }
+
+                match max {
+                    None => {
+                        let split = self.emit(Inst::Split(0, 0));
+                        self.compile(node);
+                        self.emit(Inst::Jump(split));
+                        self.set_split(split, *greedy);
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+
+                        for _ in *min..*max {
+                            splits.push(self.emit(Inst::Split(0, 0)));
+                            self.compile(node);
+                        }
+
+                        for split in splits {
+                            self.set_split(split, *greedy);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /*
+     * Let the split at "pos" prefer the next instruction if "greedy" is
+     * set and the end of the program otherwise.
+     */
+    fn set_split(&mut self, pos: usize, greedy: bool) {
+        let (body, end) = (pos + 1, self.prog.len());
+
+        self.prog[pos] = match greedy {
+            true => Inst::Split(body, end),
+            false => Inst::Split(end, body),
+        };
+    }
+}
+
+enum Job {
+    Run(usize, usize),
+    Restore(usize, Option<usize>),
+}
+
+/*
+ * A small backtracking matcher for extended regular expressions. It
+ * supports literals, ".", "^", "$", bracket expressions, the escapes
+ * "\d", "\w", and "\s", capturing and non-capturing groups, alternation,
+ * and the (lazy) quantifiers "*", "+", "?", and "{n,m}". Each state is
+ * visited at most once per position, which keeps the matching time
+ * polynomial.
+ */
+#[derive(Debug)]
+pub struct Regex {
+    prog: Vec<Inst>,
+    groups: usize,
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            chars: pattern.chars().peekable(),
+            groups: 0,
+        };
+
+        let node = parser.parse_alternate()?;
+
+        if parser.chars.next().is_some() {
+            return Err(String::from("unmatched \")\""));
+        }
+
+        let mut compiler = Compiler { prog: Vec::new() };
+        compiler.emit(Inst::Save(0));
+        compiler.compile(&node);
+        compiler.emit(Inst::Save(1));
+        compiler.emit(Inst::Match);
+
+        Ok(Self {
+            prog: compiler.prog,
+            groups: parser.groups,
+        })
+    }
+
+    pub fn groups(&self) -> usize {
+        self.groups
+    }
+
+    /*
+     * Return the byte offsets of the groups of the leftmost match in
+     * "text" which starts at or after "start". The offsets of the whole
+     * match are stored at index 0.
+     */
+    pub fn captures(
+        &self,
+        text: &str,
+        start: usize,
+    ) -> Option<Vec<Option<(usize, usize)>>> {
+        let mut slots = vec![None; 2 * self.groups + 2];
+
+        /*
+         * Whether a state leads to a match does not depend on where the
+         * match started, so the visited states are shared by all starts.
+         */
+        let mut visited = vec![false; self.prog.len() * (text.len() + 1)];
+
+        let starts = text[start..].char_indices().map(|(pos, _)| start + pos);
+
+        for pos in starts.chain([text.len()]) {
+            if self.run(text, pos, &mut slots, &mut visited) {
+                return Some(Self::pairs(&slots));
+            }
+        }
+
+        None
+    }
+
+    fn pairs(slots: &[Option<usize>]) -> Vec<Option<(usize, usize)>> {
+        slots.chunks_exact(2).map(|x| x[0].zip(x[1])).collect()
+    }
+
+    fn run(
+        &self,
+        text: &str,
+        start: usize,
+        slots: &mut [Option<usize>],
+        visited: &mut [bool],
+    ) -> bool {
+        let width = text.len() + 1;
+        let mut stack = Vec::from([Job::Run(0, start)]);
+
+        while let Some(job) = stack.pop() {
+            let (mut pc, mut sp) = match job {
+                Job::Run(pc, sp) => (pc, sp),
+                Job::Restore(slot, value) => {
+                    slots[slot] = value;
+                    continue;
+                }
+            };
+
+            loop {
+                if mem::replace(&mut visited[pc * width + sp], true) {
+                    break;
+                }
+
+                let next = text[sp..].chars().next();
+
+                match &self.prog[pc] {
+                    Inst::Char(c) if next == Some(*c) => {
+                        sp += c.len_utf8();
+                    }
+                    Inst::Any if next.is_some() => {
+                        sp += next.unwrap().len_utf8();
+                    }
+                    Inst::Class(class)
+                        if next.is_some_and(|x| class.matches(x)) =>
+                    {
+                        sp += next.unwrap().len_utf8();
+                    }
+                    Inst::Start if sp == 0 => {}
+                    Inst::End if sp == text.len() => {}
+                    Inst::Split(x, y) => {
+                        stack.push(Job::Run(*y, sp));
+                        pc = *x;
+                        continue;
+                    }
+                    Inst::Jump(x) => {
+                        pc = *x;
+                        continue;
+                    }
+                    Inst::Save(slot) => {
+                        stack.push(Job::Restore(*slot, slots[*slot]));
+                        slots[*slot] = Some(sp);
+                    }
+                    Inst::Match => return true,
+                    _ => break,
+                }
+
+                pc += 1;
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(pattern: &str, text: &str) -> Option<String> {
+        let regex = Regex::new(pattern).unwrap();
+        let groups = regex.captures(text, 0)?;
+        let (begin, end) = groups[0].unwrap();
+
+        Some(String::from(&text[begin..end]))
+    }
+
+    /**
+     * Regex::captures()
+     *
+     * Verify that the leftmost match is found and that quantifiers are
+     * greedy unless they are marked as lazy.
+     */
+    #[test]
+    fn captures_001() {
+        let tests = [
+            (
+                "^/build/sandbox/[^/]+/",
+                "/build/sandbox/x1/a.h",
+                Some("/build/sandbox/x1/"),
+            ),
+            ("^/build/", "/src/build/a.h", None),
+            ("a.*b", "xaybzb", Some("aybzb")),
+            ("a.*?b", "xaybzb", Some("ayb")),
+            ("(foo|bar)+\\.h$", "xbarfoo.h", Some("barfoo.h")),
+            ("\\d{2,3}", "a1234", Some("123")),
+            ("x{2}", "xxx", Some("xx")),
+            ("[a-c\\-]+", "zz-ab-d", Some("-ab-")),
+            ("[]x]+", "a]x]", Some("]x]")),
+            ("\\.\\w+$", "dir.d/file.hpp", Some(".hpp")),
+            ("ä+", "aää", Some("ää")),
+            ("b*", "abc", Some("")),
+            ("$", "abc", Some("")),
+            ("(a*)*b", "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaac", None),
+        ];
+
+        for (pattern, text, expected) in tests {
+            assert_eq!(expected.map(String::from), find(pattern, text));
+        }
+    }
+
+    /**
+     * Regex::captures()
+     *
+     * Verify that the offsets of the groups are reported.
+     */
+    #[test]
+    fn captures_002() {
+        let regex = Regex::new("(?:(\\w+)/)+(x)?(\\w+)\\.h").unwrap();
+        let groups = regex.captures("a/bb/c.h", 0).unwrap();
+
+        assert_eq!(3, regex.groups());
+        assert_eq!(
+            Vec::from([Some((0, 8)), Some((2, 4)), None, Some((5, 6))]),
+            groups
+        );
+    }
+
+    /**
+     * Regex::new()
+     *
+     * Verify that invalid patterns are rejected.
+     */
+    #[test]
+    fn new_001() {
+        for pattern in ["(a", "a)", "[a", "*a", "a{3,2}", "\\b", "(?=a)"] {
+            assert!(Regex::new(pattern).is_err(), "{pattern}");
+        }
+
+        for pattern in ["a{", "a{x}", "{", "a|", "()", "[-a-]"] {
+            assert!(Regex::new(pattern).is_ok(), "{pattern}");
+        }
+    }
+}
@@ -0,0 +1,293 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::borrow::Cow;
+use std::mem;
+
+use crate::regex::Regex;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    Group(usize),
+}
+
+/*
+ * A rule in the syntax of the "s" command of sed, i.e.
+ * "s/<regex>/<replacement>/[g]". Any character can be used instead of
+ * '/' and is escaped with a backslash within the rule. The replacement
+ * refers to the groups of the match with "\1" to "\9" and to the whole
+ * match with "&".
+ */
+#[derive(Debug)]
+struct Rule {
+    regex: Regex,
+    replacement: Vec<Piece>,
+    global: bool,
+}
+
+/*
+ * Split "rule" at the unescaped occurrences of "delim". Escape sequences
+ * are kept as they are, except for an escaped delimiter in the
+ * replacement, which is resolved by "parse_replacement()".
+ */
+fn split_rule(rule: &str, delim: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+
+    for (i, c) in rule.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if c == delim => {
+                parts.push(&rule[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+
+    parts.push(&rule[start..]);
+    parts
+}
+
+fn parse_replacement(
+    replacement: &str,
+    groups: usize,
+) -> Result<Vec<Piece>, String> {
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let mut chars = replacement.chars();
+
+    while let Some(c) = chars.next() {
+        let group = match c {
+            '&' => 0,
+            '\\' => match chars.next() {
+                Some(c) if c.is_ascii_digit() => c as usize - '0' as usize,
+                Some('n') => {
+                    literal.push('\n');
+                    continue;
+                }
+                Some(c) => {
+                    literal.push(c);
+                    continue;
+                }
+                None => return Err(String::from("trailing \"\\\"")),
+            },
+            _ => {
+                literal.push(c);
+                continue;
+            }
+        };
+
+        if group > groups {
+            return Err(format!("invalid reference \"\\{group}\""));
+        }
+
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(mem::take(&mut literal)));
+        }
+
+        pieces.push(Piece::Group(group));
+    }
+
+    if !literal.is_empty() {
+        pieces.push(Piece::Literal(literal));
+    }
+
+    Ok(pieces)
+}
+
+impl Rule {
+    fn parse(rule: &str) -> Result<Self, String> {
+        let invalid = |msg: &str| format!("invalid rule \"{rule}\": {msg}");
+
+        let delim = match rule.strip_prefix('s').and_then(|x| x.chars().next())
+        {
+            Some(c) if !c.is_alphanumeric() && c != '\\' && c != '\n' => c,
+            _ => return Err(invalid("expected \"s/<regex>/<replacement>/\"")),
+        };
+
+        let parts = split_rule(&rule[1 + delim.len_utf8()..], delim);
+
+        let (pattern, replacement, flags) = match parts.as_slice() {
+            [pattern, replacement, flags] => (pattern, replacement, flags),
+            _ => return Err(invalid("expected three delimiters")),
+        };
+
+        let global = match *flags {
+            "" => false,
+            "g" => true,
+            _ => return Err(invalid(&format!("unknown flags \"{flags}\""))),
+        };
+
+        let regex = Regex::new(pattern).map_err(|err| invalid(&err))?;
+        let replacement = parse_replacement(replacement, regex.groups())
+            .map_err(|err| invalid(&err))?;
+
+        Ok(Self {
+            regex,
+            replacement,
+            global,
+        })
+    }
+
+    fn apply(&self, path: &str) -> Option<String> {
+        let mut result = String::new();
+        let mut pos = 0;
+        let mut changed = false;
+
+        while pos <= path.len() {
+            let groups = match self.regex.captures(path, pos) {
+                Some(val) => val,
+                None => break,
+            };
+
+            let (begin, end) = groups[0].unwrap();
+            result.push_str(&path[pos..begin]);
+
+            for piece in &self.replacement {
+                match piece {
+                    Piece::Literal(value) => result.push_str(value),
+                    Piece::Group(index) => {
+                        if let Some((begin, end)) = groups[*index] {
+                            result.push_str(&path[begin..end]);
+                        }
+                    }
+                }
+            }
+
+            changed = true;
+            pos = end;
+
+            if !self.global {
+                break;
+            }
+
+            /* Do not match an empty string at the same position again */
+            if begin == end {
+                match path[pos..].chars().next() {
+                    Some(c) => {
+                        result.push(c);
+                        pos += c.len_utf8();
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        if !changed {
+            return None;
+        }
+
+        result.push_str(&path[pos.min(path.len())..]);
+        Some(result)
+    }
+}
+
+/*
+ * Rewrite paths with a list of rules which are applied in order. Each
+ * rule is applied to the result of the previous one.
+ */
+pub struct Rewriter {
+    rules: Vec<Rule>,
+}
+
+impl Rewriter {
+    pub fn new(rules: &[String]) -> Result<Self, String> {
+        let rules = rules
+            .iter()
+            .map(|x| Rule::parse(x))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { rules })
+    }
+
+    pub fn rewrite<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        let mut result = Cow::Borrowed(path);
+
+        for rule in &self.rules {
+            if let Some(value) = rule.apply(&result) {
+                result = Cow::Owned(value);
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn do_rewrite(rules: &[&str], path: &str) -> String {
+        let rules: Vec<String> =
+            rules.iter().map(|x| String::from(*x)).collect();
+
+        Rewriter::new(&rules).unwrap().rewrite(path).into_owned()
+    }
+
+    /**
+     * Rewriter::rewrite()
+     *
+     * Verify that the rules are applied in order and that the
+     * replacement refers to the groups of the match.
+     */
+    #[test]
+    fn rewrite_001() {
+        let tests = [
+            (
+                &["s|^/build/sandbox/[^/]+/|//|"][..],
+                "/build/sandbox/x1/a.h",
+                "//a.h",
+            ),
+            (&["s|^/build/sandbox/[^/]+/|//|"], "/src/a.h", "/src/a.h"),
+            (&["s/a/b/"], "aaa", "baa"),
+            (&["s/a/b/g"], "aaa", "bbb"),
+            (&["s/x*/-/g"], "abc", "-a-b-c-"),
+            (
+                &["s/(\\w+)\\.(c|h)$/\\2\\/\\1/"],
+                "src/main.c",
+                "src/c/main",
+            ),
+            (&["s#/#[&]#g", "s/\\[//"], "a/b/c", "a/]b[/]c"),
+            (&["s,\\,,;,"], "a,b", "a;b"),
+        ];
+
+        for (rules, path, expected) in tests {
+            assert_eq!(expected, do_rewrite(rules, path));
+        }
+    }
+
+    /**
+     * Rewriter::new()
+     *
+     * Verify that invalid rules are rejected.
+     */
+    #[test]
+    fn new_001() {
+        let rules = [
+            "", "s", "x/a/b/", "s/a/b", "s/a/b/c/", "s/a/b/x", "s/(/b/",
+            "s/a/\\1/", "sa/b/c/",
+        ];
+
+        for rule in rules {
+            assert!(Rewriter::new(&[String::from(rule)]).is_err(), "{rule}");
+        }
+    }
+}
@@ -0,0 +1,154 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::dependency::Dependency;
+use crate::normalize;
+
+/*
+ * The directories all prerequisites of a hermetic build are expected to
+ * be in, e.g. the source and the output tree.
+ */
+pub struct Sandbox {
+    roots: Vec<PathBuf>,
+    cwd: String,
+}
+
+impl Sandbox {
+    /*
+     * Relative roots and prerequisites are resolved against "cwd".
+     */
+    pub fn new(roots: &[String], cwd: &str) -> Self {
+        let mut result = Self {
+            roots: Vec::new(),
+            cwd: String::from(cwd),
+        };
+
+        result.roots = roots.iter().map(|x| result.resolve(x)).collect();
+        result
+    }
+
+    /*
+     * Resolve the symbolic links of existing files. The paths of missing
+     * files, e.g. generated ones, are only normalized.
+     */
+    fn resolve(&self, path: &str) -> PathBuf {
+        let path = normalize::join(&self.cwd, path);
+
+        match fs::canonicalize(path.as_ref()) {
+            Ok(path) => path,
+            Err(_) => PathBuf::from(normalize::normalize(&path).as_ref()),
+        }
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.roots.iter().any(|root| path.starts_with(root))
+    }
+
+    /*
+     * Describe each prerequisite outside of all roots together with the
+     * first target which requires it, in the order of the rules.
+     */
+    pub fn check(&self, deps: &[Dependency]) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut result = Vec::new();
+
+        for dep in deps {
+            for &prereq in &dep.prerequisites {
+                if !seen.insert(prereq) {
+                    continue;
+                }
+
+                let path = self.resolve(prereq);
+
+                if !self.contains(&path) {
+                    result.push(format!(
+                        "\"{prereq}\" of \"{}\" resolves to \"{}\" outside \
+                         the sandbox",
+                        dep.target,
+                        path.display()
+                    ));
+                }
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::os::unix::fs::symlink;
+    use std::process;
+
+    /**
+     * check()
+     *
+     * Verify that relative, missing, and linked prerequisites are resolved
+     * before they are compared with the roots and that each prerequisite
+     * outside is reported once.
+     */
+    #[test]
+    fn check_001() {
+        let mut dir = env::temp_dir();
+        dir.push(format!("dep2j-sandbox-{}", process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+
+        let dir = fs::canonicalize(&dir).unwrap();
+        let cwd = dir.to_str().unwrap();
+
+        fs::write(dir.join("outside.h"), b"").unwrap();
+        symlink(dir.join("outside.h"), dir.join("src/link.h")).unwrap();
+
+        let sandbox = Sandbox::new(&[String::from("src")], cwd);
+
+        let vec = Vec::from([
+            Dependency {
+                target: "a.o",
+                prerequisites: Vec::from([
+                    "src/a.c",
+                    "src/gen/../b.h",
+                    "src/link.h",
+                    "src-old/c.h",
+                ]),
+            },
+            Dependency {
+                target: "b.o",
+                prerequisites: Vec::from(["src/link.h"]),
+            },
+        ]);
+
+        let result = sandbox.check(&vec);
+
+        assert_eq!(2, result.len());
+        assert_eq!(
+            format!(
+                "\"src/link.h\" of \"a.o\" resolves to \"{cwd}/outside.h\" \
+                 outside the sandbox"
+            ),
+            result[0]
+        );
+        assert!(result[1].starts_with("\"src-old/c.h\" of \"a.o\""));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
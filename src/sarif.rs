@@ -0,0 +1,209 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::json::{JsonSerializer, Value};
+use crate::lint::Severity;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/*
+ * A finding of the "lint" or "check" command and the location in a file
+ * it refers to. The "rule" identifies the kind of the finding.
+ */
+pub struct Finding {
+    pub rule: String,
+    pub severity: Severity,
+    pub message: String,
+    pub file: String,
+    pub line: usize,
+    pub column: Option<usize>,
+}
+
+fn object(members: Vec<(&str, Value)>) -> Value {
+    Value::Object(
+        members
+            .into_iter()
+            .map(|(key, value)| (String::from(key), value))
+            .collect(),
+    )
+}
+
+fn string(value: &str) -> Value {
+    Value::String(String::from(value))
+}
+
+/*
+ * Convert a path into a relative URI reference. Only the characters which
+ * are common in paths, but not allowed in a URI, are escaped.
+ */
+fn to_uri(path: &str) -> String {
+    let mut result = String::with_capacity(path.len());
+
+    for c in path.chars() {
+        match c {
+            '\\' => result.push('/'),
+            '%' => result.push_str("%25"),
+            ' ' => result.push_str("%20"),
+            '#' => result.push_str("%23"),
+            '?' => result.push_str("%3F"),
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+fn to_value(finding: &Finding) -> Value {
+    let mut region =
+        Vec::from([("startLine", Value::Number(finding.line as f64))]);
+
+    if let Some(column) = finding.column {
+        region.push(("startColumn", Value::Number(column as f64)));
+    }
+
+    let location = object(Vec::from([(
+        "physicalLocation",
+        object(Vec::from([
+            (
+                "artifactLocation",
+                object(Vec::from([(
+                    "uri",
+                    Value::String(to_uri(&finding.file)),
+                )])),
+            ),
+            ("region", object(region)),
+        ])),
+    )]));
+
+    object(Vec::from([
+        ("ruleId", string(&finding.rule)),
+        ("level", Value::String(finding.severity.to_string())),
+        (
+            "message",
+            object(Vec::from([("text", string(&finding.message))])),
+        ),
+        ("locations", Value::Array(Vec::from([location]))),
+    ]))
+}
+
+/*
+ * Generate a SARIF 2.1.0 log with a single run of dep2j, which lists the
+ * rules of the "findings" in the order they first occur.
+ */
+pub fn report(findings: &[Finding]) -> Vec<u8> {
+    let mut rules: Vec<&str> = Vec::new();
+
+    for finding in findings {
+        if !rules.contains(&finding.rule.as_str()) {
+            rules.push(&finding.rule);
+        }
+    }
+
+    let rules = rules
+        .into_iter()
+        .map(|x| object(Vec::from([("id", string(x))])))
+        .collect();
+
+    let driver = object(Vec::from([
+        ("name", string("dep2j")),
+        ("version", string(env!("CARGO_PKG_VERSION"))),
+        ("rules", Value::Array(rules)),
+    ]));
+
+    let run = object(Vec::from([
+        ("tool", object(Vec::from([("driver", driver)]))),
+        (
+            "results",
+            Value::Array(findings.iter().map(to_value).collect()),
+        ),
+    ]));
+
+    let log = object(Vec::from([
+        ("$schema", string(SCHEMA)),
+        ("version", string("2.1.0")),
+        ("runs", Value::Array(Vec::from([run]))),
+    ]));
+
+    let mut serializer = JsonSerializer::new();
+    serializer.write_value(&log);
+
+    let mut buf = Vec::from(serializer.get_json());
+    buf.push(b'\n');
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * to_uri()
+     *
+     * Verify that spaces and the characters with a special meaning in a
+     * URI are escaped and that backslashes become slashes.
+     */
+    #[test]
+    fn to_uri_001() {
+        assert_eq!("src/a.d", to_uri("src/a.d"));
+        assert_eq!("src/my%20dir/a%2325%25.d", to_uri("src\\my dir\\a#25%.d"));
+        assert_eq!("a%3F.d", to_uri("a?.d"));
+    }
+
+    /**
+     * report()
+     *
+     * Verify that each finding becomes a result with its location and that
+     * the rules are listed once.
+     */
+    #[test]
+    fn report_001() {
+        let findings = Vec::from([
+            Finding {
+                rule: String::from("lint"),
+                severity: Severity::Error,
+                message: String::from("missing \":\""),
+                file: String::from("a.d"),
+                line: 3,
+                column: Some(7),
+            },
+            Finding {
+                rule: String::from("lint"),
+                severity: Severity::Warning,
+                message: String::from("x"),
+                file: String::from("b.d"),
+                line: 1,
+                column: None,
+            },
+        ]);
+
+        let log = String::from_utf8(report(&findings)).unwrap();
+
+        assert!(log.starts_with(&format!(
+            "{{\"$schema\":\"{SCHEMA}\",\"version\":\"2.1.0\",\"runs\":[{{"
+        )));
+        assert!(log.contains("\"rules\":[{\"id\":\"lint\"}]"));
+        assert!(log.contains(
+            "{\"ruleId\":\"lint\",\"level\":\"error\",\
+            \"message\":{\"text\":\"missing \\\":\\\"\"},\
+            \"locations\":[{\"physicalLocation\":{\
+            \"artifactLocation\":{\"uri\":\"a.d\"},\
+            \"region\":{\"startLine\":3,\"startColumn\":7}}}]}"
+        ));
+        assert!(log.contains("\"region\":{\"startLine\":1}}"));
+        assert!(log.ends_with("]}]}\n"));
+    }
+}
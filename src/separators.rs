@@ -0,0 +1,160 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::borrow::Cow;
+
+/*
+ * The path separator written to the output. "Keep" writes the paths as
+ * they are read, the others replace the separators of the other platform.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Separators {
+    Keep,
+    Posix,
+    Windows,
+}
+
+/*
+ * Make escapes a space, a tab, and '#' in a path with a backslash, which
+ * is not a separator.
+ */
+pub fn is_escape(path: &[u8], i: usize) -> bool {
+    matches!(path.get(i + 1), Some(b' ' | b'\t' | b'#'))
+}
+
+/*
+ * Remove the backslashes of escaped spaces, tabs, and '#' and replace "$$"
+ * with '$' to get the actual file name of a path written by make.
+ */
+pub fn unescape(path: &str) -> Cow<'_, str> {
+    if !path.contains('\\') && !path.contains("$$") {
+        return Cow::Borrowed(path);
+    }
+
+    let mut result = String::with_capacity(path.len());
+    let mut chars = path.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if matches!(chars.peek(), Some(' ' | '\t' | '#')) => {}
+            '$' if chars.peek() == Some(&'$') => {
+                result.extend(chars.next());
+            }
+            _ => result.push(c),
+        }
+    }
+
+    Cow::Owned(result)
+}
+
+impl Separators {
+    pub fn apply(self, path: &str) -> Cow<'_, str> {
+        let (from, to) = match self {
+            Separators::Keep => return Cow::Borrowed(path),
+            Separators::Posix => (b'\\', b'/'),
+            Separators::Windows => (b'/', b'\\'),
+        };
+
+        let bytes = path.as_bytes();
+        let replace = |i: usize| {
+            bytes[i] == from && !(from == b'\\' && is_escape(bytes, i))
+        };
+
+        if !(0..bytes.len()).any(replace) {
+            return Cow::Borrowed(path);
+        }
+
+        let result = path
+            .char_indices()
+            .map(|(i, c)| if replace(i) { char::from(to) } else { c })
+            .collect();
+
+        Cow::Owned(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * Separators::apply()
+     *
+     * Verify that backslashes are replaced except those of escaped spaces
+     * and that the paths are kept if there is nothing to replace.
+     */
+    #[test]
+    fn apply_001() {
+        let tests = [
+            ("C:\\src\\a.h", "C:/src/a.h"),
+            ("src\\my\\ file.h", "src/my\\ file.h"),
+            ("src\\\\#a.h", "src/\\#a.h"),
+            ("src/a.h", "src/a.h"),
+            ("", ""),
+        ];
+
+        for (path, expected) in tests {
+            assert_eq!(expected, Separators::Posix.apply(path), "{path}");
+        }
+
+        assert!(matches!(Separators::Posix.apply("a/b"), Cow::Borrowed(_)));
+    }
+
+    /**
+     * Separators::apply()
+     *
+     * Verify that slashes are replaced with backslashes and that the
+     * paths are kept unchanged without a conversion.
+     */
+    #[test]
+    fn apply_002() {
+        let tests = [
+            ("src/a.h", "src\\a.h"),
+            ("/usr/include/my\\ file.h", "\\usr\\include\\my\\ file.h"),
+            ("C:\\src/a.h", "C:\\src\\a.h"),
+        ];
+
+        for (path, expected) in tests {
+            assert_eq!(expected, Separators::Windows.apply(path), "{path}");
+        }
+
+        assert_eq!("a/b\\c", Separators::Keep.apply("a/b\\c"));
+    }
+
+    /**
+     * unescape()
+     *
+     * Verify that escaped spaces, tabs, '#', and "$$" are unescaped and
+     * that other backslashes are kept.
+     */
+    #[test]
+    fn unescape_001() {
+        let tests = [
+            ("my\\ file.h", "my file.h"),
+            ("a\\\tb\\#c.h", "a\tb#c.h"),
+            ("$$x$$$$.h", "$x$$.h"),
+            ("C:\\src\\a.h", "C:\\src\\a.h"),
+            ("a$.h\\", "a$.h\\"),
+        ];
+
+        for (path, expected) in tests {
+            assert_eq!(expected, unescape(path), "{path}");
+        }
+
+        assert!(matches!(unescape("a/b.h"), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,583 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::time::SystemTime;
+
+use crate::dependency::{self, Dependency, DependencyBuf};
+use crate::diff;
+use crate::graph::Graph;
+use crate::json::{JsonParser, JsonSerializer, Value};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+type Error = (i64, String);
+
+struct CacheEntry {
+    mtime: SystemTime,
+    deps: Vec<DependencyBuf>,
+}
+
+/*
+ * Answer JSON-RPC 2.0 requests which are read line by line. Parsed files
+ * are cached and only parsed again if their modification time changes.
+ */
+pub struct Server {
+    cache: HashMap<String, CacheEntry>,
+    content: Vec<DependencyBuf>,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self {
+            cache: HashMap::new(),
+            content: Vec::new(),
+        }
+    }
+
+    /*
+     * Accept connections on the unix socket "path" one after another and
+     * answer their requests until the "exit" method is called. The cache
+     * is shared by all connections, so only the first request for a file
+     * has to parse it. The socket is removed when the daemon stops.
+     */
+    #[cfg(unix)]
+    pub fn listen(&mut self, path: &str) -> io::Result<()> {
+        Self::remove_stale_socket(path)?;
+
+        let listener = UnixListener::bind(path)?;
+        let result = self.accept(&listener);
+
+        fs::remove_file(path)?;
+        result
+    }
+
+    #[cfg(unix)]
+    fn accept(&mut self, listener: &UnixListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let reader = io::BufReader::new(stream.try_clone()?);
+
+            /* A failing client must not take down the daemon */
+            match self.serve(reader, stream) {
+                Ok(true) => break,
+                Ok(false) => {}
+                Err(err) => {
+                    warning!("server-failed", "connection failed: {err}")
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /*
+     * A daemon which was killed leaves its socket behind. The socket is
+     * stale and can be replaced if no daemon accepts connections on it.
+     */
+    #[cfg(unix)]
+    fn remove_stale_socket(path: &str) -> io::Result<()> {
+        match fs::symlink_metadata(path) {
+            Ok(attr) if attr.file_type().is_socket() => {}
+            _ => return Ok(()),
+        }
+
+        match UnixStream::connect(path) {
+            Ok(_) => {
+                let msg = "another daemon is listening on the socket";
+
+                Err(io::Error::new(io::ErrorKind::AddrInUse, msg))
+            }
+            Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => {
+                fs::remove_file(path)
+            }
+            Err(_) => Ok(()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn listen(&mut self, _path: &str) -> io::Result<()> {
+        let msg = "unix sockets are not supported on this platform";
+
+        Err(io::Error::new(io::ErrorKind::Unsupported, msg))
+    }
+
+    /*
+     * Process requests until the input is exhausted or the "exit" method
+     * is called. Each response is written as a single line.
+     */
+    pub fn run<R: BufRead, W: Write>(
+        &mut self,
+        input: R,
+        output: W,
+    ) -> io::Result<()> {
+        self.serve(input, output).map(|_| ())
+    }
+
+    /*
+     * Like "run()", but return whether the "exit" method was called.
+     */
+    fn serve<R: BufRead, W: Write>(
+        &mut self,
+        input: R,
+        mut output: W,
+    ) -> io::Result<bool> {
+        for line in input.lines() {
+            let line = line?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (response, exit) = self.handle(line.as_bytes());
+
+            if let Some(mut response) = response {
+                response.push(b'\n');
+                output.write_all(&response)?;
+                output.flush()?;
+            }
+
+            if exit {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn handle(&mut self, line: &[u8]) -> (Option<Vec<u8>>, bool) {
+        let request = match JsonParser::new(line).parse() {
+            Ok(value) => value,
+            Err(err) => {
+                let err = (PARSE_ERROR, err);
+                return (Some(Self::response(&Value::Null, Err(err))), false);
+            }
+        };
+
+        let id = request.get("id").cloned();
+        let method = request.get("method").and_then(|x| x.as_str());
+
+        let method = match (&request, method) {
+            (Value::Object(_), Some(method)) => method,
+            _ => {
+                let id = id.unwrap_or(Value::Null);
+                let err = (INVALID_REQUEST, String::from("invalid request"));
+
+                return (Some(Self::response(&id, Err(err))), false);
+            }
+        };
+
+        let params = request.get("params").unwrap_or(&Value::Null);
+
+        let result = match method {
+            "parse" => self.parse(params),
+            "rdeps" => self.rdeps(params),
+            "diff" => self.diff(params),
+            "exit" => Ok(Vec::from(b"null".as_slice())),
+            _ => {
+                Err((METHOD_NOT_FOUND, format!("unknown method \"{method}\"")))
+            }
+        };
+
+        let exit = method == "exit";
+
+        /* Notifications do not receive a response */
+        match id {
+            Some(id) => (Some(Self::response(&id, result)), exit),
+            None => (None, exit),
+        }
+    }
+
+    fn response(id: &Value, result: Result<Vec<u8>, Error>) -> Vec<u8> {
+        let mut serializer = JsonSerializer::new();
+        serializer.write_value(id);
+
+        let mut buf = Vec::from(b"{\"jsonrpc\":\"2.0\",\"id\":".as_slice());
+        buf.extend_from_slice(serializer.get_json());
+
+        match result {
+            Ok(data) => {
+                buf.extend_from_slice(b",\"result\":");
+                buf.extend_from_slice(&data);
+            }
+            Err((code, message)) => {
+                let error = Value::Object(Vec::from([
+                    (String::from("code"), Value::Number(code as f64)),
+                    (String::from("message"), Value::String(message)),
+                ]));
+
+                let mut serializer = JsonSerializer::new();
+                serializer.write_value(&error);
+
+                buf.extend_from_slice(b",\"error\":");
+                buf.extend_from_slice(serializer.get_json());
+            }
+        }
+
+        buf.push(b'}');
+        buf
+    }
+
+    fn parse(&mut self, params: &Value) -> Result<Vec<u8>, Error> {
+        let files = self.read_input(params)?;
+        let deps = self.load(&files)?;
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_vec(&deps);
+
+        Ok(Vec::from(serializer.get_json()))
+    }
+
+    fn rdeps(&mut self, params: &Value) -> Result<Vec<u8>, Error> {
+        let files = self.read_input(params)?;
+        let node = Self::get_str(params, "node")?;
+        let deps = self.load(&files)?;
+
+        let graph = Graph::new(&deps);
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_str_list(&graph.affected(&[node]));
+
+        Ok(Vec::from(serializer.get_json()))
+    }
+
+    fn diff(&mut self, params: &Value) -> Result<Vec<u8>, Error> {
+        let old_files = Self::get_files(params, "old")?;
+        let new_files = Self::get_files(params, "new")?;
+
+        self.content.clear();
+        self.update(&old_files)?;
+        self.update(&new_files)?;
+
+        let old = self.merged(&old_files);
+        let new = self.merged(&new_files);
+
+        let mut serializer = JsonSerializer::new();
+        serializer.write_diff(&diff::diff(&old, &new));
+
+        Ok(Vec::from(serializer.get_json()))
+    }
+
+    fn load(&mut self, files: &[String]) -> Result<Vec<Dependency<'_>>, Error> {
+        self.update(files)?;
+
+        Ok(self.merged(files))
+    }
+
+    fn merged(&self, files: &[String]) -> Vec<Dependency<'_>> {
+        let deps = files
+            .iter()
+            .flat_map(|file| &self.cache[file].deps)
+            .chain(&self.content)
+            .map(|dep| dep.as_dependency())
+            .collect();
+
+        dependency::merge(deps)
+    }
+
+    /*
+     * Make sure that the cache holds the current content of each file.
+     */
+    fn update(&mut self, files: &[String]) -> Result<(), Error> {
+        for path in files {
+            let mtime = fs::metadata(path)
+                .and_then(|attr| attr.modified())
+                .map_err(|err| {
+                    (SERVER_ERROR, format!("failed to open \"{path}\": {err}"))
+                })?;
+
+            if let Some(entry) = self.cache.get(path) {
+                if entry.mtime == mtime {
+                    continue;
+                }
+            }
+
+            let data = fs::read(path).map_err(|err| {
+                (SERVER_ERROR, format!("failed to read \"{path}\": {err}"))
+            })?;
+
+            let parsed = dependency::parse_checked(&data).map_err(|err| {
+                (INVALID_PARAMS, format!("failed to parse \"{path}\": {err}"))
+            })?;
+            let deps = parsed.deps;
+
+            self.cache.insert(path.clone(), CacheEntry { mtime, deps });
+        }
+
+        Ok(())
+    }
+
+    /*
+     * Retrieve the input of a request. Besides a list of "files", the
+     * content of a dependency file may be passed directly as "content".
+     * Such content is not cached.
+     */
+    fn read_input(&mut self, params: &Value) -> Result<Vec<String>, Error> {
+        self.content.clear();
+
+        let content = match params.get("content") {
+            Some(value) => Some(value.as_str().ok_or_else(|| {
+                (INVALID_PARAMS, String::from("invalid string \"content\""))
+            })?),
+            None => None,
+        };
+
+        if let Some(content) = content {
+            self.content = dependency::parse_checked(content.as_bytes())
+                .map_err(|err| {
+                    (INVALID_PARAMS, format!("invalid \"content\": {err}"))
+                })?
+                .deps;
+
+            if params.get("files").is_none() {
+                return Ok(Vec::new());
+            }
+        }
+
+        Self::get_files(params, "files")
+    }
+
+    fn get_str(params: &Value, key: &str) -> Result<String, Error> {
+        params
+            .get(key)
+            .and_then(|x| x.as_str())
+            .map(String::from)
+            .ok_or_else(|| {
+                (INVALID_PARAMS, format!("missing string \"{key}\""))
+            })
+    }
+
+    fn get_files(params: &Value, key: &str) -> Result<Vec<String>, Error> {
+        let error =
+            || (INVALID_PARAMS, format!("missing list of files \"{key}\""));
+
+        params
+            .get(key)
+            .and_then(|x| x.as_array())
+            .ok_or_else(error)?
+            .iter()
+            .map(|x| x.as_str().map(String::from).ok_or_else(error))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    fn make_file(name: &str, content: &str) -> String {
+        let mut path = env::temp_dir();
+        path.push(format!("dep2j-server-{}-{name}", process::id()));
+
+        fs::write(&path, content).unwrap();
+
+        String::from(path.to_str().unwrap())
+    }
+
+    fn do_run(input: &str) -> String {
+        let mut output = Vec::new();
+
+        Server::new().run(input.as_bytes(), &mut output).unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    /**
+     * Server::run()
+     *
+     * Verify that the "parse" method returns the merged dependencies of
+     * the given files.
+     */
+    #[test]
+    fn run_001() {
+        let a = make_file("run_001_a.d", "a.o: a.c a.h\n");
+        let b = make_file("run_001_b.d", "a.o: b.h\n");
+
+        let input = format!(
+            "{{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"parse\",\
+             \"params\":{{\"files\":[\"{a}\",\"{b}\"]}}}}\n"
+        );
+
+        assert_eq!(
+            concat!(
+                "{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":",
+                "[{\"target\":\"a.o\",",
+                "\"prerequisites\":[\"a.c\",\"a.h\",\"b.h\"]}]}\n"
+            ),
+            do_run(&input)
+        );
+    }
+
+    /**
+     * Server::run()
+     *
+     * Verify that the "rdeps" method returns the targets which depend on
+     * the given node.
+     */
+    #[test]
+    fn run_002() {
+        let a = make_file("run_002.d", "a: b\nb: c\nd: e\n");
+
+        let input = format!(
+            "{{\"jsonrpc\":\"2.0\",\"id\":\"x\",\"method\":\"rdeps\",\
+             \"params\":{{\"files\":[\"{a}\"],\"node\":\"c\"}}}}\n"
+        );
+
+        assert_eq!(
+            "{\"jsonrpc\":\"2.0\",\"id\":\"x\",\"result\":[\"a\",\"b\"]}\n",
+            do_run(&input)
+        );
+    }
+
+    /**
+     * Server::run()
+     *
+     * Verify that invalid requests are answered with errors, notifications
+     * are not answered, and no requests are processed after "exit".
+     */
+    #[test]
+    fn run_003() {
+        let input = concat!(
+            "{\n",
+            "[]\n",
+            "{\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"unknown\"}\n",
+            "{\"jsonrpc\":\"2.0\",\"id\":3,\"method\":\"parse\"}\n",
+            "{\"jsonrpc\":\"2.0\",\"method\":\"parse\"}\n",
+            "{\"jsonrpc\":\"2.0\",\"id\":4,\"method\":\"exit\"}\n",
+            "{\"jsonrpc\":\"2.0\",\"id\":5,\"method\":\"exit\"}\n",
+        );
+
+        let output = do_run(input);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(5, lines.len());
+        assert!(lines[0].contains("\"id\":null,\"error\":{\"code\":-32700"));
+        assert!(lines[1].contains("\"id\":null,\"error\":{\"code\":-32600"));
+        assert!(lines[2].contains("\"id\":2,\"error\":{\"code\":-32601"));
+        assert!(lines[3].contains("\"id\":3,\"error\":{\"code\":-32602"));
+        assert_eq!("{\"jsonrpc\":\"2.0\",\"id\":4,\"result\":null}", lines[4]);
+    }
+
+    /**
+     * Server::run()
+     *
+     * Verify that the content of a dependency file can be passed directly
+     * and is merged with the given files.
+     */
+    #[test]
+    fn run_004() {
+        let a = make_file("run_004.d", "a.o: a.c\n");
+
+        let input = format!(
+            "{{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"parse\",\
+             \"params\":{{\"files\":[\"{a}\"],\"content\":\"a.o: a.h b.h\\n\"}}}}\n\
+             {{\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"parse\",\
+             \"params\":{{\"content\":\"b.o: b.c\"}}}}\n"
+        );
+
+        assert_eq!(
+            concat!(
+                "{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":",
+                "[{\"target\":\"a.o\",",
+                "\"prerequisites\":[\"a.c\",\"a.h\",\"b.h\"]}]}\n",
+                "{\"jsonrpc\":\"2.0\",\"id\":2,\"result\":",
+                "[{\"target\":\"b.o\",\"prerequisites\":[\"b.c\"]}]}\n",
+            ),
+            do_run(&input)
+        );
+    }
+
+    /**
+     * Server::run()
+     *
+     * Verify that a method name with a newline is escaped in the error, so
+     * the response is a single line of valid JSON.
+     */
+    #[test]
+    fn run_005() {
+        let input = "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"a\\nb\"}\n";
+
+        let output = do_run(input);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(1, lines.len());
+
+        let response = JsonParser::new(lines[0].as_bytes()).parse().unwrap();
+        let message = response
+            .get("error")
+            .and_then(|x| x.get("message"))
+            .and_then(|x| x.as_str());
+
+        assert_eq!(Some("unknown method \"a\nb\""), message);
+    }
+
+    /**
+     * Server::listen()
+     *
+     * Verify that a stale socket is replaced and that the socket is
+     * removed when the "exit" method is called.
+     */
+    #[cfg(unix)]
+    #[test]
+    fn listen_001() {
+        let mut path = env::temp_dir();
+        path.push(format!("dep2j-server-{}-listen_001.sock", process::id()));
+
+        let path = String::from(path.to_str().unwrap());
+        let _ = fs::remove_file(&path);
+
+        drop(UnixListener::bind(&path).unwrap());
+        assert!(fs::metadata(&path).is_ok());
+
+        let server = {
+            let path = path.clone();
+            std::thread::spawn(move || Server::new().listen(&path))
+        };
+
+        let mut stream = loop {
+            match UnixStream::connect(&path) {
+                Ok(stream) => break stream,
+                Err(_) => std::thread::yield_now(),
+            }
+        };
+
+        let request = "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"exit\"}\n";
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut response = String::new();
+        io::BufReader::new(stream).read_line(&mut response).unwrap();
+
+        assert_eq!(
+            "{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":null}\n",
+            response
+        );
+        assert!(server.join().unwrap().is_ok());
+        assert!(fs::metadata(&path).is_err());
+    }
+}
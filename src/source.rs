@@ -0,0 +1,260 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+
+use crate::dependency::Dependency;
+use crate::json::{Attributes, Value};
+use crate::kind::KindRules;
+
+/*
+ * Match "path" against a pattern with at most one '%', which matches any
+ * non-empty sequence of characters, and return the matched sequence.
+ */
+fn match_pattern<'a>(pattern: &str, path: &'a str) -> Option<&'a str> {
+    let (prefix, suffix) = match pattern.split_once('%') {
+        Some(val) => val,
+        None => return (pattern == path).then_some(""),
+    };
+
+    path.strip_prefix(prefix)?
+        .strip_suffix(suffix)
+        .filter(|stem| !stem.is_empty())
+}
+
+/*
+ * Rules like "build/%.o=src/%.c" which map a target to its source in the
+ * style of the pattern rules of make. The '%' of the source is replaced
+ * with the part of the target matched by the '%' of the target pattern.
+ */
+pub struct SourceRules {
+    rules: Vec<(String, String)>,
+}
+
+impl SourceRules {
+    pub fn new(rules: &[String]) -> Result<Self, String> {
+        let mut result = Self {
+            rules: Vec::with_capacity(rules.len()),
+        };
+
+        for rule in rules {
+            let (target, source) = rule
+                .split_once('=')
+                .filter(|(target, source)| {
+                    !target.is_empty()
+                        && !source.is_empty()
+                        && target.matches('%').count() <= 1
+                        && source.matches('%').count() <= 1
+                })
+                .ok_or_else(|| format!("invalid source rule \"{rule}\""))?;
+
+            result.rules.push((target.into(), source.into()));
+        }
+
+        Ok(result)
+    }
+
+    /*
+     * The sources of "target" according to the matching rules in the
+     * order of the rules.
+     */
+    fn sources<'a>(
+        &'a self,
+        target: &'a str,
+    ) -> impl Iterator<Item = String> + 'a {
+        self.rules.iter().filter_map(move |(pattern, source)| {
+            let stem = match_pattern(pattern, target)?;
+
+            Some(source.replacen('%', stem, 1))
+        })
+    }
+}
+
+/*
+ * Determine the source of each target. The first source given by a
+ * matching rule which is a prerequisite of the target is preferred over
+ * the first one given by any rule. Without a matching rule, the first
+ * prerequisite is the source unless it is a header, since compilers list
+ * the translation unit first.
+ */
+pub fn infer<'a>(
+    kinds: &KindRules,
+    rules: &SourceRules,
+    deps: &[Dependency<'a>],
+) -> HashMap<&'a str, String> {
+    let mut result = HashMap::with_capacity(deps.len());
+
+    for dep in deps {
+        let sources: Vec<_> = rules.sources(dep.target).collect();
+
+        let source = sources
+            .iter()
+            .find(|x| dep.prerequisites.contains(&x.as_str()))
+            .or(sources.first())
+            .cloned()
+            .or_else(|| {
+                let first = *dep.prerequisites.first()?;
+
+                (kinds.kind(first) != "header").then(|| String::from(first))
+            });
+
+        if let Some(source) = source {
+            result.insert(dep.target, source);
+        }
+    }
+
+    result
+}
+
+/*
+ * Remove the inferred sources from the prerequisites.
+ */
+pub fn strip<'a>(
+    deps: &[Dependency<'a>],
+    sources: &HashMap<&str, String>,
+) -> Vec<Dependency<'a>> {
+    deps.iter()
+        .map(|dep| Dependency {
+            target: dep.target,
+            prerequisites: match sources.get(dep.target) {
+                Some(source) => dep
+                    .prerequisites
+                    .iter()
+                    .filter(|x| **x != source)
+                    .copied()
+                    .collect(),
+                None => dep.prerequisites.clone(),
+            },
+        })
+        .collect()
+}
+
+/*
+ * The "source" of each target, which is null if it cannot be inferred.
+ */
+pub fn attributes(
+    deps: &[Dependency],
+    sources: &HashMap<&str, String>,
+) -> HashMap<String, Attributes> {
+    deps.iter()
+        .map(|dep| {
+            let source = match sources.get(dep.target) {
+                Some(val) => Value::String(val.clone()),
+                None => Value::Null,
+            };
+
+            (String::from(dep.target), Vec::from([("source", source)]))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * infer(), strip()
+     *
+     * Verify that the first prerequisite is the source unless it is a
+     * header and that only inferred sources are removed.
+     */
+    #[test]
+    fn infer_001() {
+        let deps = Vec::from([
+            Dependency {
+                target: "a.o",
+                prerequisites: Vec::from(["a.c", "a.h"]),
+            },
+            Dependency {
+                target: "b.gch",
+                prerequisites: Vec::from(["b.h", "c.h"]),
+            },
+            Dependency {
+                target: "c.o",
+                prerequisites: Vec::new(),
+            },
+        ]);
+
+        let kinds = KindRules::new(&[]).unwrap();
+        let rules = SourceRules::new(&[]).unwrap();
+        let sources = infer(&kinds, &rules, &deps);
+
+        assert_eq!(HashMap::from([("a.o", String::from("a.c"))]), sources);
+
+        let stripped = strip(&deps, &sources);
+
+        assert_eq!(Vec::from(["a.h"]), stripped[0].prerequisites);
+        assert_eq!(Vec::from(["b.h", "c.h"]), stripped[1].prerequisites);
+        assert!(stripped[2].prerequisites.is_empty());
+    }
+
+    /**
+     * infer()
+     *
+     * Verify that the source rules take precedence over the first
+     * prerequisite and that a source which is a prerequisite is preferred.
+     */
+    #[test]
+    fn infer_002() {
+        let deps = Vec::from([
+            Dependency {
+                target: "build/x/a.o",
+                prerequisites: Vec::from(["gen/a.h", "src/x/a.cpp"]),
+            },
+            Dependency {
+                target: "build/b.o",
+                prerequisites: Vec::from(["b.h"]),
+            },
+            Dependency {
+                target: "lib.a",
+                prerequisites: Vec::from(["lib.o"]),
+            },
+        ]);
+
+        let kinds = KindRules::new(&[]).unwrap();
+        let rules = SourceRules::new(&[
+            String::from("build/%.o=src/%.c"),
+            String::from("build/%.o=src/%.cpp"),
+        ])
+        .unwrap();
+
+        let expected = HashMap::from([
+            ("build/x/a.o", String::from("src/x/a.cpp")),
+            ("build/b.o", String::from("src/b.c")),
+            ("lib.a", String::from("lib.o")),
+        ]);
+
+        assert_eq!(expected, infer(&kinds, &rules, &deps));
+
+        let stripped = strip(&deps, &expected);
+
+        assert_eq!(Vec::from(["gen/a.h"]), stripped[0].prerequisites);
+        assert_eq!(Vec::from(["b.h"]), stripped[1].prerequisites);
+    }
+
+    /**
+     * SourceRules::new()
+     *
+     * Verify that invalid rules are rejected.
+     */
+    #[test]
+    fn new_001() {
+        for rule in ["build/%.o", "=src/%.c", "%.o=", "%%.o=%.c", "%.o=%%.c"] {
+            assert!(SourceRules::new(&[String::from(rule)]).is_err());
+        }
+    }
+}
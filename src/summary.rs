@@ -0,0 +1,213 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::dependency::Dependency;
+use crate::graph::Hotspot;
+
+/*
+ * Sort the entries by their count in descending order and by their name
+ * and keep the first "top" of them.
+ */
+fn rank(
+    mut entries: Vec<(&str, usize)>,
+    top: usize,
+) -> Vec<(&str, [usize; 1])> {
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    entries.truncate(top);
+    entries
+        .into_iter()
+        .map(|(path, count)| (path, [count]))
+        .collect()
+}
+
+/*
+ * Write a table with right-aligned columns of counts and a column of paths
+ * below "title".
+ */
+fn write_table<const N: usize>(
+    buf: &mut String,
+    title: &str,
+    header: ([&str; N], &str),
+    rows: &[(&str, [usize; N])],
+) {
+    let mut widths = header.0.map(str::len);
+
+    for (_, counts) in rows {
+        for (width, count) in widths.iter_mut().zip(counts) {
+            *width = (*width).max(count.to_string().len());
+        }
+    }
+
+    let _ = writeln!(buf, "{title}\n");
+    buf.push(' ');
+
+    for (name, width) in header.0.iter().zip(widths) {
+        let _ = write!(buf, " {name:>width$} ");
+    }
+
+    let _ = writeln!(buf, " {}", header.1);
+
+    for (path, counts) in rows {
+        buf.push(' ');
+
+        for (count, width) in counts.iter().zip(widths) {
+            let _ = write!(buf, " {count:>width$} ");
+        }
+
+        let _ = writeln!(buf, " {path}");
+    }
+}
+
+/*
+ * Render the targets with the most prerequisites and the prerequisites
+ * with the most targets depending on them directly as aligned tables for
+ * the terminal.
+ */
+pub fn render(deps: &[Dependency], top: usize) -> String {
+    let targets = deps
+        .iter()
+        .map(|dep| (dep.target, dep.prerequisites.len()))
+        .collect();
+
+    let mut dependents: HashMap<&str, usize> = HashMap::new();
+
+    for &prereq in deps.iter().flat_map(|dep| &dep.prerequisites) {
+        *dependents.entry(prereq).or_default() += 1;
+    }
+
+    let mut buf = String::new();
+
+    write_table(
+        &mut buf,
+        "Targets with the most prerequisites",
+        (["prerequisites"], "target"),
+        &rank(targets, top),
+    );
+
+    buf.push('\n');
+
+    write_table(
+        &mut buf,
+        "Prerequisites with the most dependents",
+        (["dependents"], "prerequisite"),
+        &rank(dependents.into_iter().collect(), top),
+    );
+
+    buf.pop();
+    buf
+}
+
+/*
+ * Render the prerequisites of "stats hotspots" with the number of targets
+ * depending on them directly and transitively as an aligned table.
+ */
+pub fn render_hotspots(hotspots: &[Hotspot]) -> String {
+    let rows: Vec<_> = hotspots
+        .iter()
+        .map(|x| (x.prerequisite, [x.direct, x.transitive]))
+        .collect();
+
+    let mut buf = String::new();
+
+    write_table(
+        &mut buf,
+        "Prerequisites with the most dependents",
+        (["direct", "transitive"], "prerequisite"),
+        &rows,
+    );
+
+    buf.pop();
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * render()
+     *
+     * Verify that both tables are ranked, limited to "top" entries, and
+     * aligned.
+     */
+    #[test]
+    fn render_001() {
+        let deps = Vec::from([
+            Dependency {
+                target: "a.o",
+                prerequisites: Vec::from(["a.c", "a.h", "b.h"]),
+            },
+            Dependency {
+                target: "b.o",
+                prerequisites: Vec::from(["b.c", "b.h"]),
+            },
+            Dependency {
+                target: "c.o",
+                prerequisites: Vec::from(["c.c"]),
+            },
+        ]);
+
+        let expected = "\
+Targets with the most prerequisites
+
+  prerequisites  target
+              3  a.o
+              2  b.o
+
+Prerequisites with the most dependents
+
+  dependents  prerequisite
+           2  b.h
+           1  a.c";
+
+        assert_eq!(expected, render(&deps, 2));
+    }
+
+    /**
+     * render_hotspots()
+     *
+     * Verify that the direct and the transitive counts are aligned in
+     * their own columns.
+     */
+    #[test]
+    fn render_hotspots_001() {
+        let hotspots = Vec::from([
+            Hotspot {
+                prerequisite: "a.h",
+                direct: 12,
+                transitive: 140,
+            },
+            Hotspot {
+                prerequisite: "b.h",
+                direct: 3,
+                transitive: 7,
+            },
+        ]);
+
+        let expected = "\
+Prerequisites with the most dependents
+
+  direct  transitive  prerequisite
+      12         140  a.h
+       3           7  b.h";
+
+        assert_eq!(expected, render_hotspots(&hotspots));
+    }
+}
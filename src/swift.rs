@@ -0,0 +1,156 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::str;
+
+use crate::dependency::DependencyBuf;
+use crate::dialect::{self, Dialect};
+
+const KEYS: &[&str] = &["provides-", "depends-", "interface-hash:"];
+
+/*
+ * Return whether "data" is a ".swiftdeps" file in YAML instead of a
+ * Makefile-style ".d" file.
+ */
+fn is_swiftdeps(data: &str) -> bool {
+    let first = data.lines().find(|x| !x.trim().is_empty());
+    let first = first.unwrap_or_default();
+
+    data.starts_with("###") || KEYS.iter().any(|key| first.starts_with(key))
+}
+
+/*
+ * Unquote a scalar of a ".swiftdeps" file. Private dependencies are
+ * tagged with "!private".
+ */
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    let value = value.strip_prefix("!private").unwrap_or(value).trim();
+
+    match value.strip_prefix('"').and_then(|x| x.strip_suffix('"')) {
+        Some(value) => value.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => String::from(value),
+    }
+}
+
+/*
+ * Read the external dependencies of a ".swiftdeps" file, i.e. the
+ * entries of "depends-external". The file itself becomes the target.
+ */
+fn parse_swiftdeps(path: &str, data: &str) -> Vec<DependencyBuf> {
+    let mut prerequisites = Vec::new();
+    let mut external = false;
+
+    for line in data.lines() {
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+
+        match line.strip_prefix('-') {
+            Some(value) if external => prerequisites.push(unquote(value)),
+            Some(_) => {}
+            None => external = line.trim_end() == "depends-external:",
+        }
+    }
+
+    Vec::from([DependencyBuf {
+        target: String::from(path),
+        prerequisites,
+    }])
+}
+
+/*
+ * Read the dependencies written by swiftc, which are either ".d" files
+ * with escaped spaces or ".swiftdeps" files in YAML.
+ */
+pub fn parse_dependencies(
+    path: &str,
+    data: &[u8],
+) -> Result<Vec<DependencyBuf>, String> {
+    let text = str::from_utf8(data)
+        .map_err(|err| format!("invalid UTF-8 input: {err}"))?;
+
+    match is_swiftdeps(text) {
+        true => Ok(parse_swiftdeps(path, text)),
+        false => dialect::parse_dependencies(data, Dialect::Swift),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * parse_dependencies()
+     *
+     * Verify that ".d" files with several targets and escaped spaces are
+     * supported.
+     */
+    #[test]
+    fn parse_dependencies_001() {
+        let data = "\
+/build/main.o /build/main.swiftmodule : /src/main.swift \
+/Applications/Xcode\\ 14.app/SDKs/MacOSX.sdk/Swift.swiftinterface
+";
+
+        let deps = parse_dependencies("main.d", data.as_bytes()).unwrap();
+        let prerequisites = Vec::from([
+            "/src/main.swift",
+            "/Applications/Xcode 14.app/SDKs/MacOSX.sdk/Swift.swiftinterface",
+        ]);
+
+        assert_eq!(2, deps.len());
+        assert_eq!("/build/main.o", deps[0].target);
+        assert_eq!(prerequisites, deps[0].prerequisites);
+        assert_eq!("/build/main.swiftmodule", deps[1].target);
+        assert_eq!(prerequisites, deps[1].prerequisites);
+    }
+
+    /**
+     * parse_dependencies()
+     *
+     * Verify that the external dependencies of ".swiftdeps" files are
+     * read.
+     */
+    #[test]
+    fn parse_dependencies_002() {
+        let data = "\
+### Swift dependencies file v0 ###
+provides-top-level:
+- \"Foo\"
+depends-top-level:
+- !private \"print\"
+depends-external:
+- \"/SDKs/MacOSX.sdk/Swift.swiftmodule\"
+- !private \"/src/My \\\"Lib\\\".swiftmodule\"
+interface-hash: \"abc\"
+";
+
+        let deps = parse_dependencies("main.swiftdeps", data.as_bytes());
+
+        assert_eq!(
+            Ok(Vec::from([DependencyBuf {
+                target: String::from("main.swiftdeps"),
+                prerequisites: Vec::from([
+                    String::from("/SDKs/MacOSX.sdk/Swift.swiftmodule"),
+                    String::from("/src/My \"Lib\".swiftmodule"),
+                ]),
+            }])),
+            deps
+        );
+    }
+}
@@ -0,0 +1,203 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dependency::Dependency;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    Target,
+    Prereq,
+    Prereqs,
+    Count,
+}
+
+/*
+ * Replace the escape sequences "\n", "\t", and "\\" which are hard to pass
+ * on the command line. Other backslashes are kept as they are.
+ */
+fn unescape(data: &str) -> String {
+    let mut result = String::with_capacity(data.len());
+    let mut chars = data.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('\\') => result.push('\\'),
+            Some(c) => {
+                result.push('\\');
+                result.push(c);
+            }
+            None => result.push('\\'),
+        }
+    }
+
+    result
+}
+
+/*
+ * A text template with the placeholders "{{target}}", "{{prereq}}",
+ * "{{prereqs}}", and "{{count}}". A template which refers to "{{prereq}}"
+ * is expanded once for each pair of target and prerequisite, any other
+ * template once for each rule. "{{prereqs}}" expands to the prerequisites
+ * separated by spaces and "{{count}}" to their number.
+ */
+pub struct Template {
+    pieces: Vec<Piece>,
+    per_edge: bool,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let template = unescape(template);
+        let mut pieces = Vec::new();
+        let mut rest = template.as_str();
+
+        while let Some(begin) = rest.find("{{") {
+            if begin != 0 {
+                pieces.push(Piece::Literal(String::from(&rest[..begin])));
+            }
+
+            let end = rest[begin..]
+                .find("}}")
+                .ok_or_else(|| String::from("unterminated placeholder"))?;
+
+            let name = rest[begin + 2..begin + end].trim();
+            let piece = match name {
+                "target" => Piece::Target,
+                "prereq" => Piece::Prereq,
+                "prereqs" => Piece::Prereqs,
+                "count" => Piece::Count,
+                _ => return Err(format!("unknown placeholder \"{name}\"")),
+            };
+
+            pieces.push(piece);
+            rest = &rest[begin + end + 2..];
+        }
+
+        if !rest.is_empty() {
+            pieces.push(Piece::Literal(String::from(rest)));
+        }
+
+        let per_edge = pieces.contains(&Piece::Prereq);
+
+        Ok(Self { pieces, per_edge })
+    }
+
+    fn expand(&self, buf: &mut Vec<u8>, dep: &Dependency, prereq: &str) {
+        for piece in &self.pieces {
+            match piece {
+                Piece::Literal(value) => {
+                    buf.extend_from_slice(value.as_bytes())
+                }
+                Piece::Target => buf.extend_from_slice(dep.target.as_bytes()),
+                Piece::Prereq => buf.extend_from_slice(prereq.as_bytes()),
+                Piece::Prereqs => buf
+                    .extend_from_slice(dep.prerequisites.join(" ").as_bytes()),
+                Piece::Count => buf.extend_from_slice(
+                    dep.prerequisites.len().to_string().as_bytes(),
+                ),
+            }
+        }
+    }
+
+    pub fn render(&self, vec: &[Dependency]) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(256 * vec.len());
+
+        for dep in vec {
+            if !self.per_edge {
+                self.expand(&mut buf, dep, "");
+                continue;
+            }
+
+            for prereq in &dep.prerequisites {
+                self.expand(&mut buf, dep, prereq);
+            }
+        }
+
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn do_render(template: &str) -> String {
+        let vec = Vec::from([
+            Dependency {
+                target: "a.o",
+                prerequisites: Vec::from(["a.c", "a.h"]),
+            },
+            Dependency {
+                target: "b.o",
+                prerequisites: Vec::new(),
+            },
+        ]);
+
+        let template = Template::parse(template).unwrap();
+
+        String::from_utf8(template.render(&vec)).unwrap()
+    }
+
+    /**
+     * Template::render()
+     *
+     * Verify that templates with "{{prereq}}" are expanded for each edge
+     * and other templates for each rule.
+     */
+    #[test]
+    fn render_001() {
+        let tests = [
+            ("{{target}} <- {{prereq}}\\n", "a.o <- a.c\na.o <- a.h\n"),
+            (
+                "{{ target }}\\t{{count}}: {{prereqs}}\\n",
+                "a.o\t2: a.c a.h\nb.o\t0: \n",
+            ),
+            ("{{target}}\\\\n,", "a.o\\n,b.o\\n,"),
+            ("x", "xx"),
+        ];
+
+        for (template, expected) in tests {
+            assert_eq!(expected, do_render(template), "{template}");
+        }
+    }
+
+    /**
+     * Template::parse()
+     *
+     * Verify that invalid placeholders are rejected.
+     */
+    #[test]
+    fn parse_001() {
+        let tests = [
+            ("{{target", "unterminated placeholder"),
+            ("{{path}}", "unknown placeholder \"path\""),
+        ];
+
+        for (template, expected) in tests {
+            let result = Template::parse(template).map(|_| ());
+            assert_eq!(Err(String::from(expected)), result);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/*
+ * The phases of a conversion in the order they run.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Read,
+    Parse,
+    Merge,
+    Transform,
+    Serialize,
+    Write,
+}
+
+impl Phase {
+    const ALL: [Phase; 6] = [
+        Phase::Read,
+        Phase::Parse,
+        Phase::Merge,
+        Phase::Transform,
+        Phase::Serialize,
+        Phase::Write,
+    ];
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Phase::Read => "read",
+            Phase::Parse => "parse",
+            Phase::Merge => "merge",
+            Phase::Transform => "transform",
+            Phase::Serialize => "serialize",
+            Phase::Write => "write",
+        };
+
+        f.pad(name)
+    }
+}
+
+/*
+ * The time spent in a phase for the input file "input", or for all input
+ * files if there is none, and the number of bytes processed.
+ */
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    pub phase: Phase,
+    pub input: Option<String>,
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+struct Timings {
+    start: Instant,
+    entries: Vec<Entry>,
+}
+
+static TIMINGS: Mutex<Option<Timings>> = Mutex::new(None);
+
+/*
+ * Start recording the phases. Without it, recording does nothing.
+ */
+pub fn enable() {
+    *TIMINGS.lock().unwrap() = Some(Timings {
+        start: Instant::now(),
+        entries: Vec::new(),
+    });
+}
+
+pub fn record(
+    phase: Phase,
+    input: Option<&str>,
+    bytes: u64,
+    elapsed: Duration,
+) {
+    if let Some(timings) = TIMINGS.lock().unwrap().as_mut() {
+        timings.entries.push(Entry {
+            phase,
+            input: input.map(String::from),
+            bytes,
+            elapsed,
+        });
+    }
+}
+
+/*
+ * Record the output which started at "begin". The outputs are written
+ * while they are serialized, so the time spent writing is deducted.
+ */
+pub fn record_output(begin: Instant) {
+    if let Some(timings) = TIMINGS.lock().unwrap().as_mut() {
+        let (bytes, written) = timings
+            .entries
+            .iter()
+            .filter(|x| x.phase == Phase::Write)
+            .fold((0, Duration::ZERO), |(n, t), x| {
+                (n + x.bytes, t + x.elapsed)
+            });
+
+        timings.entries.push(Entry {
+            phase: Phase::Serialize,
+            input: None,
+            bytes,
+            elapsed: begin.elapsed().saturating_sub(written),
+        });
+    }
+}
+
+/*
+ * Print the recorded phases to stderr, if enabled, with the total of
+ * "bytes" read from the input files.
+ */
+pub fn print(bytes: u64) {
+    if let Some(timings) = TIMINGS.lock().unwrap().as_ref() {
+        let total = timings.start.elapsed();
+
+        eprint!("{}", render(&timings.entries, bytes, total));
+    }
+}
+
+fn rate(bytes: u64, elapsed: Duration) -> String {
+    let secs = elapsed.as_secs_f64();
+
+    if bytes == 0 || secs == 0.0 {
+        return String::from("-");
+    }
+
+    format!("{:.1} MB/s", bytes as f64 / secs / 1e6)
+}
+
+fn line(
+    phase: &dyn fmt::Display,
+    input: &str,
+    bytes: u64,
+    t: Duration,
+) -> String {
+    let millis = t.as_secs_f64() * 1e3;
+    let rate = rate(bytes, t);
+
+    format!("{phase:<10} {millis:>12.3} ms {rate:>14}  {input}\n")
+}
+
+/*
+ * Render a table with the phases of each input file followed by the
+ * total of each phase and the total wall time of the conversion.
+ */
+pub fn render(entries: &[Entry], bytes: u64, total: Duration) -> String {
+    let mut result =
+        format!("{:<10} {:>12}    {:>14}  input\n", "phase", "time", "rate");
+
+    for entry in entries.iter().filter(|x| x.input.is_some()) {
+        let input = entry.input.as_deref().unwrap_or_default();
+
+        result += &line(&entry.phase, input, entry.bytes, entry.elapsed);
+    }
+
+    for phase in Phase::ALL {
+        let vec: Vec<_> = entries.iter().filter(|x| x.phase == phase).collect();
+
+        if vec.is_empty() {
+            continue;
+        }
+
+        let elapsed = vec.iter().map(|x| x.elapsed).sum();
+        let bytes = vec.iter().map(|x| x.bytes).sum();
+
+        result += &line(&phase, "(total)", bytes, elapsed);
+    }
+
+    result += &line(&"total", "(total)", bytes, total);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * render()
+     *
+     * Verify that the phases of each input file are listed first, followed
+     * by the total of each recorded phase and the wall time.
+     */
+    #[test]
+    fn render_001() {
+        let entry = |phase, input: Option<&str>, bytes, millis| Entry {
+            phase,
+            input: input.map(String::from),
+            bytes,
+            elapsed: Duration::from_millis(millis),
+        };
+
+        let entries = Vec::from([
+            entry(Phase::Read, Some("a.d"), 1000, 1),
+            entry(Phase::Read, Some("b.d"), 3000, 1),
+            entry(Phase::Parse, None, 4000, 2),
+            entry(Phase::Merge, None, 4000, 1),
+            entry(Phase::Write, None, 0, 0),
+        ]);
+
+        let text = render(&entries, 4000, Duration::from_millis(10));
+        let lines: Vec<_> = text.lines().collect();
+
+        assert_eq!(8, lines.len());
+        assert!(lines[0].starts_with("phase"));
+        assert_eq!("read              1.000 ms       1.0 MB/s  a.d", lines[1]);
+        assert_eq!(
+            "read              2.000 ms       2.0 MB/s  (total)",
+            lines[3]
+        );
+        assert_eq!(
+            "merge             1.000 ms       4.0 MB/s  (total)",
+            lines[5]
+        );
+        assert_eq!(
+            "write             0.000 ms              -  (total)",
+            lines[6]
+        );
+        assert_eq!(
+            "total            10.000 ms       0.4 MB/s  (total)",
+            lines[7]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::str;
+
+use crate::dependency::DependencyBuf;
+
+/*
+ * Decode the content of a tracking log. MSBuild writes them in UTF-16
+ * with a byte order mark, but UTF-8 and UTF-16 without a byte order mark
+ * are accepted as well.
+ */
+fn decode(data: &[u8]) -> Result<String, String> {
+    let utf16 = |data: &[u8], little_endian: bool| {
+        let chunks = data.chunks_exact(2);
+
+        if !chunks.remainder().is_empty() {
+            return Err(String::from("truncated UTF-16 input"));
+        }
+
+        let units = chunks.map(|x| match little_endian {
+            true => u16::from_le_bytes([x[0], x[1]]),
+            false => u16::from_be_bytes([x[0], x[1]]),
+        });
+
+        char::decode_utf16(units)
+            .collect::<Result<String, _>>()
+            .map_err(|_| String::from("invalid UTF-16 input"))
+    };
+
+    match data {
+        [0xff, 0xfe, rest @ ..] => utf16(rest, true),
+        [0xfe, 0xff, rest @ ..] => utf16(rest, false),
+        [_, 0, ..] => utf16(data, true),
+        [0, _, ..] => utf16(data, false),
+        _ => {
+            let data = data.strip_prefix(b"\xef\xbb\xbf").unwrap_or(data);
+
+            str::from_utf8(data)
+                .map(String::from)
+                .map_err(|_| String::from("invalid UTF-8 input"))
+        }
+    }
+}
+
+/*
+ * Read the dependencies from a tracking log of MSBuild, e.g.
+ * "CL.read.1.tlog". A line starting with '^' lists the sources of a
+ * compilation separated by '|'. The following lines list the files which
+ * were read while compiling them. Each source becomes a target with these
+ * files as prerequisites.
+ */
+pub fn parse_dependencies(data: &[u8]) -> Result<Vec<DependencyBuf>, String> {
+    let data = decode(data)?;
+    let mut deps: Vec<DependencyBuf> = Vec::new();
+    let mut group = 0;
+
+    for (i, line) in data.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(sources) = line.strip_prefix('^') {
+            group = deps.len();
+
+            deps.extend(sources.split('|').map(|x| DependencyBuf {
+                target: String::from(x),
+                prerequisites: Vec::new(),
+            }));
+
+            continue;
+        }
+
+        if group == deps.len() {
+            return Err(format!("line {}: missing source", i + 1));
+        }
+
+        for dep in &mut deps[group..] {
+            if dep.target != line {
+                dep.prerequisites.push(String::from(line));
+            }
+        }
+    }
+
+    Ok(deps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(data: &str) -> Vec<u8> {
+        let mut vec = Vec::from([0xff, 0xfe]);
+        vec.extend(data.encode_utf16().flat_map(|x| x.to_le_bytes()));
+
+        vec
+    }
+
+    /**
+     * decode()
+     *
+     * Verify that UTF-16 and UTF-8 input with and without a byte order
+     * mark is decoded.
+     */
+    #[test]
+    fn decode_001() {
+        let be: Vec<u8> = "^A\r\n"
+            .encode_utf16()
+            .flat_map(|x| x.to_be_bytes())
+            .collect();
+        let be_bom = [[0xfe, 0xff].as_slice(), &be].concat();
+
+        assert_eq!(Ok(String::from("^A\r\n")), decode(&utf16le("^A\r\n")));
+        assert_eq!(Ok(String::from("^A\r\n")), decode(&utf16le("^A\r\n")[2..]));
+        assert_eq!(Ok(String::from("^A\r\n")), decode(&be));
+        assert_eq!(Ok(String::from("^A\r\n")), decode(&be_bom));
+        assert_eq!(Ok(String::from("^A\r\n")), decode(b"\xef\xbb\xbf^A\r\n"));
+        assert!(decode(&utf16le("^A")[..3]).is_err());
+        assert!(decode(b"^\xff").is_err());
+    }
+
+    /**
+     * parse_dependencies()
+     *
+     * Verify that the files read are assigned to each source of their
+     * group.
+     */
+    #[test]
+    fn parse_dependencies_001() {
+        let data = utf16le(
+            "^C:\\SRC\\MAIN.CPP\r\n\
+             C:\\SRC\\MAIN.CPP\r\n\
+             C:\\SRC\\MAIN.H\r\n\
+             ^C:\\SRC\\A.CPP|C:\\SRC\\B.CPP\r\n\
+             C:\\SDK\\STDIO.H\r\n\
+             \r\n",
+        );
+
+        let deps = parse_dependencies(&data).unwrap();
+
+        assert_eq!(3, deps.len());
+        assert_eq!("C:\\SRC\\MAIN.CPP", deps[0].target);
+        assert_eq!(Vec::from(["C:\\SRC\\MAIN.H"]), deps[0].prerequisites);
+        assert_eq!("C:\\SRC\\A.CPP", deps[1].target);
+        assert_eq!(Vec::from(["C:\\SDK\\STDIO.H"]), deps[1].prerequisites);
+        assert_eq!("C:\\SRC\\B.CPP", deps[2].target);
+        assert_eq!(Vec::from(["C:\\SDK\\STDIO.H"]), deps[2].prerequisites);
+
+        assert!(parse_dependencies(&utf16le("C:\\SDK\\STDIO.H\r\n")).is_err());
+    }
+}
@@ -0,0 +1,267 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::unicode_data::{
+    COMBINING_CLASSES, COMPOSITION_EXCLUSIONS, DECOMPOSITIONS,
+};
+
+/*
+ * The Unicode normalization form of the paths. macOS writes decomposed
+ * paths ("nfd"), while most other tools use composed ones ("nfc").
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Form {
+    Nfc,
+    Nfd,
+}
+
+/* The Hangul syllables are composed algorithmically */
+const S_BASE: u32 = 0xAC00;
+const L_BASE: u32 = 0x1100;
+const V_BASE: u32 = 0x1161;
+const T_BASE: u32 = 0x11A7;
+const L_COUNT: u32 = 19;
+const V_COUNT: u32 = 21;
+const T_COUNT: u32 = 28;
+const N_COUNT: u32 = V_COUNT * T_COUNT;
+const S_COUNT: u32 = L_COUNT * N_COUNT;
+
+fn combining_class(c: char) -> u8 {
+    let cp = c as u32;
+    let result = COMBINING_CLASSES.binary_search_by(|&(first, last, _)| {
+        if last < cp {
+            Ordering::Less
+        } else if first > cp {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    });
+
+    result.map_or(0, |i| COMBINING_CLASSES[i].2)
+}
+
+fn to_char(cp: u32) -> char {
+    char::from_u32(cp).unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+fn decompose(c: char, out: &mut Vec<char>) {
+    let cp = c as u32;
+
+    if (S_BASE..S_BASE + S_COUNT).contains(&cp) {
+        let index = cp - S_BASE;
+
+        out.push(to_char(L_BASE + index / N_COUNT));
+        out.push(to_char(V_BASE + index % N_COUNT / T_COUNT));
+
+        if !index.is_multiple_of(T_COUNT) {
+            out.push(to_char(T_BASE + index % T_COUNT));
+        }
+
+        return;
+    }
+
+    match DECOMPOSITIONS.binary_search_by_key(&cp, |x| x.0) {
+        Ok(i) => {
+            let (_, first, second) = DECOMPOSITIONS[i];
+
+            decompose(to_char(first), out);
+
+            if second != 0 {
+                decompose(to_char(second), out);
+            }
+        }
+        Err(_) => out.push(c),
+    }
+}
+
+/*
+ * Sort each sequence of combining characters by their combining class.
+ * The sort is stable, so characters of the same class keep their order.
+ */
+fn reorder(chars: &mut [char]) {
+    let mut i = 0;
+
+    while i < chars.len() {
+        if combining_class(chars[i]) == 0 {
+            i += 1;
+            continue;
+        }
+
+        let begin = i;
+
+        while i < chars.len() && combining_class(chars[i]) != 0 {
+            i += 1;
+        }
+
+        chars[begin..i].sort_by_key(|&c| combining_class(c));
+    }
+}
+
+/*
+ * Convert paths to a Unicode normalization form, so paths which only
+ * differ in their normalization are merged and matched by the filters.
+ */
+pub struct Normalizer {
+    form: Form,
+    compositions: HashMap<(char, char), char>,
+}
+
+impl Normalizer {
+    pub fn new(form: Form) -> Self {
+        let mut compositions = HashMap::new();
+
+        if form == Form::Nfc {
+            for &(cp, first, second) in DECOMPOSITIONS {
+                if second == 0 || COMPOSITION_EXCLUSIONS.contains(&cp) {
+                    continue;
+                }
+
+                let pair = (to_char(first), to_char(second));
+                compositions.insert(pair, to_char(cp));
+            }
+        }
+
+        Self { form, compositions }
+    }
+
+    fn compose_pair(&self, first: char, second: char) -> Option<char> {
+        let (a, b) = (first as u32, second as u32);
+
+        if (L_BASE..L_BASE + L_COUNT).contains(&a)
+            && (V_BASE..V_BASE + V_COUNT).contains(&b)
+        {
+            let index = (a - L_BASE) * N_COUNT + (b - V_BASE) * T_COUNT;
+
+            return char::from_u32(S_BASE + index);
+        }
+
+        if (S_BASE..S_BASE + S_COUNT).contains(&a)
+            && (a - S_BASE).is_multiple_of(T_COUNT)
+            && (T_BASE + 1..T_BASE + T_COUNT).contains(&b)
+        {
+            return char::from_u32(a + b - T_BASE);
+        }
+
+        self.compositions.get(&(first, second)).copied()
+    }
+
+    /*
+     * Combine each character with the last starter unless a character
+     * of the same or a higher combining class is between them.
+     */
+    fn compose(&self, chars: &[char]) -> Vec<char> {
+        let mut result: Vec<char> = Vec::with_capacity(chars.len());
+        let mut starter = None;
+        let mut last_class = 0;
+
+        for &c in chars {
+            let class = combining_class(c);
+
+            if let Some(i) = starter {
+                let adjacent = result.len() == i + 1;
+                let blocked =
+                    !adjacent && (last_class == 0 || last_class >= class);
+
+                if !blocked {
+                    if let Some(x) = self.compose_pair(result[i], c) {
+                        result[i] = x;
+                        continue;
+                    }
+                }
+            }
+
+            if class == 0 {
+                starter = Some(result.len());
+            }
+
+            last_class = class;
+            result.push(c);
+        }
+
+        result
+    }
+
+    pub fn apply<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        if path.is_ascii() {
+            return Cow::Borrowed(path);
+        }
+
+        let mut chars = Vec::with_capacity(path.len());
+
+        for c in path.chars() {
+            decompose(c, &mut chars);
+        }
+
+        reorder(&mut chars);
+
+        if self.form == Form::Nfc {
+            chars = self.compose(&chars);
+        }
+
+        if chars.iter().copied().eq(path.chars()) {
+            return Cow::Borrowed(path);
+        }
+
+        Cow::Owned(chars.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * Normalizer::apply()
+     *
+     * Verify that paths are converted to both normalization forms,
+     * including Hangul syllables, reordered combining marks, and excluded
+     * compositions, and that ASCII paths are borrowed.
+     */
+    #[test]
+    fn apply_001() {
+        let nfc = Normalizer::new(Form::Nfc);
+        let nfd = Normalizer::new(Form::Nfd);
+
+        let data = [
+            ("src/Am\u{e9}lie.h", "src/Ame\u{301}lie.h"),
+            ("\u{d55c}.h", "\u{1112}\u{1161}\u{11ab}.h"),
+            ("\u{1ead}", "a\u{323}\u{302}"),
+            ("\u{915}\u{93c}", "\u{915}\u{93c}"),
+            ("\u{212b}", "A\u{30a}"),
+        ];
+
+        for (composed, decomposed) in data {
+            assert_eq!(decomposed, nfd.apply(composed));
+            assert_eq!(decomposed, nfd.apply(decomposed));
+            assert_eq!(nfc.apply(decomposed), nfc.apply(composed));
+        }
+
+        assert_eq!("src/Am\u{e9}lie.h", nfc.apply("src/Ame\u{301}lie.h"));
+        assert_eq!("\u{d55c}.h", nfc.apply("\u{1112}\u{1161}\u{11ab}.h"));
+        assert_eq!("\u{1ead}", nfc.apply("a\u{302}\u{323}"));
+        assert_eq!("\u{915}\u{93c}", nfc.apply("\u{958}"));
+        assert_eq!("\u{c5}", nfc.apply("\u{212b}"));
+
+        assert!(matches!(nfc.apply("src/a.h"), Cow::Borrowed(_)));
+        assert!(matches!(nfd.apply("src/a\u{301}.h"), Cow::Borrowed(_)));
+    }
+}
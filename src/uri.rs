@@ -0,0 +1,137 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::separators;
+
+/*
+ * Percent-encode "path" into "out". Only the unreserved characters of RFC
+ * 3986 and the separators are kept, so spaces, non-ASCII characters, and
+ * characters with a meaning in URIs, e.g. '#' and '%', are encoded.
+ */
+fn encode(path: &str, out: &mut String) {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+    for &byte in path.as_bytes() {
+        if byte.is_ascii_alphanumeric()
+            || matches!(byte, b'-' | b'.' | b'_' | b'~' | b'/')
+        {
+            out.push(char::from(byte));
+        } else {
+            out.push('%');
+            out.push(char::from(HEX[usize::from(byte >> 4)]));
+            out.push(char::from(HEX[usize::from(byte & 0xf)]));
+        }
+    }
+}
+
+fn is_drive(path: &[u8]) -> bool {
+    path.len() >= 3
+        && path[0].is_ascii_alphabetic()
+        && path[1] == b':'
+        && matches!(path[2], b'\\' | b'/')
+}
+
+/*
+ * Convert "path" into a "file://" URI. A relative path is resolved against
+ * the absolute directory "cwd". Windows paths with a drive letter, e.g.
+ * "C:\src\a.c", and UNC paths, e.g. "\\server\share\a.c", are recognized
+ * and their backslashes are taken for separators. Elsewhere, a backslash
+ * is part of a file name and encoded, unless it escapes a space, a tab,
+ * or '#' like in a Makefile. "$$" is taken for '$' as well.
+ */
+pub fn file_uri(path: &str, cwd: &str) -> String {
+    let path = &separators::unescape(path);
+    let mut uri = String::with_capacity(path.len() + cwd.len() + 8);
+
+    uri.push_str("file://");
+
+    if is_drive(path.as_bytes()) {
+        let path = path.replace('\\', "/");
+
+        uri.push('/');
+        uri.push_str(&path[..2]);
+        encode(&path[2..], &mut uri);
+    } else if let Some(unc) = path.strip_prefix("\\\\") {
+        let unc = unc.replace('\\', "/");
+        let (host, share) = unc.split_once('/').unwrap_or((&unc, ""));
+
+        encode(host, &mut uri);
+        uri.push('/');
+        encode(share, &mut uri);
+    } else if path.starts_with('/') {
+        encode(path, &mut uri);
+    } else {
+        encode(cwd, &mut uri);
+
+        if !cwd.ends_with('/') {
+            uri.push('/');
+        }
+
+        encode(path, &mut uri);
+    }
+
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * file_uri()
+     *
+     * Verify that the function encodes absolute and relative paths.
+     */
+    #[test]
+    fn file_uri_001() {
+        let tests = [
+            ("/usr/include/stdio.h", "file:///usr/include/stdio.h"),
+            ("src/my file.c", "file:///home/dev/src/my%20file.c"),
+            ("/src/Ä#1%.h", "file:///src/%C3%84%231%25.h"),
+            ("/src/a\\b.h", "file:///src/a%5Cb.h"),
+            ("a.h", "file:///home/dev/a.h"),
+            ("my\\ file\\#1.h", "file:///home/dev/my%20file%231.h"),
+        ];
+
+        for (path, expected) in tests {
+            assert_eq!(expected, file_uri(path, "/home/dev"), "{path}");
+        }
+
+        assert_eq!("file:///a.h", file_uri("a.h", "/"));
+    }
+
+    /**
+     * file_uri()
+     *
+     * Verify that the function handles Windows paths with drive letters and
+     * UNC paths.
+     */
+    #[test]
+    fn file_uri_002() {
+        let tests = [
+            ("C:\\src\\a b.c", "file:///C:/src/a%20b.c"),
+            ("C:\\Program\\ Files\\a.h", "file:///C:/Program%20Files/a.h"),
+            ("d:/src/a.c", "file:///d:/src/a.c"),
+            ("\\\\server\\share\\a.c", "file://server/share/a.c"),
+            ("C:", "file:///home/dev/C%3A"),
+        ];
+
+        for (path, expected) in tests {
+            assert_eq!(expected, file_uri(path, "/home/dev"), "{path}");
+        }
+    }
+}
@@ -0,0 +1,185 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::io::{self, Read};
+use std::{mem, str};
+
+/*
+ * How to deal with input which is not valid UTF-8. Invalid sequences are
+ * always replaced with U+FFFD by the parser, the policy only decides
+ * whether this is reported.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Utf8Policy {
+    Warn,
+    Require,
+    Lossy,
+}
+
+/*
+ * Find the location of the first invalid UTF-8 sequence in an input which
+ * is passed in arbitrary pieces. Lines and columns start at 1, columns
+ * are counted in bytes.
+ */
+pub struct Utf8Checker {
+    line: usize,
+    column: usize,
+    pending: Vec<u8>,
+    error: Option<(usize, usize)>,
+}
+
+impl Utf8Checker {
+    pub fn new() -> Self {
+        Self {
+            line: 1,
+            column: 1,
+            pending: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        if self.error.is_some() {
+            return;
+        }
+
+        /* Complete a sequence which was split by the previous piece */
+        let mut buf = mem::take(&mut self.pending);
+        let data = if buf.is_empty() {
+            data
+        } else {
+            buf.extend_from_slice(data);
+            buf.as_slice()
+        };
+
+        match str::from_utf8(data) {
+            Ok(_) => self.advance(data),
+            Err(err) => {
+                let (valid, rest) = data.split_at(err.valid_up_to());
+                self.advance(valid);
+
+                match err.error_len() {
+                    Some(_) => self.error = Some((self.line, self.column)),
+                    None => self.pending = Vec::from(rest),
+                }
+            }
+        }
+    }
+
+    /*
+     * Return the location of the first invalid sequence, including a
+     * sequence which is truncated by the end of the input.
+     */
+    pub fn finish(&self) -> Option<(usize, usize)> {
+        if self.error.is_none() && !self.pending.is_empty() {
+            return Some((self.line, self.column));
+        }
+
+        self.error
+    }
+
+    fn advance(&mut self, data: &[u8]) {
+        match data.iter().rposition(|&x| x == b'\n') {
+            Some(pos) => {
+                self.line += data.iter().filter(|&&x| x == b'\n').count();
+                self.column = data.len() - pos;
+            }
+            None => self.column += data.len(),
+        }
+    }
+}
+
+/*
+ * Pass all data read from "inner" to a checker.
+ */
+pub struct Utf8Reader<'a, R> {
+    inner: R,
+    checker: &'a mut Utf8Checker,
+}
+
+impl<'a, R: Read> Utf8Reader<'a, R> {
+    pub fn new(inner: R, checker: &'a mut Utf8Checker) -> Self {
+        Self { inner, checker }
+    }
+}
+
+impl<R: Read> Read for Utf8Reader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.checker.update(&buf[..len]);
+
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn do_check(pieces: &[&[u8]]) -> Option<(usize, usize)> {
+        let mut checker = Utf8Checker::new();
+
+        for piece in pieces {
+            checker.update(piece);
+        }
+
+        checker.finish()
+    }
+
+    /**
+     * Utf8Checker::finish()
+     *
+     * Verify that valid input is accepted, even if a multi-byte sequence
+     * is split into several pieces.
+     */
+    #[test]
+    fn finish_001() {
+        assert_eq!(None, do_check(&[]));
+        assert_eq!(None, do_check(&[b"a.o: \xc3\xa4.c\n"]));
+        assert_eq!(None, do_check(&[b"a.o: \xe2", b"\x82", b"\xac.c\n"]));
+    }
+
+    /**
+     * Utf8Checker::finish()
+     *
+     * Verify that the location of the first invalid sequence is reported.
+     */
+    #[test]
+    fn finish_002() {
+        assert_eq!(Some((1, 6)), do_check(&[b"a.o: \xff.c\n\xff"]));
+        assert_eq!(Some((2, 4)), do_check(&[b"a.o:\n", b"b\\", b" \xc3(\n"]));
+        assert_eq!(Some((3, 1)), do_check(&[b"a\n\n", b"\xe2\x82"]));
+    }
+
+    /**
+     * Utf8Reader::read()
+     *
+     * Verify that all data read is passed to the checker.
+     */
+    #[test]
+    fn read_001() {
+        let mut checker = Utf8Checker::new();
+        let mut data = Vec::new();
+
+        Utf8Reader::new(b"a: b\nc: \xff".as_slice(), &mut checker)
+            .read_to_end(&mut data)
+            .unwrap();
+
+        assert_eq!(b"a: b\nc: \xff".as_slice(), data);
+        assert_eq!(Some((2, 4)), checker.finish());
+    }
+}
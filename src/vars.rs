@@ -0,0 +1,157 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/*
+ * Expand the references to variables in paths, i.e. "$(NAME)" and
+ * "${NAME}". As in Makefiles, "$$" is expanded to a single '$'.
+ * References to unknown variables are kept. The values of the variables
+ * are not expanded themselves.
+ */
+pub struct Variables {
+    values: HashMap<String, String>,
+}
+
+impl Variables {
+    /*
+     * Create the variables from definitions given as "<name>=<value>".
+     * A later definition of a variable overrides an earlier one.
+     */
+    pub fn new(defines: &[String]) -> Result<Self, String> {
+        let mut values = HashMap::with_capacity(defines.len());
+
+        for define in defines {
+            let (name, value) = define
+                .split_once('=')
+                .filter(|(name, _)| is_name(name))
+                .ok_or_else(|| format!("invalid definition \"{define}\""))?;
+
+            values.insert(String::from(name), String::from(value));
+        }
+
+        Ok(Self { values })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|x| x.as_str())
+    }
+
+    pub fn expand<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        if !path.contains('$') {
+            return Cow::Borrowed(path);
+        }
+
+        let mut result = String::with_capacity(path.len());
+        let mut rest = path;
+
+        while let Some(pos) = rest.find('$') {
+            result.push_str(&rest[..pos]);
+            rest = &rest[pos..];
+
+            if let Some(tail) = rest.strip_prefix("$$") {
+                result.push('$');
+                rest = tail;
+                continue;
+            }
+
+            match self.reference(rest) {
+                Some((value, len)) => {
+                    result.push_str(value);
+                    rest = &rest[len..];
+                }
+                None => {
+                    result.push('$');
+                    rest = &rest[1..];
+                }
+            }
+        }
+
+        result.push_str(rest);
+
+        Cow::Owned(result)
+    }
+
+    /*
+     * Return the value of the known variable referenced at the start of
+     * "data" and the length of the reference.
+     */
+    fn reference(&self, data: &str) -> Option<(&str, usize)> {
+        let close = match data.as_bytes().get(1)? {
+            b'(' => ')',
+            b'{' => '}',
+            _ => return None,
+        };
+
+        let len = data.find(close)?;
+        let value = self.values.get(&data[2..len])?;
+
+        Some((value, len + 1))
+    }
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|x| x.is_ascii_alphanumeric() || x == b'_' || x == b'.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * Variables::new()
+     *
+     * Verify that invalid definitions are rejected.
+     */
+    #[test]
+    fn new_001() {
+        for define in ["", "OBJDIR", "=x", "OBJ DIR=x", "$(X)=y"] {
+            assert!(Variables::new(&[String::from(define)]).is_err());
+        }
+
+        assert!(Variables::new(&[String::from("EMPTY=")]).is_ok());
+    }
+
+    /**
+     * Variables::expand()
+     *
+     * Verify that references to known variables are expanded and that
+     * everything else is kept.
+     */
+    #[test]
+    fn expand_001() {
+        let defines = ["OBJDIR=build/x86", "ARCH=x86", "ARCH=arm", "E="];
+        let defines: Vec<String> = defines.map(String::from).into();
+        let vars = Variables::new(&defines).unwrap();
+
+        let tests = [
+            ("main.o", "main.o"),
+            ("$(OBJDIR)/main.o", "build/x86/main.o"),
+            ("${ARCH}/$(ARCH).h$(E)", "arm/arm.h"),
+            ("$(UNKNOWN)/$$x/$", "$(UNKNOWN)/$x/$"),
+            ("$(OBJDIR/a.h", "$(OBJDIR/a.h"),
+        ];
+
+        for (path, expected) in tests {
+            assert_eq!(expected, vars.expand(path));
+        }
+    }
+}
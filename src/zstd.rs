@@ -0,0 +1,144 @@
+/*
+ * Copyright (C) 2022   Steffen Nuessle
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::process::Stdio;
+use std::process::{Child, ChildStdin, ChildStdout, Command, ExitStatus};
+
+use crate::pipe;
+
+/*
+ * Zstandard files are compressed and decompressed by the "zstd" program,
+ * which has to be in the PATH.
+ */
+pub fn is_compressed(path: &str) -> bool {
+    path.ends_with(".zst")
+}
+
+/*
+ * Compress the data passed to "write" into the file "path" or to stdout
+ * if "path" is empty.
+ */
+pub fn compress<F>(path: &str, write: F) -> io::Result<ExitStatus>
+where
+    F: FnOnce(&mut ChildStdin) -> io::Result<()>,
+{
+    let stdout = if path.is_empty() {
+        Stdio::inherit()
+    } else {
+        Stdio::from(File::create(path)?)
+    };
+
+    pipe::feed(
+        Command::new("zstd").args(["-q", "-c"]).stdout(stdout),
+        write,
+    )
+}
+
+/*
+ * Read the decompressed content of a file. The exit status of "zstd" is
+ * checked at the end of the content, so a corrupt file is an error instead
+ * of a truncated input.
+ */
+pub struct Decoder {
+    child: Child,
+    stdout: ChildStdout,
+}
+
+impl Decoder {
+    pub fn open(path: &str) -> io::Result<Self> {
+        /* Fail like "File::open()" instead of with the message of zstd */
+        File::open(path)?;
+
+        let mut child = Command::new("zstd")
+            .args(["-q", "-d", "-c", "--", path])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let stdout = child.stdout.take().unwrap();
+
+        Ok(Self { child, stdout })
+    }
+}
+
+impl Read for Decoder {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.stdout.read(buf)?;
+
+        if len == 0 && !buf.is_empty() {
+            let status = self.child.wait()?;
+
+            if !status.success() {
+                let msg = format!("zstd failed to decompress: {status}");
+
+                return Err(io::Error::other(msg));
+            }
+        }
+
+        Ok(len)
+    }
+}
+
+impl Drop for Decoder {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+
+    use super::*;
+
+    /**
+     * compress(), Decoder::read()
+     *
+     * Verify that compressed data is decompressed again and that a file
+     * which is not compressed is an error. Skipped without "zstd".
+     */
+    #[test]
+    fn compress_001() {
+        if Command::new("zstd").arg("--version").output().is_err() {
+            return;
+        }
+
+        let dir = env::temp_dir();
+        let path = dir.join(format!("dep2j-{}.zst", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let status = compress(path, |x| x.write_all(b"a.o: a.c\n")).unwrap();
+        assert!(status.success());
+
+        let mut data = Vec::new();
+        Decoder::open(path).unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(b"a.o: a.c\n", data.as_slice());
+
+        fs::write(path, b"a.o: a.c\n").unwrap();
+
+        let mut data = Vec::new();
+        let result = Decoder::open(path).unwrap().read_to_end(&mut data);
+        assert!(result.is_err());
+
+        fs::remove_file(path).unwrap();
+    }
+}